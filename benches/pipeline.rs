@@ -0,0 +1,132 @@
+//! Timings of each stage of the pipeline, from seed to encoded bytes
+//!
+//! Run `cargo bench --bench pipeline -- --save-baseline main` on the base
+//! branch, then `cargo bench --bench pipeline -- --baseline main` on a
+//! change; criterion reports each benchmark that regressed beyond noise.
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use hashdenticon::output::{self, Encoding, Format};
+use hashdenticon::resample::Filter;
+use hashdenticon::{Identicon, Input, Options, Style, generate_pattern};
+use std::hint::black_box;
+use std::path::Path;
+use std::time::Duration;
+
+const SEED: &str = "bench@example.com";
+
+/// Hashing the seed and laying out the cells, before any pixels
+fn hash_to_pattern(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hash_to_pattern");
+    group.bench_function("sha256", |b| b.iter(|| Input::from_seed(black_box(SEED))));
+    let digest = Input::from_seed(SEED).digest;
+    for grid in [5, 15] {
+        group.bench_with_input(BenchmarkId::new("pattern", grid), &grid, |b, &grid| {
+            b.iter(|| generate_pattern(black_box(&digest), grid))
+        });
+    }
+    group.finish();
+}
+
+/// The default grid style, all flat rectangle fills, at growing sizes
+fn render_flat(c: &mut Criterion) {
+    let digest = Input::from_seed(SEED).digest;
+    let mut group = c.benchmark_group("render_flat");
+    for size in [64, 512, 2048] {
+        let options = Options {
+            size,
+            ..Options::default()
+        };
+        group.throughput(Throughput::Elements(u64::from(size) * u64::from(size)));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &options, |b, options| {
+            b.iter(|| Identicon::new(black_box(&digest), options).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// The styles with per-pixel work, and supersampling with each filter
+fn render_styled(c: &mut Criterion) {
+    let digest = Input::from_seed(SEED).digest;
+    let mut group = c.benchmark_group("render_styled");
+    for style in [
+        Style::Blob,
+        Style::Voronoi,
+        Style::Rings,
+        Style::Iso,
+        Style::Maze,
+    ] {
+        let options = Options {
+            style,
+            size: 512,
+            ..Options::default()
+        };
+        group.bench_with_input(
+            BenchmarkId::from_parameter(style),
+            &options,
+            |b, options| b.iter(|| Identicon::new(black_box(&digest), options).unwrap()),
+        );
+    }
+    for filter in [Filter::Lanczos3, Filter::Box] {
+        let options = Options {
+            style: Style::Blob,
+            size: 512,
+            supersample: 4,
+            filter,
+            ..Options::default()
+        };
+        let name = format!("blob-supersample-{:?}", filter).to_lowercase();
+        group.bench_function(name, |b| {
+            b.iter(|| Identicon::new(black_box(&digest), &options).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// Encoding one finished 512px identicon into each common format
+fn encode(c: &mut Criterion) {
+    let options = Options {
+        size: 512,
+        ..Options::default()
+    };
+    let identicon = Identicon::from_seed(SEED, &options).unwrap();
+    let encoding = Encoding::default();
+    let mut group = c.benchmark_group("encode");
+    for format in [Format::Png, Format::Webp, Format::Svg, Format::Pdf] {
+        let path = format!("identicon.{}", format.extension());
+        group.bench_function(format.extension(), |b| {
+            b.iter(|| {
+                output::encode(
+                    black_box(identicon.as_rgb()),
+                    Path::new(&path),
+                    Some(format),
+                    &encoding,
+                )
+                .unwrap()
+            })
+        });
+    }
+    let mut buf = Vec::new();
+    group.bench_function("png-reused-buffer", |b| {
+        b.iter(|| {
+            buf.clear();
+            identicon.encode_into(&mut buf, Format::Png).unwrap();
+        })
+    });
+    group.finish();
+}
+
+fn config() -> Criterion {
+    // tight enough to flag a few percent, loose enough not to cry wolf on
+    // a busy laptop
+    Criterion::default()
+        .measurement_time(Duration::from_secs(3))
+        .significance_level(0.01)
+        .noise_threshold(0.03)
+}
+
+criterion_group! {
+    name = benches;
+    config = config();
+    targets = hash_to_pattern, render_flat, render_styled, encode
+}
+criterion_main!(benches);
@@ -0,0 +1,16 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    grpc();
+}
+
+/// Generate the gRPC service from its proto, without needing protoc
+#[cfg(feature = "grpc")]
+fn grpc() {
+    println!("cargo:rerun-if-changed=proto/hashdenticon.proto");
+    let descriptors = protox::compile(["hashdenticon.proto"], ["proto"])
+        .unwrap_or_else(|err| panic!("Failed to compile proto/hashdenticon.proto: {:?}", err));
+    tonic_prost_build::configure()
+        .build_client(false)
+        .compile_fds(descriptors)
+        .unwrap_or_else(|err| panic!("Failed to generate the gRPC service: {}", err));
+}
@@ -0,0 +1,15 @@
+//! The `#rrggbb` parser behind every color flag, theme key, and serialized
+//! color; what it accepts must print back to the same color
+
+#![no_main]
+
+use hashdenticon::theme::parse_hex;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|text: &str| {
+    if let Some(color) = parse_hex(text) {
+        let [r, g, b] = color.0;
+        let printed = format!("#{:02x}{:02x}{:02x}", r, g, b);
+        assert_eq!(parse_hex(&printed), Some(color));
+    }
+});
@@ -0,0 +1,24 @@
+//! Image files, as the `decode` subcommand reads them, with the decoder
+//! capped well below what would exhaust the fuzzer's memory
+
+#![no_main]
+
+use hashdenticon::decode;
+use image::{ImageReader, Limits};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(mut reader) = ImageReader::new(Cursor::new(data)).with_guessed_format() else {
+        return;
+    };
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(512);
+    limits.max_image_height = Some(512);
+    limits.max_alloc = Some(64 << 20);
+    reader.limits(limits);
+    let Ok(img) = reader.decode() else {
+        return;
+    };
+    let _ = decode::decode(&img.to_rgb8(), "fuzz");
+});
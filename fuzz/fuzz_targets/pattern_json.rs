@@ -0,0 +1,22 @@
+//! Pattern models, as `render --from-json` imports them, drawn in their
+//! own style at a size small enough to keep each run fast
+
+#![no_main]
+
+use hashdenticon::{Identicon, Model, Options};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|json: &[u8]| {
+    let Ok(model) = serde_json::from_slice::<Model>(json) else {
+        return;
+    };
+    let options = Options {
+        size: 64,
+        style: model.style,
+        ..Options::default()
+    };
+    if options.style.draws_pattern() {
+        let _ = Identicon::from_model(&model, &options);
+    }
+    let _ = model.to_json();
+});
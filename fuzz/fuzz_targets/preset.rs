@@ -0,0 +1,14 @@
+//! Preset files, as `--preset` reads them, then checked and written back
+//! out as the tui saves them
+
+#![no_main]
+
+use hashdenticon::preset;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|text: &str| {
+    if let Ok(options) = preset::parse(text) {
+        let _ = options.validate();
+        let _ = preset::to_toml(&options);
+    }
+});
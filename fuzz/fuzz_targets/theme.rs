@@ -0,0 +1,10 @@
+//! Theme files, as `--theme-file` reads them
+
+#![no_main]
+
+use hashdenticon::theme;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|text: &str| {
+    let _ = theme::parse(text);
+});
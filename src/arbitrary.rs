@@ -0,0 +1,114 @@
+//! `proptest::arbitrary::Arbitrary` for [`Options`], with the `proptest`
+//! feature, so property tests here and in embedders can draw options
+//!
+//! Every value drawn passes [`Options::validate`]. Sides stay at 256 pixels
+//! or less, so hundreds of cases render in seconds. Options that need
+//! something besides the seed, such as a parts pack or overlay text, are
+//! left at their defaults, as are `tileable` and `tile`, which change the
+//! size of the image.
+//!
+//! ```ignore
+//! use hashdenticon::{Identicon, Options};
+//! use proptest::prelude::*;
+//!
+//! proptest! {
+//!     #[test]
+//!     fn renders(options in any::<Options>()) {
+//!         Identicon::from_seed("alice", &options).unwrap();
+//!     }
+//! }
+//! ```
+
+use crate::derive::Derivation;
+use crate::identicon::{MAX_GAP, MAX_GRID_SIZE, MAX_PADDING, MIN_SIZE, Options};
+use crate::resample::Filter;
+use crate::style::Style;
+use crate::theme::{CellShape, Mask};
+use crate::transform::Flip;
+use clap::ValueEnum;
+use proptest::prelude::*;
+use proptest::sample::select;
+
+/// Largest side drawn
+const MAX_SIDE: u32 = 256;
+
+impl Arbitrary for Options {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Options>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        // parts draws from a pack on disk
+        let styles = Style::value_variants()
+            .iter()
+            .copied()
+            .filter(|&style| style != Style::Parts)
+            .collect::<Vec<_>>();
+        (select(styles), MIN_SIZE..=MAX_SIDE, 0..=MAX_PADDING)
+            .prop_flat_map(|(style, size, padding)| {
+                let drawable = size - 2 * (size * padding / 100);
+                let height = if style == Style::Bars {
+                    proptest::option::of(MIN_SIZE..=MAX_SIDE).boxed()
+                } else {
+                    Just(None).boxed()
+                };
+                let grid_size = 1..=MAX_GRID_SIZE.min(drawable);
+                (Just((style, size, padding)), height, grid_size)
+            })
+            .prop_flat_map(|((style, size, padding), height, grid_size)| {
+                let shorter = size.min(height.unwrap_or(size));
+                let shape = (
+                    select(CellShape::value_variants()),
+                    0..=MAX_GAP,
+                    proptest::option::of(select(Mask::value_variants())),
+                    0..shorter.div_ceil(2),
+                );
+                let transform = (
+                    0..4u32,
+                    proptest::option::of(select(Flip::value_variants())),
+                );
+                let derive = (
+                    select(Derivation::value_variants()),
+                    proptest::option::of(0..=100u32),
+                    0..360u32,
+                    0..=8u32,
+                );
+                let sampling = (1..=2u32, select(Filter::value_variants()));
+                (
+                    Just((style, size, height, padding, grid_size)),
+                    shape,
+                    transform,
+                    derive,
+                    sampling,
+                )
+            })
+            .prop_map(
+                |(
+                    (style, size, height, padding, grid_size),
+                    (cell_shape, gap, mask, border),
+                    (quarter_turns, flip),
+                    (derivation, density, hue_shift, steps),
+                    (supersample, filter),
+                )| Options {
+                    size,
+                    height,
+                    grid_size,
+                    padding,
+                    style,
+                    steps,
+                    density,
+                    hue_shift,
+                    rotate: 90 * quarter_turns,
+                    flip,
+                    derivation,
+                    supersample,
+                    filter,
+                    cell_shape,
+                    gap,
+                    mask,
+                    border,
+                    ..Options::default()
+                },
+            )
+            .boxed()
+    }
+}
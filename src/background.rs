@@ -0,0 +1,61 @@
+//! Canvas backgrounds drawn before the style renders on top
+
+use crate::draw::blend;
+use image::{Rgb, RgbImage};
+
+/// Texture for otherwise flat white backgrounds
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum BackgroundPattern {
+    /// Diagonal stripes
+    Stripes,
+    /// Checkerboard squares
+    Checker,
+    /// Regular grid of dots
+    Dots,
+}
+
+/// White canvas, optionally textured in a muted tint of `color`
+pub fn canvas(
+    pattern: Option<BackgroundPattern>,
+    color: Rgb<u8>,
+    width: u32,
+    height: u32,
+) -> RgbImage {
+    canvas_on(Rgb([255, 255, 255]), pattern, color, width, height)
+}
+
+/// [`canvas`] in `base` rather than white
+pub fn canvas_on(
+    base: Rgb<u8>,
+    pattern: Option<BackgroundPattern>,
+    color: Rgb<u8>,
+    width: u32,
+    height: u32,
+) -> RgbImage {
+    let Some(pattern) = pattern else {
+        return RgbImage::from_pixel(width, height, base);
+    };
+
+    let tint = blend(base, color, 1, 7);
+
+    // texture scale follows the shorter side so it reads the same at any size
+    let period = (width.min(height) / 12).max(4);
+    let dot_radius_sq = (period / 4).pow(2);
+
+    RgbImage::from_fn(width, height, |x, y| {
+        let textured = match pattern {
+            BackgroundPattern::Stripes => ((x + y) / (period / 2)).is_multiple_of(2),
+            BackgroundPattern::Checker => ((x / period) + (y / period)).is_multiple_of(2),
+            BackgroundPattern::Dots => {
+                let dx = (x % period).abs_diff(period / 2);
+                let dy = (y % period).abs_diff(period / 2);
+                dx * dx + dy * dy <= dot_radius_sq
+            }
+        };
+
+        if textured { tint } else { base }
+    })
+}
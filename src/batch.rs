@@ -0,0 +1,320 @@
+//! `batch`: one identicon per line of a seeds file, written into a directory
+
+use crate::dedup::{self, Sample};
+use crate::gallery::{self, GalleryEntry};
+use crate::manifest::{self, Manifest, Record};
+use crate::sink;
+use crate::{NameHash, OutputArgs, RenderArgs, check_seed, generate, output_path};
+use anyhow::{Context, Result, bail};
+use hashdenticon::pool::Pending;
+use hashdenticon::{Identicon, RenderPool, input, output};
+use std::collections::{HashSet, VecDeque};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Extra files written beside the identicons, each inside the output directory
+pub struct Reports<'a> {
+    pub gallery: Option<&'a Path>,
+    pub dedup: Option<&'a Path>,
+    pub manifest: Option<&'a Path>,
+}
+
+/// A list of seeds, one per line, and where it came from
+pub struct Seeds {
+    pub source: String,
+    pub contents: String,
+}
+
+impl Seeds {
+    /// The seeds file at `path`, or stdin for `-`
+    pub fn read(path: &Path) -> Result<Seeds> {
+        let mut contents = String::new();
+        if output::is_stdout(path) {
+            std::io::stdin()
+                .read_to_string(&mut contents)
+                .context("Failed to read seeds from stdin")?;
+        } else {
+            contents = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+        }
+        Ok(Seeds {
+            source: path.display().to_string(),
+            contents,
+        })
+    }
+}
+
+/// Generate every seed in `seeds`, optionally with a gallery page, a
+/// duplicates report, and a manifest beside them
+pub fn run(
+    seeds: &Seeds,
+    reports: &Reports,
+    strict: bool,
+    resume: bool,
+    output: &OutputArgs,
+    render: &RenderArgs,
+) -> Result<()> {
+    if resume && output.name_by_hash == Some(NameHash::Content) {
+        bail!(
+            "Resuming needs to know each seed's file name, and --name-by-hash content only knows it after rendering"
+        );
+    }
+    let dir = output_dir(output)?;
+    let contents = &seeds.contents;
+
+    let done = match reports.manifest {
+        Some(path) if resume => manifest::completed(path, &dir)?,
+        _ => HashSet::new(),
+    };
+    let manifest = reports
+        .manifest
+        .map(|path| Manifest::create(path, &dir, resume))
+        .transpose()?;
+    let output = &OutputArgs {
+        stderr_status: manifest.as_ref().is_some_and(Manifest::on_stdout),
+        ..output.clone()
+    };
+
+    // seeds render on the pool, ahead of the one being recorded, and are
+    // recorded in file order once done
+    let pool = RenderPool::global();
+    let ahead = pool.workers() + pool.queue_depth();
+    let shared = Arc::new(render.clone());
+    let mut progress = Progress {
+        manifest,
+        done,
+        dedup: reports.dedup.is_some(),
+        entries: Vec::new(),
+        samples: Vec::new(),
+        failures: 0,
+        skipped: 0,
+    };
+    let mut queue = VecDeque::new();
+    for seed in lines(contents) {
+        let seed_output = seed_output(&dir, output);
+        let path = output_path(&render.file_label(seed), &seed_output);
+        let image = output.file_name(&render.file_label(seed));
+        let entry = GalleryEntry {
+            image: image.display().to_string(),
+            caption: vec![seed.to_string()],
+        };
+
+        // writes and uploads are atomic, so an existing file is a finished one
+        let pending = if resume && sink::exists(&path)? {
+            None
+        } else {
+            let shared = Arc::clone(&shared);
+            let owned = seed.to_string();
+            Some(pool.submit(move || {
+                let render = &*shared;
+                let started = Instant::now();
+                let result = check_seed(&owned, strict)
+                    .and_then(|()| render.input(&owned))
+                    .and_then(|input| {
+                        let hash = input::hex(&input.variant(render.variant).digest);
+                        let (path, identicon) = generate(&input, &seed_output, render)
+                            .with_context(|| format!("Failed to generate {}", owned))?;
+                        Ok((hash, path, identicon))
+                    });
+                (result, started.elapsed())
+            }))
+        };
+        queue.push_back(Queued {
+            seed,
+            path,
+            entry,
+            pending,
+        });
+        while queue.len() > ahead {
+            let queued = queue.pop_front().expect("queue is longer than ahead");
+            progress.finish(queued, render)?;
+        }
+    }
+    while let Some(queued) = queue.pop_front() {
+        progress.finish(queued, render)?;
+    }
+    let Progress {
+        manifest,
+        entries,
+        samples,
+        failures,
+        skipped,
+        ..
+    } = progress;
+
+    if let Some(gallery_path) = reports.gallery {
+        let path = dir.join(gallery_path);
+        let title = format!("Identicons for {}", seeds.source);
+        gallery::write_html(&path, &title, &entries)?;
+        output.status(format_args!("Gallery saved to: {}", path.display()));
+    }
+    if let Some(report_path) = reports.dedup {
+        let path = dir.join(report_path);
+        let title = format!("Duplicate identicons in {}", seeds.source);
+        let (report, collisions) = dedup::report(&title, &samples);
+        sink::write(&path, report.as_bytes()).context("Failed to write duplicates report")?;
+        output.status(format_args!(
+            "{} seeds share a look with another, report saved to: {}",
+            collisions,
+            path.display()
+        ));
+    }
+    if let Some(path) = manifest.as_ref().and_then(Manifest::path) {
+        output.status(format_args!("Manifest saved to: {}", path.display()));
+    }
+    output.status(format_args!(
+        "{} identicons written to: {}",
+        entries.len() - skipped,
+        dir.display()
+    ));
+    if skipped > 0 {
+        output.status(format_args!("{} already done, skipped", skipped));
+    }
+    if failures > 0 {
+        bail!("{} seeds failed; see the manifest to retry them", failures);
+    }
+
+    Ok(())
+}
+
+/// What a render job hands back: the hash, where it was written, and the
+/// identicon, and how long it took
+type Rendered = (Result<(String, PathBuf, Option<Identicon>)>, Duration);
+
+/// A seed waiting for its render to be recorded
+struct Queued<'s> {
+    seed: &'s str,
+    path: PathBuf,
+    entry: GalleryEntry,
+    /// None for a seed an earlier run already wrote
+    pending: Option<Pending<Rendered>>,
+}
+
+/// What the run has recorded so far
+struct Progress {
+    manifest: Option<Manifest>,
+    /// Seeds the manifest of an earlier run already lists
+    done: HashSet<String>,
+    dedup: bool,
+    entries: Vec<GalleryEntry>,
+    samples: Vec<Sample>,
+    failures: usize,
+    skipped: usize,
+}
+
+impl Progress {
+    /// Wait for `queued` to render, then record it
+    fn finish(&mut self, queued: Queued, render: &RenderArgs) -> Result<()> {
+        let Queued {
+            seed,
+            path,
+            mut entry,
+            pending,
+        } = queued;
+        let Some(pending) = pending else {
+            self.skipped += 1;
+            // the digest is only worth deriving again (argon2 is slow) for a
+            // manifest that doesn't list this seed yet
+            if let Some(manifest) = self.manifest.as_mut().filter(|_| !self.done.contains(seed)) {
+                let input = render.input(seed)?.variant(render.variant);
+                manifest.record(&Record {
+                    seed,
+                    hash: Some(&input::hex(&input.digest)),
+                    path: &path,
+                    duration: Duration::ZERO,
+                    error: None,
+                    skipped: true,
+                })?;
+            }
+            if self.dedup {
+                self.samples
+                    .push(Sample::new(seed, &render_sample(seed, render)?));
+            }
+            self.entries.push(entry);
+            return Ok(());
+        };
+
+        // with a manifest, failures are recorded for a retry instead of ending the run
+        let (result, duration) = pending.wait();
+        let (hash, path, identicon) = match (result, &mut self.manifest) {
+            (Ok(done), _) => done,
+            (Err(err), None) => return Err(err),
+            (Err(err), Some(manifest)) => {
+                self.failures += 1;
+                eprintln!("Error: {:#}", err);
+                return manifest.record(&Record {
+                    seed,
+                    hash: None,
+                    path: &path,
+                    duration,
+                    error: Some(format!("{:#}", err)),
+                    skipped: false,
+                });
+            }
+        };
+        if let Some(manifest) = &mut self.manifest {
+            manifest.record(&Record {
+                seed,
+                hash: Some(&hash),
+                path: &path,
+                duration,
+                error: None,
+                skipped: false,
+            })?;
+        }
+        // --name-by-hash content only names the file once it is encoded
+        if let Some(name) = path.file_name() {
+            entry.image = name.to_string_lossy().into_owned();
+        }
+        if self.dedup {
+            // identicons from the --store were never rendered
+            let identicon = match identicon {
+                Some(identicon) => identicon,
+                None => render_sample(seed, render)?,
+            };
+            self.samples.push(Sample::new(seed, &identicon));
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+}
+
+/// The directory given with -o or --out-dir, which batch modes write one file per seed into
+pub fn output_dir(output: &OutputArgs) -> Result<PathBuf> {
+    let dir = output
+        .output
+        .clone()
+        .or_else(|| output.out_dir.clone())
+        .unwrap_or_else(|| PathBuf::from("."));
+    if output::is_stdout(&dir) {
+        bail!("Batch modes write one file per seed and need an output directory");
+    }
+    sink::create_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Seeds in a seeds file, one per line, skipping blanks and # comments
+pub fn lines(contents: &str) -> impl Iterator<Item = &str> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+/// Output settings for writing each seed under its default name in `dir`
+pub fn seed_output(dir: &Path, output: &OutputArgs) -> OutputArgs {
+    OutputArgs {
+        output: None,
+        out_dir: Some(dir.to_path_buf()),
+        ..output.clone()
+    }
+}
+
+/// Render `seed` in memory only, for the duplicates report
+pub fn render_sample(seed: &str, render: &RenderArgs) -> Result<Identicon> {
+    let input = render.input(seed)?.variant(render.variant);
+    Identicon::new(&input.digest, &render.options(seed))
+        .with_context(|| format!("Failed to generate {}", seed))
+}
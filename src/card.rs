@@ -0,0 +1,237 @@
+//! Social preview cards: the identicon on the left and a title, with an
+//! optional subtitle, on the right, at the 1200x630 that link previews on
+//! most sites expect
+
+use crate::error::Result;
+use crate::font::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::identicon::{Identicon, Options};
+use crate::overlay;
+use image::{Rgb, RgbImage, imageops};
+
+pub const WIDTH: u32 = 1200;
+pub const HEIGHT: u32 = 630;
+
+/// Space around the identicon and the text, and between the two
+const MARGIN: u32 = 80;
+
+/// Height of the strip in the identicon's color along the bottom edge
+const ACCENT: u32 = 12;
+
+/// Font scales tried for the title, largest first, and its longest wrap
+const TITLE_SCALES: [u32; 4] = [10, 8, 6, 4];
+const TITLE_LINES: usize = 3;
+
+const SUBTITLE_SCALE: u32 = 4;
+const SUBTITLE_LINES: usize = 2;
+
+/// Render the card for the identicon of `hash`; `options` sets everything
+/// but the identicon's size, which the card decides
+pub fn render(
+    hash: &[u8],
+    options: &Options,
+    title: &str,
+    subtitle: Option<&str>,
+) -> Result<RgbImage> {
+    let side = HEIGHT - 2 * MARGIN;
+    let options = Options {
+        size: side,
+        height: None,
+        ..options.clone()
+    };
+    let identicon = Identicon::new(hash, &options)?;
+    let background = options.canvas_color();
+    let mut img = RgbImage::from_pixel(WIDTH, HEIGHT, background);
+    imageops::overlay(
+        &mut img,
+        identicon.as_rgb(),
+        i64::from(MARGIN),
+        i64::from(MARGIN),
+    );
+    for y in HEIGHT - ACCENT..HEIGHT {
+        for x in 0..WIDTH {
+            img.put_pixel(x, y, identicon.color());
+        }
+    }
+
+    let left = MARGIN + side + MARGIN;
+    let width = WIDTH - left - MARGIN;
+    // the largest title that fits whole, without breaking a word
+    let title_scale = TITLE_SCALES
+        .into_iter()
+        .find(|&scale| {
+            let fits = fits(width, scale);
+            let whole_words = title
+                .split_whitespace()
+                .all(|word| word.chars().count() <= fits);
+            whole_words && !wrap(title, fits, TITLE_LINES).1
+        })
+        .unwrap_or(TITLE_SCALES[TITLE_SCALES.len() - 1]);
+    let (title, _) = wrap(title, fits(width, title_scale), TITLE_LINES);
+    let (subtitle, _) = wrap(
+        subtitle.unwrap_or_default(),
+        fits(width, SUBTITLE_SCALE),
+        SUBTITLE_LINES,
+    );
+
+    let ink = overlay::contrasting_color(&img, left, MARGIN, width, side);
+    // the subtitle a step back from the title, toward the background
+    let muted = Rgb(std::array::from_fn(|i| {
+        ((3 * u16::from(ink[i]) + u16::from(background[i])) / 4) as u8
+    }));
+
+    // the two blocks centered together beside the identicon, spaced like
+    // the title's lines when both are there
+    let title_height = block_height(title.len(), title_scale);
+    let subtitle_height = block_height(subtitle.len(), SUBTITLE_SCALE);
+    let gap = if title.is_empty() || subtitle.is_empty() {
+        0
+    } else {
+        line_height(title_scale) - GLYPH_HEIGHT * title_scale
+    };
+    let total = title_height + gap + subtitle_height;
+    let top = MARGIN + side.saturating_sub(total) / 2;
+    for (row, line) in title.iter().enumerate() {
+        let y = top + row as u32 * line_height(title_scale);
+        font::draw_text(&mut img, line, left, y, title_scale, |_| ink);
+    }
+    let top = top + title_height + gap;
+    for (row, line) in subtitle.iter().enumerate() {
+        let y = top + row as u32 * line_height(SUBTITLE_SCALE);
+        font::draw_text(&mut img, line, left, y, SUBTITLE_SCALE, |_| muted);
+    }
+
+    Ok(img)
+}
+
+/// Characters of a line `width` pixels wide at `scale`
+fn fits(width: u32, scale: u32) -> usize {
+    // glyphs after the first each bring a blank column before them
+    ((width + scale) / ((GLYPH_WIDTH + 1) * scale)) as usize
+}
+
+/// Distance from the top of one line to the top of the next
+fn line_height(scale: u32) -> u32 {
+    (GLYPH_HEIGHT + 3) * scale
+}
+
+fn block_height(lines: usize, scale: u32) -> u32 {
+    match lines {
+        0 => 0,
+        lines => (lines as u32 - 1) * line_height(scale) + GLYPH_HEIGHT * scale,
+    }
+}
+
+/// Break `text` into lines of at most `fits` characters at its spaces, and
+/// words too long for a line wherever they hit the edge, keeping at most
+/// `max_lines`; whether the text was cut short comes back with the lines,
+/// the last of which then ends in dots
+fn wrap(text: &str, fits: usize, max_lines: usize) -> (Vec<String>, bool) {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        let used = line.chars().count();
+        if used > 0 && used + 1 + word.len() <= fits {
+            line.push(' ');
+            line.extend(&word);
+            continue;
+        }
+        if used > 0 {
+            lines.push(std::mem::take(&mut line));
+        }
+        while word.len() > fits {
+            lines.push(word.drain(..fits).collect());
+        }
+        line.extend(&word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    let cut = lines.len() > max_lines;
+    if cut {
+        lines.truncate(max_lines);
+        let last = &mut lines[max_lines - 1];
+        *last = last.chars().take(fits.saturating_sub(2)).collect();
+        last.push_str("..");
+    }
+    (lines, cut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check that `text` wraps to `expected`, cut short or not as `cut` says
+    fn wraps_to(text: &str, fits: usize, max_lines: usize, expected: &[&str], cut: bool) {
+        assert_eq!(
+            wrap(text, fits, max_lines),
+            (to_strings(expected), cut),
+            "{:?}",
+            text
+        );
+    }
+
+    fn to_strings(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn wraps_at_spaces() {
+        wraps_to(
+            "the quick brown fox jumps",
+            10,
+            3,
+            &["the quick", "brown fox", "jumps"],
+            false,
+        );
+        // a word that exactly fills the line stays on it
+        wraps_to("abc defghi", 10, 3, &["abc defghi"], false);
+    }
+
+    #[test]
+    fn collapses_whitespace() {
+        wraps_to("  a \t b\n\nc  ", 10, 3, &["a b c"], false);
+        wraps_to("", 10, 3, &[], false);
+        wraps_to("   ", 10, 3, &[], false);
+    }
+
+    #[test]
+    fn breaks_words_longer_than_a_line() {
+        wraps_to("abcdefghijkl mn", 5, 4, &["abcde", "fghij", "kl mn"], false);
+        wraps_to("abcdefghij", 5, 4, &["abcde", "fghij"], false);
+    }
+
+    #[test]
+    fn cuts_short_with_dots() {
+        wraps_to(
+            "one two three four five",
+            9,
+            2,
+            &["one two", "three.."],
+            true,
+        );
+        // the dots replace the end of a full last line
+        wraps_to(
+            "abcdefghij klmnopqrst uvw",
+            10,
+            2,
+            &["abcdefghij", "klmnopqr.."],
+            true,
+        );
+    }
+
+    #[test]
+    fn counts_characters_not_bytes() {
+        wraps_to("äöü äöü", 7, 1, &["äöü äöü"], false);
+        wraps_to("ääääää", 3, 1, &["ä.."], true);
+    }
+
+    #[test]
+    fn fits_counts_the_gaps_between_glyphs() {
+        // two glyphs: 5 + 1 + 5 columns
+        assert_eq!(fits(11, 1), 2);
+        assert_eq!(fits(10, 1), 1);
+        assert_eq!(fits(22, 2), 2);
+    }
+}
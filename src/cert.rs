@@ -0,0 +1,234 @@
+//! Minimal X.509 parsing to locate a certificate's SubjectPublicKeyInfo
+
+use crate::error::{HashdenticonError, Result};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_INTEGER: u8 = 0x02;
+const TAG_VERSION: u8 = 0xa0;
+
+/// Extract the DER-encoded SPKI from the first certificate in a PEM or DER file
+pub fn spki_from_bytes(data: &[u8]) -> Result<Vec<u8>> {
+    let der = match std::str::from_utf8(data) {
+        Ok(text) if text.contains("-----BEGIN") => decode_pem(text)?,
+        _ => data.to_vec(),
+    };
+
+    // Certificate ::= SEQUENCE { tbsCertificate, signatureAlgorithm, signature }
+    let (certificate, _) = read_tlv(&der, TAG_SEQUENCE)?;
+    let (tbs, _) = read_tlv(certificate.contents, TAG_SEQUENCE)?;
+
+    // skip version (optional), serialNumber, signature, issuer, validity, subject
+    let mut rest = tbs.contents;
+    if rest.first() == Some(&TAG_VERSION) {
+        rest = read_tlv(rest, TAG_VERSION)?.1;
+    }
+    rest = read_tlv(rest, TAG_INTEGER)
+        .map_err(|_| malformed("Missing serial number"))?
+        .1;
+    for field in ["signature", "issuer", "validity", "subject"] {
+        rest = read_tlv(rest, TAG_SEQUENCE)
+            .map_err(|_| malformed(format!("Missing {} field", field)))?
+            .1;
+    }
+
+    let (spki, _) =
+        read_tlv(rest, TAG_SEQUENCE).map_err(|_| malformed("Missing subjectPublicKeyInfo"))?;
+    Ok(spki.raw.to_vec())
+}
+
+fn decode_pem(text: &str) -> Result<Vec<u8>> {
+    let begin = "-----BEGIN CERTIFICATE-----";
+    let end = "-----END CERTIFICATE-----";
+
+    let start = text
+        .find(begin)
+        .ok_or_else(|| malformed("No certificate found in PEM file"))?
+        + begin.len();
+    let stop = text[start..]
+        .find(end)
+        .ok_or_else(|| malformed("Unterminated PEM certificate"))?
+        + start;
+
+    let body: String = text[start..stop]
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    STANDARD
+        .decode(body)
+        .map_err(|_| malformed("Invalid base64 in PEM certificate"))
+}
+
+struct Tlv<'a> {
+    /// Full encoding including tag and length
+    raw: &'a [u8],
+    contents: &'a [u8],
+}
+
+/// Read one DER element with the expected tag, returning it and the remaining input
+fn read_tlv(input: &[u8], tag: u8) -> Result<(Tlv<'_>, &[u8])> {
+    let (&actual, rest) = input
+        .split_first()
+        .ok_or_else(|| malformed("Unexpected end of data"))?;
+    if actual != tag {
+        return Err(malformed(format!(
+            "Expected DER tag {:#04x}, found {:#04x}",
+            tag, actual
+        )));
+    }
+
+    let (&first, mut rest) = rest
+        .split_first()
+        .ok_or_else(|| malformed("Missing DER length"))?;
+    let len = if first & 0x80 == 0 {
+        first as usize
+    } else {
+        // long form: low bits give the number of length bytes
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return Err(malformed("Unsupported DER length encoding"));
+        }
+        let len = rest[..count]
+            .iter()
+            .fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        rest = &rest[count..];
+        len
+    };
+
+    if rest.len() < len {
+        return Err(malformed("DER element is truncated"));
+    }
+
+    let header_len = input.len() - rest.len();
+    Ok((
+        Tlv {
+            raw: &input[..header_len + len],
+            contents: &rest[..len],
+        },
+        &rest[len..],
+    ))
+}
+
+fn malformed(reason: impl Into<String>) -> HashdenticonError {
+    HashdenticonError::InvalidCertificate(reason.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    /// A self-signed P-256 certificate
+    const PEM: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBjjCCATOgAwIBAgIUPORmSNx8axz9l8+bRBWOVlANb10wCgYIKoZIzj0EAwIw
+HDEaMBgGA1UEAwwRaGFzaGRlbnRpY29uIHRlc3QwHhcNMjYxMDE0MTkwMDIwWhcN
+MzYxMDExMTkwMDIwWjAcMRowGAYDVQQDDBFoYXNoZGVudGljb24gdGVzdDBZMBMG
+ByqGSM49AgEGCCqGSM49AwEHA0IABLG1534iSe2dJmbLBepDLJNONour5MCHBdHm
+PGxWHX7Gs9+Y5bEIAE29C59iR6kWBZ+p9GaH+q2hWdgJRWeUFi6jUzBRMB0GA1Ud
+DgQWBBQAnjx4ijbluA7X3jZsoIZkmH9ACzAfBgNVHSMEGDAWgBQAnjx4ijbluA7X
+3jZsoIZkmH9ACzAPBgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA0kAMEYCIQDL
+GslomsLNZ2XzCa5hF8if9SWIJ75mAvsJWnkLISMtjAIhAKdr3cQFgT/x/K1/9Ve2
+65PiTC727yw3ie8kPnlslOBv
+-----END CERTIFICATE-----
+";
+    /// `openssl x509 -pubkey -noout | openssl pkey -pubin -outform der |
+    /// openssl dgst -sha256 -binary | base64` of `PEM`
+    const PIN: &str = "TyhfK4OTvbrUAYq3QVzG/Zagteq9o5gXut6zLdMEJi8=";
+
+    fn reason(result: Result<impl Sized>) -> String {
+        match result {
+            Err(HashdenticonError::InvalidCertificate(reason)) => reason,
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn spki_matches_the_openssl_pin() {
+        let spki = spki_from_bytes(PEM.as_bytes()).unwrap();
+        assert_eq!(STANDARD.encode(Sha256::digest(&spki)), PIN);
+    }
+
+    #[test]
+    fn der_and_pem_give_the_same_spki() {
+        let der = decode_pem(PEM).unwrap();
+        assert_eq!(
+            spki_from_bytes(&der).unwrap(),
+            spki_from_bytes(PEM.as_bytes()).unwrap()
+        );
+    }
+
+    #[test]
+    fn truncated_certificates_are_an_error() {
+        let der = decode_pem(PEM).unwrap();
+        for len in [0, 1, 2, 4, 100, der.len() - 1] {
+            assert!(spki_from_bytes(&der[..len]).is_err(), "{} bytes", len);
+        }
+        assert_eq!(
+            reason(read_tlv(&[0x30], TAG_SEQUENCE)),
+            "Missing DER length"
+        );
+        assert_eq!(
+            reason(read_tlv(&[0x30, 0x03, 1, 2], TAG_SEQUENCE)),
+            "DER element is truncated"
+        );
+        assert_eq!(
+            reason(read_tlv(&[0x30, 0x82, 0x01], TAG_SEQUENCE)),
+            "Unsupported DER length encoding"
+        );
+    }
+
+    #[test]
+    fn reads_long_form_lengths() {
+        let (tlv, rest) = read_tlv(&[0x30, 0x81, 0x03, 1, 2, 3, 9], TAG_SEQUENCE).unwrap();
+        assert_eq!(tlv.contents, [1, 2, 3]);
+        assert_eq!(tlv.raw, [0x30, 0x81, 0x03, 1, 2, 3]);
+        assert_eq!(rest, [9]);
+
+        let mut input = vec![0x30, 0x82, 0x01, 0x00];
+        input.extend([7; 256]);
+        let (tlv, rest) = read_tlv(&input, TAG_SEQUENCE).unwrap();
+        assert_eq!(tlv.contents.len(), 256);
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn rejects_a_wrong_outer_tag() {
+        let mut der = decode_pem(PEM).unwrap();
+        der[0] = 0x31;
+        assert_eq!(
+            reason(spki_from_bytes(&der)),
+            "Expected DER tag 0x30, found 0x31"
+        );
+    }
+
+    #[test]
+    fn rejects_oversized_and_indefinite_lengths() {
+        // five length bytes, and the BER-only indefinite form
+        let oversized = [0x30, 0x85, 0, 0, 0, 0, 1, 0];
+        assert_eq!(
+            reason(read_tlv(&oversized, TAG_SEQUENCE)),
+            "Unsupported DER length encoding"
+        );
+        assert_eq!(
+            reason(read_tlv(&[0x30, 0x80, 0, 0], TAG_SEQUENCE)),
+            "Unsupported DER length encoding"
+        );
+    }
+
+    #[test]
+    fn pem_needs_an_end_line() {
+        let unterminated = PEM.replace("-----END CERTIFICATE-----", "");
+        assert_eq!(
+            reason(spki_from_bytes(unterminated.as_bytes())),
+            "Unterminated PEM certificate"
+        );
+        let garbled = PEM.replace("MIIB", "M!IB");
+        assert_eq!(
+            reason(spki_from_bytes(garbled.as_bytes())),
+            "Invalid base64 in PEM certificate"
+        );
+    }
+}
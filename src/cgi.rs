@@ -0,0 +1,61 @@
+//! `hashdenticon cgi`: answer one request of the serve endpoints from the
+//! CGI environment, for web servers that run a program per request
+
+use crate::serve::{self, Config, Incoming};
+use anyhow::{Context, Result};
+use std::io::{Read, Write};
+use tiny_http::StatusCode;
+
+pub fn run(config: &Config) -> Result<()> {
+    let var = |name: &str| std::env::var(name).unwrap_or_default();
+    let path = match var("PATH_INFO") {
+        path if path.is_empty() => "/".to_string(),
+        path => path,
+    };
+    let url = match var("QUERY_STRING") {
+        query if query.is_empty() => path,
+        query => format!("{}?{}", path, query),
+    };
+
+    // request headers arrive as HTTP_* variables, apart from the body's type
+    let mut headers: Vec<(String, String)> = std::env::vars()
+        .filter_map(|(name, value)| {
+            let field = name.strip_prefix("HTTP_")?.replace('_', "-");
+            Some((field, value))
+        })
+        .collect();
+    if let Ok(content_type) = std::env::var("CONTENT_TYPE") {
+        headers.push(("Content-Type".to_string(), content_type));
+    }
+
+    let length: u64 = var("CONTENT_LENGTH").parse().unwrap_or(0);
+    let reply = match serve::read_body(std::io::stdin().take(length)) {
+        Ok(body) => {
+            let incoming = Incoming {
+                method: var("REQUEST_METHOD"),
+                url,
+                headers,
+                body,
+            };
+            serve::answer(&incoming, config)
+        }
+        Err(err) => serve::error_reply(&err),
+    };
+
+    let mut head = format!(
+        "Status: {} {}\r\n",
+        reply.status,
+        StatusCode(reply.status).default_reason_phrase()
+    );
+    for (field, value) in &reply.headers {
+        head.push_str(&format!("{}: {}\r\n", field, value));
+    }
+    head.push_str("\r\n");
+
+    let mut stdout = std::io::stdout().lock();
+    stdout
+        .write_all(head.as_bytes())
+        .and_then(|()| stdout.write_all(&reply.body))
+        .and_then(|()| stdout.flush())
+        .context("Failed to write response")
+}
@@ -0,0 +1,192 @@
+//! Color space helpers
+
+use image::Rgb;
+
+/// Convert HSL to RGB using integer math only
+///
+/// `hue` is in 0..1536 (six sectors of 256), `saturation` and `lightness`
+/// are in 0..=255.
+pub fn hsl_to_rgb(hue: u32, saturation: u32, lightness: u32) -> Rgb<u8> {
+    let hue = hue % 1536;
+
+    // chroma = (1 - |2L - 1|) * S
+    let chroma = (255 - (2 * lightness as i32 - 255).unsigned_abs()) * saturation / 255;
+
+    // secondary component rises or falls linearly within each sector
+    let sector = hue / 256;
+    let frac = hue % 256;
+    let ramp = if sector.is_multiple_of(2) {
+        frac
+    } else {
+        255 - frac
+    };
+    let x = chroma * ramp / 255;
+
+    let (r, g, b) = match sector {
+        0 => (chroma, x, 0),
+        1 => (x, chroma, 0),
+        2 => (0, chroma, x),
+        3 => (0, x, chroma),
+        4 => (x, 0, chroma),
+        _ => (chroma, 0, x),
+    };
+
+    let m = lightness.saturating_sub(chroma / 2);
+    Rgb([(r + m) as u8, (g + m) as u8, (b + m) as u8])
+}
+
+/// Convert RGB to HSL on the same integer scales as [`hsl_to_rgb`]
+pub fn rgb_to_hsl(color: Rgb<u8>) -> (u32, u32, u32) {
+    let [r, g, b] = color.0.map(i32::from);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let chroma = max - min;
+    let lightness = (max + min) / 2;
+    if chroma == 0 {
+        return (0, 0, lightness as u32);
+    }
+
+    let saturation = chroma * 255 / (255 - (max + min - 255).abs()).max(1);
+    let hue = if max == r {
+        (g - b) * 256 / chroma
+    } else if max == g {
+        (b - r) * 256 / chroma + 512
+    } else {
+        (r - g) * 256 / chroma + 1024
+    };
+
+    (
+        hue.rem_euclid(1536) as u32,
+        saturation.min(255) as u32,
+        lightness as u32,
+    )
+}
+
+/// Rotate the hue of `color` by `degrees`, keeping saturation and lightness
+pub fn shift_hue(color: Rgb<u8>, degrees: u32) -> Rgb<u8> {
+    if degrees.is_multiple_of(360) {
+        return color;
+    }
+    let (hue, saturation, lightness) = rgb_to_hsl(color);
+    hsl_to_rgb(hue + degrees % 360 * 1536 / 360, saturation, lightness)
+}
+
+/// Parse `#rrggbb`, with or without the `#`
+pub fn parse_hex(text: &str) -> Option<Rgb<u8>> {
+    let hex = text.trim().strip_prefix('#').unwrap_or(text.trim());
+    // from_str_radix takes a sign too, so check each digit first
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |idx: usize| u8::from_str_radix(&hex[idx..idx + 2], 16).ok();
+    Some(Rgb([channel(0)?, channel(2)?, channel(4)?]))
+}
+
+/// Colors as `#rrggbb` strings in serialized options and models
+#[cfg(feature = "serde")]
+pub(crate) mod hex {
+    use image::Rgb;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(color: &Rgb<u8>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format(*color))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Rgb<u8>, D::Error> {
+        parse(&String::deserialize(deserializer)?)
+    }
+
+    fn format(color: Rgb<u8>) -> String {
+        let [r, g, b] = color.0;
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+
+    fn parse<E: Error>(text: &str) -> Result<Rgb<u8>, E> {
+        super::parse_hex(text).ok_or_else(|| E::custom(format!("invalid color {:?}", text)))
+    }
+
+    pub mod option {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(
+            color: &Option<Rgb<u8>>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match color {
+                Some(color) => serializer.serialize_some(&format(*color)),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Rgb<u8>>, D::Error> {
+            Option::<String>::deserialize(deserializer)?
+                .map(|text| parse(&text))
+                .transpose()
+        }
+    }
+
+    pub mod list {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(
+            colors: &[Rgb<u8>],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(colors.iter().map(|color| format(*color)))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<Rgb<u8>>, D::Error> {
+            Vec::<String>::deserialize(deserializer)?
+                .iter()
+                .map(|text| parse(text))
+                .collect()
+        }
+    }
+
+    pub mod option_list {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(
+            colors: &Option<Vec<Rgb<u8>>>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match colors {
+                Some(colors) => serializer
+                    .serialize_some(&colors.iter().map(|c| format(*c)).collect::<Vec<_>>()),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Vec<Rgb<u8>>>, D::Error> {
+            Option::<Vec<String>>::deserialize(deserializer)?
+                .map(|colors| colors.iter().map(|text| parse(text)).collect())
+                .transpose()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse_hex("#0a1B2c"), Some(Rgb([0x0a, 0x1b, 0x2c])));
+        assert_eq!(parse_hex(" ffffff "), Some(Rgb([255, 255, 255])));
+    }
+
+    #[test]
+    fn rejects_signs_and_other_non_digits() {
+        for text in [
+            "+1+2+3", "#+1+2+3", "-1-2-3", "12345g", "#fff", "#1234567", "é12345",
+        ] {
+            assert_eq!(parse_hex(text), None, "{:?}", text);
+        }
+    }
+}
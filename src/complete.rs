@@ -0,0 +1,73 @@
+//! Shell completions, answered by the binary itself as the shell asks, so
+//! named presets, themes and style packs complete as they are added
+
+use crate::dirs;
+use anyhow::{Context, Result};
+use clap_complete::engine::ValueCompleter;
+use clap_complete::env::Shells;
+use clap_complete::{CompletionCandidate, PathCompleter, Shell};
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// Variable the registered shell function sets to ask for completions
+pub const VAR: &str = "COMPLETE";
+const BIN: &str = "hashdenticon";
+
+/// Write the script that hooks `shell` up to the binary's completions
+pub fn write_registration(shell: Shell) -> Result<()> {
+    let name = shell.to_string();
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(&name)
+        .with_context(|| format!("No completions for {}", name))?;
+    let mut stdout = std::io::stdout().lock();
+    completer
+        .write_registration(VAR, BIN, BIN, BIN, &mut stdout)
+        .context("Failed to write completions")
+}
+
+/// --preset values: files, and presets saved in the config directory
+pub fn presets(current: &OsStr) -> Vec<CompletionCandidate> {
+    let saved = dirs::config().map(|dir| dir.join("presets"));
+    named_or_files(current, saved.as_deref(), "preset")
+}
+
+/// --theme-file values: files, and themes saved in the config directory
+pub fn themes(current: &OsStr) -> Vec<CompletionCandidate> {
+    let saved = dirs::config().map(|dir| dir.join("themes"));
+    named_or_files(current, saved.as_deref(), "theme")
+}
+
+/// --parts-dir values: directories, and style packs in the data directory
+pub fn style_packs(current: &OsStr) -> Vec<CompletionCandidate> {
+    let mut candidates = entries(dirs::data().map(|dir| dir.join("parts")).as_deref())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| candidate(&path, current, "style pack"))
+        .collect::<Vec<_>>();
+    candidates.extend(PathCompleter::dir().complete(current));
+    candidates
+}
+
+fn named_or_files(current: &OsStr, saved: Option<&Path>, kind: &str) -> Vec<CompletionCandidate> {
+    let mut candidates = entries(saved)
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .filter_map(|path| candidate(&path.with_extension(""), current, kind))
+        .collect::<Vec<_>>();
+    candidates.extend(PathCompleter::file().complete(current));
+    candidates
+}
+
+fn entries(dir: Option<&Path>) -> impl Iterator<Item = std::path::PathBuf> {
+    dir.and_then(|dir| std::fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+}
+
+/// The file name of `path`, if it completes what has been typed so far
+fn candidate(path: &Path, current: &OsStr, kind: &str) -> Option<CompletionCandidate> {
+    let name = path.file_name()?.to_str()?;
+    let typed = current.to_str()?;
+    name.starts_with(typed)
+        .then(|| CompletionCandidate::new(name).help(Some(format!("saved {}", kind).into())))
+}
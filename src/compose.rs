@@ -0,0 +1,52 @@
+//! Placing an identicon onto an existing image, e.g. a report header
+
+use crate::identicon::{MAX_SIZE, MIN_SIZE};
+use image::{RgbImage, imageops};
+
+/// Where on the base image the identicon goes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Position {
+    #[default]
+    Center,
+    /// Top left corner
+    Tl,
+    /// Top right corner
+    Tr,
+    /// Bottom left corner
+    Bl,
+    /// Bottom right corner
+    Br,
+}
+
+/// Identicon side length covering `scale` percent of the shorter side of a
+/// `width` x `height` base image
+pub fn side_for(width: u32, height: u32, scale: u32) -> u32 {
+    (width.min(height) as u64 * scale as u64 / 100).clamp(MIN_SIZE as u64, MAX_SIZE as u64) as u32
+}
+
+/// Draw `identicon` over `base` at `position`, inset from the corners by an
+/// eighth of its size
+pub fn compose(base: &mut RgbImage, identicon: &RgbImage, position: Position) {
+    let (width, height) = base.dimensions();
+    let (w, h) = identicon.dimensions();
+    let margin = w.min(h) / 8;
+
+    let left = margin as i64;
+    let top = margin as i64;
+    let right = width as i64 - w as i64 - margin as i64;
+    let bottom = height as i64 - h as i64 - margin as i64;
+    let (x, y) = match position {
+        Position::Center => (
+            (width as i64 - w as i64) / 2,
+            (height as i64 - h as i64) / 2,
+        ),
+        Position::Tl => (left, top),
+        Position::Tr => (right, top),
+        Position::Bl => (left, bottom),
+        Position::Br => (right, bottom),
+    };
+
+    // overlay clips whatever falls outside the base
+    imageops::overlay(base, identicon, x, y);
+}
@@ -0,0 +1,329 @@
+//! Deterministic touch-ups applied to a pattern after it is generated
+
+use crate::identicon::Options;
+use crate::rng::SplitMix64;
+use crate::tile;
+
+/// Apply the touch-ups `options` asks for to a freshly generated `pattern`
+///
+/// Fill normalization runs after the passes that set particular cells, and
+/// keeps to them, so the fill it leaves is the final one. Symbol filtering
+/// runs last, so no other pass can put a filtered shape back; where it
+/// clears a cell `force_center` filled, or takes the fill under
+/// `min_fill`, the filter wins.
+pub fn apply(mut pattern: Vec<Vec<bool>>, hash: &[u8], options: &Options) -> Vec<Vec<bool>> {
+    if options.clear_border {
+        clear_border(&mut pattern);
+    }
+    if options.force_center {
+        force_center(&mut pattern);
+    }
+    if options.tileable {
+        pattern = tile::wrap_symmetric(pattern);
+    }
+    if options.min_fill.is_some() || options.max_fill.is_some() {
+        normalize_fill(&mut pattern, hash, options);
+    }
+    if options.filter_symbols {
+        filter_symbols(&mut pattern, options.tileable);
+    }
+    pattern
+}
+
+/// Cells of the left half (and center column), as `(y, x)`, ordered by a
+/// hash-seeded score from highest to lowest
+pub fn ranked_half_cells(hash: &[u8], size: usize) -> Vec<(usize, usize)> {
+    let half_width = size.div_ceil(2);
+    let mut rng = SplitMix64::from_bytes(hash);
+    let mut cells: Vec<(u64, usize, usize)> = (0..size)
+        .flat_map(|y| (0..half_width).map(move |x| (y, x)))
+        .map(|(y, x)| (rng.next_u64(), y, x))
+        .collect();
+    cells.sort_unstable_by(|a, b| b.cmp(a));
+    cells.into_iter().map(|(_, y, x)| (y, x)).collect()
+}
+
+/// Bring the filled share of the grid within `min_fill..=max_fill` percent,
+/// filling the highest ranked empty cells or clearing the lowest ranked
+/// filled ones, keeping the mirror symmetry, and with `tileable` the top to
+/// bottom one too. The border stays empty with `clear_border` and the
+/// center filled with `force_center`, even if that leaves the share short
+/// of the range.
+pub fn normalize_fill(pattern: &mut [Vec<bool>], hash: &[u8], options: &Options) {
+    let min = options.min_fill.unwrap_or(0) as usize;
+    let max = options.max_fill.unwrap_or(100) as usize;
+    let size = pattern.len();
+    let total = size * size;
+    let filled = |pattern: &[Vec<bool>]| pattern.iter().flatten().filter(|&&c| c).count();
+    let set = |pattern: &mut [Vec<bool>], (y, x): (usize, usize), value: bool| {
+        let rows = if options.tileable {
+            vec![y, size - 1 - y]
+        } else {
+            vec![y]
+        };
+        for row in rows {
+            pattern[row][x] = value;
+            pattern[row][size - 1 - x] = value;
+        }
+    };
+    let on_border = |(y, x): (usize, usize)| y == 0 || x == 0 || y + 1 == size || x + 1 == size;
+    let in_center = |(y, x): (usize, usize)| {
+        let center = (size.saturating_sub(1) / 2)..=(size / 2);
+        center.contains(&y) && center.contains(&x)
+    };
+    let ranked = ranked_half_cells(hash, size);
+
+    // compare as count * 100 against percent * total to stay in integers
+    for &cell in &ranked {
+        if filled(pattern) * 100 >= min * total {
+            break;
+        }
+        if !(options.clear_border && on_border(cell)) {
+            set(pattern, cell, true);
+        }
+    }
+    for &cell in ranked.iter().rev() {
+        if filled(pattern) * 100 <= max * total {
+            break;
+        }
+        if !(options.force_center && in_center(cell)) {
+            set(pattern, cell, false);
+        }
+    }
+}
+
+/// Fill the center cell, or the center 2x2 block of an even grid
+pub fn force_center(pattern: &mut [Vec<bool>]) {
+    let size = pattern.len();
+    if size == 0 {
+        return;
+    }
+    let low = (size - 1) / 2;
+    let high = size / 2;
+    for row in &mut pattern[low..=high] {
+        row[low..=high].fill(true);
+    }
+}
+
+/// Empty the outer ring of cells
+pub fn clear_border(pattern: &mut [Vec<bool>]) {
+    let size = pattern.len();
+    for (y, row) in pattern.iter_mut().enumerate() {
+        if y == 0 || y + 1 == size {
+            row.fill(false);
+        } else if let Some(last) = row.len().checked_sub(1) {
+            row[0] = false;
+            row[last] = false;
+        }
+    }
+}
+
+/// Shapes that shouldn't turn up on a public avatar, as rows of `#` (filled)
+/// and `.` (empty); each is also matched rotated and mirrored
+const SYMBOLS: &[[&str; 5]] = &[
+    // swastika-like hooked cross
+    ["#.###", "#.#..", "#####", "..#.#", "###.#"],
+];
+
+/// Clear the center cell of any window matching a listed symbol, along with
+/// its mirror twin, and with `vertical` its twins in the mirrored bottom
+/// half too, until none match
+pub fn filter_symbols(pattern: &mut [Vec<bool>], vertical: bool) {
+    let size = pattern.len();
+    let templates: Vec<[[bool; 5]; 5]> = SYMBOLS.iter().flat_map(variants).collect();
+
+    // each pass clears at least one filled cell, so this terminates
+    while let Some((y, x)) = find_symbol(pattern, &templates) {
+        let (cy, cx) = (y + 2, x + 2);
+        let rows = if vertical {
+            vec![cy, size - 1 - cy]
+        } else {
+            vec![cy]
+        };
+        for row in rows {
+            pattern[row][cx] = false;
+            pattern[row][size - 1 - cx] = false;
+        }
+    }
+}
+
+/// Top left corner of the first window matching any template
+fn find_symbol(pattern: &[Vec<bool>], templates: &[[[bool; 5]; 5]]) -> Option<(usize, usize)> {
+    let size = pattern.len();
+    if size < 5 {
+        return None;
+    }
+    for y in 0..=size - 5 {
+        for x in 0..=size - 5 {
+            let matches = |template: &[[bool; 5]; 5]| {
+                (0..5).all(|dy| (0..5).all(|dx| pattern[y + dy][x + dx] == template[dy][dx]))
+            };
+            if templates.iter().any(matches) {
+                return Some((y, x));
+            }
+        }
+    }
+    None
+}
+
+/// The four rotations of a template and of its mirror image
+fn variants(rows: &[&str; 5]) -> Vec<[[bool; 5]; 5]> {
+    let base: [[bool; 5]; 5] =
+        std::array::from_fn(|y| std::array::from_fn(|x| rows[y].as_bytes()[x] == b'#'));
+    let mirrored: [[bool; 5]; 5] = std::array::from_fn(|y| std::array::from_fn(|x| base[y][4 - x]));
+
+    let mut out = Vec::new();
+    for mut grid in [base, mirrored] {
+        for _ in 0..4 {
+            out.push(grid);
+            grid = std::array::from_fn(|y| std::array::from_fn(|x| grid[4 - x][y]));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn templates() -> Vec<[[bool; 5]; 5]> {
+        SYMBOLS.iter().flat_map(variants).collect()
+    }
+
+    /// A `size` grid holding the first template with its top left at (`y`, `x`)
+    fn with_symbol(size: usize, y: usize, x: usize) -> Vec<Vec<bool>> {
+        let template = templates()[0];
+        let mut pattern = vec![vec![false; size]; size];
+        for dy in 0..5 {
+            for dx in 0..5 {
+                pattern[y + dy][x + dx] = template[dy][dx];
+            }
+        }
+        pattern
+    }
+
+    #[test]
+    fn force_center_cannot_restore_a_filtered_symbol() {
+        // the window's center (2, 2) is inside the 2x2 center block of a 6 grid
+        let pattern = with_symbol(6, 0, 0);
+        assert!(find_symbol(&pattern, &templates()).is_some());
+        let options = Options {
+            grid_size: 6,
+            force_center: true,
+            filter_symbols: true,
+            ..Options::default()
+        };
+        let pattern = apply(pattern, &[0; 32], &options);
+        assert_eq!(find_symbol(&pattern, &templates()), None);
+    }
+
+    /// The filled share of the final pattern of `seed`, in percent
+    fn fill(seed: &str, options: &Options) -> usize {
+        let identicon = crate::Identicon::from_seed(seed, options).unwrap();
+        let rows = identicon.pattern().rows();
+        let filled = rows.iter().flatten().filter(|&&c| c).count();
+        filled * 100 / (rows.len() * rows.len())
+    }
+
+    #[test]
+    fn the_final_pattern_keeps_to_the_fill_range() {
+        for (clear_border, force_center, tileable) in [
+            (true, false, false),
+            (false, true, false),
+            (false, false, true),
+            (true, true, true),
+        ] {
+            let options = Options {
+                size: 70,
+                grid_size: 7,
+                min_fill: Some(30),
+                max_fill: Some(50),
+                clear_border,
+                force_center,
+                tileable,
+                ..Options::default()
+            };
+            for n in 0..64 {
+                let seed = format!("seed {}", n);
+                let fill = fill(&seed, &options);
+                assert!((30..=50).contains(&fill), "{} at {}%", seed, fill);
+            }
+        }
+    }
+
+    #[test]
+    fn normalizing_keeps_the_border_and_center() {
+        let options = Options {
+            grid_size: 7,
+            min_fill: Some(80),
+            max_fill: Some(100),
+            clear_border: true,
+            ..Options::default()
+        };
+        let pattern = apply(vec![vec![false; 7]; 7], &[3; 32], &options);
+        assert!(pattern[0].iter().chain(&pattern[6]).all(|&c| !c));
+        assert!(pattern.iter().all(|row| !row[0] && !row[6]));
+        assert!(pattern[1..6].iter().all(|row| row[1..6].iter().all(|&c| c)));
+
+        let options = Options {
+            grid_size: 6,
+            max_fill: Some(0),
+            force_center: true,
+            ..Options::default()
+        };
+        let pattern = apply(vec![vec![true; 6]; 6], &[3; 32], &options);
+        let filled: Vec<(usize, usize)> = (0..6)
+            .flat_map(|y| (0..6).map(move |x| (y, x)))
+            .filter(|&(y, x)| pattern[y][x])
+            .collect();
+        assert_eq!(filled, [(2, 2), (2, 3), (3, 2), (3, 3)]);
+    }
+
+    #[test]
+    fn tiling_cannot_restore_a_filtered_symbol() {
+        // mirroring the top rows onto the bottom copies the symbol down
+        let pattern = with_symbol(11, 0, 3);
+        let options = Options {
+            grid_size: 11,
+            tileable: true,
+            filter_symbols: true,
+            ..Options::default()
+        };
+        let pattern = apply(pattern, &[0; 32], &options);
+        assert_eq!(find_symbol(&pattern, &templates()), None);
+        assert!((0..11).all(|y| pattern[y] == pattern[10 - y]));
+    }
+
+    #[test]
+    fn every_automaton_generation_is_filtered() {
+        let options = Options {
+            size: 110,
+            grid_size: 11,
+            style: crate::Style::Automaton,
+            steps: 6,
+            ..Options::default()
+        };
+        let streams = crate::derive::Streams::new(&[7; 32], options.derivation);
+        let color = image::Rgb([40, 80, 160]);
+        let start = with_symbol(11, 2, 3);
+
+        let unfiltered =
+            crate::Identicon::evolve(&streams, color, start.clone(), &options).unwrap();
+        assert!(find_symbol(unfiltered[0].pattern().rows(), &templates()).is_some());
+
+        let options = Options {
+            filter_symbols: true,
+            ..options
+        };
+        let generations = crate::Identicon::evolve(&streams, color, start, &options).unwrap();
+        assert_eq!(generations.len(), 7);
+        for (step, frame) in generations.iter().enumerate() {
+            assert_eq!(
+                find_symbol(frame.pattern().rows(), &templates()),
+                None,
+                "step {}",
+                step
+            );
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! Recover the pattern model from a rendered grid identicon
+
+use crate::error::{HashdenticonError, Result};
+use crate::identicon::MIN_SIZE;
+use crate::model::Model;
+use crate::style::Style;
+use image::{Rgb, RgbImage};
+use std::collections::HashMap;
+
+/// Grid geometry found in an image
+#[derive(Debug, Clone, Copy)]
+pub struct Geometry {
+    pub grid_size: u32,
+    pub padding_percent: u32,
+    pub cell_size: u32,
+    /// Fraction of pixels that disagree with the reconstructed pattern
+    pub error: f64,
+}
+
+impl Geometry {
+    /// `error` as a percentage
+    pub fn error_percent(&self) -> f64 {
+        self.error * 100.0
+    }
+}
+
+/// Detect grid size and padding by trying every combination the renderer
+/// can produce and keeping the one that explains the image best, then read
+/// each cell by majority vote. Recompressed or resampled images still
+/// decode, as long as the cells survive.
+#[tracing::instrument(level = "debug", skip(img))]
+pub fn decode(img: &RgbImage, seed: &str) -> Result<(Model, Geometry)> {
+    let (width, height) = img.dimensions();
+    if width != height {
+        return Err(HashdenticonError::DecodeError(format!(
+            "expected a square image, got {}x{}",
+            width, height
+        )));
+    }
+    if width < MIN_SIZE {
+        return Err(HashdenticonError::DecodeError(format!(
+            "expected at least {0}x{0} pixels, the smallest identicon, got {1}x{1}",
+            MIN_SIZE, width
+        )));
+    }
+
+    let background = border_color(img);
+    let Some(color) = dominant_color(img, background) else {
+        return Err(HashdenticonError::DecodeError(
+            "image is a single flat color".to_string(),
+        ));
+    };
+
+    // classify each pixel by the nearer of the two colors
+    let table = SummedArea::new(img, |pixel| {
+        distance(pixel, color) < distance(pixel, background)
+    });
+    let total_ink = table.sum(0, 0, width, height);
+
+    let mut best: Option<Geometry> = None;
+    let mut candidates = Vec::new();
+    for grid_size in 3..=15 {
+        for padding_percent in 0..=25 {
+            let layout = Layout::new(width, grid_size, padding_percent);
+            if layout.cell_size == 0 {
+                continue;
+            }
+
+            // ink outside the grid and the minority within each cell is error
+            let mut inside = 0;
+            let mut wrong = 0;
+            for row in 0..grid_size {
+                for col in 0..grid_size {
+                    let (x, y) = layout.origin(col, row);
+                    let ink = table.sum(x, y, layout.cell_size, layout.cell_size);
+                    inside += ink;
+                    wrong += ink.min(layout.cell_size.pow(2) - ink);
+                }
+            }
+            let error = f64::from(wrong + total_ink - inside) / f64::from(width * height);
+            let geometry = Geometry {
+                grid_size,
+                padding_percent,
+                cell_size: layout.cell_size,
+                error,
+            };
+            candidates.push(geometry);
+            if best.is_none_or(|best| error < best.error) {
+                best = Some(geometry);
+            }
+        }
+    }
+
+    // a grid of half-size cells explains an image just as well, so take
+    // the coarsest grid that is about as good as the best
+    let best = best.expect("at least one layout fits");
+    let tolerance = best.error + 0.001;
+    let geometry = candidates
+        .into_iter()
+        .filter(|candidate| candidate.error <= tolerance)
+        .min_by_key(|candidate| (candidate.grid_size, candidate.padding_percent))
+        .expect("best is within tolerance");
+
+    let layout = Layout::new(width, geometry.grid_size, geometry.padding_percent);
+    let pattern = (0..geometry.grid_size)
+        .map(|row| {
+            (0..geometry.grid_size)
+                .map(|col| {
+                    let (x, y) = layout.origin(col, row);
+                    2 * table.sum(x, y, layout.cell_size, layout.cell_size)
+                        > layout.cell_size.pow(2)
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut model = Model::new(seed, Style::Grid, color, pattern);
+    model.background = background;
+    Ok((model, geometry))
+}
+
+/// The same cell layout `render_grid` uses
+struct Layout {
+    cell_size: u32,
+    offset: u32,
+}
+
+impl Layout {
+    fn new(image_size: u32, grid_size: u32, padding_percent: u32) -> Layout {
+        let padding = image_size * padding_percent / 100;
+        let drawable_size = image_size - 2 * padding;
+        let cell_size = drawable_size / grid_size;
+        let extra_padding = (drawable_size - cell_size * grid_size) / 2;
+        Layout {
+            cell_size,
+            offset: padding + extra_padding,
+        }
+    }
+
+    fn origin(&self, col: u32, row: u32) -> (u32, u32) {
+        (
+            self.offset + col * self.cell_size,
+            self.offset + row * self.cell_size,
+        )
+    }
+}
+
+/// Counts of matching pixels over any rectangle in constant time
+struct SummedArea {
+    width: u32,
+    sums: Vec<u32>,
+}
+
+impl SummedArea {
+    fn new(img: &RgbImage, matches: impl Fn(Rgb<u8>) -> bool) -> SummedArea {
+        let (width, height) = img.dimensions();
+        let stride = (width + 1) as usize;
+        let mut sums = vec![0u32; stride * (height + 1) as usize];
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let hit = u32::from(matches(*img.get_pixel(x as u32, y as u32)));
+                sums[(y + 1) * stride + x + 1] =
+                    hit + sums[y * stride + x + 1] + sums[(y + 1) * stride + x]
+                        - sums[y * stride + x];
+            }
+        }
+        SummedArea { width, sums }
+    }
+
+    fn sum(&self, x: u32, y: u32, w: u32, h: u32) -> u32 {
+        let stride = (self.width + 1) as usize;
+        let at = |x: u32, y: u32| self.sums[y as usize * stride + x as usize];
+        at(x + w, y + h) + at(x, y) - at(x + w, y) - at(x, y + h)
+    }
+}
+
+/// Most common color along the image border
+fn border_color(img: &RgbImage) -> Rgb<u8> {
+    let (width, height) = img.dimensions();
+    let mut counts: HashMap<Rgb<u8>, u32> = HashMap::new();
+    for x in 0..width {
+        *counts.entry(*img.get_pixel(x, 0)).or_default() += 1;
+        *counts.entry(*img.get_pixel(x, height - 1)).or_default() += 1;
+    }
+    for y in 0..height {
+        *counts.entry(*img.get_pixel(0, y)).or_default() += 1;
+        *counts.entry(*img.get_pixel(width - 1, y)).or_default() += 1;
+    }
+    most_common(counts).expect("image is not empty")
+}
+
+/// Most common color clearly different from the background
+fn dominant_color(img: &RgbImage, background: Rgb<u8>) -> Option<Rgb<u8>> {
+    let mut counts: HashMap<Rgb<u8>, u32> = HashMap::new();
+    for pixel in img.pixels() {
+        if distance(*pixel, background) > 48 * 48 {
+            *counts.entry(*pixel).or_default() += 1;
+        }
+    }
+    most_common(counts)
+}
+
+fn most_common(counts: HashMap<Rgb<u8>, u32>) -> Option<Rgb<u8>> {
+    counts
+        .into_iter()
+        .max_by_key(|&(color, count)| (count, color.0))
+        .map(|(color, _)| color)
+}
+
+fn distance(a: Rgb<u8>, b: Rgb<u8>) -> u32 {
+    a.0.iter()
+        .zip(b.0)
+        .map(|(&a, b)| u32::from(a.abs_diff(b)).pow(2))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identicon::{Identicon, Options};
+    use sha2::{Digest, Sha256};
+
+    fn round_trip(seed: &str, options: &Options) {
+        let identicon = Identicon::new(&Sha256::digest(seed), options).unwrap();
+        let img = identicon.to_image().to_rgb8();
+        let (model, geometry) = decode(&img, seed).unwrap();
+        let context = format!("{} at {:?}", seed, geometry);
+        assert_eq!(geometry.grid_size, options.grid_size, "{}", context);
+        assert_eq!(model.pattern, identicon.pattern().rows(), "{}", context);
+        assert_eq!(model.color, identicon.color(), "{}", context);
+    }
+
+    #[test]
+    fn rendered_patterns_decode_back() {
+        for (grid_size, padding, size) in [(5, 8, 420), (7, 4, 210), (9, 20, 333), (15, 12, 997)] {
+            let options = Options {
+                size,
+                grid_size,
+                padding,
+                ..Options::default()
+            };
+            for seed in ["alice", "bob", "carol", "dave", "erin"] {
+                round_trip(seed, &options);
+            }
+        }
+    }
+}
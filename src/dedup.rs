@@ -0,0 +1,97 @@
+//! Collisions within a batch: seeds whose identicons can't be told apart
+
+use crate::neighbors;
+use hashdenticon::{BitMatrix, Identicon};
+use image::RgbImage;
+use image::imageops;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+/// Thumbnails closer than this, in percent, count as near-identical
+const NEAR_DISTANCE: f64 = 2.0;
+
+/// Side of the thumbnails compared for near-identical images
+const THUMBNAIL_SIZE: u32 = 16;
+
+/// What is kept of each identicon to compare it with the rest
+pub struct Sample {
+    seed: String,
+    pattern: BitMatrix,
+    thumbnail: RgbImage,
+}
+
+impl Sample {
+    pub fn new(seed: &str, identicon: &Identicon) -> Self {
+        Self {
+            seed: seed.to_string(),
+            pattern: identicon.pattern().clone(),
+            thumbnail: imageops::thumbnail(identicon.as_rgb(), THUMBNAIL_SIZE, THUMBNAIL_SIZE),
+        }
+    }
+}
+
+/// Plain text report of identical patterns and near-identical images, along
+/// with the number of seeds involved in any collision
+pub fn report(title: &str, samples: &[Sample]) -> (String, usize) {
+    let mut groups: HashMap<&BitMatrix, Vec<&str>> = HashMap::new();
+    for sample in samples {
+        groups
+            .entry(&sample.pattern)
+            .or_default()
+            .push(&sample.seed);
+    }
+    let mut identical: Vec<Vec<&str>> = groups
+        .into_values()
+        .filter(|seeds| seeds.len() > 1)
+        .collect();
+    identical.sort();
+
+    // every pair, so this grows quadratically with the batch
+    let mut near = Vec::new();
+    for (idx, a) in samples.iter().enumerate() {
+        for b in &samples[idx + 1..] {
+            if a.pattern == b.pattern {
+                continue;
+            }
+            let distance = neighbors::distance(&a.thumbnail, &b.thumbnail);
+            if distance < NEAR_DISTANCE {
+                near.push((a.seed.as_str(), b.seed.as_str(), distance));
+            }
+        }
+    }
+
+    let mut involved: Vec<&str> = identical.iter().flatten().copied().collect();
+    involved.extend(near.iter().flat_map(|&(a, b, _)| [a, b]));
+    involved.sort_unstable();
+    involved.dedup();
+
+    let mut out = String::new();
+    let _ = writeln!(out, "# {}", title);
+    let _ = writeln!(
+        out,
+        "{} seeds, {} sharing a look with another",
+        samples.len(),
+        involved.len()
+    );
+    let _ = writeln!(out, "\nIdentical patterns ({} groups):", identical.len());
+    for seeds in &identical {
+        let _ = writeln!(out, "  {}", seeds.join(", "));
+    }
+    let _ = writeln!(
+        out,
+        "\nNear-identical images, under {}% apart ({} pairs):",
+        NEAR_DISTANCE,
+        near.len()
+    );
+    for (a, b, distance) in &near {
+        let _ = writeln!(out, "  {} ~ {} ({:.1}%)", a, b, distance);
+    }
+    if !involved.is_empty() {
+        let _ = writeln!(
+            out,
+            "\nA larger --grid gives each seed more room to differ."
+        );
+    }
+
+    (out, involved.len())
+}
@@ -0,0 +1,106 @@
+//! Splitting one digest into independent byte streams for each purpose
+
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+/// How the digest is turned into color, pattern, and style bytes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Derivation {
+    /// Every purpose reads the digest directly, so color and pattern share
+    /// bytes; keeps the identicons of earlier releases
+    #[default]
+    Legacy,
+    /// Separate HKDF-SHA256 expansions per purpose, with no correlation
+    /// between color and pattern
+    Hkdf,
+}
+
+/// Salt for the HKDF extract step, fixed so streams are reproducible
+const SALT: &[u8] = b"hashdenticon";
+
+/// Length of each derived stream, matching a SHA-256 digest
+const STREAM_LEN: usize = 32;
+
+/// Bytes for each part of the identicon
+pub struct Streams {
+    pub color: Vec<u8>,
+    pub pattern: Vec<u8>,
+    /// Everything style-specific: walks, rules, lighting, stamps, rotation
+    pub style: Vec<u8>,
+}
+
+impl Streams {
+    pub fn new(hash: &[u8], derivation: Derivation) -> Self {
+        match derivation {
+            Derivation::Legacy => Self {
+                color: hash.to_vec(),
+                pattern: hash.to_vec(),
+                style: hash.to_vec(),
+            },
+            Derivation::Hkdf => {
+                let hkdf = Hkdf::<Sha256>::new(Some(SALT), hash);
+                Self {
+                    color: expand(&hkdf, b"color"),
+                    pattern: expand(&hkdf, b"pattern"),
+                    style: expand(&hkdf, b"style"),
+                }
+            }
+        }
+    }
+}
+
+fn expand(hkdf: &Hkdf<Sha256>, info: &[u8]) -> Vec<u8> {
+    let mut out = vec![0; STREAM_LEN];
+    hkdf.expand(info, &mut out)
+        .expect("stream is far below the HKDF output limit");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIGEST: [u8; 32] = [7; 32];
+
+    #[test]
+    fn streams_are_deterministic() {
+        for derivation in [Derivation::Legacy, Derivation::Hkdf] {
+            let (one, two) = (
+                Streams::new(&DIGEST, derivation),
+                Streams::new(&DIGEST, derivation),
+            );
+            assert_eq!(one.color, two.color);
+            assert_eq!(one.pattern, two.pattern);
+            assert_eq!(one.style, two.style);
+        }
+    }
+
+    #[test]
+    fn hkdf_streams_differ_by_purpose() {
+        let streams = Streams::new(&DIGEST, Derivation::Hkdf);
+        assert_ne!(streams.color, streams.pattern);
+        assert_ne!(streams.color, streams.style);
+        assert_ne!(streams.pattern, streams.style);
+        assert!(
+            [&streams.color, &streams.pattern, &streams.style]
+                .iter()
+                .all(|stream| stream.len() == STREAM_LEN && stream[..] != DIGEST)
+        );
+        assert_ne!(
+            Streams::new(&[8; 32], Derivation::Hkdf).style,
+            streams.style
+        );
+    }
+
+    #[test]
+    fn legacy_reproduces_the_raw_digest() {
+        let digest: Vec<u8> = (0..48).collect();
+        let streams = Streams::new(&digest, Derivation::Legacy);
+        assert_eq!(streams.color, digest);
+        assert_eq!(streams.pattern, digest);
+        assert_eq!(streams.style, digest);
+    }
+}
@@ -0,0 +1,119 @@
+//! XDG base directories: named presets and themes under the config
+//! directory, style packs under the data directory, and the identicon cache
+
+use std::path::{Component, Path, PathBuf};
+
+const APP: &str = "hashdenticon";
+
+/// `$XDG_CONFIG_HOME/hashdenticon`, else `~/.config/hashdenticon`
+pub fn config() -> Option<PathBuf> {
+    base("XDG_CONFIG_HOME", ".config")
+}
+
+/// `$XDG_CACHE_HOME/hashdenticon`, else `~/.cache/hashdenticon`
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+pub fn cache() -> Option<PathBuf> {
+    base("XDG_CACHE_HOME", ".cache")
+}
+
+/// `$XDG_DATA_HOME/hashdenticon`, else `~/.local/share/hashdenticon`
+pub fn data() -> Option<PathBuf> {
+    base("XDG_DATA_HOME", ".local/share")
+}
+
+/// The spec ignores relative paths in these variables, as if unset
+fn base(var: &str, fallback: &str) -> Option<PathBuf> {
+    let dir = std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| {
+            let home = PathBuf::from(std::env::var_os("HOME")?);
+            Some(home.join(fallback))
+        })?;
+    Some(dir.join(APP))
+}
+
+/// --preset: a file, or the name of one saved as `presets/<name>.toml` in
+/// the config directory
+pub fn preset(arg: &str) -> Result<PathBuf, String> {
+    config_file(arg, "presets")
+}
+
+/// --theme-file: a file, or the name of one saved as `themes/<name>.toml`
+/// in the config directory
+pub fn theme(arg: &str) -> Result<PathBuf, String> {
+    config_file(arg, "themes")
+}
+
+fn config_file(arg: &str, subdir: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(arg);
+    if !is_name(&path) || path.exists() {
+        return Ok(path);
+    }
+    let dir = config().ok_or("HOME is not set, so there is no config directory to look in")?;
+    Ok(dir.join(subdir).join(format!("{}.toml", arg)))
+}
+
+/// --parts-dir: a directory, or the name of a style pack installed as
+/// `parts/<name>` in the data directory
+pub fn style_pack(arg: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(arg);
+    if !is_name(&path) || path.exists() {
+        return Ok(path);
+    }
+    let installed = data().map(|dir| dir.join("parts").join(arg));
+    Ok(installed.filter(|dir| dir.is_dir()).unwrap_or(path))
+}
+
+/// A bare name, rather than anything that reads as a path
+fn is_name(path: &Path) -> bool {
+    let mut components = path.components();
+    matches!(components.next(), Some(Component::Normal(_)))
+        && components.next().is_none()
+        && path.extension().is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_single_bare_components() {
+        for name in ["dark", "team-blue", "v2_final"] {
+            assert!(is_name(Path::new(name)), "{}", name);
+        }
+        for path in [
+            "dark.toml",
+            "./dark",
+            "presets/dark",
+            "/etc/dark",
+            ".",
+            "..",
+            "",
+        ] {
+            assert!(!is_name(Path::new(path)), "{}", path);
+        }
+    }
+
+    #[test]
+    fn paths_are_taken_as_given() {
+        for arg in ["dark.toml", "./dark", "presets/dark", "/nonexistent/dark"] {
+            assert_eq!(config_file(arg, "presets"), Ok(PathBuf::from(arg)));
+        }
+    }
+
+    #[test]
+    fn names_resolve_into_the_config_directory() {
+        let Some(dir) = config() else {
+            return;
+        };
+        assert_eq!(
+            config_file("no-such-preset-here", "presets"),
+            Ok(dir.join("presets").join("no-such-preset-here.toml"))
+        );
+        assert_eq!(
+            config_file("no-such-theme-here", "themes"),
+            Ok(dir.join("themes").join("no-such-theme-here.toml"))
+        );
+    }
+}
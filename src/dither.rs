@@ -0,0 +1,100 @@
+//! Conversion to 1-bit bitmaps for e-ink displays and thermal printers
+
+use image::RgbImage;
+
+/// How gray levels are reduced to black and white
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Dither {
+    /// Anything noticeably darker than white becomes black
+    #[default]
+    None,
+    /// Error diffusion; preserves tone, looks organic
+    FloydSteinberg,
+    /// 4x4 Bayer matrix; regular pattern, prints cleanly
+    Ordered,
+}
+
+/// Black-and-white image, `true` meaning black (ink)
+pub struct Bitmap {
+    pub width: u32,
+    pub height: u32,
+    pixels: Vec<bool>,
+}
+
+impl Bitmap {
+    pub fn get(&self, x: u32, y: u32) -> bool {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Rows packed 8 pixels per byte, most significant bit first, each row
+    /// padded to a whole byte (the PBM and ESC/POS layout)
+    pub fn packed_rows_msb(&self) -> Vec<u8> {
+        let row_bytes = self.width.div_ceil(8);
+        let mut out = vec![0u8; (row_bytes * self.height) as usize];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.get(x, y) {
+                    out[(y * row_bytes + x / 8) as usize] |= 0x80 >> (x % 8);
+                }
+            }
+        }
+        out
+    }
+}
+
+// 4x4 Bayer thresholds, scaled to 0..256
+const BAYER: [[i32; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+// luma below this counts as ink when not dithering
+const FLAT_THRESHOLD: i32 = 240;
+
+/// Reduce `img` to one bit per pixel
+pub fn to_bitmap(img: &RgbImage, dither: Dither) -> Bitmap {
+    let (width, height) = img.dimensions();
+    let mut luma: Vec<i32> = img
+        .pixels()
+        .map(|p| (299 * p[0] as i32 + 587 * p[1] as i32 + 114 * p[2] as i32) / 1000)
+        .collect();
+    let mut pixels = vec![false; luma.len()];
+
+    for y in 0..height {
+        for x in 0..width {
+            let idx = (y * width + x) as usize;
+            let value = luma[idx];
+
+            pixels[idx] = match dither {
+                Dither::None => value < FLAT_THRESHOLD,
+                Dither::Ordered => {
+                    let threshold = BAYER[(y % 4) as usize][(x % 4) as usize] * 16 + 8;
+                    value < threshold
+                }
+                Dither::FloydSteinberg => {
+                    let ink = value < 128;
+                    let error = value - if ink { 0 } else { 255 };
+
+                    // push the error to unvisited neighbours: 7/16 right,
+                    // 3/16 below-left, 5/16 below, 1/16 below-right
+                    let mut spread = |dx: i64, dy: i64, weight: i32| {
+                        let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                        if nx >= 0 && nx < width as i64 && ny < height as i64 {
+                            luma[(ny as u32 * width + nx as u32) as usize] += error * weight / 16;
+                        }
+                    };
+                    spread(1, 0, 7);
+                    spread(-1, 1, 3);
+                    spread(0, 1, 5);
+                    spread(1, 1, 1);
+
+                    ink
+                }
+            };
+        }
+    }
+
+    Bitmap {
+        width,
+        height,
+        pixels,
+    }
+}
@@ -0,0 +1,134 @@
+//! Low-level drawing primitives shared by the styles
+
+use image::{Rgb, RgbImage};
+
+/// Fill an axis-aligned rectangle, clipped to the image bounds
+pub fn fill_rect(img: &mut RgbImage, x: u32, y: u32, width: u32, height: u32, color: Rgb<u8>) {
+    let x_end = x.saturating_add(width).min(img.width());
+    let y_end = y.saturating_add(height).min(img.height());
+    if x >= x_end || y >= y_end {
+        return;
+    }
+
+    // fill the first row, then copy it down: whole-row memcpys instead of
+    // a bounds-checked write per pixel
+    let stride = img.width() as usize * 3;
+    let span = x as usize * 3..x_end as usize * 3;
+    let first = y as usize * stride;
+    let buf: &mut [u8] = img;
+    fill_row(&mut buf[first + span.start..first + span.end], color);
+    for row in y as usize + 1..y_end as usize {
+        buf.copy_within(
+            first + span.start..first + span.end,
+            row * stride + span.start,
+        );
+    }
+}
+
+/// Fill pixels `x_start..x_end` of row `y`, which must be in bounds
+fn fill_span(img: &mut RgbImage, y: u32, x_start: u32, x_end: u32, color: Rgb<u8>) {
+    let offset = y as usize * img.width() as usize * 3;
+    let buf: &mut [u8] = img;
+    fill_row(
+        &mut buf[offset + x_start as usize * 3..offset + x_end as usize * 3],
+        color,
+    );
+}
+
+/// Fill packed RGB `row` with `color`
+fn fill_row(row: &mut [u8], color: Rgb<u8>) {
+    let [r, g, b] = color.0;
+    if r == g && g == b {
+        // grays, including black and white, are a plain memset
+        row.fill(r);
+        return;
+    }
+    // one pixel, then keep doubling the filled prefix with memcpy
+    let Some(first) = row.get_mut(..3) else {
+        return;
+    };
+    first.copy_from_slice(&color.0);
+    let mut filled = 3;
+    while filled < row.len() {
+        let count = filled.min(row.len() - filled);
+        row.copy_within(..count, filled);
+        filled += count;
+    }
+}
+
+/// Linearly blend from `from` towards `to` by `num / den`
+pub fn blend(from: Rgb<u8>, to: Rgb<u8>, num: u32, den: u32) -> Rgb<u8> {
+    let mix = |a: u8, b: u8| ((a as u32 * (den - num) + b as u32 * num) / den) as u8;
+    Rgb([
+        mix(from[0], to[0]),
+        mix(from[1], to[1]),
+        mix(from[2], to[2]),
+    ])
+}
+
+/// Sub-pixel units per pixel for polygon coordinates
+pub const SUBPIXEL: i64 = 256;
+
+/// Fill a polygon given in sub-pixel coordinates, using the even-odd rule
+///
+/// Pixels are sampled at their centers with integer math only, so the
+/// output is identical on every platform.
+pub fn fill_polygon(img: &mut RgbImage, points: &[(i64, i64)], color: Rgb<u8>) {
+    if points.len() < 3 {
+        return;
+    }
+
+    let min_y = points.iter().map(|p| p.1).min().unwrap_or(0);
+    let max_y = points.iter().map(|p| p.1).max().unwrap_or(0);
+    let row_start = (min_y / SUBPIXEL).max(0);
+    let row_end = (max_y / SUBPIXEL + 1).min(img.height() as i64);
+
+    let mut crossings = Vec::new();
+    for row in row_start..row_end {
+        let center_y = row * SUBPIXEL + SUBPIXEL / 2;
+
+        // x positions where polygon edges cross this row's center line
+        crossings.clear();
+        for (idx, &(x0, y0)) in points.iter().enumerate() {
+            let (x1, y1) = points[(idx + 1) % points.len()];
+            if (y0 <= center_y) != (y1 <= center_y) {
+                crossings.push(x0 + (center_y - y0) * (x1 - x0) / (y1 - y0));
+            }
+        }
+        crossings.sort_unstable();
+
+        for span in crossings.chunks_exact(2) {
+            // first and last pixel whose center lies inside the span
+            let px_start = ((span[0] - SUBPIXEL / 2 + SUBPIXEL - 1).div_euclid(SUBPIXEL)).max(0);
+            let px_end = ((span[1] - SUBPIXEL / 2 + SUBPIXEL - 1).div_euclid(SUBPIXEL))
+                .min(img.width() as i64);
+            if px_start < px_end {
+                fill_span(img, row as u32, px_start as u32, px_end as u32, color);
+            }
+        }
+    }
+}
+
+/// Fill a disc centered on (`cx`, `cy`), sampling pixel centers
+pub fn fill_circle(img: &mut RgbImage, cx: u32, cy: u32, radius: u32, color: Rgb<u8>) {
+    let (cx, cy, radius) = (cx as i64, cy as i64, radius as i64);
+
+    // compare doubled coordinates so pixel centers stay integral
+    let limit = (2 * radius).pow(2);
+    for py in (cy - radius).max(0)..(cy + radius).min(img.height() as i64) {
+        let dy = 2 * (py - cy) + 1;
+        if dy * dy > limit {
+            continue;
+        }
+        // the row's pixels with dx = 2 * (px - cx) + 1 and dx * dx + dy * dy
+        // <= limit, solved for px: one span from the integer square root
+        let reach = (limit - dy * dy).isqrt();
+        let start = (cx - (reach + 1) / 2).max(cx - radius).max(0);
+        let end = (cx + (reach - 1).div_euclid(2) + 1)
+            .min(cx + radius)
+            .min(img.width() as i64);
+        if start < end {
+            fill_span(img, py as u32, start as u32, end as u32, color);
+        }
+    }
+}
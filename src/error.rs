@@ -0,0 +1,92 @@
+//! Errors returned by the library
+
+use image::ImageError;
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Everything that can go wrong generating or encoding an identicon
+#[derive(Debug, Error)]
+pub enum HashdenticonError {
+    #[error("Image size {size} is outside {min}..={max} pixels", min = crate::MIN_SIZE, max = crate::MAX_SIZE)]
+    InvalidSize { size: u32 },
+
+    #[error("Padding of {padding}% leaves no room to draw (at most {max}%)", max = crate::MAX_PADDING)]
+    InvalidPadding { padding: u32 },
+
+    #[error("Grid size must be at least 1")]
+    InvalidGridSize,
+
+    #[error("A grid of {grid_size} cells is too large; at most {max} fit")]
+    GridTooLarge { grid_size: usize, max: usize },
+
+    /// An option the chosen style or source cannot honor
+    #[error("{0}")]
+    Unsupported(String),
+
+    /// A UUID or fingerprint that could not be parsed
+    #[error("{0}")]
+    InvalidInput(String),
+
+    #[error("Malformed certificate: {0}")]
+    InvalidCertificate(String),
+
+    #[error("Invalid pattern model: {0}")]
+    InvalidModel(String),
+
+    #[error("Invalid preset: {0}")]
+    InvalidPreset(String),
+
+    #[error("Invalid theme: {0}")]
+    InvalidTheme(String),
+
+    #[error("Invalid parts pack: {0}")]
+    InvalidParts(String),
+
+    #[error("Cannot decode identicon: {0}")]
+    DecodeError(String),
+
+    #[error("Failed to load {}", path.display())]
+    LoadError {
+        path: PathBuf,
+        #[source]
+        source: ImageError,
+    },
+
+    #[error("Failed to encode image")]
+    EncodeError(#[from] ImageError),
+
+    #[cfg(feature = "serde")]
+    #[error("Failed to encode json")]
+    JsonError(#[from] serde_json::Error),
+
+    /// A [`RenderPool`](crate::RenderPool) with no room left in its queue
+    #[error("Too many identicons are waiting to render; try again shortly")]
+    Busy,
+
+    /// The runtime shut down before a [`nonblocking`](crate::nonblocking) task ran
+    #[cfg(feature = "async")]
+    #[error("Rendering was cancelled as the runtime shut down")]
+    Cancelled,
+
+    /// No usable GPU, or a failure on it, for [`Options::gpu`](crate::Options::gpu)
+    #[cfg(feature = "wgpu")]
+    #[error("GPU rendering failed: {0}")]
+    Gpu(String),
+
+    #[error("Failed to access {}", path.display())]
+    IoError {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+pub type Result<T, E = HashdenticonError> = std::result::Result<T, E>;
+
+impl HashdenticonError {
+    pub(crate) fn io(path: impl Into<PathBuf>) -> impl FnOnce(io::Error) -> HashdenticonError {
+        let path = path.into();
+        move |source| HashdenticonError::IoError { path, source }
+    }
+}
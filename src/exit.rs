@@ -0,0 +1,225 @@
+//! Exit codes by kind of failure, and the --json-errors report, so scripts
+//! can tell a bad argument from a full disk
+
+use hashdenticon::HashdenticonError;
+use image::ImageError;
+use serde_json::json;
+use std::process::ExitCode;
+use thiserror::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// Anything not covered below
+    Failure,
+    /// Invalid arguments, seeds, or input files
+    Usage,
+    /// Reading or writing a file failed
+    Io,
+    /// Encoding or decoding an image or model failed
+    Encode,
+    /// An identicon did not match what was expected of it
+    Mismatch,
+}
+
+impl Kind {
+    pub fn code(self) -> u8 {
+        match self {
+            Kind::Failure => 1,
+            Kind::Usage => 2,
+            Kind::Io => 3,
+            Kind::Encode => 4,
+            Kind::Mismatch => 5,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Failure => "failure",
+            Kind::Usage => "usage",
+            Kind::Io => "io",
+            Kind::Encode => "encode",
+            Kind::Mismatch => "mismatch",
+        }
+    }
+
+    /// The first cause in the chain of `err` with a known kind
+    pub fn of(err: &anyhow::Error) -> Kind {
+        err.chain()
+            .find_map(|cause| {
+                if cause.is::<Mismatch>() {
+                    Some(Kind::Mismatch)
+                } else if cause.is::<Invalid>() {
+                    Some(Kind::Usage)
+                } else if let Some(err) = cause.downcast_ref::<HashdenticonError>() {
+                    Some(library_kind(err))
+                } else if let Some(err) = cause.downcast_ref::<ImageError>() {
+                    Some(image_kind(err))
+                } else if cause.is::<std::io::Error>() {
+                    Some(Kind::Io)
+                } else if cause.is::<serde_json::Error>() {
+                    Some(Kind::Encode)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(Kind::Failure)
+    }
+}
+
+fn library_kind(err: &HashdenticonError) -> Kind {
+    match err {
+        HashdenticonError::InvalidSize { .. }
+        | HashdenticonError::InvalidPadding { .. }
+        | HashdenticonError::InvalidGridSize
+        | HashdenticonError::GridTooLarge { .. }
+        | HashdenticonError::Unsupported(_)
+        | HashdenticonError::InvalidInput(_)
+        | HashdenticonError::InvalidCertificate(_)
+        | HashdenticonError::InvalidModel(_)
+        | HashdenticonError::InvalidPreset(_)
+        | HashdenticonError::InvalidTheme(_)
+        | HashdenticonError::InvalidParts(_) => Kind::Usage,
+        HashdenticonError::IoError { .. } => Kind::Io,
+        HashdenticonError::LoadError { source, .. } => image_kind(source),
+        HashdenticonError::EncodeError(source) => image_kind(source),
+        HashdenticonError::JsonError(_) | HashdenticonError::DecodeError(_) => Kind::Encode,
+        HashdenticonError::Busy => Kind::Failure,
+        // a render dropped on shutdown, not anything wrong with the request
+        #[cfg(feature = "async")]
+        HashdenticonError::Cancelled => Kind::Failure,
+        #[cfg(feature = "wgpu")]
+        HashdenticonError::Gpu(_) => Kind::Failure,
+    }
+}
+
+fn image_kind(err: &ImageError) -> Kind {
+    match err {
+        ImageError::IoError(_) => Kind::Io,
+        ImageError::Unsupported(_) | ImageError::Parameter(_) => Kind::Usage,
+        _ => Kind::Encode,
+    }
+}
+
+/// Input that was rejected, such as a seed refused by --strict
+#[derive(Debug, Error)]
+#[error("{0}")]
+pub struct Invalid(pub String);
+
+/// An identicon that differs from the one it was checked against
+#[derive(Debug, Error)]
+#[error("{0}")]
+pub struct Mismatch(pub String);
+
+/// Print `err` to stderr, as a json object when asked for, and give the
+/// exit code for its kind
+pub fn report(err: &anyhow::Error, as_json: bool) -> ExitCode {
+    let kind = Kind::of(err);
+    if as_json {
+        let causes: Vec<String> = err.chain().skip(1).map(|cause| cause.to_string()).collect();
+        let report = json!({
+            "error": err.to_string(),
+            "kind": kind.name(),
+            "code": kind.code(),
+            "causes": causes,
+        });
+        eprintln!("{}", report);
+    } else {
+        eprintln!("Error: {:?}", err);
+    }
+    ExitCode::from(kind.code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+    use image::error::{ParameterError, ParameterErrorKind};
+    use std::io;
+
+    /// `err` wrapped in context, as the binary's errors usually are
+    fn kind_of(err: impl std::error::Error + Send + Sync + 'static) -> Kind {
+        let wrapped = Err::<(), _>(err)
+            .context("Failed to generate alice")
+            .context("Failed to write alice.png");
+        Kind::of(&wrapped.unwrap_err())
+    }
+
+    fn image_io() -> ImageError {
+        ImageError::IoError(io::Error::other("disk full"))
+    }
+
+    fn image_parameter() -> ImageError {
+        ImageError::Parameter(ParameterError::from_kind(
+            ParameterErrorKind::DimensionMismatch,
+        ))
+    }
+
+    #[test]
+    fn kinds_are_found_through_context() {
+        assert_eq!(kind_of(Invalid("empty seed".to_string())), Kind::Usage);
+        assert_eq!(kind_of(Mismatch("differs".to_string())), Kind::Mismatch);
+        assert_eq!(kind_of(io::Error::other("disk full")), Kind::Io);
+        assert_eq!(Kind::of(&anyhow::anyhow!("something else")), Kind::Failure);
+    }
+
+    #[test]
+    fn library_errors_are_classified_by_variant() {
+        let usage = [
+            HashdenticonError::InvalidSize { size: 0 },
+            HashdenticonError::InvalidPadding { padding: 99 },
+            HashdenticonError::InvalidGridSize,
+            HashdenticonError::GridTooLarge {
+                grid_size: 99,
+                max: 15,
+            },
+            HashdenticonError::Unsupported(String::new()),
+            HashdenticonError::InvalidInput(String::new()),
+            HashdenticonError::InvalidCertificate(String::new()),
+            HashdenticonError::InvalidModel(String::new()),
+            HashdenticonError::InvalidPreset(String::new()),
+            HashdenticonError::InvalidTheme(String::new()),
+            HashdenticonError::InvalidParts(String::new()),
+            HashdenticonError::EncodeError(image_parameter()),
+        ];
+        for err in usage {
+            assert_eq!(kind_of(err), Kind::Usage);
+        }
+
+        let json = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        assert_eq!(kind_of(HashdenticonError::JsonError(json)), Kind::Encode);
+        assert_eq!(
+            kind_of(HashdenticonError::DecodeError(String::new())),
+            Kind::Encode
+        );
+        let io = HashdenticonError::IoError {
+            path: "alice.png".into(),
+            source: io::Error::other("disk full"),
+        };
+        assert_eq!(kind_of(io), Kind::Io);
+        let load = HashdenticonError::LoadError {
+            path: "parts/eyes.png".into(),
+            source: image_io(),
+        };
+        assert_eq!(kind_of(load), Kind::Io);
+        assert_eq!(
+            kind_of(HashdenticonError::EncodeError(image_io())),
+            Kind::Io
+        );
+        assert_eq!(kind_of(HashdenticonError::Busy), Kind::Failure);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn cancelled_renders_are_failures_not_usage() {
+        assert_eq!(kind_of(HashdenticonError::Cancelled), Kind::Failure);
+    }
+
+    #[cfg(feature = "wgpu")]
+    #[test]
+    fn gpu_errors_are_failures() {
+        assert_eq!(
+            kind_of(HashdenticonError::Gpu(String::new())),
+            Kind::Failure
+        );
+    }
+}
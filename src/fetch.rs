@@ -0,0 +1,88 @@
+//! `batch --url`: seed lists downloaded from an HTTP(S) endpoint
+
+use crate::batch::Seeds;
+use crate::exit;
+use anyhow::{Context, Result, bail};
+use hashdenticon::input;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// Wait before the first retry, doubled for each one after
+const BACKOFF: Duration = Duration::from_secs(1);
+
+/// Download the seed list at `url`, retrying failed connections and server
+/// errors up to `retries` times, and checking it against a hex SHA-256
+/// `checksum` when one is given
+pub fn seed_list(url: &str, retries: u32, checksum: Option<&str>) -> Result<Seeds> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(TIMEOUT))
+        .http_status_as_error(false)
+        .build()
+        .into();
+
+    let mut attempt = 0;
+    let body = loop {
+        let failure = match agent.get(url).call() {
+            Ok(mut response) if response.status().is_success() => {
+                match response.body_mut().read_to_vec() {
+                    Ok(body) => break body,
+                    Err(err) => format!("the download broke off: {}", err),
+                }
+            }
+            // the request itself is wrong; asking again won't help
+            Ok(response) if response.status().is_client_error() => {
+                bail!("Failed to fetch {}: status {}", url, response.status());
+            }
+            Ok(response) => format!("status {}", response.status()),
+            Err(err) => err.to_string(),
+        };
+        if attempt == retries {
+            bail!("Failed to fetch {}: {}", url, failure);
+        }
+        let wait = BACKOFF * 2u32.pow(attempt);
+        attempt += 1;
+        eprintln!(
+            "Fetching {} failed ({}); retry {} of {} in {}s",
+            url,
+            failure,
+            attempt,
+            retries,
+            wait.as_secs()
+        );
+        std::thread::sleep(wait);
+    };
+
+    if let Some(expected) = checksum {
+        let actual = input::hex(&Sha256::digest(&body));
+        if !actual.eq_ignore_ascii_case(expected.trim()) {
+            let message = format!(
+                "Checksum mismatch for {}: expected {}, downloaded {}",
+                url, expected, actual
+            );
+            return Err(exit::Mismatch(message).into());
+        }
+    }
+
+    let text = String::from_utf8(body).context("The seed list is not UTF-8")?;
+    Ok(Seeds {
+        source: url.to_string(),
+        contents: from_json(&text).unwrap_or(text),
+    })
+}
+
+/// A json array of seeds, or json lines of them, as one seed per line
+fn from_json(text: &str) -> Option<String> {
+    if text.trim_start().starts_with('[') {
+        let seeds: Vec<String> = serde_json::from_str(text).ok()?;
+        return Some(seeds.join("\n"));
+    }
+    let seeds: Vec<String> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()
+        .ok()?;
+    Some(seeds.join("\n"))
+}
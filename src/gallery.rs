@@ -0,0 +1,84 @@
+//! Gallery pages and contact sheets for sets of identicons
+
+use crate::sink;
+use anyhow::{Context, Result};
+use image::{Rgb, RgbImage, imageops};
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// One identicon in a gallery, with caption lines shown below it
+pub struct GalleryEntry {
+    /// Image path, relative to the gallery page
+    pub image: String,
+    pub caption: Vec<String>,
+}
+
+/// Write a static HTML page showing every entry with its caption
+pub fn write_html(path: &Path, title: &str, entries: &[GalleryEntry]) -> Result<()> {
+    let mut html = String::new();
+    let _ = writeln!(html, "<!DOCTYPE html>");
+    let _ = writeln!(html, "<html>\n<head>\n<meta charset=\"utf-8\">");
+    let _ = writeln!(html, "<title>{}</title>", escape(title));
+    let _ = writeln!(
+        html,
+        "<style>\
+         body {{ font-family: sans-serif; margin: 2em; }}\
+         .grid {{ display: flex; flex-wrap: wrap; gap: 1.5em; }}\
+         figure {{ margin: 0; width: 160px; }}\
+         img {{ width: 160px; height: 160px; image-rendering: pixelated; }}\
+         figcaption {{ font-size: 0.8em; word-break: break-all; }}\
+         </style>"
+    );
+    let _ = writeln!(html, "</head>\n<body>\n<h1>{}</h1>", escape(title));
+    let _ = writeln!(html, "<div class=\"grid\">");
+
+    for entry in entries {
+        let alt = entry
+            .caption
+            .first()
+            .map(String::as_str)
+            .unwrap_or_default();
+        let _ = writeln!(html, "<figure>");
+        let _ = writeln!(
+            html,
+            "<img src=\"{}\" alt=\"{}\">",
+            escape(&entry.image),
+            escape(alt)
+        );
+        let caption: Vec<String> = entry.caption.iter().map(|line| escape(line)).collect();
+        let _ = writeln!(html, "<figcaption>{}</figcaption>", caption.join("<br>"));
+        let _ = writeln!(html, "</figure>");
+    }
+
+    let _ = writeln!(html, "</div>\n</body>\n</html>");
+
+    sink::write(path, html.as_bytes()).context("Failed to write gallery page")
+}
+
+/// Tile images into a single sheet, `columns` wide, separated by `gap` pixels
+pub fn contact_sheet(images: &[RgbImage], columns: u32, gap: u32) -> RgbImage {
+    let cell = images.iter().map(|img| img.width()).max().unwrap_or(0);
+    let columns = columns.clamp(1, images.len().max(1) as u32);
+    let rows = (images.len() as u32).div_ceil(columns);
+
+    let width = columns * cell + (columns + 1) * gap;
+    let height = rows * cell + (rows + 1) * gap;
+    let mut sheet = RgbImage::from_pixel(width, height, Rgb([255, 255, 255]));
+
+    for (idx, img) in images.iter().enumerate() {
+        let col = idx as u32 % columns;
+        let row = idx as u32 / columns;
+        let x = gap + col * (cell + gap);
+        let y = gap + row * (cell + gap);
+        imageops::replace(&mut sheet, img, x as i64, y as i64);
+    }
+
+    sheet
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
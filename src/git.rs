@@ -0,0 +1,51 @@
+//! Commit authors of a git repository, read through the `git` command
+
+use anyhow::{Context, Result, bail};
+use std::path::Path;
+use std::process::Command;
+
+/// One unique author, keyed by email
+pub struct Contributor {
+    pub name: String,
+    /// Trimmed and lowercased, so case variations collapse
+    pub email: String,
+    pub commits: usize,
+}
+
+/// Unique authors across `git log`, most commits first
+pub fn contributors(repo: &Path) -> Result<Vec<Contributor>> {
+    // %aN and %aE honor .mailmap
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["log", "--format=%aN%x00%aE"])
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git log failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let log = String::from_utf8_lossy(&output.stdout);
+    let mut contributors: Vec<Contributor> = Vec::new();
+    for line in log.lines() {
+        let Some((name, email)) = line.split_once('\0') else {
+            continue;
+        };
+        let email = email.trim().to_lowercase();
+        match contributors.iter_mut().find(|c| c.email == email) {
+            Some(contributor) => contributor.commits += 1,
+            None => contributors.push(Contributor {
+                name: name.trim().to_string(),
+                email,
+                commits: 1,
+            }),
+        }
+    }
+
+    // stable, so ties stay in order of most recent commit
+    contributors.sort_by_key(|c| std::cmp::Reverse(c.commits));
+    Ok(contributors)
+}
@@ -0,0 +1,267 @@
+//! `--gpu`: compute shaders for the renders that dominate bulk jobs at
+//! high resolution, on whatever GPU wgpu finds
+//!
+//! Voronoi regions and box-filter downsampling are integer math on both
+//! sides, so the GPU draws exactly the image the CPU would. Everything
+//! else still renders on the CPU.
+//!
+//! ```no_run
+//! use hashdenticon::{Identicon, Options, Style};
+//!
+//! let options = Options {
+//!     style: Style::Voronoi,
+//!     size: 4096,
+//!     gpu: true,
+//!     ..Options::default()
+//! };
+//! let identicon = Identicon::from_seed("alice", &options)?;
+//! # Ok::<(), hashdenticon::HashdenticonError>(())
+//! ```
+
+use crate::error::{HashdenticonError, Result};
+use crate::resample::{self, Filter};
+use crate::style::voronoi;
+use image::RgbImage;
+use std::sync::{OnceLock, mpsc};
+use wgpu::util::DeviceExt;
+
+/// Side of the square of pixels each workgroup of the shaders covers
+const WORKGROUP: u32 = 8;
+
+static GLOBAL: OnceLock<std::result::Result<Gpu, String>> = OnceLock::new();
+
+/// A GPU device with the shaders compiled for it
+pub struct Gpu {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    voronoi: wgpu::ComputePipeline,
+    downsample: wgpu::ComputePipeline,
+}
+
+impl Gpu {
+    /// Open the most capable adapter on the system; `WGPU_BACKEND` and
+    /// `WGPU_ADAPTER_NAME` pick another
+    pub fn new() -> Result<Gpu> {
+        pollster::block_on(Gpu::connect()).map_err(HashdenticonError::Gpu)
+    }
+
+    /// The device [`Options::gpu`](crate::Options::gpu) renders on, opened
+    /// on first use; a failure to open it is returned on every call
+    pub fn global() -> Result<&'static Gpu> {
+        GLOBAL
+            .get_or_init(|| pollster::block_on(Gpu::connect()))
+            .as_ref()
+            .map_err(|err| HashdenticonError::Gpu(err.clone()))
+    }
+
+    async fn connect() -> std::result::Result<Gpu, String> {
+        let instance =
+            wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle_from_env());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| err.to_string())?;
+        // large supersampled renders need the biggest buffers the adapter has
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: Some("hashdenticon"),
+                required_limits: adapter.limits(),
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| err.to_string())?;
+
+        let pipeline = |label: &str, source: &str| {
+            let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            });
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: None,
+                module: &module,
+                entry_point: Some("main"),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let voronoi = pipeline("voronoi", include_str!("gpu/voronoi.wgsl"));
+        let downsample = pipeline("downsample", include_str!("gpu/downsample.wgsl"));
+        Ok(Gpu {
+            device,
+            queue,
+            voronoi,
+            downsample,
+        })
+    }
+
+    /// Draw the voronoi style into `img`, as
+    /// [`style::voronoi::render`](crate::style::voronoi::render) would;
+    /// on the CPU if it is too large for the device's buffers
+    pub fn voronoi(
+        &self,
+        hash: &[u8],
+        max_points: u32,
+        img: &mut RgbImage,
+        padding_percent: u32,
+    ) -> Result<()> {
+        let image_size = img.width();
+        let padding = image_size * padding_percent / 100;
+        let drawable_size = image_size - 2 * padding;
+        if !self.fits(u64::from(drawable_size).pow(2) * 4) {
+            voronoi::render(hash, max_points, img, padding_percent);
+            return Ok(());
+        }
+
+        let points = voronoi::points(hash, max_points, drawable_size as i64);
+        if points.is_empty() {
+            return Ok(());
+        }
+        let packed = points
+            .iter()
+            .flat_map(|&(x, y, color)| [x as i32, y as i32, pack(color.0) as i32, 0])
+            .flat_map(i32::to_le_bytes)
+            .collect::<Vec<_>>();
+        let params = [drawable_size, points.len() as u32, 0, 0];
+        let pixels = self.dispatch(
+            &self.voronoi,
+            params,
+            &packed,
+            (drawable_size, drawable_size),
+        )?;
+
+        let stride = image_size as usize * 3;
+        let buf: &mut [u8] = img;
+        for (y, row) in pixels.chunks_exact(drawable_size as usize).enumerate() {
+            let start = (padding as usize + y) * stride + padding as usize * 3;
+            for (out, &color) in buf[start..].chunks_exact_mut(3).zip(row) {
+                out.copy_from_slice(&unpack(color));
+            }
+        }
+        Ok(())
+    }
+
+    /// Scale `img` to `width` x `height` with the box filter, on the GPU
+    /// when it is a whole factor smaller and fits in the device's buffers
+    pub fn downsample(&self, img: &RgbImage, width: u32, height: u32) -> Result<RgbImage> {
+        let factor = resample::block_factor(img, width, height)
+            .filter(|_| self.fits(img.as_raw().len() as u64));
+        let Some(factor) = factor else {
+            return Ok(resample::resize(img, width, height, Filter::Box));
+        };
+
+        let mut source = img.as_raw().clone();
+        // storage buffers are read a word at a time
+        source.resize(source.len().next_multiple_of(4), 0);
+        let params = [width, height, factor, img.width() * 3];
+        let pixels = self.dispatch(&self.downsample, params, &source, (width, height))?;
+        let raw = pixels.into_iter().flat_map(unpack).collect();
+        Ok(RgbImage::from_raw(width, height, raw).expect("one word per pixel"))
+    }
+
+    /// Whether a buffer of `bytes` can be bound to the shaders
+    fn fits(&self, bytes: u64) -> bool {
+        let limits = self.device.limits();
+        bytes <= limits.max_storage_buffer_binding_size && bytes <= limits.max_buffer_size
+    }
+
+    /// Run `pipeline` once per cell of a `width` x `height` grid and read
+    /// back the word each writes
+    fn dispatch(
+        &self,
+        pipeline: &wgpu::ComputePipeline,
+        params: [u32; 4],
+        input: &[u8],
+        (width, height): (u32, u32),
+    ) -> Result<Vec<u32>> {
+        let device = &self.device;
+        let size = u64::from(width) * u64::from(height) * 4;
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("params"),
+            contents: &params.map(u32::to_le_bytes).concat(),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let input = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("input"),
+            contents: input,
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let output = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("output"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: input.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: output.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = device.create_command_encoder(&Default::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(width.div_ceil(WORKGROUP), height.div_ceil(WORKGROUP), 1);
+        }
+        encoder.copy_buffer_to_buffer(&output, 0, &readback, 0, size);
+        self.queue.submit([encoder.finish()]);
+
+        let (sender, receiver) = mpsc::channel();
+        readback.map_async(wgpu::MapMode::Read, .., move |mapped| {
+            let _ = sender.send(mapped);
+        });
+        device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .map_err(|err| HashdenticonError::Gpu(err.to_string()))?;
+        receiver
+            .recv()
+            .map_err(|err| HashdenticonError::Gpu(err.to_string()))?
+            .map_err(|err| HashdenticonError::Gpu(err.to_string()))?;
+
+        let words = {
+            let mapped = readback
+                .get_mapped_range(..)
+                .map_err(|err| HashdenticonError::Gpu(err.to_string()))?;
+            mapped
+                .chunks_exact(4)
+                .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+                .collect()
+        };
+        readback.unmap();
+        Ok(words)
+    }
+}
+
+/// An RGB color as the shaders' `0xBBGGRR` word
+fn pack([r, g, b]: [u8; 3]) -> u32 {
+    u32::from_le_bytes([r, g, b, 0])
+}
+
+fn unpack(word: u32) -> [u8; 3] {
+    let [r, g, b, _] = word.to_le_bytes();
+    [r, g, b]
+}
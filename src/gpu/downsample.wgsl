@@ -0,0 +1,33 @@
+// Average of each factor x factor block, rounded to nearest, as the CPU box filter
+
+// output width, output height, factor, source row stride in bytes
+@group(0) @binding(0) var<uniform> params: vec4<u32>;
+// packed RGB source bytes, four to a word
+@group(0) @binding(1) var<storage, read> source: array<u32>;
+// one 0xBBGGRR word per output pixel
+@group(0) @binding(2) var<storage, read_write> pixels: array<u32>;
+
+fn byte_at(index: u32) -> u32 {
+    return (source[index / 4u] >> ((index % 4u) * 8u)) & 0xffu;
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let width = params.x;
+    let factor = params.z;
+    if (id.x >= width || id.y >= params.y) {
+        return;
+    }
+
+    var sum = vec3<u32>(0u);
+    for (var dy = 0u; dy < factor; dy++) {
+        let row = (id.y * factor + dy) * params.w;
+        for (var dx = 0u; dx < factor; dx++) {
+            let at = row + (id.x * factor + dx) * 3u;
+            sum += vec3<u32>(byte_at(at), byte_at(at + 1u), byte_at(at + 2u));
+        }
+    }
+    let area = factor * factor;
+    let rgb = (sum + vec3<u32>(area / 2u)) / vec3<u32>(area);
+    pixels[id.y * width + id.x] = rgb.x | (rgb.y << 8u) | (rgb.z << 16u);
+}
@@ -0,0 +1,32 @@
+// Nearest seed point per pixel, as style::voronoi colors them on the CPU
+
+// drawable size, point count
+@group(0) @binding(0) var<uniform> params: vec4<u32>;
+// x, y, and the color packed as 0xBBGGRR, per point
+@group(0) @binding(1) var<storage, read> points: array<vec4<i32>>;
+@group(0) @binding(2) var<storage, read_write> pixels: array<u32>;
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = params.x;
+    if (id.x >= size || id.y >= size) {
+        return;
+    }
+
+    let px = i32(id.x);
+    let py = i32(id.y);
+    var best: i32 = 2147483647;
+    var color = 0u;
+    for (var i = 0u; i < params.y; i++) {
+        let point = points[i];
+        let dx = px - point.x;
+        let dy = py - point.y;
+        let distance = dx * dx + dy * dy;
+        // strictly closer, so ties go to the earlier point as on the CPU
+        if (distance < best) {
+            best = distance;
+            color = u32(point.z);
+        }
+    }
+    pixels[id.y * size + id.x] = color;
+}
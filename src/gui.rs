@@ -0,0 +1,27 @@
+//! Preview window for `--show`, so options can be iterated on without an
+//! external image viewer
+
+use anyhow::{Context, Result};
+use image::RgbImage;
+use minifb::{Key, Window, WindowOptions};
+
+/// Display `img` until the window is closed or Escape is pressed
+pub fn show(img: &RgbImage, title: &str) -> Result<()> {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    // minifb wants one 0RGB word per pixel
+    let buffer: Vec<u32> = img
+        .pixels()
+        .map(|p| u32::from_be_bytes([0, p[0], p[1], p[2]]))
+        .collect();
+
+    let mut window = Window::new(title, width, height, WindowOptions::default())
+        .context("Failed to open preview window")?;
+    window.set_target_fps(30);
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        window
+            .update_with_buffer(&buffer, width, height)
+            .context("Failed to update preview window")?;
+    }
+    Ok(())
+}
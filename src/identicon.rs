@@ -0,0 +1,874 @@
+//! The identicon itself: options, the derived cell pattern, and the
+//! rendered image with the ways to get it out
+
+use crate::background::{self, BackgroundPattern};
+use crate::color;
+use crate::constraint;
+use crate::derive::{Derivation, Streams};
+use crate::error::{HashdenticonError, Result};
+#[cfg(feature = "wgpu")]
+use crate::gpu::Gpu;
+use crate::input::Input;
+use crate::model::Model;
+#[cfg(feature = "raster")]
+use crate::output::raster;
+#[cfg(feature = "svg")]
+use crate::output::svg;
+use crate::output::{self, Encoding, Format};
+use crate::overlay;
+use crate::resample::{self, Filter};
+use crate::stamp::{self, StampKind};
+use crate::style::automaton::Rule;
+use crate::style::{Style, bars, blob, invader, iso, maze, parts, randomart, rings, voronoi};
+use crate::theme::{self, CellShape, Mask};
+use crate::tile;
+use crate::transform::{self, Flip, Orient};
+#[cfg(feature = "svg")]
+use crate::vector::Scene;
+use image::{DynamicImage, Rgb, RgbImage};
+use std::path::PathBuf;
+
+/// How an identicon is rendered. With the `serde` feature, missing fields
+/// deserialize to their defaults, so a partial JSON body is a valid request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Options {
+    /// Width (and default height) in pixels
+    pub size: u32,
+    /// Height in pixels, for styles that support non-square output
+    pub height: Option<u32>,
+    /// Cells along each side of the pattern
+    pub grid_size: u32,
+    /// Padding as a percentage of size
+    pub padding: u32,
+    pub style: Style,
+    /// Generations to evolve for the automaton style
+    pub steps: u32,
+    /// Layered PNG parts for the parts style
+    pub parts_dir: Option<PathBuf>,
+    /// Text (up to 3 characters) drawn centered over the identicon
+    pub initials: Option<String>,
+    /// Small half-transparent text along the bottom edge
+    pub watermark: Option<String>,
+    /// Hash-selected glyph stamped at the center
+    pub stamp: Option<StampKind>,
+    /// Texture drawn in a muted tint behind the pattern
+    pub background_pattern: Option<BackgroundPattern>,
+    /// Percentage of cells to fill, instead of one hash bit per cell
+    pub density: Option<u32>,
+    /// Degrees to rotate the hue of the derived color
+    pub hue_shift: u32,
+    /// Quarter turns clockwise, in degrees, applied after rendering
+    pub rotate: u32,
+    /// Mirroring applied after the rotation
+    pub flip: Option<Flip>,
+    /// Whether `rotate` applies or the hash picks the rotation
+    pub orient: Orient,
+    /// Least percentage of cells filled, topped up from hash-ranked cells;
+    /// `filter_symbols` may still clear a few
+    pub min_fill: Option<u32>,
+    /// Greatest percentage of cells filled, cleared from hash-ranked cells
+    pub max_fill: Option<u32>,
+    /// Break up shapes from a small list of symbols unfit for public avatars
+    pub filter_symbols: bool,
+    /// Always fill the center cell (the center 2x2 block on even grids)
+    pub force_center: bool,
+    /// Always leave the outer ring of cells empty
+    pub clear_border: bool,
+    /// Mirror the pattern on both axes and drop the padding, so the image
+    /// tiles seamlessly
+    pub tileable: bool,
+    /// Width and height of a wallpaper to fill with copies of the identicon
+    pub tile: Option<(u32, u32)>,
+    /// How the digest is split into color, pattern, and style bytes
+    pub derivation: Derivation,
+    /// Render at this many times the size and scale down, smoothing edges
+    pub supersample: u32,
+    /// Filter for supersampling and for resizing parts
+    pub filter: Filter,
+    /// Render voronoi regions and box-filter downsampling on the
+    /// [`Gpu`](crate::gpu::Gpu); the image is the same, only faster at
+    /// large sizes. Not part of presets
+    #[cfg(feature = "wgpu")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub gpu: bool,
+    /// Canvas color, in place of white
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::hex::option"))]
+    pub background: Option<Rgb<u8>>,
+    /// Colors the hash picks the foreground from, in place of deriving it
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::hex::list"))]
+    pub palette: Vec<Rgb<u8>>,
+    /// Shape of each filled cell, for the grid and automaton styles
+    pub cell_shape: CellShape,
+    /// Percentage of each cell left empty around its shape
+    pub gap: u32,
+    /// Outline the finished identicon is cut to
+    pub mask: Option<Mask>,
+    /// Width in pixels of a border just inside the outline
+    pub border: u32,
+    /// Border color, or the foreground color when unset
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::hex::option"))]
+    pub border_color: Option<Rgb<u8>>,
+}
+
+/// Smallest image side the renderers support, in pixels
+pub const MIN_SIZE: u32 = 16;
+/// Largest image side, in pixels, to keep memory use bounded
+pub const MAX_SIZE: u32 = 8192;
+/// Largest padding percentage that leaves room to draw
+pub const MAX_PADDING: u32 = 40;
+/// Largest grid the hash has enough bits for
+pub const MAX_GRID_SIZE: u32 = 15;
+/// Largest gap percentage that leaves each cell a shape to draw
+pub const MAX_GAP: u32 = 50;
+/// Most generations the automaton style evolves, to keep rendering time bounded
+pub const MAX_STEPS: u32 = 64;
+
+impl Options {
+    /// Whether [`gpu`](Options::gpu) would render any part of these
+    /// identicons, the voronoi style or box-filter supersampling; the rest
+    /// always renders on the CPU
+    #[cfg(feature = "wgpu")]
+    pub fn gpu_applies(&self) -> bool {
+        self.style == Style::Voronoi || (self.supersample > 1 && self.filter == Filter::Box)
+    }
+
+    /// Check the options against what the renderers can draw
+    pub fn validate(&self) -> Result<()> {
+        let tile = self.tile.into_iter().flat_map(|(w, h)| [w, h]);
+        for size in [Some(self.size), self.height]
+            .into_iter()
+            .flatten()
+            .chain(tile)
+        {
+            if !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+                return Err(HashdenticonError::InvalidSize { size });
+            }
+        }
+        if self.padding > MAX_PADDING {
+            return Err(HashdenticonError::InvalidPadding {
+                padding: self.padding,
+            });
+        }
+        if let Some(density) = self.density
+            && density > 100
+        {
+            return Err(HashdenticonError::Unsupported(format!(
+                "Density of {}% is over 100%",
+                density
+            )));
+        }
+        if !self.rotate.is_multiple_of(90) {
+            return Err(HashdenticonError::Unsupported(format!(
+                "Rotation of {} degrees is not a quarter turn",
+                self.rotate
+            )));
+        }
+        for percent in [self.min_fill, self.max_fill].into_iter().flatten() {
+            if percent > 100 {
+                return Err(HashdenticonError::Unsupported(format!(
+                    "Fill ratio of {}% is over 100%",
+                    percent
+                )));
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_fill, self.max_fill)
+            && min > max
+        {
+            return Err(HashdenticonError::Unsupported(format!(
+                "Minimum fill of {}% is above the maximum of {}%",
+                min, max
+            )));
+        }
+        if self.grid_size == 0 {
+            return Err(HashdenticonError::InvalidGridSize);
+        }
+        if self.steps > MAX_STEPS {
+            return Err(HashdenticonError::Unsupported(format!(
+                "{} automaton steps is over {}",
+                self.steps, MAX_STEPS
+            )));
+        }
+        if self.gap > MAX_GAP {
+            return Err(HashdenticonError::Unsupported(format!(
+                "Gap of {}% is over {}%",
+                self.gap, MAX_GAP
+            )));
+        }
+        let shorter = self.size.min(self.height.unwrap_or(self.size));
+        if self.border.saturating_mul(2) >= shorter {
+            return Err(HashdenticonError::Unsupported(format!(
+                "A {}px border leaves no room to draw",
+                self.border
+            )));
+        }
+
+        let drawable = self.size - 2 * (self.size * self.padding / 100);
+        let max = MAX_GRID_SIZE.min(drawable);
+        if self.grid_size > max {
+            return Err(HashdenticonError::GridTooLarge {
+                grid_size: self.grid_size as usize,
+                max: max as usize,
+            });
+        }
+
+        if self.supersample == 0 {
+            return Err(HashdenticonError::Unsupported(
+                "Supersampling needs a factor of at least 1".to_string(),
+            ));
+        }
+        let largest = self.size.max(self.height.unwrap_or(0));
+        if largest.saturating_mul(self.supersample) > MAX_SIZE {
+            return Err(HashdenticonError::Unsupported(format!(
+                "Supersampling {}px by {} exceeds {}px",
+                largest, self.supersample, MAX_SIZE
+            )));
+        }
+        if self.tileable && self.supersample > 1 {
+            return Err(HashdenticonError::Unsupported(
+                "--supersample would blur the edges of a seamless tile".to_string(),
+            ));
+        }
+        if self.tileable && !tile::supports(self.style) {
+            return Err(HashdenticonError::Unsupported(format!(
+                "The {} style cannot be drawn as a seamless tile",
+                self.style
+            )));
+        }
+        if self.height.is_some() && self.style != Style::Bars {
+            return Err(HashdenticonError::Unsupported(
+                "--height is only supported by the bars style".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The canvas color, white unless a background is given
+    pub(crate) fn canvas_color(&self) -> Rgb<u8> {
+        self.background.unwrap_or(Rgb([255, 255, 255]))
+    }
+
+    /// Cut the finished `image` to the mask and draw its border
+    fn frame(&self, image: &mut RgbImage, color: Rgb<u8>) {
+        let border_color = self.border_color.unwrap_or(color);
+        theme::frame(
+            image,
+            self.mask,
+            self.border,
+            border_color,
+            self.canvas_color(),
+        );
+    }
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            size: 420,
+            height: None,
+            grid_size: 5,
+            padding: 8,
+            style: Style::Grid,
+            steps: 4,
+            parts_dir: None,
+            initials: None,
+            watermark: None,
+            stamp: None,
+            background_pattern: None,
+            density: None,
+            hue_shift: 0,
+            rotate: 0,
+            flip: None,
+            orient: Orient::Fixed,
+            min_fill: None,
+            max_fill: None,
+            filter_symbols: false,
+            force_center: false,
+            clear_border: false,
+            tileable: false,
+            tile: None,
+            derivation: Derivation::Legacy,
+            supersample: 1,
+            filter: Filter::Lanczos3,
+            #[cfg(feature = "wgpu")]
+            gpu: false,
+            background: None,
+            palette: Vec::new(),
+            cell_shape: CellShape::Square,
+            gap: 0,
+            mask: None,
+            border: 0,
+            border_color: None,
+        }
+    }
+}
+
+/// Row-major grid of cells, `true` meaning filled
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct BitMatrix {
+    rows: Vec<Vec<bool>>,
+}
+
+impl BitMatrix {
+    /// Wrap rows of cells; every row should be the same length
+    pub fn from_rows(rows: Vec<Vec<bool>>) -> BitMatrix {
+        BitMatrix { rows }
+    }
+
+    pub fn width(&self) -> usize {
+        self.rows.first().map_or(0, Vec::len)
+    }
+
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Whether the cell at column `x`, row `y` is filled
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.rows[y][x]
+    }
+
+    pub fn rows(&self) -> &[Vec<bool>] {
+        &self.rows
+    }
+
+    pub fn into_rows(self) -> Vec<Vec<bool>> {
+        self.rows
+    }
+}
+
+/// A rendered identicon
+#[derive(Debug, Clone)]
+pub struct Identicon {
+    image: RgbImage,
+    pattern: BitMatrix,
+    color: Rgb<u8>,
+}
+
+impl Identicon {
+    /// Render the identicon for a plain seed string
+    pub fn from_seed(seed: &str, options: &Options) -> Result<Identicon> {
+        Identicon::new(&Input::from_seed(seed).digest, options)
+    }
+
+    /// Render the identicon for a digest
+    #[tracing::instrument(name = "identicon", level = "debug", skip_all, fields(style = %options.style))]
+    pub fn new(hash: &[u8], options: &Options) -> Result<Identicon> {
+        let (streams, color, mut pattern) = Identicon::start(hash, options)?;
+        if options.style == Style::Automaton {
+            pattern = Rule::from_hash(&streams.style).generation(pattern, options.steps);
+        }
+        Identicon::finish(&streams, pattern, color, options)
+    }
+
+    /// Render every generation of the automaton style, from the first to
+    /// the one `new` renders, each with the same constraints and overlays
+    pub fn generations(hash: &[u8], options: &Options) -> Result<Vec<Identicon>> {
+        let (streams, color, pattern) = Identicon::start(hash, options)?;
+        Identicon::evolve(&streams, color, pattern, options)
+    }
+
+    /// Every generation from `pattern` on, each constrained and drawn
+    pub(crate) fn evolve(
+        streams: &Streams,
+        color: Rgb<u8>,
+        pattern: Vec<Vec<bool>>,
+        options: &Options,
+    ) -> Result<Vec<Identicon>> {
+        Rule::from_hash(&streams.style)
+            .generations(pattern, options.steps)
+            .into_iter()
+            .map(|pattern| Identicon::finish(streams, pattern, color, options))
+            .collect()
+    }
+
+    /// The streams, color and unconstrained first pattern for a digest
+    fn start(hash: &[u8], options: &Options) -> Result<(Streams, Rgb<u8>, Vec<Vec<bool>>)> {
+        options.validate()?;
+        let streams = Streams::new(hash, options.derivation);
+        let needed = digest_len(options.grid_size);
+        if streams.pattern.len() < needed {
+            return Err(HashdenticonError::InvalidInput(format!(
+                "A {}x{} grid needs a digest of at least {} bytes, got {}",
+                options.grid_size,
+                options.grid_size,
+                needed,
+                streams.pattern.len()
+            )));
+        }
+        let color = match theme::pick(&options.palette, &streams.color) {
+            Some(color) => color,
+            None => generate_color(&streams.color)?,
+        };
+        let color = color::shift_hue(color, options.hue_shift);
+        let pattern = match options.density {
+            Some(percent) => {
+                generate_pattern_with_density(&streams.pattern, options.grid_size, percent)
+            }
+            None => generate_pattern(&streams.pattern, options.grid_size),
+        };
+        Ok((streams, color, pattern))
+    }
+
+    /// Constrain `pattern` and draw it with everything `options` adds
+    fn finish(
+        streams: &Streams,
+        pattern: Vec<Vec<bool>>,
+        color: Rgb<u8>,
+        options: &Options,
+    ) -> Result<Identicon> {
+        let hash = streams.style.as_slice();
+        let pattern = constraint::apply(pattern, &streams.pattern, options);
+
+        let mut image = if options.tileable {
+            let unpadded = Options {
+                padding: 0,
+                ..options.clone()
+            };
+            let image = create_identicon(hash, &pattern, color, &unpadded)?;
+            tile::trim(&image, options.grid_size)
+        } else if options.supersample > 1 {
+            let factor = options.supersample;
+            let large = Options {
+                size: options.size * factor,
+                height: options.height.map(|height| height * factor),
+                ..options.clone()
+            };
+            let image = create_identicon(hash, &pattern, color, &large)?;
+            let height = options.height.unwrap_or(options.size);
+            downsample(&image, options.size, height, options)?
+        } else {
+            create_identicon(hash, &pattern, color, options)?
+        };
+        let rotation = transform::rotation(options.orient, options.rotate, hash);
+        image = transform::apply(image, rotation, options.flip)?;
+        if let Some((width, height)) = options.tile {
+            image = tile::repeat(&image, width, height);
+        }
+        if let Some(kind) = options.stamp {
+            stamp::select(hash).draw(&mut image, kind);
+        }
+        if let Some(text) = &options.initials {
+            overlay::draw_initials(&mut image, text);
+        }
+        if let Some(text) = &options.watermark {
+            overlay::draw_watermark(&mut image, text);
+        }
+        options.frame(&mut image, color);
+
+        Ok(Identicon {
+            image,
+            pattern: BitMatrix::from_rows(pattern),
+            color,
+        })
+    }
+
+    /// Redraw a pattern model, in the style given by `options`
+    #[tracing::instrument(name = "identicon", level = "debug", skip_all, fields(style = %options.style))]
+    pub fn from_model(model: &Model, options: &Options) -> Result<Identicon> {
+        let grid_size = model.pattern.len();
+        if grid_size == 0 || model.pattern.iter().any(|row| row.len() != grid_size) {
+            return Err(HashdenticonError::InvalidModel(
+                "pattern must be a non-empty square grid".to_string(),
+            ));
+        }
+        // models may hold grids larger than the hash could derive
+        let options = &Options {
+            grid_size: 1,
+            ..options.clone()
+        };
+        options.validate()?;
+        let drawable = options.size - 2 * (options.size * options.padding / 100);
+        if grid_size > drawable as usize {
+            return Err(HashdenticonError::GridTooLarge {
+                grid_size,
+                max: drawable as usize,
+            });
+        }
+        if options.tileable {
+            return Err(HashdenticonError::Unsupported(
+                "--tileable would change the model's pattern; mirror it in the model instead"
+                    .to_string(),
+            ));
+        }
+        if options.stamp.is_some() || options.orient == Orient::FromHash {
+            return Err(HashdenticonError::Unsupported(
+                "--stamp and --orient from-hash need the seed digest, which a pattern model does not carry"
+                    .to_string(),
+            ));
+        }
+
+        let size = options.size;
+        let base = options.background.unwrap_or(model.background);
+        let mut image =
+            background::canvas_on(base, options.background_pattern, model.color, size, size);
+
+        // the model already holds any automaton generations, so draw it as is
+        match options.style {
+            Style::Grid | Style::Automaton => render_cells(
+                &mut image,
+                &model.pattern,
+                model.color,
+                options.padding,
+                options.cell_shape,
+                options.gap,
+            ),
+            // the model does not record which side lights the cubes
+            Style::Iso => iso::render(
+                &model.pattern,
+                model.color,
+                &[],
+                &mut image,
+                options.padding,
+            ),
+            Style::Blob => blob::render(&model.pattern, model.color, &mut image, options.padding),
+            other => {
+                return Err(HashdenticonError::Unsupported(format!(
+                    "The {} style cannot be drawn from a pattern model",
+                    other
+                )));
+            }
+        }
+        let mut image = transform::apply(image, options.rotate, options.flip)?;
+        if let Some((width, height)) = options.tile {
+            image = tile::repeat(&image, width, height);
+        }
+        if let Some(text) = &options.initials {
+            overlay::draw_initials(&mut image, text);
+        }
+        if let Some(text) = &options.watermark {
+            overlay::draw_watermark(&mut image, text);
+        }
+        options.frame(&mut image, model.color);
+
+        Ok(Identicon {
+            image,
+            pattern: BitMatrix::from_rows(model.pattern.clone()),
+            color: model.color,
+        })
+    }
+
+    /// The cell pattern derived from the hash; grid-based styles draw
+    /// exactly this, after any automaton generations
+    pub fn pattern(&self) -> &BitMatrix {
+        &self.pattern
+    }
+
+    /// Foreground color derived from the hash
+    pub fn color(&self) -> Rgb<u8> {
+        self.color
+    }
+
+    pub fn as_rgb(&self) -> &RgbImage {
+        &self.image
+    }
+
+    pub fn into_rgb(self) -> RgbImage {
+        self.image
+    }
+
+    pub fn to_image(&self) -> DynamicImage {
+        DynamicImage::ImageRgb8(self.image.clone())
+    }
+
+    /// The image encoded as PNG, tagged sRGB; with the `raster` feature
+    #[cfg(feature = "raster")]
+    #[tracing::instrument(name = "encode", level = "debug", skip_all, fields(format = "png"))]
+    pub fn to_png_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        raster::png(&self.image, &Encoding::default(), &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Append the image to `buf` as `format`, with default encoder
+    /// settings; clear `buf` first to reuse its allocation across images.
+    /// On failure `buf` is left as it was
+    pub fn encode_into(&self, buf: &mut Vec<u8>, format: Format) -> Result<()> {
+        // source formats name their arrays after the file
+        let name = PathBuf::from(format!("identicon.{}", format.extension()));
+        output::encode_into(&self.image, &name, Some(format), &Encoding::default(), buf)
+    }
+
+    /// The image as an SVG document of filled rectangles; with the `svg`
+    /// feature
+    #[cfg(feature = "svg")]
+    #[tracing::instrument(name = "encode", level = "debug", skip_all, fields(format = "svg"))]
+    pub fn to_svg_string(&self) -> String {
+        svg::encode(&Scene::trace(&self.image))
+    }
+}
+
+/// Bytes of digest the color and a `grid_size` pattern read, the first
+/// three for the color and a bit per cell of the left half after them
+fn digest_len(grid_size: u32) -> usize {
+    COLOR_BYTES + (grid_size * grid_size.div_ceil(2)).div_ceil(8) as usize
+}
+
+const COLOR_BYTES: usize = 3;
+
+/// The color of a digest, from its first three bytes
+pub fn generate_color(hash: &[u8]) -> Result<Rgb<u8>> {
+    let &[r, g, b, ..] = hash else {
+        return Err(HashdenticonError::InvalidInput(format!(
+            "A color needs a digest of at least {} bytes, got {}",
+            COLOR_BYTES,
+            hash.len()
+        )));
+    };
+
+    // normalize over range to ensure the color isn't too dark or too light
+    let min = 50;
+    let max = 200;
+
+    Ok(Rgb([
+        (min + (r as u32 * (max - min) / 255)) as u8,
+        (min + (g as u32 * (max - min) / 255)) as u8,
+        (min + (b as u32 * (max - min) / 255)) as u8,
+    ]))
+}
+
+#[tracing::instrument(name = "pattern", level = "debug", skip(hash))]
+pub fn generate_pattern(hash: &[u8], grid_size: u32) -> Vec<Vec<bool>> {
+    let mut pattern = vec![vec![false; grid_size as usize]; grid_size as usize];
+
+    // max cells to generate: 15 x 8 = 120
+    // available bits from hash: 29 bytes * 8 = 232 bits
+    let mut byte_idx = 3; // skip first 3 bytes used for color
+    let mut bit_idx = 0;
+
+    // only need to generate half the width, then mirror horizontally
+    let half_width = grid_size.div_ceil(2);
+
+    // iterate through reduced grid
+    for y in 0..grid_size {
+        for x in 0..half_width {
+            if byte_idx < hash.len() {
+                // choose corresponding bit in hash for cell
+                let byte = hash[byte_idx];
+                let bit = (byte >> bit_idx) & 1;
+
+                // fill cell with bit
+                pattern[y as usize][x as usize] = bit == 1;
+
+                // mirror horizontally; skip mid on odd
+                let mirror_x = grid_size - 1 - x;
+                if mirror_x != x {
+                    pattern[y as usize][mirror_x as usize] = bit == 1;
+                }
+
+                // increment indices
+                bit_idx += 1;
+                if bit_idx >= 8 {
+                    bit_idx = 0;
+                    byte_idx += 1;
+                }
+            }
+        }
+    }
+
+    pattern
+}
+
+/// Like [`generate_pattern`], but fill a fixed percentage of cells: every
+/// cell of the left half gets a hash-seeded score and the highest scoring
+/// ones are filled, then mirrored
+#[tracing::instrument(name = "pattern", level = "debug", skip(hash))]
+pub fn generate_pattern_with_density(hash: &[u8], grid_size: u32, percent: u32) -> Vec<Vec<bool>> {
+    let size = grid_size as usize;
+    let cells = constraint::ranked_half_cells(hash, size);
+
+    let target = (cells.len() * percent as usize + 50) / 100;
+    let mut pattern = vec![vec![false; size]; size];
+    for &(y, x) in &cells[..target] {
+        pattern[y][x] = true;
+        pattern[y][size - 1 - x] = true;
+    }
+    pattern
+}
+
+/// Scale a supersampled render down to `width` x `height`
+fn downsample(image: &RgbImage, width: u32, height: u32, options: &Options) -> Result<RgbImage> {
+    #[cfg(feature = "wgpu")]
+    if options.gpu && options.filter == Filter::Box {
+        return Gpu::global()?.downsample(image, width, height);
+    }
+    Ok(resample::resize(image, width, height, options.filter))
+}
+
+#[tracing::instrument(name = "render", level = "debug", skip_all, fields(size = options.size))]
+fn create_identicon(
+    hash: &[u8],
+    pattern: &[Vec<bool>],
+    color: Rgb<u8>,
+    options: &Options,
+) -> Result<RgbImage> {
+    let (image_size, padding_percent) = (options.size, options.padding);
+
+    let height = options.height.unwrap_or(image_size);
+    let mut img = background::canvas_on(
+        options.canvas_color(),
+        options.background_pattern,
+        color,
+        image_size,
+        height,
+    );
+    let canvas = &mut img;
+
+    match options.style {
+        Style::Grid | Style::Automaton => render_cells(
+            canvas,
+            pattern,
+            color,
+            padding_percent,
+            options.cell_shape,
+            options.gap,
+        ),
+        Style::Randomart => randomart::Field::walk(hash).render(color, canvas, padding_percent),
+        Style::Iso => iso::render(pattern, color, hash, canvas, padding_percent),
+        Style::Blob => blob::render(pattern, color, canvas, padding_percent),
+        #[cfg(feature = "wgpu")]
+        Style::Voronoi if options.gpu => {
+            Gpu::global()?.voronoi(hash, options.grid_size, canvas, padding_percent)?;
+        }
+        Style::Voronoi => voronoi::render(hash, options.grid_size, canvas, padding_percent),
+        Style::Rings => {
+            let rings = options.grid_size.div_ceil(2);
+            rings::render(hash, rings, color, canvas, padding_percent);
+        }
+        Style::Maze => maze::render(hash, options.grid_size, color, canvas, padding_percent),
+        Style::Invader => invader::render(hash, color, canvas, padding_percent),
+        Style::Parts => {
+            let parts_dir = options.parts_dir.as_deref().ok_or_else(|| {
+                HashdenticonError::Unsupported("The parts style requires --parts-dir".to_string())
+            })?;
+            parts::render(hash, parts_dir, canvas, padding_percent, options.filter)?;
+        }
+        Style::Bars => bars::render(hash, canvas, padding_percent),
+    }
+
+    Ok(img)
+}
+
+pub fn render_grid(
+    img: &mut RgbImage,
+    pattern: &[Vec<bool>],
+    color: Rgb<u8>,
+    padding_percent: u32,
+) {
+    render_cells(img, pattern, color, padding_percent, CellShape::Square, 0);
+}
+
+/// [`render_grid`], drawing each filled cell as `shape` with `gap` percent
+/// of it left empty
+pub fn render_cells(
+    img: &mut RgbImage,
+    pattern: &[Vec<bool>],
+    color: Rgb<u8>,
+    padding_percent: u32,
+    shape: CellShape,
+    gap: u32,
+) {
+    let grid_size = pattern.len() as u32;
+    let image_size = img.width();
+
+    // calculate padding based on percentage
+    let padding = image_size * padding_percent / 100;
+    let drawable_size = image_size - (2 * padding);
+
+    // calculate cell size and additional padding to handle non-exact divisions
+    let cell_size = drawable_size / grid_size;
+    let total_used = cell_size * grid_size;
+    let extra_padding = (drawable_size - total_used) / 2;
+    let total_padding = padding + extra_padding;
+
+    // draw the pattern; sparse iteration through filled cells
+    for (y, row) in pattern.iter().enumerate() {
+        for (x, &filled) in row.iter().enumerate() {
+            if filled {
+                let x_start = total_padding + (x as u32) * cell_size;
+                let y_start = total_padding + (y as u32) * cell_size;
+
+                theme::draw_cell(img, (x_start, y_start), cell_size, shape, gap, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_digests_are_an_error() {
+        let options = Options {
+            grid_size: 15,
+            ..Options::default()
+        };
+        // 3 color bytes and 120 bits of pattern
+        assert_eq!(digest_len(15), 18);
+        for len in [0, 2, 3, 17] {
+            assert!(
+                matches!(
+                    Identicon::new(&vec![0xa5; len], &options),
+                    Err(HashdenticonError::InvalidInput(_))
+                ),
+                "{} bytes",
+                len
+            );
+        }
+        assert!(Identicon::new(&[0xa5; 18], &options).is_ok());
+    }
+
+    #[test]
+    fn smaller_grids_need_fewer_bytes() {
+        let options = Options {
+            grid_size: 5,
+            ..Options::default()
+        };
+        assert_eq!(digest_len(5), 5);
+        assert!(Identicon::new(&[0xa5; 5], &options).is_ok());
+        assert!(Identicon::new(&[0xa5; 4], &options).is_err());
+    }
+
+    #[test]
+    fn hkdf_stretches_any_digest() {
+        let options = Options {
+            grid_size: 15,
+            derivation: Derivation::Hkdf,
+            ..Options::default()
+        };
+        assert!(Identicon::new(&[], &options).is_ok());
+    }
+
+    #[test]
+    fn steps_are_bounded() {
+        let options = Options {
+            style: Style::Automaton,
+            steps: MAX_STEPS,
+            ..Options::default()
+        };
+        assert!(Identicon::from_seed("alice", &options).is_ok());
+        let options = Options {
+            steps: MAX_STEPS + 1,
+            ..options
+        };
+        assert!(matches!(
+            Identicon::from_seed("alice", &options),
+            Err(HashdenticonError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn colors_need_three_bytes() {
+        for len in 0..3 {
+            assert!(matches!(
+                generate_color(&vec![0; len]),
+                Err(HashdenticonError::InvalidInput(_))
+            ));
+        }
+        assert_eq!(generate_color(&[0, 255, 0]).unwrap(), Rgb([50, 200, 50]));
+    }
+}
@@ -0,0 +1,705 @@
+//! Normalization of the different seed inputs into digest bytes
+
+#[cfg(feature = "keys")]
+use crate::cert;
+use crate::error::{HashdenticonError, Result};
+#[cfg(feature = "keys")]
+use base64::Engine;
+#[cfg(feature = "keys")]
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+use sha2::{Digest, Sha256};
+use std::io::{self, Read};
+#[cfg(feature = "argon2")]
+use std::str::FromStr;
+#[cfg(feature = "uuid")]
+use uuid::Uuid;
+
+/// Digest bytes to visualize, along with how they were produced
+pub struct Input {
+    /// Canonical text form, used for messages and default file names
+    pub label: String,
+    /// Bytes fed into color and pattern generation
+    pub digest: Vec<u8>,
+    /// Name of the algorithm that produced `digest`
+    pub algorithm: &'static str,
+}
+
+impl Input {
+    /// Hash an arbitrary seed string
+    #[tracing::instrument(name = "hash", level = "debug", skip_all)]
+    pub fn from_seed(seed: &str) -> Self {
+        Self {
+            label: seed.to_string(),
+            digest: hash_seed(seed),
+            algorithm: "SHA256",
+        }
+    }
+
+    /// Hash a seed with keyed BLAKE3, so identicons can't be linked back to
+    /// seeds without the key
+    #[cfg(feature = "keyed")]
+    #[tracing::instrument(name = "hash", level = "debug", skip_all)]
+    pub fn from_seed_keyed(seed: &str, key: &[u8; 32]) -> Self {
+        Self {
+            label: seed.to_string(),
+            digest: blake3::keyed_hash(key, seed.as_bytes()).as_bytes().to_vec(),
+            algorithm: "BLAKE3",
+        }
+    }
+
+    /// Stretch a low-entropy seed such as a PIN with Argon2id, so the
+    /// identicon can't be cheaply brute-forced back to it
+    #[cfg(feature = "argon2")]
+    #[tracing::instrument(name = "hash", level = "debug", skip_all)]
+    pub fn from_seed_argon2(seed: &str, params: &Argon2Params) -> Result<Self> {
+        let invalid = |err: argon2::Error| {
+            HashdenticonError::InvalidInput(format!("Invalid Argon2 parameters: {}", err))
+        };
+        let argon2_params = argon2::Params::new(
+            params.memory_kib,
+            params.iterations,
+            params.parallelism,
+            Some(32),
+        )
+        .map_err(invalid)?;
+        let mut digest = vec![0; 32];
+        argon2::Argon2::new(
+            argon2::Algorithm::Argon2id,
+            argon2::Version::V0x13,
+            argon2_params,
+        )
+        .hash_password_into(seed.as_bytes(), ARGON2_SALT, &mut digest)
+        .map_err(invalid)?;
+
+        Ok(Self {
+            label: seed.to_string(),
+            digest,
+            algorithm: "Argon2id",
+        })
+    }
+
+    /// Canonicalize a UUID before hashing, so formatting doesn't matter
+    #[cfg(feature = "uuid")]
+    pub fn from_uuid(input: &str) -> Result<Self> {
+        Ok(Self::from_seed(&canonicalize_uuid(input)?))
+    }
+
+    /// Hash a certificate's SubjectPublicKeyInfo, as used for HPKP pins
+    #[cfg(feature = "keys")]
+    #[tracing::instrument(name = "hash", level = "debug", skip_all)]
+    pub fn from_certificate(data: &[u8]) -> Result<Self> {
+        let spki = cert::spki_from_bytes(data)?;
+        let digest = Sha256::digest(&spki).to_vec();
+
+        Ok(Self {
+            label: hex(&digest),
+            digest,
+            algorithm: "SHA256",
+        })
+    }
+
+    /// Stream arbitrary content through SHA-256, e.g. a file checksum
+    #[tracing::instrument(name = "hash", level = "debug", skip_all)]
+    pub fn from_reader(mut reader: impl Read) -> io::Result<Self> {
+        let mut hasher = Sha256::new();
+        io::copy(&mut reader, &mut hasher)?;
+        let digest = hasher.finalize().to_vec();
+
+        Ok(Self {
+            label: hex(&digest),
+            digest,
+            algorithm: "SHA256",
+        })
+    }
+
+    /// Fingerprint an SSH public key blob the way `ssh-keygen -l` does
+    #[cfg(feature = "keys")]
+    pub fn from_key_blob(blob: &[u8]) -> Self {
+        let digest = Sha256::digest(blob).to_vec();
+
+        Self {
+            label: format!("SHA256:{}", STANDARD_NO_PAD.encode(&digest)),
+            digest,
+            algorithm: "SHA256",
+        }
+    }
+
+    /// Use an existing key fingerprint as the digest, without rehashing.
+    /// MD5 and SHA-1 fingerprints are too short to fill the larger grids,
+    /// so they are stretched to 32 bytes, starting with the fingerprint and
+    /// followed by a SHA-256 of it; their randomart walks the stretched
+    /// digest, and so differs from `ssh-keygen -E md5 -lv`.
+    #[cfg(feature = "keys")]
+    pub fn from_fingerprint(input: &str) -> Result<Self> {
+        let (algorithm, mut digest) = parse_fingerprint(input.trim()).map_err(|reason| {
+            HashdenticonError::InvalidInput(format!("Invalid fingerprint {}: {}", input, reason))
+        })?;
+        if digest.len() < FINGERPRINT_LEN {
+            let mut hasher = Sha256::new();
+            hasher.update(b"hashdenticon fingerprint\0");
+            hasher.update(&digest);
+            let tail = hasher.finalize();
+            digest.extend_from_slice(&tail[..FINGERPRINT_LEN - digest.len()]);
+        }
+
+        Ok(Self {
+            label: input.trim().to_string(),
+            digest,
+            algorithm,
+        })
+    }
+
+    /// An alternate digest for the same input, domain-separated by `index`,
+    /// so a disliked identicon can be re-rolled reproducibly. Variant 0 is
+    /// the input itself.
+    pub fn variant(&self, index: u32) -> Self {
+        if index == 0 {
+            return Self {
+                label: self.label.clone(),
+                digest: self.digest.clone(),
+                algorithm: self.algorithm,
+            };
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(b"hashdenticon variant\0");
+        hasher.update(index.to_be_bytes());
+        hasher.update(&self.digest);
+        Self {
+            label: self.label.clone(),
+            digest: hasher.finalize().to_vec(),
+            algorithm: "SHA256",
+        }
+    }
+
+    /// HPKP-style pin of the digest, e.g. `pin-sha256="..."`
+    #[cfg(feature = "keys")]
+    pub fn pin(&self) -> String {
+        format!(
+            "pin-{}=\"{}\"",
+            self.algorithm.to_ascii_lowercase(),
+            STANDARD.encode(&self.digest)
+        )
+    }
+}
+
+/// Seeds shared by so many users that their identicons identify nobody
+const COMMON_SEEDS: &[&str] = &[
+    "123456",
+    "12345678",
+    "abc",
+    "admin",
+    "administrator",
+    "anonymous",
+    "default",
+    "demo",
+    "example",
+    "foo",
+    "guest",
+    "hello",
+    "info",
+    "mail",
+    "me",
+    "null",
+    "password",
+    "root",
+    "support",
+    "test",
+    "test@example.com",
+    "user",
+    "user@example.com",
+    "username",
+];
+
+/// Seeds shorter than this many characters have too few possibilities
+pub const MIN_SEED_LEN: usize = 4;
+
+/// Why many users could end up sharing the identicon of `seed`, if they could
+pub fn low_entropy_reason(seed: &str) -> Option<String> {
+    let seed = seed.trim();
+    let len = seed.chars().count();
+    if len < MIN_SEED_LEN {
+        return Some(format!("the seed is only {} characters long", len));
+    }
+    let lower = seed.to_lowercase();
+    if COMMON_SEEDS.contains(&lower.as_str()) {
+        return Some(format!("{:?} is a very common seed", seed));
+    }
+    let mut chars = lower.chars();
+    if let Some(first) = chars.next()
+        && chars.all(|c| c == first)
+    {
+        return Some(format!("{:?} is one character repeated", seed));
+    }
+    None
+}
+
+/// Parse a 32-byte key given as 64 hex digits
+pub fn parse_key(hex: &str) -> Result<[u8; 32]> {
+    let invalid = || HashdenticonError::InvalidInput("Key must be 64 hex digits".to_string());
+    let hex = hex.trim();
+    // from_str_radix takes a sign too, so check each digit first
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let mut key = [0u8; 32];
+    for (idx, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[idx * 2..idx * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(key)
+}
+
+/// Fixed salt, so the same seed always stretches to the same identicon;
+/// namespace seeds to keep them apart between deployments
+#[cfg(feature = "argon2")]
+const ARGON2_SALT: &[u8] = b"hashdenticon argon2 seed";
+
+/// Argon2id costs, written like `m=19456,t=2,p=1`
+#[cfg(feature = "argon2")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Argon2Params {
+    /// Memory in KiB (`m`)
+    pub memory_kib: u32,
+    /// Passes over memory (`t`)
+    pub iterations: u32,
+    /// Lanes (`p`)
+    pub parallelism: u32,
+}
+
+#[cfg(feature = "argon2")]
+impl Default for Argon2Params {
+    /// The OWASP-recommended minimum, as used by the argon2 crate
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+#[cfg(feature = "argon2")]
+impl FromStr for Argon2Params {
+    type Err = HashdenticonError;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let mut params = Self::default();
+        for pair in text.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let invalid = || {
+                HashdenticonError::InvalidInput(format!(
+                    "Invalid Argon2 parameter {:?}, expected m=, t=, or p= with a number",
+                    pair
+                ))
+            };
+            let (name, value) = pair.split_once('=').ok_or_else(invalid)?;
+            let value: u32 = value.trim().parse().map_err(|_| invalid())?;
+            match name.trim() {
+                "m" => params.memory_kib = value,
+                "t" => params.iterations = value,
+                "p" => params.parallelism = value,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(params)
+    }
+}
+
+/// Lowercase hex encoding of `bytes`
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hash_seed(seed: &str) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(seed.as_bytes());
+    hasher.finalize().to_vec()
+}
+
+#[cfg(feature = "uuid")]
+fn canonicalize_uuid(input: &str) -> Result<String> {
+    // accepts hyphenated, simple, braced, and urn forms in any case
+    let uuid = Uuid::parse_str(input.trim()).map_err(|err| {
+        HashdenticonError::InvalidInput(format!("Invalid UUID {}: {}", input, err))
+    })?;
+    Ok(uuid.hyphenated().to_string())
+}
+
+/// Bytes a fingerprint digest is stretched to, enough for every grid size
+#[cfg(feature = "keys")]
+const FINGERPRINT_LEN: usize = 32;
+
+#[cfg(feature = "keys")]
+fn parse_fingerprint(input: &str) -> Result<(&'static str, Vec<u8>), String> {
+    // openssh style: "SHA256:" followed by unpadded base64
+    if let Some((prefix, rest)) = input.split_once(':') {
+        let algorithm = match prefix.to_ascii_uppercase().as_str() {
+            "SHA256" => Some(("SHA256", 32)),
+            "SHA384" => Some(("SHA384", 48)),
+            "SHA512" => Some(("SHA512", 64)),
+            _ => None,
+        };
+        if let Some((algorithm, len)) = algorithm {
+            let bytes = STANDARD_NO_PAD
+                .decode(rest.trim_end_matches('='))
+                .map_err(|_| "not valid base64".to_string())?;
+            if bytes.len() != len {
+                return Err(format!(
+                    "{} fingerprint must be {} bytes, got {}",
+                    algorithm,
+                    len,
+                    bytes.len()
+                ));
+            }
+            return Ok((algorithm, bytes));
+        }
+
+        // legacy openssh style: optional "MD5:" followed by colon-separated hex
+        let hex = if prefix.eq_ignore_ascii_case("MD5") {
+            rest
+        } else {
+            input
+        };
+        let bytes = hex
+            .split(':')
+            .map(|pair| {
+                if pair.len() != 2 {
+                    return Err(format!("expected two hex digits per group, got {:?}", pair));
+                }
+                if !pair.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err("not valid hex".to_string());
+                }
+                u8::from_str_radix(pair, 16).map_err(|_| "not valid hex".to_string())
+            })
+            .collect::<Result<Vec<u8>, _>>()?;
+        return Ok((algorithm_for_len(bytes.len())?, bytes));
+    }
+
+    // gpg style: plain hex, optionally grouped with whitespace
+    let hex: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+    if !hex.len().is_multiple_of(2) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("unrecognized format".to_string());
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| "not valid hex".to_string())?;
+
+    Ok((algorithm_for_len(bytes.len())?, bytes))
+}
+
+#[cfg(feature = "keys")]
+fn algorithm_for_len(len: usize) -> Result<&'static str, String> {
+    match len {
+        16 => Ok("MD5"),
+        20 => Ok("SHA1"),
+        32 => Ok("SHA256"),
+        _ => Err(format!("unsupported length of {} bytes", len)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn uuid_forms_hash_alike() {
+        let canonical = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        let expected = Input::from_seed(canonical).digest;
+        for form in [
+            canonical,
+            "67e5504410b1426f9247bb680e5fe0c8",
+            "{67e55044-10b1-426f-9247-bb680e5fe0c8}",
+            "urn:uuid:67e55044-10b1-426f-9247-bb680e5fe0c8",
+            "67E55044-10B1-426F-9247-BB680E5FE0C8",
+            " 67e55044-10b1-426f-9247-bb680e5fe0c8\n",
+        ] {
+            assert_eq!(canonicalize_uuid(form).unwrap(), canonical, "{}", form);
+            assert_eq!(Input::from_uuid(form).unwrap().digest, expected, "{}", form);
+        }
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn rejects_malformed_uuids() {
+        for text in [
+            "",
+            "67e55044-10b1-426f-9247-bb680e5fe0c",
+            "67e55044-10b1-426f-9247-bb680e5fe0c8a",
+            "67e55044-10b1-426f-9247-bb680e5fe0cg",
+            "67e5504410b1-426f-9247-bb680e5fe0c8",
+            "{67e55044-10b1-426f-9247-bb680e5fe0c8",
+            "urn:67e55044-10b1-426f-9247-bb680e5fe0c8",
+        ] {
+            assert!(
+                matches!(
+                    Input::from_uuid(text),
+                    Err(HashdenticonError::InvalidInput(_))
+                ),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn short_seeds_are_low_entropy() {
+        assert_eq!(MIN_SEED_LEN, 4);
+        assert_eq!(
+            low_entropy_reason("ab1"),
+            Some("the seed is only 3 characters long".to_string())
+        );
+        assert_eq!(
+            low_entropy_reason("  xy \n"),
+            Some("the seed is only 2 characters long".to_string())
+        );
+        // counted in characters, not bytes
+        assert!(low_entropy_reason("éèê").is_some());
+        assert_eq!(low_entropy_reason("ab12"), None);
+        assert_eq!(low_entropy_reason("éèêë"), None);
+    }
+
+    #[test]
+    fn common_seeds_are_low_entropy() {
+        assert_eq!(
+            low_entropy_reason("Admin"),
+            Some("\"Admin\" is a very common seed".to_string())
+        );
+        assert!(low_entropy_reason("test@example.com").is_some());
+        assert_eq!(low_entropy_reason("admin7"), None);
+    }
+
+    #[test]
+    fn repeated_characters_are_low_entropy() {
+        assert_eq!(
+            low_entropy_reason("aaaa"),
+            Some("\"aaaa\" is one character repeated".to_string())
+        );
+        assert!(low_entropy_reason("AaAaAa").is_some());
+        assert!(low_entropy_reason("0000000000").is_some());
+        assert_eq!(low_entropy_reason("aaab"), None);
+    }
+
+    #[test]
+    fn good_seeds_are_not_low_entropy() {
+        for seed in ["alice@example.org", "user-8f3a2c", "Zoë Ng"] {
+            assert_eq!(low_entropy_reason(seed), None, "{}", seed);
+        }
+    }
+
+    #[test]
+    fn parses_keys_in_either_case() {
+        let key: [u8; 32] = std::array::from_fn(|idx| idx as u8 * 8);
+        let lower = hex(&key);
+        assert_eq!(parse_key(&lower).unwrap(), key);
+        assert_eq!(parse_key(&lower.to_uppercase()).unwrap(), key);
+        assert_eq!(parse_key(&format!(" {}\n", lower)).unwrap(), key);
+    }
+
+    #[test]
+    fn rejects_keys_of_the_wrong_length() {
+        for len in [0, 62, 63, 65, 66] {
+            assert!(parse_key(&"a".repeat(len)).is_err(), "{} digits", len);
+        }
+    }
+
+    #[test]
+    fn rejects_keys_with_other_characters() {
+        let key = "ab".repeat(32);
+        for bad in ["+f", "-1", "zz", "0x", "é"] {
+            let text = format!("{}{}", bad, &key[bad.len()..]);
+            assert!(parse_key(&text).is_err(), "{}", text);
+        }
+    }
+
+    #[cfg(feature = "keys")]
+    #[test]
+    fn parses_openssh_sha256_fingerprints() {
+        let digest: Vec<u8> = (0..32).collect();
+        let unpadded = format!("SHA256:{}", STANDARD_NO_PAD.encode(&digest));
+        let padded = format!("sha256:{}", STANDARD.encode(&digest));
+        for text in [&unpadded, &padded] {
+            assert_eq!(
+                parse_fingerprint(text),
+                Ok(("SHA256", digest.clone())),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[cfg(feature = "keys")]
+    #[test]
+    fn parses_longer_openssh_fingerprints() {
+        let digest = vec![0xab; 48];
+        let text = format!("SHA384:{}", STANDARD_NO_PAD.encode(&digest));
+        assert_eq!(parse_fingerprint(&text), Ok(("SHA384", digest)));
+        let digest = vec![0xcd; 64];
+        let text = format!("SHA512:{}", STANDARD_NO_PAD.encode(&digest));
+        assert_eq!(parse_fingerprint(&text), Ok(("SHA512", digest)));
+    }
+
+    #[cfg(feature = "keys")]
+    #[test]
+    fn rejects_openssh_fingerprints_of_the_wrong_length() {
+        let text = format!("SHA256:{}", STANDARD_NO_PAD.encode([1; 20]));
+        assert_eq!(
+            parse_fingerprint(&text),
+            Err("SHA256 fingerprint must be 32 bytes, got 20".to_string())
+        );
+        assert_eq!(
+            parse_fingerprint("SHA256:not base64!"),
+            Err("not valid base64".to_string())
+        );
+    }
+
+    #[cfg(feature = "keys")]
+    #[test]
+    fn parses_legacy_colon_hex() {
+        let md5 = "MD5:16:27:ac:a5:76:28:2d:36:63:1b:56:4d:eb:df:a6:48";
+        let bare = &md5[4..];
+        let upper = bare.to_uppercase();
+        let expected = vec![
+            0x16, 0x27, 0xac, 0xa5, 0x76, 0x28, 0x2d, 0x36, 0x63, 0x1b, 0x56, 0x4d, 0xeb, 0xdf,
+            0xa6, 0x48,
+        ];
+        for text in [md5, bare, &upper] {
+            assert_eq!(
+                parse_fingerprint(text),
+                Ok(("MD5", expected.clone())),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[cfg(feature = "keys")]
+    #[test]
+    fn rejects_malformed_colon_hex() {
+        assert_eq!(
+            parse_fingerprint("16:2:ac"),
+            Err("expected two hex digits per group, got \"2\"".to_string())
+        );
+        for text in ["MD5:zz:27", "16:+2:ac", "16:-2:ac"] {
+            assert_eq!(
+                parse_fingerprint(text),
+                Err("not valid hex".to_string()),
+                "{}",
+                text
+            );
+        }
+        assert_eq!(
+            parse_fingerprint("16:27:ac"),
+            Err("unsupported length of 3 bytes".to_string())
+        );
+    }
+
+    #[cfg(feature = "keys")]
+    #[test]
+    fn parses_gpg_hex() {
+        let grouped = "ABCD 1234 ABCD 1234 ABCD  1234 ABCD 1234 ABCD 1234";
+        let expected = [0xab, 0xcd, 0x12, 0x34].repeat(5);
+        assert_eq!(parse_fingerprint(grouped), Ok(("SHA1", expected.clone())));
+        assert_eq!(
+            parse_fingerprint(&grouped.replace(' ', "").to_lowercase()),
+            Ok(("SHA1", expected))
+        );
+        assert_eq!(
+            parse_fingerprint(&"00".repeat(32)),
+            Ok(("SHA256", vec![0; 32]))
+        );
+    }
+
+    #[cfg(feature = "keys")]
+    #[test]
+    fn rejects_malformed_gpg_hex() {
+        for text in ["abc", "xyz0", "ab-cd"] {
+            assert_eq!(
+                parse_fingerprint(text),
+                Err("unrecognized format".to_string()),
+                "{}",
+                text
+            );
+        }
+        assert_eq!(
+            parse_fingerprint("abcd"),
+            Err("unsupported length of 2 bytes".to_string())
+        );
+    }
+
+    #[cfg(feature = "keys")]
+    #[test]
+    fn short_fingerprints_are_stretched() {
+        let md5 = "MD5:16:27:ac:a5:76:28:2d:36:63:1b:56:4d:eb:df:a6:48";
+        let (_, fingerprint) = parse_fingerprint(md5).unwrap();
+        let input = Input::from_fingerprint(md5).unwrap();
+        assert_eq!(input.digest.len(), FINGERPRINT_LEN);
+        assert_eq!(input.digest[..16], fingerprint[..]);
+        assert_eq!(input.algorithm, "MD5");
+        assert_eq!(input.label, md5);
+
+        let sha1 = "ABCD 1234 ABCD 1234 ABCD 1234 ABCD 1234 ABCD 1234";
+        assert_eq!(
+            Input::from_fingerprint(sha1).unwrap().digest.len(),
+            FINGERPRINT_LEN
+        );
+    }
+
+    #[cfg(feature = "keys")]
+    #[test]
+    fn full_length_fingerprints_are_used_as_they_are() {
+        let digest = vec![0xab; 48];
+        let text = format!("SHA384:{}", STANDARD_NO_PAD.encode(&digest));
+        assert_eq!(Input::from_fingerprint(&text).unwrap().digest, digest);
+        let text = "00".repeat(32);
+        assert_eq!(Input::from_fingerprint(&text).unwrap().digest, vec![0; 32]);
+    }
+
+    #[cfg(feature = "argon2")]
+    #[test]
+    fn argon2_params_default_what_is_left_out() {
+        assert_eq!("".parse::<Argon2Params>().unwrap(), Argon2Params::default());
+        assert_eq!(
+            "t=3".parse::<Argon2Params>().unwrap(),
+            Argon2Params {
+                iterations: 3,
+                ..Argon2Params::default()
+            }
+        );
+    }
+
+    #[cfg(feature = "argon2")]
+    #[test]
+    fn argon2_params_parse_in_any_order_and_spacing() {
+        let expected = Argon2Params {
+            memory_kib: 65536,
+            iterations: 4,
+            parallelism: 2,
+        };
+        for text in [
+            "m=65536,t=4,p=2",
+            "p=2,m=65536,t=4",
+            " m = 65536 , t=4,p=2, ",
+        ] {
+            assert_eq!(text.parse::<Argon2Params>().unwrap(), expected, "{}", text);
+        }
+        // a later value wins
+        assert_eq!("t=1,t=5".parse::<Argon2Params>().unwrap().iterations, 5);
+    }
+
+    #[cfg(feature = "argon2")]
+    #[test]
+    fn argon2_params_reject_anything_else() {
+        for text in ["m", "m=", "m=lots", "m=-1", "x=1", "m=1;t=2", "M=1"] {
+            assert!(
+                matches!(
+                    text.parse::<Argon2Params>(),
+                    Err(HashdenticonError::InvalidInput(_))
+                ),
+                "{}",
+                text
+            );
+        }
+    }
+}
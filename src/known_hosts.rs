@@ -0,0 +1,139 @@
+//! Parsing of OpenSSH `known_hosts` files
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+/// One trusted host key
+#[derive(Debug)]
+pub struct HostKey {
+    /// Host patterns, or a placeholder when hostnames are hashed
+    pub hosts: String,
+    /// Marker such as `@cert-authority` or `@revoked`, if present
+    pub marker: Option<String>,
+    pub key_type: String,
+    /// Decoded public key blob
+    pub blob: Vec<u8>,
+}
+
+/// Parse all host keys, skipping blank lines and comments
+pub fn parse(contents: &str) -> Result<Vec<HostKey>> {
+    let mut keys = Vec::new();
+
+    for (idx, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = line.split_whitespace();
+        let mut hosts = fields.next().unwrap_or_default();
+        let mut marker = None;
+        if hosts.starts_with('@') {
+            marker = Some(hosts.to_string());
+            hosts = fields.next().unwrap_or_default();
+        }
+
+        let (Some(key_type), Some(key)) = (fields.next(), fields.next()) else {
+            anyhow::bail!("Line {}: expected hosts, key type, and key", idx + 1);
+        };
+        let blob = STANDARD
+            .decode(key)
+            .with_context(|| format!("Line {}: key is not valid base64", idx + 1))?;
+
+        // hashed entries (HashKnownHosts) look like |1|salt|hash
+        let hosts = if hosts.starts_with("|1|") {
+            "(hashed hostname)".to_string()
+        } else {
+            hosts.to_string()
+        };
+
+        keys.push(HostKey {
+            hosts,
+            marker,
+            key_type: key_type.to_string(),
+            blob,
+        });
+    }
+
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIG9wZW5zc2ggdGVzdCBrZXkgYnl0ZXMgaGVyZQ==";
+
+    #[test]
+    fn parses_plain_entries() {
+        let contents = format!("github.com,140.82.112.3 ssh-ed25519 {} a comment\n", KEY);
+        let keys = parse(&contents).unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].hosts, "github.com,140.82.112.3");
+        assert_eq!(keys[0].marker, None);
+        assert_eq!(keys[0].key_type, "ssh-ed25519");
+        assert_eq!(keys[0].blob, STANDARD.decode(KEY).unwrap());
+    }
+
+    #[test]
+    fn keeps_markers() {
+        let contents = format!(
+            "@cert-authority *.example.com ssh-ed25519 {key}\n@revoked old.example.com ssh-ed25519 {key}\n",
+            key = KEY
+        );
+        let keys = parse(&contents).unwrap();
+        assert_eq!(keys[0].marker.as_deref(), Some("@cert-authority"));
+        assert_eq!(keys[0].hosts, "*.example.com");
+        assert_eq!(keys[1].marker.as_deref(), Some("@revoked"));
+        assert_eq!(keys[1].hosts, "old.example.com");
+        assert_eq!(keys[1].key_type, "ssh-ed25519");
+    }
+
+    #[test]
+    fn hides_hashed_hostnames() {
+        let contents = format!(
+            "|1|JfKTdBh7rNbXkVAQCRp4OQoPfmI=|USECr3SWf1JUPsms5AqfD5QfxkM= ssh-ed25519 {}\n",
+            KEY
+        );
+        assert_eq!(parse(&contents).unwrap()[0].hosts, "(hashed hostname)");
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let contents = format!(
+            "# known hosts\n\n   \n  # indented comment\nexample.com ssh-ed25519 {}\n\t\n",
+            KEY
+        );
+        let keys = parse(&contents).unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].hosts, "example.com");
+        assert!(parse("# only a comment\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn reports_missing_fields_by_line() {
+        let contents = format!("example.com ssh-ed25519 {}\nexample.org ssh-ed25519\n", KEY);
+        assert_eq!(
+            parse(&contents).unwrap_err().to_string(),
+            "Line 2: expected hosts, key type, and key"
+        );
+        let marked = "@revoked ssh-ed25519 AAAA\n";
+        assert_eq!(
+            parse(marked).unwrap_err().to_string(),
+            "Line 1: expected hosts, key type, and key"
+        );
+    }
+
+    #[test]
+    fn reports_bad_base64_by_line() {
+        let contents = format!(
+            "# header\nexample.com ssh-ed25519 {}\nexample.org ssh-rsa not*base64\n",
+            KEY
+        );
+        assert_eq!(
+            parse(&contents).unwrap_err().to_string(),
+            "Line 3: key is not valid base64"
+        );
+    }
+}
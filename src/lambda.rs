@@ -0,0 +1,79 @@
+//! `hashdenticon lambda`: the serve endpoints as an AWS Lambda function
+//! behind API Gateway or an ALB, with this binary as its bootstrap
+
+use crate::serve::{self, Config, Incoming};
+use anyhow::{Context, Result, anyhow};
+use lambda_http::request::RequestContext;
+use lambda_http::{Body, Request, RequestExt, Response, service_fn};
+use std::convert::Infallible;
+
+/// Answer invocations until the Lambda runtime stops the process
+pub fn run(config: &Config) -> Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to start the Lambda runtime")?;
+    let handler =
+        service_fn(|request: Request| async move { Ok::<_, Infallible>(handle(&request, config)) });
+    runtime
+        .block_on(lambda_http::run(handler))
+        .map_err(|err| anyhow!("Lambda runtime failed: {}", err))
+}
+
+fn handle(request: &Request, config: &Config) -> Response<Body> {
+    let path = match request.raw_http_path() {
+        "" => request.uri().path(),
+        path => path,
+    };
+    // without the API Gateway stage, so the routes match those of serve
+    let path = stage(request)
+        .and_then(|stage| path.strip_prefix(&format!("/{}", stage)))
+        .filter(|rest| rest.starts_with('/'))
+        .unwrap_or(path);
+    let url = match request.uri().query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_string(),
+    };
+    let reply = match serve::read_body(request.body().as_ref()) {
+        Ok(body) => {
+            let incoming = Incoming {
+                method: request.method().to_string(),
+                url: url.clone(),
+                headers: request
+                    .headers()
+                    .iter()
+                    .filter_map(|(name, value)| {
+                        Some((name.to_string(), value.to_str().ok()?.to_string()))
+                    })
+                    .collect(),
+                body,
+            };
+            serve::answer(&incoming, config)
+        }
+        Err(err) => serve::error_reply(&err),
+    };
+    eprintln!("{} {} {}", request.method(), url, reply.status);
+    let mut response = Response::builder().status(reply.status);
+    for (field, value) in reply.headers {
+        response = response.header(field, value);
+    }
+    response
+        .body(Body::Binary(reply.body))
+        .unwrap_or_else(|err| {
+            let message = format!("Failed to build the response: {}\n", err);
+            Response::builder()
+                .status(500)
+                .body(Body::Text(message))
+                .expect("a plain 500 is always valid")
+        })
+}
+
+/// The API Gateway stage named in paths, if any
+fn stage(request: &Request) -> Option<&str> {
+    let stage = match request.request_context_ref()? {
+        RequestContext::ApiGatewayV1(context) => context.stage.as_deref(),
+        RequestContext::ApiGatewayV2(context) => context.stage.as_deref(),
+        _ => None,
+    };
+    stage.filter(|stage| *stage != "$default")
+}
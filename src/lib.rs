@@ -0,0 +1,65 @@
+//! Generate identicons from hashed seed strings
+//!
+//! ```no_run
+//! use hashdenticon::{Identicon, Options};
+//!
+//! let identicon = Identicon::from_seed("alice", &Options::default())?;
+//! let pixels = identicon.as_rgb();
+//! // encoders come with the raster and svg features
+//! #[cfg(feature = "raster")]
+//! let png = identicon.to_png_bytes()?;
+//! #[cfg(feature = "svg")]
+//! let svg = identicon.to_svg_string();
+//! # Ok::<(), hashdenticon::HashdenticonError>(())
+//! ```
+
+#[cfg(feature = "proptest")]
+mod arbitrary;
+pub mod background;
+pub mod card;
+#[cfg(feature = "keys")]
+mod cert;
+mod color;
+pub mod compose;
+mod constraint;
+pub mod decode;
+pub mod derive;
+pub mod dither;
+mod draw;
+mod error;
+mod font;
+#[cfg(feature = "wgpu")]
+pub mod gpu;
+mod identicon;
+pub mod input;
+pub mod model;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+pub mod output;
+pub mod overlay;
+pub mod pool;
+#[cfg(feature = "serde")]
+pub mod preset;
+#[cfg(feature = "qr")]
+pub mod qr;
+pub mod resample;
+mod rng;
+pub mod stamp;
+pub mod style;
+pub mod theme;
+pub mod tile;
+pub mod transform;
+#[cfg(feature = "svg")]
+mod vector;
+pub mod wallpaper;
+
+pub use error::{HashdenticonError, Result};
+pub use identicon::{
+    BitMatrix, Identicon, MAX_GAP, MAX_GRID_SIZE, MAX_PADDING, MAX_SIZE, MAX_STEPS, MIN_SIZE,
+    Options, generate_color, generate_pattern, generate_pattern_with_density, render_cells,
+    render_grid,
+};
+pub use input::Input;
+pub use model::Model;
+pub use pool::RenderPool;
+pub use style::Style;
@@ -1,167 +1,2400 @@
-use anyhow::{Context, Result};
-use clap::Parser;
-use image::{Rgb, RgbImage};
+mod batch;
+#[cfg(feature = "serve")]
+mod cgi;
+mod complete;
+mod dedup;
+mod dirs;
+mod exit;
+#[cfg(feature = "http")]
+mod fetch;
+mod gallery;
+mod git;
+#[cfg(feature = "gui")]
+mod gui;
+mod known_hosts;
+#[cfg(feature = "lambda")]
+mod lambda;
+mod manifest;
+mod neighbors;
+mod prompt;
+#[cfg(feature = "s3")]
+mod s3;
+#[cfg(feature = "serve")]
+mod serve;
+mod sink;
+mod slug;
+#[cfg(feature = "sqlite")]
+mod store;
+#[cfg(feature = "tui")]
+mod tui;
+mod watch;
+#[cfg(feature = "serve")]
+mod zip;
+
+use anyhow::{Context, Result, bail};
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+use clap::{ArgAction, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap_complete::{ArgValueCompleter, CompleteEnv, Shell};
+use dedup::Sample;
+use gallery::GalleryEntry;
+use hashdenticon::background::BackgroundPattern;
+use hashdenticon::card;
+use hashdenticon::compose::{self, Position};
+use hashdenticon::derive::{Derivation, Streams};
+use hashdenticon::dither::Dither;
+use hashdenticon::input::{self, Argon2Params};
+use hashdenticon::output::{self, Encoding, Format, Paper, PngFilter};
+use hashdenticon::pool::{self, Pending, RenderPool};
+use hashdenticon::preset;
+#[cfg(feature = "qr")]
+use hashdenticon::qr::Qr;
+use hashdenticon::resample::Filter;
+use hashdenticon::stamp::{self, StampKind};
+use hashdenticon::style::automaton::Rule;
+use hashdenticon::style::randomart;
+use hashdenticon::theme::{self, CellShape, Mask};
+use hashdenticon::transform::{Flip, Orient};
+use hashdenticon::wallpaper;
+use hashdenticon::{Identicon, Input, Model, Options, Style, decode, overlay};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+use image::{Delay, ExtendedColorType, Frame, Rgb};
 use sha2::{Digest, Sha256};
-use std::path::PathBuf;
+use std::fs::File;
+use std::num::IntErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+#[cfg(feature = "sqlite")]
+use std::sync::OnceLock;
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
 
 /// Generate identicons from hashed seed strings
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
-struct Args {
-    /// Seed text (username, email, etc.) to generate identicon from
-    seed: String,
+#[command(
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    // `generate` is what runs when no command is named
+    #[command(flatten)]
+    generate: GenerateArgs,
+
+    /// Log timings of hashing, rendering, and encoding to stderr (-vv for more); RUST_LOG overrides
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Report errors on stderr as json objects with a kind and exit code
+    #[arg(long, global = true)]
+    json_errors: bool,
+
+    /// Render on at most this many threads, in batch modes and servers [default: one per core]
+    #[arg(long, value_name = "N", global = true, env = "HASHDENTICON_JOBS",
+          value_parser = clap::value_parser!(u16).range(1..))]
+    jobs: Option<u16>,
+
+    /// Renders that may wait for a free thread before servers answer 503 and batch modes pause [default: 4 per thread]
+    #[arg(long, value_name = "N", global = true)]
+    queue_depth: Option<usize>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Generate the identicon of a seed; what runs when no command is named
+    Generate(GenerateArgs),
+
+    /// Generate an identicon for each line of a seeds file, into the -o directory
+    Batch(BatchArgs),
+
+    /// Visualize the SHA-256 checksum of a file
+    File {
+        /// File to checksum
+        path: PathBuf,
+
+        #[command(flatten)]
+        output: OutputArgs,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Recover the pattern model from an existing grid identicon image
+    Decode {
+        /// Identicon image to decode
+        image: PathBuf,
+
+        /// Write the model to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Check that a grid identicon image is the default identicon of a seed, failing with exit code 5 when it is not
+    Verify {
+        /// Identicon image to check
+        image: PathBuf,
+
+        /// Seed the image should belong to
+        seed: String,
+    },
+
+    /// Print what the hash of a seed decides: its digest, color, and cell pattern
+    Inspect {
+        /// Seed text to inspect
+        seed: String,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Report the seeds of a seeds file whose identicons are identical or nearly so, without writing any
+    Analyze {
+        /// Seeds file, one per line (- for stdin)
+        seeds: PathBuf,
+
+        /// Fail instead of warning when a seed is too short or too common to tell users apart
+        #[arg(long)]
+        strict: bool,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Rasterize a pattern model exported with --format json
+    Render {
+        /// Pattern model to render
+        #[arg(long, value_name = "FILE")]
+        from_json: PathBuf,
+
+        #[command(flatten)]
+        output: OutputArgs,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Tweak options interactively with a live terminal preview
+    #[cfg(feature = "tui")]
+    Tui {
+        /// Seed text to preview
+        seed: String,
+
+        /// Where pressing `e` exports the chosen settings as a preset
+        #[arg(long, value_name = "FILE")]
+        preset_out: Option<PathBuf>,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Render an identicon for every key in an SSH known_hosts file
+    KnownHosts {
+        /// known_hosts file to audit [default: ~/.ssh/known_hosts]
+        file: Option<PathBuf>,
+
+        /// Directory to write the identicons and gallery into
+        #[arg(short, long, default_value = "known_hosts")]
+        out_dir: PathBuf,
+
+        /// Kind of gallery to write alongside the identicons
+        #[arg(long, value_enum, default_value_t = GalleryKind::Html)]
+        gallery: GalleryKind,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Fill a desktop-resolution wallpaper with the identicon of a seed
+    Wallpaper {
+        /// Seed text to build the wallpaper from
+        seed: String,
+
+        /// Wallpaper size in pixels
+        #[arg(long, value_name = "WxH", default_value = "3840x2160", value_parser = parse_dimensions)]
+        resolution: (u32, u32),
+
+        /// How the identicon fills the wallpaper
+        #[arg(long, value_enum, default_value_t = wallpaper::Mode::Repeat)]
+        mode: wallpaper::Mode,
+
+        #[command(flatten)]
+        output: OutputArgs,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Make a 1200x630 social preview card with the identicon of a seed beside a title
+    Card {
+        /// Seed text to build the card from
+        seed: String,
+
+        /// Large text beside the identicon [default: the seed]
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Smaller text under the title
+        #[arg(long)]
+        subtitle: Option<String>,
+
+        #[command(flatten)]
+        output: OutputArgs,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Write a website's favicon.ico and home screen icons for the identicon of a seed
+    Favicon {
+        /// Seed text to build the icons from
+        seed: String,
+
+        /// Directory to write the icons into
+        #[arg(short, long, default_value = "favicon")]
+        out_dir: PathBuf,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Compare a seed's identicon with those of typo and homoglyph lookalikes
+    Neighbors {
+        /// Seed to find lookalikes of
+        seed: String,
+
+        /// Directory to write the identicons and gallery into
+        #[arg(short, long, default_value = "neighbors")]
+        out_dir: PathBuf,
+
+        /// Kind of gallery to write alongside the identicons
+        #[arg(long, value_enum, default_value_t = GalleryKind::Html)]
+        gallery: GalleryKind,
+
+        /// Show only this many of the closest lookalikes
+        #[arg(long, default_value_t = 24)]
+        limit: usize,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Render an identicon for every commit author in a git repository
+    Git {
+        /// Repository to read the history of [default: current directory]
+        repo: Option<PathBuf>,
+
+        /// Directory to write the identicons into, named by email hash
+        #[arg(short, long, default_value = "contributors")]
+        out_dir: PathBuf,
+
+        /// Also write an index.html gallery of the contributors
+        #[arg(long)]
+        html: bool,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Serve identicons over HTTP: GET /identicon/<seed>[.<ext>] and POST /batch
+    #[cfg(feature = "serve")]
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: String,
+
+        /// Also answer the gRPC Generate service of proto/hashdenticon.proto on this address
+        #[cfg(feature = "grpc")]
+        #[arg(long, value_name = "ADDR")]
+        grpc: Option<String>,
+
+        #[command(flatten)]
+        serve: ServeArgs,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Answer one request of the serve endpoints as a CGI program
+    #[cfg(feature = "serve")]
+    Cgi {
+        #[command(flatten)]
+        serve: ServeArgs,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Answer the serve endpoints as an AWS Lambda function, behind API Gateway or an ALB
+    #[cfg(feature = "lambda")]
+    Lambda {
+        #[command(flatten)]
+        serve: ServeArgs,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+
+    /// Sign a URL for a serve --signing-key server, so it works until it expires
+    #[cfg(feature = "serve")]
+    SignUrl {
+        /// URL or path to sign, like /identicon/alice.png?size=64
+        url: String,
 
-    /// Output file path [default: <seed_hash>.png]
-    #[arg(short, long)]
+        /// Key the server was started with, as 64 hex digits
+        #[arg(long, value_name = "HEX", value_parser = parse_key)]
+        signing_key: [u8; 32],
+
+        /// Seconds until the signed URL expires
+        #[arg(long, value_name = "SECONDS", default_value_t = 3600)]
+        ttl: u64,
+    },
+
+    /// Print the script that sets up tab completion in a shell, e.g. `source <(hashdenticon completions bash)`
+    Completions {
+        /// Shell to complete in
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+
+    /// Print the man page, generated from these very options, e.g. `hashdenticon man | man -l -`
+    Man {
+        /// Write hashdenticon.1 and a hashdenticon-<command>.1 page per subcommand into this directory instead
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+    },
+}
+
+/// What `generate` renders, and where it goes
+#[derive(Args, Debug)]
+struct GenerateArgs {
+    /// Seed text (username, email, etc.) to generate identicon from; asked for when left out on a terminal
+    seed: Option<String>,
+
+    /// When asking for the seed, don't echo it, and keep it out of messages and the default file name
+    #[arg(long, conflicts_with_all = ["seed", "cert"])]
+    hide_seed: bool,
+
+    /// Interpret the seed as a UUID and canonicalize it before hashing
+    #[arg(long, conflicts_with = "fingerprint")]
+    uuid: bool,
+
+    /// Interpret the seed as a key fingerprint (SHA256:base64, colon hex, or GPG hex)
+    #[arg(long)]
+    fingerprint: bool,
+
+    /// Visualize the public key of a PEM or DER certificate instead of a seed
+    #[arg(long, value_name = "PEM", conflicts_with_all = ["seed", "uuid", "fingerprint"])]
+    cert: Option<PathBuf>,
+
+    /// Fail instead of warning when a seed is too short or too common to tell users apart
+    #[arg(long)]
+    strict: bool,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+/// The seeds `batch` renders, and the reports it writes beside them
+#[derive(Args, Debug)]
+struct BatchArgs {
+    /// Seeds file, one per line (- for stdin)
+    #[cfg_attr(not(feature = "http"), arg(required = true))]
+    #[cfg_attr(feature = "http", arg(required_unless_present = "url", conflicts_with_all = ["url", "upload_url"]))]
+    seeds: Option<PathBuf>,
+
+    /// Download the seeds from this http(s) URL instead, one per line or as json
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "URL", conflicts_with_all = ["watch", "upload_url"])]
+    url: Option<String>,
+
+    /// With --url, retry failed downloads this many times, waiting longer each time
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "N", default_value_t = 3, requires = "url")]
+    retries: u32,
+
+    /// With --url, refuse the seed list unless its SHA-256 is this hex digest
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "SHA256", requires = "url")]
+    checksum: Option<String>,
+
+    /// Also write a static HTML page of every identicon, inside the -o directory
+    #[arg(long, value_name = "FILE", conflicts_with = "watch")]
+    gallery: Option<PathBuf>,
+
+    /// Also write a report of seeds with identical or near-identical identicons, inside the -o directory
+    #[arg(long, value_name = "FILE", conflicts_with = "watch")]
+    dedup_report: Option<PathBuf>,
+
+    /// Also write a json line per seed (seed, hash, path, bytes, duration, status), inside the -o directory or - for stdout; failed seeds are recorded and skipped
+    #[arg(long, value_name = "FILE", conflicts_with = "watch")]
+    manifest: Option<PathBuf>,
+
+    /// Skip seeds whose files a previous run finished, appending to its --manifest
+    #[arg(long, conflicts_with = "watch")]
+    resume: bool,
+
+    /// Keep running, regenerating identicons for new or edited lines as the seeds file changes
+    #[arg(long)]
+    watch: bool,
+
+    /// Fail instead of warning when a seed is too short or too common to tell users apart
+    #[arg(long)]
+    strict: bool,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Kdf {
+    /// Hash the seed directly
+    None,
+    /// Argon2id, tuned with --kdf-params
+    Argon2,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum GalleryKind {
+    /// Static index.html listing each host with its identicon
+    Html,
+    /// Single gallery.png contact sheet of all identicons
+    Image,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum NameHash {
+    /// SHA-256 of the encoded file, so the name changes whenever the image does
+    Content,
+    /// SHA-256 of the seed, the same name whatever the options
+    Seed,
+}
+
+#[derive(Args, Clone, Debug)]
+struct OutputArgs {
+    /// Output file path, - for stdout, or s3://bucket/key with the s3 feature [default: <seed_hash>.png]
+    #[arg(short, long, visible_alias = "out")]
     output: Option<PathBuf>,
 
+    /// Directory for outputs given no -o path, and for batch modes without -o; created if missing
+    #[arg(long, value_name = "DIR", env = "HASHDENTICON_OUT_DIR")]
+    out_dir: Option<PathBuf>,
+
+    /// Output format [default: inferred from the output path, else png]
+    #[arg(short('f'), long, value_enum, env = "HASHDENTICON_FORMAT")]
+    format: Option<Format>,
+
+    /// Name default outputs for seeds like emails by a readable slug plus a short hash, instead of the full hash
+    #[arg(long)]
+    slug: bool,
+
+    /// Name default outputs by a SHA-256 hash instead of the seed, for content-addressed storage and cache busting
+    #[arg(long, value_enum, value_name = "HASH", conflicts_with = "slug")]
+    name_by_hash: Option<NameHash>,
+
+    /// Hex digits of the --name-by-hash hash to keep, 4 to 64 [default: 64]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(4..=64), requires = "name_by_hash")]
+    hash_length: Option<u8>,
+
+    /// Dithering used when reducing to 1-bit formats
+    #[arg(long, value_enum, default_value_t = Dither::None)]
+    dither: Dither,
+
+    /// Record this print resolution in png and tiff output, for correct physical size
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    dpi: Option<u32>,
+
+    /// Write png with an exact color palette, a fraction of the size for flat styles
+    #[arg(long)]
+    indexed: bool,
+
+    /// PNG deflate level, 0 (fastest, stored) to 9 (smallest) [default: 6]
+    #[arg(long, value_name = "LEVEL", value_parser = clap::value_parser!(u8).range(0..=9))]
+    png_compression: Option<u8>,
+
+    /// PNG row filter strategy [default: adaptive]
+    #[arg(long, value_enum, value_name = "FILTER")]
+    png_filter: Option<PngFilter>,
+
+    /// Quality for avif and webp, 1 to 100; webp is lossless at 100 or without it [default: 80 for avif]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: Option<u8>,
+
+    /// Receipt roll width escpos output is centered on, and sized to without -s
+    #[arg(long, value_enum, default_value_t = Paper::Mm58)]
+    paper: Paper,
+
+    /// Text on the right of --format badge [default: the first 8 hex digits of the hash]
+    #[arg(long, value_name = "TEXT")]
+    badge_label: Option<String>,
+
+    /// Draw the identicon onto this image instead of a plain canvas
+    #[arg(long, value_name = "IMAGE")]
+    compose: Option<PathBuf>,
+
+    /// Where to place the identicon on the --compose image
+    #[arg(long, value_enum, default_value_t = Position::Center, requires = "compose")]
+    position: Position,
+
+    /// Identicon size as a percentage of the --compose image's shorter side
+    #[arg(long, value_name = "PERCENT", default_value_t = 25, value_parser = clap::value_parser!(u32).range(1..=100), requires = "compose")]
+    scale: u32,
+
+    /// Draw a QR code of this text, such as a profile URL, with the identicon at its center; -s sizes the whole code
+    #[cfg(feature = "qr")]
+    #[arg(long, value_name = "DATA", conflicts_with = "compose")]
+    qr: Option<String>,
+
+    /// Print progress on stderr, when stdout carries something else
+    #[arg(skip)]
+    stderr_status: bool,
+
+    /// Cache encoded identicons in this SQLite database and reuse them instead of rendering again, or in identicons.db in the XDG cache directory when no DB is given
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "DB", env = "HASHDENTICON_STORE", num_args = 0..=1)]
+    store: Option<Option<PathBuf>>,
+
+    /// The store once opened, shared by every seed of a run
+    #[cfg(feature = "sqlite")]
+    #[arg(skip)]
+    opened: Arc<OnceLock<store::Store>>,
+
+    /// PUT the image to this http(s) URL instead of writing a file, in the format -o or -f names
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "URL")]
+    upload_url: Option<String>,
+
+    /// With --upload-url, authorize the upload with this bearer token
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "TOKEN", requires = "upload_url")]
+    upload_token: Option<String>,
+
+    /// Also display the identicon in a preview window
+    #[cfg(feature = "gui")]
+    #[arg(long)]
+    show: bool,
+}
+
+impl OutputArgs {
+    /// Default file name for `label`, when no -o path is given; with
+    /// --name-by-hash content, [`write`](Self::write) renames it once the
+    /// bytes are known
+    fn file_name(&self, label: &str) -> PathBuf {
+        match self.name_by_hash {
+            Some(NameHash::Seed) => {
+                let extension = self.format.map_or("png", Format::extension);
+                PathBuf::from(self.hash_name(label.as_bytes(), extension))
+            }
+            _ => default_file_name(label, self.format, self.slug),
+        }
+    }
+
+    /// The SHA-256 of `bytes`, cut to --hash-length, as a file name
+    fn hash_name(&self, bytes: &[u8], extension: &str) -> String {
+        let hash = format!("{:x}", Sha256::digest(bytes));
+        let len = self.hash_length.map_or(hash.len(), usize::from);
+        format!("{}.{}", &hash[..len], extension)
+    }
+
+    /// `path` renamed after the hash of `bytes` for --name-by-hash content,
+    /// unless it was given with -o
+    fn content_path(&self, path: &Path, bytes: &[u8]) -> PathBuf {
+        if self.name_by_hash != Some(NameHash::Content) || self.output.is_some() {
+            return path.to_path_buf();
+        }
+        let extension = path
+            .extension()
+            .map_or("png".into(), |ext| ext.to_string_lossy());
+        path.with_file_name(self.hash_name(bytes, &extension))
+    }
+
+    fn encoding(&self) -> Encoding {
+        Encoding {
+            dither: self.dither,
+            dpi: self.dpi,
+            indexed: self.indexed,
+            compression: self.png_compression,
+            filter: self.png_filter,
+            quality: self.quality,
+            paper: self.paper,
+            label: self.badge_label.clone(),
+        }
+    }
+
+    /// [`encoding`](Self::encoding), with badges labeled by `digest` unless
+    /// --badge-label says otherwise
+    fn encoding_for(&self, digest: &[u8]) -> Encoding {
+        let encoding = self.encoding();
+        Encoding {
+            label: encoding.label.or_else(|| Some(output::badge_label(digest))),
+            ..encoding
+        }
+    }
+
+    /// Write encoded output to `path`, or upload it to the --upload-url,
+    /// and hand back the path, renamed by --name-by-hash content
+    fn write(&self, path: &Path, bytes: &[u8], content_type: &str) -> Result<PathBuf> {
+        let path = self.content_path(path, bytes);
+        #[cfg(feature = "http")]
+        if let Some(url) = &self.upload_url {
+            sink::put_url(url, self.upload_token.as_deref(), bytes, content_type)?;
+            return Ok(path);
+        }
+        if let Some(dir) = &self.out_dir
+            && self.output.is_none()
+        {
+            sink::create_dir(dir)?;
+        }
+        sink::write_as(&path, bytes, content_type)?;
+        Ok(path)
+    }
+
+    /// Encode `img` for `path` and [`write`](Self::write) it
+    fn save(&self, img: &image::RgbImage, path: &Path) -> Result<PathBuf> {
+        let bytes = output::encode(img, path, self.format, &self.encoding())?;
+        self.write(path, &bytes, output::content_type(path, self.format))
+    }
+
+    /// Report where [`write`](Self::write) put `what`
+    fn report_saved(&self, what: &str, path: &Path) {
+        #[cfg(feature = "http")]
+        if let Some(url) = &self.upload_url {
+            return self.status(format_args!("{} uploaded to: {}", what, url));
+        }
+        if output::is_stdout(path) {
+            self.status(format_args!("{} written to stdout", what));
+        } else {
+            self.status(format_args!("{} saved to: {}", what, path.display()));
+        }
+    }
+
+    /// The --store database, or the one in the cache directory for a bare
+    /// --store; no cache at all without it, or when that directory can't
+    /// be made
+    #[cfg(feature = "sqlite")]
+    fn store_path(&self) -> Option<PathBuf> {
+        match self.store.clone()? {
+            Some(db) => Some(db),
+            None => {
+                let dir = dirs::cache()?;
+                std::fs::create_dir_all(&dir).ok()?;
+                Some(dir.join("identicons.db"))
+            }
+        }
+    }
+
+    /// The store, opened on first use and kept for the rest of the run
+    #[cfg(feature = "sqlite")]
+    fn store(&self) -> Result<Option<&store::Store>> {
+        if let Some(store) = self.opened.get() {
+            return Ok(Some(store));
+        }
+        let Some(db) = self.store_path() else {
+            return Ok(None);
+        };
+        let store = store::Store::open(&db)?;
+        Ok(Some(self.opened.get_or_init(|| store)))
+    }
+
+    /// Print a progress message, on stderr when stdout carries the image
+    fn status(&self, message: impl std::fmt::Display) {
+        if self.stderr_status || self.output.as_deref().is_some_and(output::is_stdout) {
+            eprintln!("{}", message);
+        } else {
+            println!("{}", message);
+        }
+    }
+}
+
+/// Settings shared by everything that answers the serve endpoints
+#[cfg(feature = "serve")]
+#[derive(Args, Debug)]
+struct ServeArgs {
+    /// Most seeds a single POST /batch may ask for
+    #[arg(long, value_name = "N", default_value_t = 256, value_parser = clap::value_parser!(u16).range(1..))]
+    max_batch: u16,
+
+    /// Longest image side a request may have drawn, supersampling and --tile included; larger ones answer 400
+    #[arg(long, value_name = "PX", default_value_t = 2000, value_parser = clap::value_parser!(u32).range(16..=8192))]
+    max_size: u32,
+
+    /// Cache encoded identicons in this SQLite database (sqlite feature)
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "DB")]
+    store: Option<PathBuf>,
+
+    /// Only answer URLs signed under this 64-hex-digit key, as made by sign-url
+    #[arg(long, value_name = "HEX", value_parser = parse_key)]
+    signing_key: Option<[u8; 32]>,
+
+    /// Let browser apps on this origin fetch identicons cross-origin, or * for any; repeatable
+    #[arg(long = "cors-origin", value_name = "ORIGIN")]
+    cors_origins: Vec<String>,
+
+    /// Have /readyz render an identicon, proving the pipeline works, instead of only answering
+    #[arg(long)]
+    canary: bool,
+
+    /// Answer email seeds with their Gravatar, if they have one, and an identicon otherwise
+    #[arg(long)]
+    gravatar_fallback: bool,
+
+    /// Only let clients vary this option, e.g. size=16-256 or style=grid,bars; repeatable, and the rest stay fixed
+    #[arg(long, value_name = "OPTION[=VALUES]", value_parser = serve::allow::Rule::parse)]
+    allow: Vec<serve::allow::Rule>,
+
+    /// Also write each identicon rendered into this directory, or s3://bucket/prefix with the s3 feature, named after the SHA-256 of its contents
+    #[arg(long, value_name = "DIR")]
+    out_dir: Option<PathBuf>,
+}
+
+#[cfg(feature = "serve")]
+impl ServeArgs {
+    fn config(self, render: RenderArgs, matches: &ArgMatches) -> Result<serve::Config> {
+        if let Some(dir) = &self.out_dir {
+            sink::create_dir(dir)?;
+        }
+        Ok(serve::Config {
+            max_batch: self.max_batch,
+            max_size: self.max_size,
+            signing_key: self.signing_key,
+            cors_origins: self.cors_origins,
+            canary: self.canary,
+            gravatar_fallback: self.gravatar_fallback,
+            allow: self.allow,
+            out_dir: self.out_dir,
+            #[cfg(feature = "grpc")]
+            grpc: None,
+            #[cfg(feature = "sqlite")]
+            store: self.store.as_deref().map(store::Store::open).transpose()?,
+            render: serve::Settings::new(render, matches.clone())?,
+        })
+    }
+}
+
+#[derive(Args, Clone, Debug)]
+struct RenderArgs {
     /// Size of the identicon in pixels
-    #[arg(short('s'), long("size"), default_value_t = 420, value_parser = clap::value_parser!(u32).range(50..=2000))]
+    #[arg(short('s'), long("size"), env = "HASHDENTICON_SIZE", default_value_t = 420, value_parser = clap::value_parser!(u32).range(50..=2000))]
     image_size: u32,
 
+    /// Image height in pixels, for styles that support non-square output [default: size]
+    #[arg(long, env = "HASHDENTICON_HEIGHT", value_parser = clap::value_parser!(u32).range(8..=2000))]
+    height: Option<u32>,
+
     /// Grid size for the pattern
-    #[arg(short('g'), long("grid"), default_value_t = 5, value_parser = clap::value_parser!(u32).range(3..=15))]
+    #[arg(short('g'), long("grid"), env = "HASHDENTICON_GRID", default_value_t = 5, value_parser = clap::value_parser!(u32).range(3..=15))]
     grid_size: u32,
 
     /// Padding as a percentage of size
-    #[arg(short, long, default_value_t = 8, value_parser = clap::value_parser!(u32).range(0..=25))]
+    #[arg(short, long, env = "HASHDENTICON_PADDING", default_value_t = 8, value_parser = clap::value_parser!(u32).range(0..=25))]
     padding: u32,
+
+    /// Visual style of the identicon
+    #[arg(long, value_enum, env = "HASHDENTICON_STYLE", default_value_t = Style::Grid)]
+    style: Style,
+
+    /// Generations to evolve for the automaton style (gif output animates them)
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(0..=64))]
+    steps: u32,
+
+    /// Directory of layered PNG parts for the parts style, or the name of a style pack in the data directory's parts/
+    #[arg(long, value_name = "DIR", value_parser = dirs::style_pack, add = ArgValueCompleter::new(complete::style_packs))]
+    parts_dir: Option<PathBuf>,
+
+    /// Text (up to 3 characters) to draw centered over the identicon
+    #[arg(long, conflicts_with = "auto_initials")]
+    initials: Option<String>,
+
+    /// Draw initials extracted from the seed over the identicon
+    #[arg(long)]
+    auto_initials: bool,
+
+    /// Small half-transparent text along the bottom edge; {seed} becomes the seed label
+    #[arg(long, value_name = "TEXT")]
+    watermark: Option<String>,
+
+    /// Stamp a hash-selected glyph from a curated set at the center
+    #[arg(long, value_enum)]
+    stamp: Option<StampKind>,
+
+    /// Texture drawn in a muted tint behind the pattern
+    #[arg(long, value_enum)]
+    background_pattern: Option<BackgroundPattern>,
+
+    /// Canvas color, e.g. #f4f1ea [default: white]
+    #[arg(long, value_name = "COLOR", env = "HASHDENTICON_BACKGROUND", value_parser = parse_color)]
+    background: Option<Rgb<u8>>,
+
+    /// Comma-separated colors the hash picks the identicon's color from, instead of deriving it
+    #[arg(long, value_name = "COLORS", env = "HASHDENTICON_PALETTE", value_delimiter = ',', value_parser = parse_color)]
+    palette: Vec<Rgb<u8>>,
+
+    /// Shape of each filled cell, for the grid and automaton styles
+    #[arg(long, value_enum, default_value_t = CellShape::Square)]
+    cell_shape: CellShape,
+
+    /// Percentage of each cell to leave empty around its shape
+    #[arg(long, value_name = "PERCENT", default_value_t = 0, value_parser = clap::value_parser!(u32).range(0..=hashdenticon::MAX_GAP as i64))]
+    gap: u32,
+
+    /// Cut the finished identicon to this outline
+    #[arg(long, value_enum)]
+    mask: Option<Mask>,
+
+    /// Draw a border this many pixels wide just inside the outline
+    #[arg(long, value_name = "PX", default_value_t = 0)]
+    border: u32,
+
+    /// Border color [default: the identicon's color]
+    #[arg(long, value_name = "COLOR", value_parser = parse_color)]
+    border_color: Option<Rgb<u8>>,
+
+    /// Percentage of cells to fill, instead of one hash bit per cell
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=100))]
+    density: Option<u32>,
+
+    /// Degrees to rotate the hue of the derived color
+    #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u32).range(0..360))]
+    hue_shift: u32,
+
+    /// Degrees to rotate the rendered identicon clockwise
+    #[arg(long, default_value_t = 0, value_parser = parse_rotation)]
+    rotate: u32,
+
+    /// Mirror the rendered identicon, after any rotation
+    #[arg(long, value_enum)]
+    flip: Option<Flip>,
+
+    /// Take the rotation from the hash instead of --rotate, for extra variety
+    #[arg(long, value_enum, default_value_t = Orient::Fixed, conflicts_with = "rotate")]
+    orient: Orient,
+
+    /// Least percentage of cells filled; sparser patterns gain hash-ranked cells
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u32).range(0..=100))]
+    min_fill: Option<u32>,
+
+    /// Greatest percentage of cells filled; denser patterns lose hash-ranked cells
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u32).range(0..=100))]
+    max_fill: Option<u32>,
+
+    /// Break up shapes from a small list of symbols unfit for public avatars
+    #[arg(long)]
+    filter_symbols: bool,
+
+    /// Always fill the center cell (the center 2x2 block on even grids)
+    #[arg(long)]
+    force_center: bool,
+
+    /// Always leave the outer ring of cells empty
+    #[arg(long)]
+    clear_border: bool,
+
+    /// Mirror the pattern on both axes and drop the padding, so the image tiles seamlessly
+    #[arg(long)]
+    tileable: bool,
+
+    /// Repeat the identicon over a wallpaper of this many pixels, e.g. 1920x1080
+    #[arg(long, value_name = "WxH", value_parser = parse_dimensions)]
+    tile: Option<(u32, u32)>,
+
+    /// Render at N times the size and scale down, for smoother curves and edges
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=8))]
+    supersample: u32,
+
+    /// Filter for --supersample and for resizing parts (nearest keeps pixel art crisp)
+    #[arg(long, value_enum, default_value_t = Filter::Lanczos3)]
+    filter: Filter,
+
+    /// Render the voronoi style and --supersample with --filter box on the GPU, for big batches of large identicons; other styles and filters stay on the CPU
+    #[cfg(feature = "wgpu")]
+    #[arg(long)]
+    gpu: bool,
+
+    /// Hash seeds with keyed BLAKE3 under this 64-hex-digit key instead of plain SHA-256
+    #[arg(long, value_name = "HEX", env = "HASHDENTICON_BLAKE3_KEY", hide_env_values = true, value_parser = parse_key)]
+    blake3_key: Option<[u8; 32]>,
+
+    /// Stretch seeds with a slow key derivation function, for seeds like PINs that are cheap to guess
+    #[arg(long, value_enum, default_value_t = Kdf::None, conflicts_with = "blake3_key")]
+    kdf: Kdf,
+
+    /// Costs for --kdf argon2, e.g. m=65536,t=3,p=1 (memory KiB, passes, lanes) [default: m=19456,t=2,p=1]
+    #[arg(long, value_name = "PARAMS")]
+    kdf_params: Option<Argon2Params>,
+
+    /// How the digest is split into color, pattern, and style bytes (hkdf decorrelates them, but changes every identicon)
+    #[arg(long, value_enum, env = "HASHDENTICON_DERIVATION", default_value_t = Derivation::Legacy)]
+    derivation: Derivation,
+
+    /// Re-roll: derive alternate identicon N for the same seed (0 is the original)
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    variant: u32,
+
+    /// Preset TOML file, or the name of one in the config directory's presets/, with defaults for any of these options; flags given here win
+    #[arg(long, value_name = "FILE", env = "HASHDENTICON_PRESET", value_parser = dirs::preset, add = ArgValueCompleter::new(complete::presets))]
+    preset: Option<PathBuf>,
+
+    /// Theme TOML file, or the name of one in the config directory's themes/, setting the background, palette, cell shape, gap, mask and border; it wins over --preset, and flags given here over both
+    #[arg(long, value_name = "FILE", env = "HASHDENTICON_THEME", value_parser = dirs::theme, add = ArgValueCompleter::new(complete::themes))]
+    theme_file: Option<PathBuf>,
 }
 
-fn hash_seed(seed: &str) -> Vec<u8> {
-    let mut hasher = Sha256::new();
-    hasher.update(seed.as_bytes());
-    hasher.finalize().to_vec()
+fn parse_color(arg: &str) -> Result<Rgb<u8>, String> {
+    theme::parse_hex(arg).ok_or_else(|| "expected a color as #rrggbb".to_string())
 }
 
-fn generate_color(hash: &[u8]) -> Rgb<u8> {
-    // use first 3 bytes for RGB
-    let r = hash[0];
-    let g = hash[1];
-    let b = hash[2];
+fn parse_key(arg: &str) -> Result<[u8; 32], String> {
+    input::parse_key(arg).map_err(|err| err.to_string())
+}
 
-    // normalize over range to ensure the color isn't too dark or too light
-    let min = 50;
-    let max = 200;
+fn parse_rotation(arg: &str) -> Result<u32, String> {
+    match arg {
+        "0" | "90" | "180" | "270" => Ok(arg.parse().expect("matched digits")),
+        _ => Err("expected 0, 90, 180, or 270".to_string()),
+    }
+}
 
-    Rgb([
-        (min + (r as u32 * (max - min) / 255)) as u8,
-        (min + (g as u32 * (max - min) / 255)) as u8,
-        (min + (b as u32 * (max - min) / 255)) as u8,
-    ])
+fn parse_dimensions(arg: &str) -> Result<(u32, u32), String> {
+    let invalid = || "expected WIDTHxHEIGHT, e.g. 1920x1080".to_string();
+    let (width, height) = arg.split_once(['x', 'X']).ok_or_else(invalid)?;
+    let parse = |n: &str| match n.trim().parse::<u32>() {
+        Ok(0) => Err("width and height must be at least 1".to_string()),
+        Ok(n) => Ok(n),
+        Err(err) if *err.kind() == IntErrorKind::PosOverflow => {
+            Err(format!("{} is too large", n.trim()))
+        }
+        Err(_) => Err(invalid()),
+    };
+    Ok((parse(width)?, parse(height)?))
 }
 
-fn generate_pattern(hash: &[u8], grid_size: u32) -> Vec<Vec<bool>> {
-    let mut pattern = vec![vec![false; grid_size as usize]; grid_size as usize];
+/// The output path given, or one named after the seed label
+fn output_path(label: &str, output: &OutputArgs) -> PathBuf {
+    match (&output.output, &output.out_dir) {
+        (Some(path), _) => path.clone(),
+        (None, Some(dir)) => dir.join(output.file_name(label)),
+        (None, None) => output.file_name(label),
+    }
+}
 
-    // max cells to generate: 15 x 8 = 120
-    // available bits from hash: 29 bytes * 8 = 232 bits
-    let mut byte_idx = 3; // skip first 3 bytes used for color
-    let mut bit_idx = 0;
+/// Whether `id` was set on the command line or through its environment
+/// variable, and so wins over presets and themes
+fn is_explicit(matches: &ArgMatches, id: &str) -> bool {
+    matches!(
+        matches.value_source(id),
+        Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    )
+}
 
-    // only need to generate half the width, then mirror horizontally
-    let half_width = grid_size.div_ceil(2);
+/// The seed itself when it makes a tame file name, otherwise its slug when
+/// asked for, else its hash
+fn default_file_name(label: &str, format: Option<Format>, use_slug: bool) -> PathBuf {
+    let extension = format.map_or("png", Format::extension);
+    let name = if label
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        && label.len() <= 64
+    {
+        label.to_string()
+    } else if use_slug {
+        slug::slug(label)
+    } else {
+        format!("{:x}", Sha256::digest(label.as_bytes()))
+    };
+    PathBuf::from(format!("{}.{}", name, extension))
+}
 
-    // iterate through reduced grid
-    for y in 0..grid_size {
-        for x in 0..half_width {
-            if byte_idx < hash.len() {
-                // choose corresponding bit in hash for cell
-                let byte = hash[byte_idx];
-                let bit = (byte >> bit_idx) & 1;
+impl RenderArgs {
+    /// Refuse --kdf-params unless --kdf argon2 would use them; --kdf has a
+    /// default, so clap's `requires` can't tell
+    fn check_kdf(&self) -> Result<()> {
+        if self.kdf_params.is_some() && self.kdf != Kdf::Argon2 {
+            return Err(exit::Invalid("--kdf-params needs --kdf argon2".to_string()).into());
+        }
+        Ok(())
+    }
 
-                // fill cell with bit
-                pattern[y as usize][x as usize] = bit == 1;
+    /// Refuse --kdf and --blake3-key where the digest is a key's SHA-256
+    /// fingerprint rather than a hashed seed, so it stays what `ssh-keygen -l` shows
+    fn check_fingerprinted(&self) -> Result<()> {
+        if self.kdf != Kdf::None || self.blake3_key.is_some() {
+            return Err(exit::Invalid(
+                "--kdf and --blake3-key hash seeds; keys are fingerprinted with SHA-256, as ssh-keygen -l does"
+                    .to_string(),
+            )
+            .into());
+        }
+        Ok(())
+    }
 
-                // mirror horizontally; skip mid on odd
-                let mirror_x = grid_size - 1 - x;
-                if mirror_x != x {
-                    pattern[y as usize][mirror_x as usize] = bit == 1;
-                }
+    /// Warn when --gpu would render none of these identicons
+    fn check_gpu(&self) {
+        #[cfg(feature = "wgpu")]
+        if self.gpu && !self.options("").gpu_applies() {
+            eprintln!(
+                "Warning: --gpu only renders the voronoi style and --supersample with --filter box; these identicons render on the CPU"
+            );
+        }
+    }
 
-                // increment indices
-                bit_idx += 1;
-                if bit_idx >= 8 {
-                    bit_idx = 0;
-                    byte_idx += 1;
-                }
-            }
+    /// Library options for rendering the identicon of `label`
+    fn options(&self, label: &str) -> Options {
+        let initials = match &self.initials {
+            Some(text) => Some(text.clone()),
+            None if self.auto_initials => Some(overlay::initials_from(label)),
+            None => None,
+        };
+        Options {
+            size: self.image_size,
+            height: self.height,
+            grid_size: self.grid_size,
+            padding: self.padding,
+            style: self.style,
+            steps: self.steps,
+            parts_dir: self.parts_dir.clone(),
+            initials,
+            watermark: self
+                .watermark
+                .as_ref()
+                .map(|text| text.replace("{seed}", label)),
+            stamp: self.stamp,
+            background_pattern: self.background_pattern,
+            density: self.density,
+            hue_shift: self.hue_shift,
+            rotate: self.rotate,
+            flip: self.flip,
+            orient: self.orient,
+            min_fill: self.min_fill,
+            max_fill: self.max_fill,
+            filter_symbols: self.filter_symbols,
+            force_center: self.force_center,
+            clear_border: self.clear_border,
+            tileable: self.tileable,
+            tile: self.tile,
+            derivation: self.derivation,
+            supersample: self.supersample,
+            filter: self.filter,
+            #[cfg(feature = "wgpu")]
+            gpu: self.gpu,
+            background: self.background,
+            palette: self.palette.clone(),
+            cell_shape: self.cell_shape,
+            gap: self.gap,
+            mask: self.mask,
+            border: self.border,
+            border_color: self.border_color,
+        }
+    }
+
+    /// Hash `seed` the way these options ask for
+    fn input(&self, seed: &str) -> Result<Input> {
+        if self.kdf == Kdf::Argon2 {
+            let params = self.kdf_params.clone().unwrap_or_default();
+            return Ok(Input::from_seed_argon2(seed, &params)?);
+        }
+        Ok(match &self.blake3_key {
+            Some(key) => Input::from_seed_keyed(seed, key),
+            None => Input::from_seed(seed),
+        })
+    }
+
+    /// Name to base default file names on, telling variants apart
+    fn file_label(&self, label: &str) -> String {
+        match self.variant {
+            0 => label.to_string(),
+            n => format!("{}-v{}", label, n),
+        }
+    }
+
+    /// Size escpos output to the width of its paper, unless -s or a preset
+    /// sized it, so it fits as it is
+    fn fit_paper(&mut self, output: &OutputArgs, matches: &ArgMatches) {
+        if output.format == Some(Format::Escpos)
+            && self.preset.is_none()
+            && !is_explicit(matches, "image_size")
+        {
+            self.image_size = output.paper.dots();
         }
     }
 
-    pattern
+    /// Fill every option not given on the command line from --preset,
+    /// then whatever --theme-file sets over that
+    fn apply_preset(&mut self, matches: &ArgMatches) -> Result<()> {
+        if let Some(path) = &self.preset {
+            let preset = preset::load(path).context("Failed to load preset")?;
+            self.fill_from_preset(preset, matches);
+        }
+        if let Some(path) = &self.theme_file {
+            let theme = theme::load(path).context("Failed to load theme")?;
+            self.fill_from_theme(theme, matches);
+        }
+        Ok(())
+    }
+
+    fn fill_from_preset(&mut self, preset: Options, matches: &ArgMatches) {
+        let explicit = |id: &str| is_explicit(matches, id);
+        if !explicit("image_size") {
+            self.image_size = preset.size;
+        }
+        if !explicit("height") {
+            self.height = preset.height;
+        }
+        if !explicit("grid_size") {
+            self.grid_size = preset.grid_size;
+        }
+        if !explicit("padding") {
+            self.padding = preset.padding;
+        }
+        if !explicit("style") {
+            self.style = preset.style;
+        }
+        if !explicit("steps") {
+            self.steps = preset.steps;
+        }
+        if !explicit("parts_dir") {
+            self.parts_dir = preset.parts_dir;
+        }
+        if !explicit("initials") && !self.auto_initials {
+            self.initials = preset.initials;
+        }
+        if !explicit("watermark") {
+            self.watermark = preset.watermark;
+        }
+        if !explicit("stamp") {
+            self.stamp = preset.stamp;
+        }
+        if !explicit("background_pattern") {
+            self.background_pattern = preset.background_pattern;
+        }
+        if !explicit("density") {
+            self.density = preset.density;
+        }
+        if !explicit("hue_shift") {
+            self.hue_shift = preset.hue_shift;
+        }
+        if !explicit("rotate") {
+            self.rotate = preset.rotate;
+        }
+        if !explicit("flip") {
+            self.flip = preset.flip;
+        }
+        if !explicit("orient") {
+            self.orient = preset.orient;
+        }
+        if !explicit("min_fill") {
+            self.min_fill = preset.min_fill;
+        }
+        if !explicit("max_fill") {
+            self.max_fill = preset.max_fill;
+        }
+        if !explicit("filter_symbols") {
+            self.filter_symbols = preset.filter_symbols;
+        }
+        if !explicit("force_center") {
+            self.force_center = preset.force_center;
+        }
+        if !explicit("clear_border") {
+            self.clear_border = preset.clear_border;
+        }
+        if !explicit("tileable") {
+            self.tileable = preset.tileable;
+        }
+        if !explicit("tile") {
+            self.tile = preset.tile;
+        }
+        if !explicit("derivation") {
+            self.derivation = preset.derivation;
+        }
+        if !explicit("supersample") {
+            self.supersample = preset.supersample;
+        }
+        if !explicit("filter") {
+            self.filter = preset.filter;
+        }
+        if !explicit("background") {
+            self.background = preset.background;
+        }
+        if !explicit("palette") {
+            self.palette = preset.palette;
+        }
+        if !explicit("cell_shape") {
+            self.cell_shape = preset.cell_shape;
+        }
+        if !explicit("gap") {
+            self.gap = preset.gap;
+        }
+        if !explicit("mask") {
+            self.mask = preset.mask;
+        }
+        if !explicit("border") {
+            self.border = preset.border;
+        }
+        if !explicit("border_color") {
+            self.border_color = preset.border_color;
+        }
+    }
+
+    fn fill_from_theme(&mut self, theme: theme::Theme, matches: &ArgMatches) {
+        let explicit = |id: &str| is_explicit(matches, id);
+        if let Some(background) = theme.background
+            && !explicit("background")
+        {
+            self.background = Some(background);
+        }
+        if let Some(palette) = theme.palette
+            && !explicit("palette")
+        {
+            self.palette = palette;
+        }
+        if let Some(shape) = theme.cell_shape
+            && !explicit("cell_shape")
+        {
+            self.cell_shape = shape;
+        }
+        if let Some(gap) = theme.gap
+            && !explicit("gap")
+        {
+            self.gap = gap;
+        }
+        if let Some(mask) = theme.mask
+            && !explicit("mask")
+        {
+            self.mask = Some(mask);
+        }
+        if let Some(border) = theme.border
+            && !explicit("border")
+        {
+            self.border = border;
+        }
+        if let Some(color) = theme.border_color
+            && !explicit("border_color")
+        {
+            self.border_color = Some(color);
+        }
+    }
 }
 
-fn create_identicon(
-    seed: &str,
-    image_size: u32,
-    grid_size: u32,
-    padding_percent: u32,
-) -> Result<RgbImage> {
-    let hash = hash_seed(seed);
-    let color = generate_color(&hash);
-    let pattern = generate_pattern(&hash, grid_size);
-
-    // create white background
-    let mut img = RgbImage::from_pixel(image_size, image_size, Rgb([255, 255, 255]));
-
-    // calculate padding based on percentage
-    let padding = image_size * padding_percent / 100;
-    let drawable_size = image_size - (2 * padding);
-
-    // calculate cell size and additional padding to handle non-exact divisions
-    let cell_size = drawable_size / grid_size;
-    let total_used = cell_size * grid_size;
-    let extra_padding = (drawable_size - total_used) / 2;
-    let total_padding = padding + extra_padding;
-
-    // draw the pattern; sparse iteration through filled cells
-    for (y, row) in pattern.iter().enumerate() {
-        for (x, &filled) in row.iter().enumerate() {
-            if filled {
-                let x_start = total_padding + (x as u32) * cell_size;
-                let y_start = total_padding + (y as u32) * cell_size;
-
-                for dy in 0..cell_size {
-                    for dx in 0..cell_size {
-                        let px = x_start + dx;
-                        let py = y_start + dy;
-                        img.put_pixel(px, py, color);
-                    }
-                }
+/// Render the identicon of `input` and write it out, handing back where it
+/// went and the identicon, for callers that look further at it; `None`
+/// when it came from the --store
+fn generate(
+    input: &Input,
+    output: &OutputArgs,
+    render: &RenderArgs,
+) -> Result<(PathBuf, Option<Identicon>)> {
+    let input = &input.variant(render.variant);
+    let label = &input.label;
+
+    let output_path = output_path(&render.file_label(label), output);
+
+    // generate and save identicon
+    output.status(format_args!("Generating identicon for seed: {}", label));
+    let mut options = render.options(label);
+    let backdrop = Backdrop::from_args(output, &mut options)?;
+    let streams = Streams::new(&input.digest, render.derivation);
+
+    if let Some(kind) = render.stamp {
+        let stamp = stamp::select(&streams.style);
+        let glyph = match kind {
+            StampKind::Emoji => stamp.emoji,
+            StampKind::Dingbat => stamp.dingbat,
+        };
+        output.status(format_args!("Stamp: {} {}", glyph, stamp.name));
+    }
+
+    // randomart is also shown in its familiar text form
+    if render.style == Style::Randomart {
+        let field = randomart::Field::walk(&streams.style);
+        output.status(field.to_ascii("", input.algorithm));
+    }
+
+    let is_gif = output_path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gif"));
+    let is_animation = render.style == Style::Automaton && is_gif && output.format.is_none();
+    // only plain images go through the store
+    let is_plain = output.format != Some(Format::Json) && backdrop.is_none() && !is_animation;
+    let content_type = output::content_type(&output_path, output.format);
+
+    #[cfg(feature = "sqlite")]
+    let entry = match output.store()? {
+        Some(store) if is_plain => Some(store.entry(
+            &input.digest,
+            &options,
+            output.format,
+            output_path.extension().and_then(|ext| ext.to_str()),
+            &output.encoding_for(&input.digest),
+        )?),
+        _ => None,
+    };
+    #[cfg(feature = "sqlite")]
+    if let Some(bytes) = entry.as_ref().map(store::Entry::get).transpose()?.flatten() {
+        let saved = output.write(&output_path, &bytes, content_type)?;
+        output.report_saved("Identicon", &saved);
+        return Ok((saved, None));
+    }
+
+    let identicon =
+        Identicon::new(&input.digest, &options).context("Failed to generate identicon")?;
+    let saved = if output.format == Some(Format::Json) {
+        if !render.style.draws_pattern() {
+            bail!("The {} style has no grid pattern to export", render.style);
+        }
+        let pattern = identicon.pattern().rows().to_vec();
+        let mut model = Model::new(label, render.style, identicon.color(), pattern);
+        if let Some(background) = options.background {
+            model.background = background;
+        }
+        output.write(&output_path, &model.to_json()?, content_type)?
+    } else if let Some(backdrop) = backdrop {
+        let img = backdrop.around(identicon.as_rgb(), output.position);
+        output.save(&img, &output_path)?
+    } else if is_animation {
+        let bytes = automaton_gif(&input.digest, &options, output)?;
+        output
+            .write(&output_path, &bytes, content_type)
+            .context("Failed to save animation")?
+    } else {
+        debug_assert!(is_plain);
+        let bytes = output::encode(
+            identicon.as_rgb(),
+            &output_path,
+            output.format,
+            &output.encoding_for(&input.digest),
+        )?;
+        let saved = output.write(&output_path, &bytes, content_type)?;
+        #[cfg(feature = "sqlite")]
+        if let Some(entry) = &entry {
+            entry.put(content_type, &bytes)?;
+        }
+        saved
+    };
+    output.report_saved("Identicon", &saved);
+
+    #[cfg(feature = "gui")]
+    if output.show {
+        gui::show(identicon.as_rgb(), label)?;
+    }
+
+    Ok((saved, Some(identicon)))
+}
+
+/// A larger image the identicon is drawn into
+enum Backdrop {
+    /// The --compose image
+    Compose(image::RgbImage),
+    #[cfg(feature = "qr")]
+    Qr(Qr),
+}
+
+impl Backdrop {
+    /// The backdrop `output` asks for, if any, with `options` resized to fit it
+    fn from_args(output: &OutputArgs, options: &mut Options) -> Result<Option<Backdrop>> {
+        #[cfg(feature = "qr")]
+        if let Some(data) = &output.qr {
+            let qr = Qr::new(data.as_bytes(), options.size).context("Failed to make QR code")?;
+            options.size = qr.identicon_side();
+            options.height = None;
+            return Ok(Some(Backdrop::Qr(qr)));
+        }
+        let Some(path) = &output.compose else {
+            return Ok(None);
+        };
+        let base = image::open(path)
+            .with_context(|| format!("Failed to load {}", path.display()))?
+            .to_rgb8();
+        options.size = compose::side_for(base.width(), base.height(), output.scale);
+        options.height = None;
+        Ok(Some(Backdrop::Compose(base)))
+    }
+
+    /// The backdrop with `identicon` drawn in, at `position` on a --compose image
+    fn around(self, identicon: &image::RgbImage, position: Position) -> image::RgbImage {
+        match self {
+            Backdrop::Compose(mut base) => {
+                compose::compose(&mut base, identicon, position);
+                base
             }
+            #[cfg(feature = "qr")]
+            Backdrop::Qr(qr) => qr.render(identicon),
         }
     }
+}
+
+fn save_wallpaper(
+    input: &Input,
+    (width, height): (u32, u32),
+    mode: wallpaper::Mode,
+    output: &OutputArgs,
+    render: &RenderArgs,
+) -> Result<()> {
+    let input = &input.variant(render.variant);
+    let label = &input.label;
+    output.status(format_args!(
+        "Generating {}x{} wallpaper for seed: {}",
+        width, height, label
+    ));
+    let img = wallpaper::render(&input.digest, &render.options(label), width, height, mode)
+        .context("Failed to generate wallpaper")?;
 
-    Ok(img)
+    let output_path = output_path(&format!("{}-wallpaper", render.file_label(label)), output);
+    let saved = output.save(&img, &output_path)?;
+    output.report_saved("Wallpaper", &saved);
+
+    Ok(())
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+fn save_card(
+    input: &Input,
+    title: Option<&str>,
+    subtitle: Option<&str>,
+    output: &OutputArgs,
+    render: &RenderArgs,
+) -> Result<()> {
+    let input = &input.variant(render.variant);
+    let label = &input.label;
+    output.status(format_args!("Generating card for seed: {}", label));
+    let img = card::render(
+        &input.digest,
+        &render.options(label),
+        title.unwrap_or(label),
+        subtitle,
+    )
+    .context("Failed to generate card")?;
 
-    // generate output path
-    let output_path = args.output.unwrap_or_else(|| {
-        let name = if args
-            .seed
-            .chars()
-            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
-            && args.seed.len() <= 64
-        {
-            args.seed.clone()
-        } else {
-            format!("{:x}", Sha256::digest(args.seed.as_bytes()))
+    let output_path = output_path(&format!("{}-card", render.file_label(label)), output);
+    let saved = output.save(&img, &output_path)?;
+    output.report_saved("Card", &saved);
+
+    Ok(())
+}
+
+/// Sides packed into favicon.ico, for browser tabs, taskbars, and shortcuts
+const FAVICON_SIZES: [u32; 3] = [16, 32, 48];
+
+/// PNG icons written beside favicon.ico, for iOS home screens and web app
+/// manifests
+const TOUCH_ICONS: [(&str, u32); 3] = [
+    ("apple-touch-icon.png", 180),
+    ("icon-192.png", 192),
+    ("icon-512.png", 512),
+];
+
+/// Write favicon.ico and the touch icons into `out_dir`, each rendered at
+/// its own size rather than scaled, then print the tags that link them
+fn save_favicon(input: &Input, out_dir: &Path, render: &RenderArgs) -> Result<()> {
+    let input = &input.variant(render.variant);
+    let png = |size: u32| -> Result<Vec<u8>> {
+        let options = Options {
+            size,
+            ..render.options(&input.label)
         };
-        PathBuf::from(format!("{}.png", name))
-    });
+        Identicon::new(&input.digest, &options)
+            .context("Failed to generate identicon")?
+            .to_png_bytes()
+            .context("Failed to encode icon")
+    };
 
-    // generate and save identicon
-    println!("Generating identicon for seed: {}", args.seed);
-    let img = create_identicon(&args.seed, args.image_size, args.grid_size, args.padding)
+    sink::create_dir(out_dir)?;
+    let frames = FAVICON_SIZES
+        .into_iter()
+        .map(|size| {
+            IcoFrame::with_encoded(png(size)?, size, size, ExtendedColorType::Rgb8)
+                .context("Failed to encode favicon.ico")
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut ico = Vec::new();
+    IcoEncoder::new(&mut ico)
+        .encode_images(&frames)
+        .context("Failed to encode favicon.ico")?;
+    sink::write(&out_dir.join("favicon.ico"), &ico).context("Failed to save favicon.ico")?;
+    for (name, size) in TOUCH_ICONS {
+        sink::write(&out_dir.join(name), &png(size)?)
+            .with_context(|| format!("Failed to save {}", name))?;
+    }
+
+    eprintln!(
+        "Icons for seed {} saved to: {}",
+        input.label,
+        out_dir.display()
+    );
+    println!(r#"<link rel="icon" href="/favicon.ico" sizes="16x16 32x32 48x48">"#);
+    println!(r#"<link rel="apple-touch-icon" href="/apple-touch-icon.png">"#);
+    Ok(())
+}
+
+fn decode_image(path: &Path, output: Option<&Path>) -> Result<()> {
+    let (model, geometry) = decode_file(path)?;
+
+    // the model itself may be going to stdout, so the summary goes to stderr
+    eprintln!(
+        "Detected {0}x{0} grid of {1}px cells with {2}% padding, color #{3} ({4:.2}% of pixels unexplained)",
+        geometry.grid_size,
+        geometry.cell_size,
+        geometry.padding_percent,
+        hashdenticon::input::hex(&model.color.0),
+        geometry.error_percent()
+    );
+    for line in pattern_lines(&model.pattern) {
+        eprintln!("{}", line);
+    }
+
+    let json = model.to_json()?;
+    match output {
+        Some(path) => {
+            sink::write(path, &json).context("Failed to save model")?;
+            eprintln!("Pattern model saved to: {}", path.display());
+        }
+        None => sink::write(Path::new("-"), &json)?,
+    }
+    Ok(())
+}
+
+/// The pattern model of the identicon image at `path`, labelled with the
+/// file's name
+fn decode_file(path: &Path) -> Result<(Model, decode::Geometry)> {
+    let img = image::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?
+        .to_rgb8();
+    let seed = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    decode::decode(&img, &seed).context("Failed to decode identicon")
+}
+
+/// Compare the identicon image at `path` with the default identicon of
+/// `seed`, at the grid size found in the image
+fn verify_image(path: &Path, seed: &str) -> Result<()> {
+    let (model, geometry) = decode_file(path)?;
+    let options = Options {
+        grid_size: geometry.grid_size,
+        ..Options::default()
+    };
+    let expected = Identicon::from_seed(seed, &options).context("Failed to generate identicon")?;
+    if expected.color() != model.color {
+        return Err(exit::Mismatch(format!(
+            "Color #{} is not the #{} of seed {:?}",
+            hashdenticon::input::hex(&model.color.0),
+            hashdenticon::input::hex(&expected.color().0),
+            seed
+        ))
+        .into());
+    }
+    if expected.pattern().rows() != model.pattern.as_slice() {
+        return Err(exit::Mismatch(format!("Pattern differs from that of seed {:?}", seed)).into());
+    }
+    println!(
+        "{} matches the identicon of seed {:?}",
+        path.display(),
+        seed
+    );
+    Ok(())
+}
+
+/// A grid pattern as lines of block characters, two per cell
+fn pattern_lines(pattern: &[Vec<bool>]) -> impl Iterator<Item = String> + '_ {
+    pattern.iter().map(|row| {
+        let line: String = row
+            .iter()
+            .map(|&cell| if cell { "██" } else { "  " })
+            .collect();
+        line.trim_end().to_string()
+    })
+}
+
+/// Print the digest, color, and pattern the hash of `seed` decides, before
+/// any style draws them
+fn inspect_seed(seed: &str, render: &RenderArgs) -> Result<()> {
+    let input = render.input(seed)?.variant(render.variant);
+    let identicon = Identicon::new(&input.digest, &render.options(&input.label))
         .context("Failed to generate identicon")?;
+    let pattern = identicon.pattern();
+    let cells = pattern.width() * pattern.height();
+    let filled = pattern
+        .rows()
+        .iter()
+        .flatten()
+        .filter(|&&cell| cell)
+        .count();
 
-    img.save(&output_path).context("Failed to save image")?;
+    println!("Seed:     {}", input.label);
+    println!("Digest:   {}", input::hex(&input.digest));
+    println!("Color:    #{}", input::hex(&identicon.color().0));
+    println!(
+        "Pattern:  {}x{}, {} of {} cells filled",
+        pattern.width(),
+        pattern.height(),
+        filled,
+        cells
+    );
+    for line in pattern_lines(pattern.rows()) {
+        println!("{}", line);
+    }
+    Ok(())
+}
 
-    println!("Identicon saved to: {}", output_path.display());
+/// Print the duplicates report of every seed in the file at `path`,
+/// rendered in memory only
+fn analyze_seeds(path: &Path, strict: bool, render: &RenderArgs) -> Result<()> {
+    let seeds = batch::Seeds::read(path)?;
+    let render = Arc::new(render.clone());
+    let mut pending = Vec::new();
+    for seed in batch::lines(&seeds.contents) {
+        check_seed(seed, strict)?;
+        let seed = seed.to_string();
+        let render = Arc::clone(&render);
+        pending.push(RenderPool::global().submit(move || {
+            batch::render_sample(&seed, &render).map(|identicon| Sample::new(&seed, &identicon))
+        }));
+    }
+    let samples = pending
+        .into_iter()
+        .map(Pending::wait)
+        .collect::<Result<Vec<_>>>()?;
 
+    let title = format!("Duplicate identicons in {}", seeds.source);
+    let (report, _) = dedup::report(&title, &samples);
+    print!("{}", report);
     Ok(())
 }
+
+fn render_model(
+    path: &Path,
+    style: Option<Style>,
+    output: &OutputArgs,
+    render: &RenderArgs,
+) -> Result<()> {
+    let json = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let model: Model = serde_json::from_slice(&json).context("Failed to parse pattern model")?;
+
+    // the model's own style applies unless one was asked for
+    let mut options = render.options(&model.seed);
+    options.style = style.unwrap_or(model.style);
+    if !options.style.draws_pattern() {
+        bail!(
+            "The {} style cannot be drawn from a pattern model",
+            options.style
+        );
+    }
+
+    output.status(format_args!(
+        "Rendering pattern model for seed: {}",
+        model.seed
+    ));
+    let identicon = Identicon::from_model(&model, &options)?;
+
+    let output_path = output_path(&model.seed, output);
+    let saved = if output.format == Some(Format::Json) {
+        let restyled = Model {
+            style: options.style,
+            ..model.clone()
+        };
+        output.write(&output_path, &restyled.to_json()?, Format::Json.mime_type())?
+    } else {
+        output.save(identicon.as_rgb(), &output_path)?
+    };
+    output.report_saved("Identicon", &saved);
+
+    #[cfg(feature = "gui")]
+    if output.show {
+        gui::show(identicon.as_rgb(), &model.seed)?;
+    }
+
+    Ok(())
+}
+
+fn automaton_gif(hash: &[u8], options: &Options, output: &OutputArgs) -> Result<Vec<u8>> {
+    let rule = Rule::from_hash(&Streams::new(hash, options.derivation).style);
+    output.status(format_args!(
+        "Evolving {} steps with rule {}",
+        options.steps, rule.name
+    ));
+
+    let frames = Identicon::generations(hash, options)
+        .context("Failed to generate identicon")?
+        .iter()
+        .map(|generation| {
+            let delay = Delay::from_numer_denom_ms(400, 1);
+            Frame::from_parts(generation.to_image().into_rgba8(), 0, 0, delay)
+        })
+        .collect::<Vec<_>>();
+
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut bytes);
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .context("Failed to encode animation")?;
+        encoder
+            .encode_frames(frames)
+            .context("Failed to encode animation")?;
+    }
+    Ok(bytes)
+}
+
+fn known_hosts_report(
+    file: Option<PathBuf>,
+    out_dir: &Path,
+    gallery_kind: GalleryKind,
+    render: &RenderArgs,
+) -> Result<()> {
+    render.check_fingerprinted()?;
+    let file = match file {
+        Some(file) => file,
+        None => {
+            let home = std::env::var_os("HOME").context("HOME is not set")?;
+            PathBuf::from(home).join(".ssh").join("known_hosts")
+        }
+    };
+
+    let contents = std::fs::read_to_string(&file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let keys = known_hosts::parse(&contents).context("Failed to parse known_hosts")?;
+
+    sink::create_dir(out_dir)?;
+
+    let mut entries = Vec::new();
+    let mut images = Vec::new();
+    for (idx, key) in keys.iter().enumerate() {
+        let input = Input::from_key_blob(&key.blob).variant(render.variant);
+        let img = Identicon::new(&input.digest, &render.options(&input.label))
+            .context("Failed to generate identicon")?
+            .into_rgb();
+
+        let name = format!("{:03}.png", idx + 1);
+        sink::save(&img, &out_dir.join(&name), None, &Encoding::default())
+            .context("Failed to save image")?;
+        println!("{}  {} {}  {}", name, key.hosts, key.key_type, input.label);
+
+        let mut caption = vec![key.hosts.clone(), key.key_type.clone(), input.label.clone()];
+        if let Some(marker) = &key.marker {
+            caption.push(marker.clone());
+        }
+        entries.push(GalleryEntry {
+            image: name,
+            caption,
+        });
+        images.push(img);
+    }
+
+    let gallery_path = match gallery_kind {
+        GalleryKind::Html => {
+            let path = out_dir.join("index.html");
+            let title = format!("Host keys in {}", file.display());
+            gallery::write_html(&path, &title, &entries)?;
+            path
+        }
+        GalleryKind::Image => {
+            let path = out_dir.join("gallery.png");
+            let sheet = gallery::contact_sheet(&images, 6, render.image_size / 10);
+            sink::save(&sheet, &path, None, &Encoding::default())
+                .context("Failed to save gallery")?;
+            path
+        }
+    };
+
+    println!(
+        "{} host keys written, gallery saved to: {}",
+        keys.len(),
+        gallery_path.display()
+    );
+
+    Ok(())
+}
+
+fn neighbors_report(
+    seed: &str,
+    out_dir: &Path,
+    gallery_kind: GalleryKind,
+    limit: usize,
+    render: &RenderArgs,
+) -> Result<()> {
+    let render_seed = |seed: &str| -> Result<image::RgbImage> {
+        let input = render.input(seed)?.variant(render.variant);
+        Ok(Identicon::new(&input.digest, &render.options(seed))
+            .context("Failed to generate identicon")?
+            .into_rgb())
+    };
+    let original = render_seed(seed)?;
+
+    let mut scored = Vec::new();
+    for neighbor in neighbors::variants(seed) {
+        let img = render_seed(&neighbor.seed)?;
+        let distance = neighbors::distance(&original, &img);
+        scored.push((distance, neighbor, img));
+    }
+    // closest first: those are the lookalikes the identicon fails to expose
+    scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+    scored.truncate(limit);
+
+    sink::create_dir(out_dir)?;
+    sink::save(
+        &original,
+        &out_dir.join("000.png"),
+        None,
+        &Encoding::default(),
+    )
+    .context("Failed to save image")?;
+    let mut entries = vec![GalleryEntry {
+        image: "000.png".to_string(),
+        caption: vec![seed.to_string(), "original".to_string()],
+    }];
+    let mut images = vec![original];
+
+    println!("distance  kind           seed");
+    for (idx, (distance, neighbor, img)) in scored.into_iter().enumerate() {
+        let name = format!("{:03}.png", idx + 1);
+        sink::save(&img, &out_dir.join(&name), None, &Encoding::default())
+            .context("Failed to save image")?;
+        println!(
+            "{:>7.1}%  {:<13}  {}",
+            distance, neighbor.kind, neighbor.seed
+        );
+
+        entries.push(GalleryEntry {
+            image: name,
+            caption: vec![
+                neighbor.seed,
+                format!("{}, {:.1}% apart", neighbor.kind, distance),
+            ],
+        });
+        images.push(img);
+    }
+
+    let gallery_path = match gallery_kind {
+        GalleryKind::Html => {
+            let path = out_dir.join("index.html");
+            let title = format!("Lookalikes of {}", seed);
+            gallery::write_html(&path, &title, &entries)?;
+            path
+        }
+        GalleryKind::Image => {
+            let path = out_dir.join("gallery.png");
+            let sheet = gallery::contact_sheet(&images, 6, render.image_size / 10);
+            sink::save(&sheet, &path, None, &Encoding::default())
+                .context("Failed to save gallery")?;
+            path
+        }
+    };
+    println!("Gallery saved to: {}", gallery_path.display());
+
+    Ok(())
+}
+
+fn git_contributors(
+    repo: Option<&Path>,
+    out_dir: &Path,
+    html: bool,
+    render: &RenderArgs,
+) -> Result<()> {
+    let repo = repo.unwrap_or(Path::new("."));
+    let contributors = git::contributors(repo)?;
+
+    sink::create_dir(out_dir)?;
+
+    let mut entries = Vec::new();
+    for contributor in &contributors {
+        let input = render.input(&contributor.email)?.variant(render.variant);
+        let name = default_file_name(&render.file_label(&contributor.email), None, false);
+        let identicon = Identicon::new(&input.digest, &render.options(&contributor.name))
+            .context("Failed to generate identicon")?;
+        sink::save(
+            identicon.as_rgb(),
+            &out_dir.join(&name),
+            None,
+            &Encoding::default(),
+        )
+        .context("Failed to save image")?;
+        println!(
+            "{}  {} <{}>",
+            name.display(),
+            contributor.name,
+            contributor.email
+        );
+
+        entries.push(GalleryEntry {
+            image: name.display().to_string(),
+            caption: vec![
+                contributor.name.clone(),
+                contributor.email.clone(),
+                format!("{} commits", contributor.commits),
+            ],
+        });
+    }
+
+    if html {
+        let path = out_dir.join("index.html");
+        let title = format!("Contributors to {}", repo.display());
+        gallery::write_html(&path, &title, &entries)?;
+        println!("Gallery saved to: {}", path.display());
+    }
+    println!(
+        "{} contributors written to: {}",
+        contributors.len(),
+        out_dir.display()
+    );
+
+    Ok(())
+}
+
+/// Warn, or fail when `strict`, about seeds many users could share
+fn check_seed(seed: &str, strict: bool) -> Result<()> {
+    let Some(reason) = input::low_entropy_reason(seed) else {
+        return Ok(());
+    };
+    let advice = "many users could share this identicon. Namespace or salt the seed, e.g. \"example.com:alice\"";
+    if strict {
+        return Err(exit::Invalid(format!("Weak seed: {}; {}", reason, advice)).into());
+    }
+    eprintln!("Warning: {}; {}", reason, advice);
+    Ok(())
+}
+
+/// Log spans as they close, with their durations, when asked for
+fn init_tracing(verbose: u8) {
+    let level = match verbose {
+        0 => "warn",
+        1 => "debug",
+        _ => "trace",
+    };
+    // --gpu: wgpu reports every backend it probes and fails to open as an
+    // error, even when another one works; serve: tiny_http reports the
+    // listener it is shut down from as one, and serve reports real failures
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(format!("{},wgpu_hal=off,tiny_http=off", level)));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(std::io::stderr)
+        .init();
+}
+
+fn main() -> ExitCode {
+    // answers the shell's completion requests, set up by `completions`
+    CompleteEnv::with_factory(Cli::command)
+        .var(complete::VAR)
+        .complete();
+
+    // needed before parsing, so usage errors can be reported as json too
+    let json_errors = std::env::args_os().any(|arg| arg == "--json-errors");
+    let parsed = Cli::command().try_get_matches().and_then(|matches| {
+        let cli = Cli::from_arg_matches(&matches)?;
+        Ok((matches, cli))
+    });
+    let (matches, cli) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) if !json_errors || !err.use_stderr() => err.exit(),
+        Err(err) => {
+            // the first line is the message; the rest is usage help for people
+            let text = err.to_string();
+            let message = text.lines().next().unwrap_or_default();
+            let message = message.strip_prefix("error: ").unwrap_or(message);
+            return exit::report(&exit::Invalid(message.to_string()).into(), true);
+        }
+    };
+    init_tracing(cli.verbose);
+
+    match run(&matches, cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => exit::report(&err, json_errors),
+    }
+}
+
+/// The man page on stdout, or pages for every command written into `out_dir`
+fn man_pages(out_dir: Option<&Path>) -> Result<()> {
+    let Some(dir) = out_dir else {
+        let mut stdout = std::io::stdout().lock();
+        return clap_mangen::Man::new(Cli::command())
+            .render(&mut stdout)
+            .context("Failed to write man page");
+    };
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    clap_mangen::generate_to(Cli::command(), dir)
+        .with_context(|| format!("Failed to write man pages to {}", dir.display()))?;
+    println!("Man pages written to: {}", dir.display());
+    Ok(())
+}
+
+fn run(matches: &ArgMatches, mut cli: Cli) -> Result<()> {
+    if cli.jobs.is_some() || cli.queue_depth.is_some() {
+        let workers = cli.jobs.map_or_else(pool::default_workers, usize::from);
+        let queue_depth = cli
+            .queue_depth
+            .unwrap_or(workers * pool::DEFAULT_QUEUE_PER_WORKER);
+        // nothing has rendered yet, so the global pool cannot exist
+        let _ = RenderPool::set_global(RenderPool::new(workers, queue_depth));
+    }
+
+    // presets fill in whatever the command line left out
+    let sub_matches = matches.subcommand().map_or(matches, |(_, matches)| matches);
+    match &mut cli.command {
+        Some(
+            Command::Generate(GenerateArgs { render, .. })
+            | Command::Batch(BatchArgs { render, .. })
+            | Command::File { render, .. }
+            | Command::Inspect { render, .. }
+            | Command::Analyze { render, .. }
+            | Command::Render { render, .. }
+            | Command::KnownHosts { render, .. }
+            | Command::Git { render, .. }
+            | Command::Wallpaper { render, .. }
+            | Command::Card { render, .. }
+            | Command::Favicon { render, .. }
+            | Command::Neighbors { render, .. },
+        ) => {
+            render.check_kdf()?;
+            render.apply_preset(sub_matches)?;
+            render.check_gpu();
+        }
+        #[cfg(feature = "tui")]
+        Some(Command::Tui { render, .. }) => {
+            render.check_kdf()?;
+            render.apply_preset(sub_matches)?;
+        }
+        // serve applies its preset itself, again on every change
+        #[cfg(feature = "serve")]
+        Some(Command::Serve { render, .. } | Command::Cgi { render, .. }) => render.check_kdf()?,
+        #[cfg(feature = "lambda")]
+        Some(Command::Lambda { render, .. }) => render.check_kdf()?,
+        Some(Command::Decode { .. } | Command::Verify { .. }) => {}
+        #[cfg(feature = "serve")]
+        Some(Command::SignUrl { .. }) => {}
+        Some(Command::Completions { .. }) => {}
+        Some(Command::Man { .. }) => {}
+        None => {
+            cli.generate.render.check_kdf()?;
+            cli.generate.render.apply_preset(matches)?;
+            cli.generate.render.check_gpu();
+        }
+    }
+    match &mut cli.command {
+        Some(
+            Command::Generate(GenerateArgs { render, output, .. })
+            | Command::Batch(BatchArgs { render, output, .. })
+            | Command::File { render, output, .. },
+        ) => render.fit_paper(output, sub_matches),
+        None => cli.generate.render.fit_paper(&cli.generate.output, matches),
+        _ => {}
+    }
+
+    match cli.command {
+        Some(Command::Generate(args)) => generate_seed(args, sub_matches),
+        Some(Command::Batch(args)) => batch_seeds(args),
+        Some(Command::File {
+            path,
+            output,
+            render,
+        }) => {
+            let file =
+                File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+            let input = Input::from_reader(file).context("Failed to read file")?;
+
+            // same layout as sha256sum, so it can be compared directly
+            println!("{}  {}", input.label, path.display());
+
+            generate(&input, &output, &render)?;
+            Ok(())
+        }
+        Some(Command::Decode { image, output }) => decode_image(&image, output.as_deref()),
+        Some(Command::Verify { image, seed }) => verify_image(&image, &seed),
+        Some(Command::Inspect { seed, render }) => inspect_seed(&seed, &render),
+        Some(Command::Analyze {
+            seeds,
+            strict,
+            render,
+        }) => analyze_seeds(&seeds, strict, &render),
+        Some(Command::Render {
+            from_json,
+            output,
+            render,
+        }) => {
+            let style = is_explicit(sub_matches, "style").then_some(render.style);
+            render_model(&from_json, style, &output, &render)
+        }
+        Some(Command::KnownHosts {
+            file,
+            out_dir,
+            gallery,
+            render,
+        }) => known_hosts_report(file, &out_dir, gallery, &render),
+        Some(Command::Wallpaper {
+            seed,
+            resolution,
+            mode,
+            output,
+            render,
+        }) => save_wallpaper(&render.input(&seed)?, resolution, mode, &output, &render),
+        Some(Command::Card {
+            seed,
+            title,
+            subtitle,
+            output,
+            render,
+        }) => save_card(
+            &render.input(&seed)?,
+            title.as_deref(),
+            subtitle.as_deref(),
+            &output,
+            &render,
+        ),
+        Some(Command::Favicon {
+            seed,
+            out_dir,
+            render,
+        }) => save_favicon(&render.input(&seed)?, &out_dir, &render),
+        Some(Command::Neighbors {
+            seed,
+            out_dir,
+            gallery,
+            limit,
+            render,
+        }) => neighbors_report(&seed, &out_dir, gallery, limit, &render),
+        Some(Command::Git {
+            repo,
+            out_dir,
+            html,
+            render,
+        }) => git_contributors(repo.as_deref(), &out_dir, html, &render),
+        #[cfg(feature = "tui")]
+        Some(Command::Tui {
+            seed,
+            preset_out,
+            mut render,
+        }) => {
+            let preset_out = preset_out.unwrap_or_else(|| PathBuf::from("preset.toml"));
+            let input = render.input(&seed)?.variant(render.variant);
+            tui::run(&input, &mut render, &preset_out)
+        }
+        #[cfg(feature = "serve")]
+        Some(Command::Serve {
+            listen,
+            #[cfg(feature = "grpc")]
+            grpc,
+            serve,
+            render,
+        }) => {
+            #[cfg_attr(not(feature = "grpc"), allow(unused_mut))]
+            let mut config = serve.config(render, sub_matches)?;
+            #[cfg(feature = "grpc")]
+            {
+                config.grpc = grpc;
+            }
+            serve::run(&listen, config)
+        }
+        #[cfg(feature = "serve")]
+        Some(Command::Cgi { serve, render }) => cgi::run(&serve.config(render, sub_matches)?),
+        #[cfg(feature = "lambda")]
+        Some(Command::Lambda { serve, render }) => lambda::run(&serve.config(render, sub_matches)?),
+        Some(Command::Completions { shell }) => complete::write_registration(shell),
+        Some(Command::Man { out_dir }) => man_pages(out_dir.as_deref()),
+        #[cfg(feature = "serve")]
+        Some(Command::SignUrl {
+            url,
+            signing_key,
+            ttl,
+        }) => {
+            let ttl = std::time::Duration::from_secs(ttl);
+            println!("{}", serve::signing::sign_url(&signing_key, &url, ttl));
+            Ok(())
+        }
+        None => generate_seed(cli.generate, matches),
+    }
+}
+
+/// `generate`, given as a command or not, with `matches` for its arguments
+fn generate_seed(mut args: GenerateArgs, matches: &ArgMatches) -> Result<()> {
+    // normalize seed input into the digest to visualize
+    let input = if let Some(cert_path) = &args.cert {
+        let data = std::fs::read(cert_path).context("Failed to read certificate")?;
+        let input = Input::from_certificate(&data).context("Failed to parse certificate")?;
+        args.output
+            .status(format_args!("Certificate public key pin: {}", input.pin()));
+        input
+    } else {
+        let seed = match args.seed.take() {
+            Some(seed) => seed,
+            None => prompt::seed(args.hide_seed)?,
+        };
+        if !args.uuid && !args.fingerprint {
+            check_seed(&seed, args.strict)?;
+        }
+        let mut input = if args.uuid {
+            Input::from_uuid(&seed)?
+        } else if args.fingerprint {
+            Input::from_fingerprint(&seed)?
+        } else {
+            args.render.input(&seed)?
+        };
+        if args.hide_seed {
+            input.label = input::hex(&input.digest[..8]);
+        }
+        input
+    };
+
+    // a prompted seed gets a look before choosing where it goes
+    if args.cert.is_none() && matches.value_source("seed").is_none() {
+        let input = input.variant(args.render.variant);
+        prompt::preview(&input, &args.render.options(&input.label))?;
+        if args.output.output.is_none() {
+            let default = output_path(&args.render.file_label(&input.label), &args.output);
+            args.output.output = Some(prompt::save_path(&default)?);
+        }
+    }
+
+    generate(&input, &args.output, &args.render)?;
+    Ok(())
+}
+
+/// `batch`: every seed of a file or URL, once or on every change
+fn batch_seeds(args: BatchArgs) -> Result<()> {
+    if args.watch {
+        let seeds = args.seeds.as_deref().expect("--watch conflicts with --url");
+        return watch::run(seeds, &args.output, &args.render);
+    }
+
+    #[cfg(feature = "http")]
+    let seeds = match &args.url {
+        Some(url) => fetch::seed_list(url, args.retries, args.checksum.as_deref())?,
+        None => batch::Seeds::read(args.seeds.as_deref().expect("required without --url"))?,
+    };
+    #[cfg(not(feature = "http"))]
+    let seeds = batch::Seeds::read(args.seeds.as_deref().expect("required"))?;
+    let reports = batch::Reports {
+        gallery: args.gallery.as_deref(),
+        dedup: args.dedup_report.as_deref(),
+        manifest: args.manifest.as_deref(),
+    };
+    batch::run(
+        &seeds,
+        &reports,
+        args.strict,
+        args.resume,
+        &args.output,
+        &args.render,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_args(args: &[&str]) -> RenderArgs {
+        let args = ["hashdenticon", "alice"].iter().chain(args);
+        Cli::try_parse_from(args).unwrap().generate.render
+    }
+
+    #[test]
+    fn fingerprints_refuse_seed_hashing() {
+        let key = "00".repeat(32);
+        for args in [&["--kdf", "argon2"][..], &["--blake3-key", &key]] {
+            let err = render_args(args).check_fingerprinted().unwrap_err();
+            assert!(err.is::<exit::Invalid>());
+        }
+        assert!(
+            render_args(&["--variant", "2"])
+                .check_fingerprinted()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn kdf_params_need_argon2() {
+        for args in [
+            &["--kdf-params", "t=3"][..],
+            &["--kdf", "none", "--kdf-params", "t=3"],
+        ] {
+            let err = render_args(args).check_kdf().unwrap_err();
+            assert_eq!(err.to_string(), "--kdf-params needs --kdf argon2");
+            assert!(err.is::<exit::Invalid>());
+        }
+        assert!(
+            render_args(&["--kdf", "argon2", "--kdf-params", "t=3"])
+                .check_kdf()
+                .is_ok()
+        );
+        assert!(render_args(&["--kdf", "argon2"]).check_kdf().is_ok());
+        assert!(render_args(&[]).check_kdf().is_ok());
+    }
+
+    #[test]
+    fn parses_dimensions() {
+        assert_eq!(parse_dimensions("1920x1080"), Ok((1920, 1080)));
+        assert_eq!(parse_dimensions("640X480"), Ok((640, 480)));
+        assert_eq!(parse_dimensions(" 1 x 1 "), Ok((1, 1)));
+    }
+
+    #[test]
+    fn rejects_zero_dimensions() {
+        for arg in ["0x1080", "1920x0", "0x0"] {
+            assert_eq!(
+                parse_dimensions(arg),
+                Err("width and height must be at least 1".to_string()),
+                "{}",
+                arg
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_dimensions() {
+        for arg in [
+            "1920",
+            "1920x",
+            "x1080",
+            "",
+            "1920x1080x2",
+            "19.2x10",
+            "-1x5",
+            "axb",
+        ] {
+            assert_eq!(
+                parse_dimensions(arg),
+                Err("expected WIDTHxHEIGHT, e.g. 1920x1080".to_string()),
+                "{}",
+                arg
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_overflowing_dimensions() {
+        assert_eq!(parse_dimensions("4294967295x1"), Ok((u32::MAX, 1)));
+        assert_eq!(
+            parse_dimensions("4294967296x1"),
+            Err("4294967296 is too large".to_string())
+        );
+        assert_eq!(
+            parse_dimensions("1x99999999999"),
+            Err("99999999999 is too large".to_string())
+        );
+    }
+
+    #[test]
+    fn parses_quarter_turns_only() {
+        for (arg, degrees) in [("0", 0), ("90", 90), ("180", 180), ("270", 270)] {
+            assert_eq!(parse_rotation(arg), Ok(degrees));
+        }
+        for arg in ["45", "360", "-90", "+90", " 90", "090", ""] {
+            assert_eq!(
+                parse_rotation(arg),
+                Err("expected 0, 90, 180, or 270".to_string()),
+                "{}",
+                arg
+            );
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! `--manifest`: one json line per batch seed, for systems that ingest the
+//! results and retry failures
+
+use crate::sink;
+use anyhow::{Context, Result, bail};
+use hashdenticon::output;
+use serde_json::{Value, json};
+use std::collections::HashSet;
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Outcome of generating one seed
+pub struct Record<'a> {
+    pub seed: &'a str,
+    /// Hex digest the identicon was drawn from, for seeds that succeeded
+    pub hash: Option<&'a str>,
+    pub path: &'a Path,
+    pub duration: Duration,
+    /// Why the seed failed, if it did
+    pub error: Option<String>,
+    /// Left alone by --resume, as its file already existed
+    pub skipped: bool,
+}
+
+impl Record<'_> {
+    fn status(&self) -> &'static str {
+        match (&self.error, self.skipped) {
+            (Some(_), _) => "error",
+            (None, true) => "skipped",
+            (None, false) => "ok",
+        }
+    }
+}
+
+/// Line-buffered, so every finished seed is on disk even if the run is cut short
+pub struct Manifest {
+    out: Box<dyn Write>,
+    path: Option<PathBuf>,
+}
+
+impl Manifest {
+    /// A manifest at `path` inside `dir`, or on stdout for `-`; when
+    /// resuming, new lines go after those of the earlier run
+    pub fn create(path: &Path, dir: &Path, resume: bool) -> Result<Manifest> {
+        if output::is_stdout(path) {
+            return Ok(Manifest {
+                out: Box::new(std::io::stdout()),
+                path: None,
+            });
+        }
+        if sink::is_remote(dir) {
+            bail!("Objects can't be appended to line by line; use --manifest - with s3:// output");
+        }
+        let path = dir.join(path);
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resume)
+            .truncate(!resume)
+            .open(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        Ok(Manifest {
+            out: Box::new(LineWriter::new(file)),
+            path: Some(path),
+        })
+    }
+
+    /// Whether the manifest takes up stdout, so progress has to go elsewhere
+    pub fn on_stdout(&self) -> bool {
+        self.path.is_none()
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn record(&mut self, record: &Record) -> Result<()> {
+        // unknown for failures and for uploads
+        let bytes = match record.error {
+            None => std::fs::metadata(record.path).ok().map(|meta| meta.len()),
+            Some(_) => None,
+        };
+        let line = json!({
+            "seed": record.seed,
+            "hash": record.hash,
+            "path": record.path,
+            "bytes": bytes,
+            "duration": record.duration.as_secs_f64(),
+            "status": record.status(),
+            "error": record.error,
+        });
+        writeln!(self.out, "{}", line)
+            .and_then(|()| self.out.flush())
+            .context("Failed to write manifest")
+    }
+}
+
+/// Seeds that an earlier run's manifest at `path` inside `dir` records as
+/// done, or none when there is no such manifest yet
+pub fn completed(path: &Path, dir: &Path) -> Result<HashSet<String>> {
+    if output::is_stdout(path) {
+        return Ok(HashSet::new());
+    }
+    let path = dir.join(path);
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(HashSet::new()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("Failed to read {}", path.display()));
+        }
+    };
+    // a line cut short by a crash is just not counted
+    let done = contents
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|line| matches!(line["status"].as_str(), Some("ok" | "skipped")))
+        .filter_map(|line| line["seed"].as_str().map(str::to_string))
+        .collect();
+    Ok(done)
+}
@@ -0,0 +1,127 @@
+//! Structured pattern model, the data behind an identicon without pixels
+
+use crate::style::Style;
+use image::Rgb;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+#[cfg(feature = "serde")]
+use serde_json::ser::{Formatter, PrettyFormatter};
+#[cfg(feature = "serde")]
+use std::io;
+
+/// Everything a renderer needs to redraw a grid-based identicon
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, serde::Deserialize))]
+pub struct Model {
+    /// Seed label the pattern was derived from
+    pub seed: String,
+    pub style: Style,
+    /// Foreground color as `#rrggbb`
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::hex"))]
+    pub color: Rgb<u8>,
+    /// Background color as `#rrggbb`
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::hex"))]
+    pub background: Rgb<u8>,
+    pub width: usize,
+    pub height: usize,
+    /// Rows of cells, `true` meaning filled
+    pub pattern: Vec<Vec<bool>>,
+}
+
+impl Model {
+    pub fn new(seed: &str, style: Style, color: Rgb<u8>, pattern: Vec<Vec<bool>>) -> Model {
+        Model {
+            seed: seed.to_string(),
+            style,
+            color,
+            background: Rgb([255, 255, 255]),
+            width: pattern.first().map_or(0, Vec::len),
+            height: pattern.len(),
+            pattern,
+        }
+    }
+
+    /// Pretty-printed JSON with each pattern row kept on one line
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> serde_json::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        let mut serializer =
+            serde_json::Serializer::with_formatter(&mut out, RowFormatter::default());
+        self.serialize(&mut serializer)?;
+        out.push(b'\n');
+        Ok(out)
+    }
+}
+
+/// Pretty formatter that writes arrays nested inside arrays inline
+#[cfg(feature = "serde")]
+#[derive(Default)]
+struct RowFormatter {
+    pretty: PrettyFormatter<'static>,
+    depth: usize,
+}
+
+#[cfg(feature = "serde")]
+impl Formatter for RowFormatter {
+    fn begin_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.depth += 1;
+        if self.depth > 1 {
+            writer.write_all(b"[")
+        } else {
+            self.pretty.begin_array(writer)
+        }
+    }
+
+    fn end_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.depth -= 1;
+        if self.depth > 0 {
+            writer.write_all(b"]")
+        } else {
+            self.pretty.end_array(writer)
+        }
+    }
+
+    fn begin_array_value<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        if self.depth > 1 {
+            writer.write_all(if first { b"" } else { b", " })
+        } else {
+            self.pretty.begin_array_value(writer, first)
+        }
+    }
+
+    fn end_array_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        if self.depth > 1 {
+            Ok(())
+        } else {
+            self.pretty.end_array_value(writer)
+        }
+    }
+
+    fn begin_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.begin_object(writer)
+    }
+
+    fn end_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.end_object(writer)
+    }
+
+    fn begin_object_key<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.pretty.begin_object_key(writer, first)
+    }
+
+    fn begin_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.begin_object_value(writer)
+    }
+
+    fn end_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.end_object_value(writer)
+    }
+}
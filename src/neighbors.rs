@@ -0,0 +1,81 @@
+//! Lookalike variants of a seed, for judging how far apart their
+//! identicons land
+
+use image::RgbImage;
+
+/// Characters commonly mistaken for one another
+const HOMOGLYPHS: &[(&str, &str)] = &[
+    ("l", "1"),
+    ("l", "I"),
+    ("I", "1"),
+    ("o", "0"),
+    ("O", "0"),
+    ("rn", "m"),
+    ("vv", "w"),
+    ("cl", "d"),
+    ("e", "3"),
+    ("a", "@"),
+    ("s", "5"),
+    ("i", "j"),
+    ("-", "_"),
+    (".", ","),
+];
+
+/// A lookalike seed and how it was made
+pub struct Neighbor {
+    pub seed: String,
+    pub kind: &'static str,
+}
+
+/// Homoglyph swaps, dropped, doubled, and transposed characters of
+/// `seed`, without duplicates or the seed itself
+pub fn variants(seed: &str) -> Vec<Neighbor> {
+    let chars: Vec<char> = seed.chars().collect();
+    let mut out: Vec<Neighbor> = Vec::new();
+    let mut push = |candidate: String, kind| {
+        if candidate != seed && !candidate.is_empty() && !out.iter().any(|n| n.seed == candidate) {
+            out.push(Neighbor {
+                seed: candidate,
+                kind,
+            });
+        }
+    };
+
+    for &(a, b) in HOMOGLYPHS {
+        for (from, to) in [(a, b), (b, a)] {
+            for (idx, _) in seed.match_indices(from) {
+                let mut candidate = seed.to_string();
+                candidate.replace_range(idx..idx + from.len(), to);
+                push(candidate, "homoglyph");
+            }
+        }
+    }
+    for idx in 0..chars.len() {
+        let mut dropped = chars.clone();
+        dropped.remove(idx);
+        push(dropped.into_iter().collect(), "deletion");
+
+        let mut doubled = chars.clone();
+        doubled.insert(idx, chars[idx]);
+        push(doubled.into_iter().collect(), "duplication");
+
+        if idx + 1 < chars.len() {
+            let mut swapped = chars.clone();
+            swapped.swap(idx, idx + 1);
+            push(swapped.into_iter().collect(), "transposition");
+        }
+    }
+    out
+}
+
+/// Mean per-channel difference of two same-sized images, as a percentage
+pub fn distance(a: &RgbImage, b: &RgbImage) -> f64 {
+    let total: u64 = a
+        .as_raw()
+        .iter()
+        .zip(b.as_raw())
+        .map(|(&x, &y)| x.abs_diff(y) as u64)
+        .sum();
+    let count = a.as_raw().len().max(1) as f64;
+    total as f64 / count / 255.0 * 100.0
+}
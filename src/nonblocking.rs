@@ -0,0 +1,96 @@
+//! Async entry points for servers on Tokio, such as axum or actix-web
+//!
+//! Rendering and encoding are CPU work that can take milliseconds for
+//! large or supersampled identicons. These run it on Tokio's blocking
+//! thread pool, so the runtime's workers stay free for other requests.
+//!
+//! ```no_run
+//! use hashdenticon::output::Format;
+//! use hashdenticon::{Options, nonblocking};
+//!
+//! # async fn avatar() -> hashdenticon::Result<()> {
+//! let png = nonblocking::generate("alice", Options::default(), Format::Png).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{HashdenticonError, Result};
+use crate::output::{self, Encoding, Format};
+use crate::{Identicon, Input, Options};
+use std::path::PathBuf;
+
+/// Render the identicon of a plain seed string and encode it as `format`
+pub async fn generate(
+    seed: impl Into<String>,
+    options: Options,
+    format: Format,
+) -> Result<Vec<u8>> {
+    let seed = seed.into();
+    blocking(move || {
+        encode_identicon(
+            &Input::from_seed(&seed),
+            &options,
+            format,
+            &Encoding::default(),
+        )
+    })
+    .await
+}
+
+/// Render the identicon of `input`, however it was hashed, and encode it
+/// as `format` with `encoding`
+pub async fn generate_input(
+    input: Input,
+    options: Options,
+    format: Format,
+    encoding: Encoding,
+) -> Result<Vec<u8>> {
+    blocking(move || encode_identicon(&input, &options, format, &encoding)).await
+}
+
+/// Encode an identicon already rendered
+pub async fn encode(identicon: Identicon, format: Format, encoding: Encoding) -> Result<Vec<u8>> {
+    blocking(move || {
+        output::encode(
+            identicon.as_rgb(),
+            &file_name(format),
+            Some(format),
+            &encoding,
+        )
+    })
+    .await
+}
+
+/// Run any other library work, such as [`Identicon::from_model`], on the
+/// blocking pool. A panic in `work` resumes in the caller
+pub async fn blocking<T, F>(work: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    match tokio::task::spawn_blocking(work).await {
+        Ok(result) => result,
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        Err(_) => Err(HashdenticonError::Cancelled),
+    }
+}
+
+fn encode_identicon(
+    input: &Input,
+    options: &Options,
+    format: Format,
+    encoding: &Encoding,
+) -> Result<Vec<u8>> {
+    let identicon = Identicon::new(&input.digest, options)?;
+    output::encode(
+        identicon.as_rgb(),
+        &file_name(format),
+        Some(format),
+        encoding,
+    )
+}
+
+/// Source formats name their arrays after the file
+fn file_name(format: Format) -> PathBuf {
+    PathBuf::from(format!("identicon.{}", format.extension()))
+}
@@ -0,0 +1,560 @@
+//! Output encodings beyond the raster formats `image` infers from the path
+
+#[cfg(feature = "svg")]
+mod badge;
+mod escpos;
+#[cfg(feature = "svg")]
+mod html;
+mod pbm;
+#[cfg(feature = "svg")]
+mod pdf;
+#[cfg(feature = "raster")]
+pub(crate) mod raster;
+mod raw;
+mod source;
+#[cfg(feature = "svg")]
+pub(crate) mod svg;
+#[cfg(feature = "raster")]
+mod webp;
+
+use crate::dither::{self, Dither};
+use crate::error::{HashdenticonError, Result};
+#[cfg(feature = "svg")]
+use crate::vector::Scene;
+use image::RgbImage;
+use std::fs::File;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Explicit output encoding
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Format {
+    Png,
+    /// WebP, lossless unless --quality is below 100
+    Webp,
+    /// AVIF, lossy at --quality
+    Avif,
+    /// 1-bit portable bitmap, see --dither
+    Pbm,
+    /// ESC/POS raster commands for thermal receipt printers, see --paper and --dither
+    Escpos,
+    /// 1-bit X BitMap C source
+    Xbm,
+    /// X PixMap C source, full color
+    Xpm,
+    /// C header with a 1-bit bitmap array
+    Carray,
+    /// Rust source with a 1-bit bitmap const array
+    Rust,
+    /// farbfeld, 16-bit RGBA with a small header
+    Ff,
+    /// Headerless 8-bit RGBA
+    Raw,
+    /// Single-page vector PDF
+    Pdf,
+    /// Encapsulated PostScript, vector
+    Eps,
+    /// Vector SVG of filled rectangles
+    Svg,
+    /// Self-contained HTML/CSS snippet, for places images are blocked
+    Html,
+    /// shields.io-style SVG badge with a label beside the identicon, see --badge-label
+    Badge,
+    /// Structured pattern model: colors, grid dimensions, and cells
+    Json,
+}
+
+impl Format {
+    /// Every format, in the order help lists them
+    pub const ALL: [Format; 17] = [
+        Format::Png,
+        Format::Webp,
+        Format::Avif,
+        Format::Pbm,
+        Format::Escpos,
+        Format::Xbm,
+        Format::Xpm,
+        Format::Carray,
+        Format::Rust,
+        Format::Ff,
+        Format::Raw,
+        Format::Pdf,
+        Format::Eps,
+        Format::Svg,
+        Format::Html,
+        Format::Badge,
+        Format::Json,
+    ];
+
+    /// File extension used for default output names
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Webp => "webp",
+            Format::Avif => "avif",
+            Format::Pbm => "pbm",
+            Format::Escpos => "escpos",
+            Format::Xbm => "xbm",
+            Format::Xpm => "xpm",
+            Format::Carray => "h",
+            Format::Rust => "rs",
+            Format::Ff => "ff",
+            Format::Raw => "rgba",
+            Format::Pdf => "pdf",
+            Format::Eps => "eps",
+            Format::Svg => "svg",
+            Format::Html => "html",
+            Format::Badge => "badge.svg",
+            Format::Json => "json",
+        }
+    }
+
+    /// Media type for uploads and HTTP responses
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Format::Png => "image/png",
+            Format::Webp => "image/webp",
+            Format::Avif => "image/avif",
+            Format::Pbm => "image/x-portable-bitmap",
+            Format::Escpos => "application/octet-stream",
+            Format::Xbm => "image/x-xbitmap",
+            Format::Xpm => "image/x-xpixmap",
+            Format::Carray => "text/x-c",
+            Format::Rust => "text/x-rust",
+            Format::Ff => "image/x-farbfeld",
+            Format::Raw => "application/octet-stream",
+            Format::Pdf => "application/pdf",
+            Format::Eps => "application/postscript",
+            Format::Svg => "image/svg+xml",
+            Format::Html => "text/html",
+            Format::Badge => "image/svg+xml",
+            Format::Json => "application/json",
+        }
+    }
+}
+
+/// Encoder settings that apply across formats
+#[derive(Clone, Debug, Default)]
+pub struct Encoding {
+    /// Dithering used when reducing to 1-bit formats
+    pub dither: Dither,
+    /// Physical resolution to record, for png and tiff
+    pub dpi: Option<u32>,
+    /// Write png with a palette when the image has at most 256 colors
+    pub indexed: bool,
+    /// Deflate level for png, 0 (store) to 9 (smallest) [default: 6]
+    pub compression: Option<u8>,
+    /// Row filter strategy for png [default: adaptive]
+    pub filter: Option<PngFilter>,
+    /// Quality for lossy formats, 1 to 100, where 100 is lossless for webp
+    /// [default: 80 for avif, lossless webp]
+    pub quality: Option<u8>,
+    /// Roll width escpos output is centered on
+    pub paper: Paper,
+    /// Text on the right of badge output [default: the file name]
+    pub label: Option<String>,
+}
+
+/// Width of a thermal receipt roll
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Paper {
+    /// 58 mm, 384 dots across at 203 dpi
+    #[default]
+    #[cfg_attr(feature = "clap", value(name = "58mm"))]
+    Mm58,
+    /// 80 mm, 576 dots across at 203 dpi
+    #[cfg_attr(feature = "clap", value(name = "80mm"))]
+    Mm80,
+}
+
+impl Paper {
+    /// Printable width in dots
+    pub fn dots(self) -> u32 {
+        match self {
+            Paper::Mm58 => 384,
+            Paper::Mm80 => 576,
+        }
+    }
+}
+
+impl std::fmt::Display for Paper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Paper::Mm58 => "58mm",
+            Paper::Mm80 => "80mm",
+        })
+    }
+}
+
+/// Per-row predictor applied to png data before compression
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum PngFilter {
+    None,
+    Sub,
+    Up,
+    Avg,
+    Paeth,
+    /// Pick the best predictor per row by a quick heuristic
+    Adaptive,
+    /// Pick the predictor per row that minimizes entropy, slower but smaller
+    MinEntropy,
+}
+
+/// Write `img` to `path`, inferring the format from the extension when
+/// none is given. A path of `-` writes to stdout, as png unless a format
+/// is given.
+pub fn save(
+    img: &RgbImage,
+    path: &Path,
+    format: Option<Format>,
+    encoding: &Encoding,
+) -> Result<()> {
+    write(path, &encode(img, path, format, encoding)?)
+}
+
+/// Encode `img` the way [`save`] would for `path`, without writing it
+pub fn encode(
+    img: &RgbImage,
+    path: &Path,
+    format: Option<Format>,
+    encoding: &Encoding,
+) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    encode_into(img, path, format, encoding, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Append `img` to `out`, encoded as [`encode`] would, so servers can
+/// reuse one buffer across requests. On failure `out` is left as it was
+#[tracing::instrument(name = "encode", level = "debug", skip(img, encoding, out), fields(path = %path.display()))]
+pub fn encode_into(
+    img: &RgbImage,
+    path: &Path,
+    format: Option<Format>,
+    encoding: &Encoding,
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    let len = out.len();
+    let result = append(img, path, format, encoding, out);
+    if result.is_err() {
+        out.truncate(len);
+    }
+    result
+}
+
+fn append(
+    img: &RgbImage,
+    path: &Path,
+    format: Option<Format>,
+    encoding: &Encoding,
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    let to_stdout = is_stdout(path);
+    let format = match format {
+        Some(format) => format,
+        None if to_stdout => Format::Png,
+        None => match image::ImageFormat::from_path(path) {
+            Ok(image::ImageFormat::Png) => Format::Png,
+            Ok(image::ImageFormat::WebP) => Format::Webp,
+            Ok(image::ImageFormat::Avif) => Format::Avif,
+            #[cfg(feature = "raster")]
+            Ok(image::ImageFormat::Tiff) => {
+                // tiff offsets count from the start of the stream
+                out.extend_from_slice(&raster::tiff(img, encoding)?);
+                return Ok(());
+            }
+            #[cfg(not(feature = "raster"))]
+            Ok(image::ImageFormat::Tiff) => return Err(needs_feature("tiff", "raster")),
+            _ if encoding.dpi.is_some() => return Err(no_dpi()),
+            _ if encoding.quality.is_some() => return Err(no_quality()),
+            Ok(other) => {
+                let mut cursor = Cursor::new(Vec::new());
+                img.write_to(&mut cursor, other)?;
+                out.extend_from_slice(cursor.get_ref());
+                return Ok(());
+            }
+            Err(err) => return Err(err.into()),
+        },
+    };
+    if encoding.dpi.is_some() && format != Format::Png {
+        return Err(no_dpi());
+    }
+    if encoding.quality.is_some() && !matches!(format, Format::Webp | Format::Avif) {
+        return Err(no_quality());
+    }
+
+    let name = identifier(path);
+    let bitmap = || dither::to_bitmap(img, encoding.dither);
+    match format {
+        #[cfg(feature = "raster")]
+        Format::Png => raster::png(img, encoding, out)?,
+        #[cfg(feature = "raster")]
+        Format::Webp => raster::webp(img, encoding, out)?,
+        #[cfg(feature = "raster")]
+        Format::Avif => raster::avif(img, encoding, out)?,
+        #[cfg(not(feature = "raster"))]
+        Format::Png | Format::Webp | Format::Avif => {
+            return Err(needs_feature(format.extension(), "raster"));
+        }
+        Format::Pbm => pbm::encode(&bitmap(), out),
+        Format::Escpos => escpos::encode(&bitmap(), encoding.paper, out)?,
+        Format::Xbm => out.extend_from_slice(source::xbm(&bitmap(), &name).as_bytes()),
+        Format::Xpm => out.extend_from_slice(source::xpm(img, &name).as_bytes()),
+        Format::Carray => out.extend_from_slice(source::c_array(&bitmap(), &name).as_bytes()),
+        Format::Rust => out.extend_from_slice(source::rust_array(&bitmap(), &name).as_bytes()),
+        Format::Ff => raw::farbfeld(img, out),
+        Format::Raw => raw::rgba(img, out),
+        #[cfg(feature = "svg")]
+        Format::Pdf => out.extend_from_slice(&pdf::encode(&Scene::trace(img))),
+        #[cfg(feature = "svg")]
+        Format::Eps => out.extend_from_slice(&pdf::encode_eps(&Scene::trace(img))),
+        #[cfg(feature = "svg")]
+        Format::Svg => out.extend_from_slice(svg::encode(&Scene::trace(img)).as_bytes()),
+        #[cfg(feature = "svg")]
+        Format::Html => out.extend_from_slice(html::encode(&Scene::trace(img)).as_bytes()),
+        #[cfg(feature = "svg")]
+        Format::Badge => {
+            let label = encoding.label.clone().unwrap_or_else(|| badge_name(path));
+            out.extend_from_slice(badge::encode(&Scene::trace(img), &label).as_bytes());
+        }
+        #[cfg(not(feature = "svg"))]
+        Format::Pdf | Format::Eps | Format::Svg | Format::Html | Format::Badge => {
+            return Err(needs_feature(format.extension(), "svg"));
+        }
+        Format::Json => {
+            return Err(HashdenticonError::Unsupported(
+                "The json format describes a pattern model, not an image".to_string(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// The error for a format whose encoder was left out of the build
+#[cfg(not(all(feature = "raster", feature = "svg")))]
+fn needs_feature(format: &str, feature: &str) -> HashdenticonError {
+    HashdenticonError::Unsupported(format!(
+        "{} output needs hashdenticon's {} feature",
+        format, feature
+    ))
+}
+
+/// MIME type of what [`save`] writes to `path` in `format`
+pub fn content_type(path: &Path, format: Option<Format>) -> &'static str {
+    let extension = path.extension().and_then(|ext| ext.to_str());
+    let format = format.or_else(|| {
+        Format::ALL.into_iter().find(|format| {
+            extension.is_some_and(|ext| ext.eq_ignore_ascii_case(format.extension()))
+        })
+    });
+    match format {
+        Some(format) => format.mime_type(),
+        None => image::ImageFormat::from_path(path)
+            .map_or("application/octet-stream", |format| format.to_mime_type()),
+    }
+}
+
+/// Write encoded output to `path`, or to stdout for `-`
+///
+/// Files are written to a temporary sibling and renamed into place, so an
+/// interrupted run leaves either the old file or the complete new one. A
+/// symlink is written through to its target, and a replaced file keeps its
+/// permissions.
+pub fn write(path: &Path, bytes: &[u8]) -> Result<()> {
+    if is_stdout(path) {
+        let mut stdout = std::io::stdout().lock();
+        return stdout
+            .write_all(bytes)
+            .and_then(|()| stdout.flush())
+            .map_err(HashdenticonError::io("stdout"));
+    }
+
+    let target = resolve(path);
+    let tmp = temp_path(&target);
+    let written = File::create(&tmp)
+        .and_then(|mut file| {
+            if let Ok(old) = std::fs::metadata(&target) {
+                file.set_permissions(old.permissions())?;
+            }
+            file.write_all(bytes).and_then(|()| file.sync_all())
+        })
+        .and_then(|()| std::fs::rename(&tmp, &target));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    written.map_err(HashdenticonError::io(path))
+}
+
+/// The file a write to `path` lands in: what a symlink points to, even a
+/// target that doesn't exist yet, rather than the link itself
+fn resolve(path: &Path) -> PathBuf {
+    let mut path = path.to_path_buf();
+    // as many links as Linux follows before giving up with ELOOP
+    for _ in 0..40 {
+        let Ok(target) = std::fs::read_link(&path) else {
+            break;
+        };
+        path = path.parent().unwrap_or(Path::new("")).join(target);
+    }
+    path
+}
+
+/// Hidden file next to `path`, unique to this process and this call, so
+/// that threads writing the same path don't share one
+fn temp_path(path: &Path) -> PathBuf {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let call = NEXT.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{}.{}.{}.tmp", name, std::process::id(), call))
+}
+
+/// Whether `path` is the conventional `-` for stdout
+pub fn is_stdout(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Symbol name for source formats, derived from the output file stem
+fn identifier(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut name: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !name.chars().any(|c| c.is_ascii_alphanumeric()) {
+        return "identicon".to_string();
+    }
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        name.insert_str(0, "identicon_");
+    }
+    name
+}
+
+/// Badge label for output to `path`: the file name without its extension
+/// or the `.badge.svg` it defaults to
+#[cfg(feature = "svg")]
+fn badge_name(path: &Path) -> String {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let name = name.strip_suffix(".badge.svg").unwrap_or(&name);
+    match name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem.to_string(),
+        _ => name.to_string(),
+    }
+}
+
+/// Hex digits of the hash a badge shows by default, enough to tell
+/// identicons apart at a glance
+pub const BADGE_DIGITS: usize = 8;
+
+/// The label the CLI and server badge the identicon of `digest` with
+pub fn badge_label(digest: &[u8]) -> String {
+    let mut hex = crate::input::hex(digest);
+    hex.truncate(BADGE_DIGITS);
+    hex
+}
+
+fn no_dpi() -> HashdenticonError {
+    HashdenticonError::Unsupported("--dpi is only recorded in png and tiff output".to_string())
+}
+
+fn no_quality() -> HashdenticonError {
+    HashdenticonError::Unsupported("--quality only applies to webp and avif output".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_writes_of_one_path_all_land() {
+        let dir = std::env::temp_dir().join(format!("hashdenticon-write-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("same.png");
+        std::thread::scope(|scope| {
+            for byte in 0..8u8 {
+                let path = &path;
+                scope.spawn(move || write(path, &[byte; 4096]).unwrap());
+            }
+        });
+        // one writer's bytes whole, and no temporary files left behind
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(bytes.len() == 4096 && bytes.iter().all(|&b| b == bytes[0]));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "clap")]
+    #[test]
+    fn names_match_the_argument_names() {
+        use clap::ValueEnum;
+        assert_eq!(Format::ALL, Format::value_variants());
+        for paper in Paper::value_variants() {
+            let value = paper.to_possible_value().unwrap();
+            assert_eq!(paper.to_string(), value.get_name());
+        }
+    }
+
+    #[cfg(feature = "webp")]
+    #[test]
+    fn webp_is_lossy_below_full_quality() {
+        let img = RgbImage::from_fn(16, 16, |x, y| image::Rgb([x as u8 * 16, y as u8 * 16, 90]));
+        let chunk = |quality: Option<u8>| {
+            let encoding = Encoding {
+                quality,
+                ..Encoding::default()
+            };
+            let bytes = encode(&img, "a.webp".as_ref(), Some(Format::Webp), &encoding).unwrap();
+            bytes[12..16].to_vec()
+        };
+        assert_eq!(chunk(Some(60)), b"VP8 ");
+        assert_eq!(chunk(Some(100)), b"VP8L");
+        assert_eq!(chunk(None), b"VP8L");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_and_permissions_survive_a_write() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("hashdenticon-link-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("target.png");
+        let link = dir.join("link.png");
+        std::fs::write(&target, b"old").unwrap();
+        std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o640)).unwrap();
+        std::os::unix::fs::symlink("target.png", &link).unwrap();
+
+        write(&link, b"new").unwrap();
+        assert!(std::fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(std::fs::read(&target).unwrap(), b"new");
+        let mode = std::fs::metadata(&target).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+
+        // a dangling link creates its target
+        std::fs::remove_file(&target).unwrap();
+        write(&link, b"again").unwrap();
+        assert_eq!(std::fs::read(&target).unwrap(), b"again");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn temp_paths_differ_per_call() {
+        let path = Path::new("out/alice.png");
+        assert_ne!(temp_path(path), temp_path(path));
+        assert_eq!(temp_path(path).parent(), Some(Path::new("out")));
+    }
+}
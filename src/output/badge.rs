@@ -0,0 +1,128 @@
+//! shields.io-style SVG badges: the identicon as the left block and a
+//! label on the right, for READMEs and dashboards
+
+use crate::overlay;
+use crate::vector::Scene;
+use image::Rgb;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+const HEIGHT: u32 = 20;
+
+/// Space either side of the label
+const PADDING: u32 = 6;
+
+/// Advance of a character of 11px Verdana, which is what shields.io badges
+/// set their text in; `textLength` holds other fonts to the same width
+const CHAR_WIDTH: u32 = 7;
+
+/// A badge `HEIGHT` pixels tall with the traced identicon scaled into its
+/// left block and `label` on the identicon's main color to the right
+pub fn encode(scene: &Scene, label: &str) -> String {
+    let icon = (HEIGHT * scene.width).div_ceil(scene.height.max(1)).max(1);
+    let text = CHAR_WIDTH * label.chars().count() as u32;
+    let width = icon + text + 2 * PADDING;
+    let color = main_color(scene);
+    let ink = overlay::ink_for(overlay::luma(color));
+    let label = escape(label);
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" role=\"img\" aria-label=\"{}\">",
+        width, HEIGHT, label
+    );
+    let _ = writeln!(out, "<title>{}</title>", label);
+    out.push_str("<linearGradient id=\"s\" x2=\"0\" y2=\"100%\"><stop offset=\"0\" stop-color=\"#bbb\" stop-opacity=\".1\"/><stop offset=\"1\" stop-opacity=\".1\"/></linearGradient>\n");
+    let _ = writeln!(
+        out,
+        "<clipPath id=\"r\"><rect width=\"{}\" height=\"{}\" rx=\"3\" fill=\"#fff\"/></clipPath>",
+        width, HEIGHT
+    );
+    out.push_str("<g clip-path=\"url(#r)\">\n");
+    let _ = writeln!(
+        out,
+        "<svg width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" preserveAspectRatio=\"none\" shape-rendering=\"crispEdges\">",
+        icon, HEIGHT, scene.width, scene.height
+    );
+    let _ = writeln!(
+        out,
+        "<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+        scene.width,
+        scene.height,
+        hex(scene.background)
+    );
+    for rect in &scene.rects {
+        let _ = writeln!(
+            out,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+            rect.x,
+            rect.y,
+            rect.width,
+            rect.height,
+            hex(rect.color)
+        );
+    }
+    out.push_str("</svg>\n");
+    let _ = writeln!(
+        out,
+        "<rect x=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+        icon,
+        text + 2 * PADDING,
+        HEIGHT,
+        hex(color)
+    );
+    let _ = writeln!(
+        out,
+        "<rect width=\"{}\" height=\"{}\" fill=\"url(#s)\"/>",
+        width, HEIGHT
+    );
+    out.push_str("</g>\n");
+
+    let _ = writeln!(
+        out,
+        "<g fill=\"{}\" text-anchor=\"middle\" font-family=\"Verdana,Geneva,DejaVu Sans,sans-serif\" font-size=\"11\">",
+        hex(ink)
+    );
+    let center = icon + PADDING + text / 2;
+    // the drop shadow shields.io gives light text
+    if ink == Rgb([255, 255, 255]) {
+        let _ = writeln!(
+            out,
+            "<text x=\"{}\" y=\"15\" fill=\"#010101\" fill-opacity=\".3\" textLength=\"{}\">{}</text>",
+            center, text, label
+        );
+    }
+    let _ = writeln!(
+        out,
+        "<text x=\"{}\" y=\"14\" textLength=\"{}\">{}</text>",
+        center, text, label
+    );
+    out.push_str("</g>\n</svg>\n");
+    out
+}
+
+/// The color covering the most of the identicon besides its background,
+/// the background itself when nothing else was drawn
+fn main_color(scene: &Scene) -> Rgb<u8> {
+    let mut areas: HashMap<Rgb<u8>, u64> = HashMap::new();
+    for rect in &scene.rects {
+        *areas.entry(rect.color).or_default() += u64::from(rect.width) * u64::from(rect.height);
+    }
+    areas
+        .into_iter()
+        .max_by_key(|&(color, area)| (area, color.0))
+        .map_or(scene.background, |(color, _)| color)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn hex(color: Rgb<u8>) -> String {
+    let [r, g, b] = color.0;
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
@@ -0,0 +1,106 @@
+//! ESC/POS raster bit images, for thermal receipt printers
+
+use super::Paper;
+use crate::dither::Bitmap;
+use crate::error::{HashdenticonError, Result};
+
+/// Rows sent per `GS v 0` command; many printers buffer no more of one image
+const STRIP_ROWS: usize = 256;
+
+/// Reset the printer, print `bitmap` centered across `paper`, then feed it
+/// past the cutter and cut, which printers without one ignore
+pub fn encode(bitmap: &Bitmap, paper: Paper, out: &mut Vec<u8>) -> Result<()> {
+    let dots = paper.dots();
+    if bitmap.width > dots {
+        return Err(HashdenticonError::Unsupported(format!(
+            "A {} pixel wide image doesn't fit the {} dots of {} paper; use -s {} or less",
+            bitmap.width, dots, paper, dots
+        )));
+    }
+
+    let row_bytes = bitmap.width.div_ceil(8) as usize;
+    let paper_bytes = (dots / 8) as usize;
+    let margin = (paper_bytes - row_bytes) / 2;
+    let rows = bitmap.packed_rows_msb();
+
+    // ESC @: initialize
+    out.extend_from_slice(&[0x1b, b'@']);
+    for strip in rows.chunks(row_bytes * STRIP_ROWS) {
+        let height = (strip.len() / row_bytes) as u16;
+        // GS v 0, normal density, then width in bytes and height in dots
+        out.extend_from_slice(&[0x1d, b'v', b'0', 0]);
+        out.extend_from_slice(&(paper_bytes as u16).to_le_bytes());
+        out.extend_from_slice(&height.to_le_bytes());
+        for row in strip.chunks(row_bytes) {
+            out.extend(std::iter::repeat_n(0, margin));
+            out.extend_from_slice(row);
+            out.extend(std::iter::repeat_n(0, paper_bytes - margin - row_bytes));
+        }
+    }
+    // GS V 66 0: feed to the cutter, then cut leaving a hinge
+    out.extend_from_slice(&[0x1d, b'V', 66, 0]);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dither::{Dither, to_bitmap};
+    use image::{Rgb, RgbImage};
+
+    // a 10x3 image with only its first and last column inked
+    fn edges() -> Bitmap {
+        let img = RgbImage::from_fn(10, 3, |x, _| {
+            if x == 0 || x == 9 {
+                Rgb([0, 0, 0])
+            } else {
+                Rgb([255, 255, 255])
+            }
+        });
+        to_bitmap(&img, Dither::None)
+    }
+
+    #[test]
+    fn rows_are_padded_and_centered() {
+        let mut out = Vec::new();
+        encode(&edges(), Paper::Mm58, &mut out).unwrap();
+
+        // 384 dots are 48 bytes: xL 48, xH 0, then 3 rows: yL 3, yH 0
+        assert_eq!(out[..10], [0x1b, b'@', 0x1d, b'v', b'0', 0, 48, 0, 3, 0]);
+        let rows = &out[10..out.len() - 4];
+        assert_eq!(rows.len(), 3 * 48);
+        for row in rows.chunks(48) {
+            // 10 dots round up to 2 bytes, with (48 - 2) / 2 bytes either side
+            let mut expected = [0u8; 48];
+            expected[23] = 0x80;
+            expected[24] = 0x40;
+            assert_eq!(row, expected);
+        }
+        assert_eq!(out[out.len() - 4..], [0x1d, b'V', 66, 0]);
+    }
+
+    #[test]
+    fn tall_images_are_sent_in_strips() {
+        let img = RgbImage::from_pixel(8, 300, Rgb([0, 0, 0]));
+        let mut out = Vec::new();
+        encode(&to_bitmap(&img, Dither::None), Paper::Mm80, &mut out).unwrap();
+
+        // 576 dots are 72 bytes, 0x0048; 256 rows are 0x0100
+        assert_eq!(out[2..10], [0x1d, b'v', b'0', 0, 72, 0, 0, 1]);
+        let second = 10 + 256 * 72;
+        assert_eq!(out[second..second + 8], [0x1d, b'v', b'0', 0, 72, 0, 44, 0]);
+        assert_eq!(out.len(), second + 8 + 44 * 72 + 4);
+    }
+
+    #[test]
+    fn images_wider_than_the_paper_are_refused() {
+        let fits = to_bitmap(&RgbImage::new(384, 1), Dither::None);
+        assert!(encode(&fits, Paper::Mm58, &mut Vec::new()).is_ok());
+        let wide = to_bitmap(&RgbImage::new(385, 1), Dither::None);
+        assert!(matches!(
+            encode(&wide, Paper::Mm58, &mut Vec::new()),
+            Err(HashdenticonError::Unsupported(_))
+        ));
+        assert!(encode(&wide, Paper::Mm80, &mut Vec::new()).is_ok());
+    }
+}
@@ -0,0 +1,37 @@
+//! Self-contained HTML snippet reproducing the identicon with CSS grid
+
+use crate::vector::Scene;
+use image::Rgb;
+use std::fmt::Write as _;
+
+/// A single `<div>` laid out as a grid of 1px tracks, each traced
+/// rectangle spanning its cells. Styles are inline so the snippet survives
+/// email clients that strip `<style>` blocks.
+pub fn encode(scene: &Scene) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<div style=\"display:grid;grid-template-columns:repeat({w},1px);grid-template-rows:repeat({h},1px);width:{w}px;height:{h}px;background:{bg}\">",
+        w = scene.width,
+        h = scene.height,
+        bg = hex(scene.background)
+    );
+    for rect in &scene.rects {
+        let _ = writeln!(
+            out,
+            "<div style=\"grid-area:{}/{}/{}/{};background:{}\"></div>",
+            rect.y + 1,
+            rect.x + 1,
+            rect.y + rect.height + 1,
+            rect.x + rect.width + 1,
+            hex(rect.color)
+        );
+    }
+    out.push_str("</div>\n");
+    out
+}
+
+fn hex(color: Rgb<u8>) -> String {
+    let [r, g, b] = color.0;
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
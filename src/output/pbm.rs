@@ -0,0 +1,8 @@
+//! Binary (P4) portable bitmap encoding
+
+use crate::dither::Bitmap;
+
+pub fn encode(bitmap: &Bitmap, out: &mut Vec<u8>) {
+    out.extend_from_slice(format!("P4\n{} {}\n", bitmap.width, bitmap.height).as_bytes());
+    out.extend(bitmap.packed_rows_msb());
+}
@@ -0,0 +1,82 @@
+//! Single-page vector PDF and EPS encodings
+
+use crate::vector::Scene;
+use image::Rgb;
+use std::fmt::Write as _;
+
+/// One PDF point per pixel; the page is the identicon
+pub fn encode(scene: &Scene) -> Vec<u8> {
+    let mut content = String::new();
+    paint(&mut content, scene, "rg", "re f");
+
+    let objects = [
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents 4 0 R /Resources << >> >>",
+            scene.width, scene.height
+        ),
+        format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ),
+    ];
+
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (idx, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        let _ = write!(out, "{} 0 obj\n{}\nendobj\n", idx + 1, object);
+    }
+
+    let xref = out.len();
+    let _ = write!(out, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(out, "{:010} 00000 n ", offset);
+    }
+    let _ = write!(
+        out,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    );
+    out.into_bytes()
+}
+
+/// Encapsulated PostScript with the same drawing as [`encode`]
+pub fn encode_eps(scene: &Scene) -> Vec<u8> {
+    let mut out = String::from("%!PS-Adobe-3.0 EPSF-3.0\n");
+    let _ = writeln!(out, "%%BoundingBox: 0 0 {} {}", scene.width, scene.height);
+    out.push_str("%%EndComments\n");
+    paint(&mut out, scene, "setrgbcolor", "rectfill");
+    out.push_str("showpage\n%%EOF\n");
+    out.into_bytes()
+}
+
+/// Fill operators for the background and every rectangle, flipping to the
+/// bottom-left origin both formats use
+fn paint(out: &mut String, scene: &Scene, set_color: &str, fill_rect: &str) {
+    let mut current = None;
+    let mut fill = |out: &mut String, x: u32, y: u32, width: u32, height: u32, color: Rgb<u8>| {
+        if current != Some(color) {
+            let [r, g, b] = color.0.map(|c| f64::from(c) / 255.0);
+            let _ = writeln!(out, "{:.3} {:.3} {:.3} {}", r, g, b, set_color);
+            current = Some(color);
+        }
+        let _ = writeln!(
+            out,
+            "{} {} {} {} {}",
+            x,
+            scene.height - y - height,
+            width,
+            height,
+            fill_rect
+        );
+    };
+
+    fill(out, 0, 0, scene.width, scene.height, scene.background);
+    for rect in &scene.rects {
+        fill(out, rect.x, rect.y, rect.width, rect.height, rect.color);
+    }
+}
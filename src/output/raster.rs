@@ -0,0 +1,181 @@
+//! PNG, TIFF, WebP and AVIF encoding, with the metadata and tuning `image`
+//! has no way to set from its path-based `save`
+
+use super::{Encoding, PngFilter};
+use crate::error::Result;
+use image::codecs::avif::AvifEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::error::{EncodingError, ImageFormatHint};
+use image::{ExtendedColorType, ImageEncoder, ImageError, ImageFormat, RgbImage};
+use std::collections::HashMap;
+use std::io::Cursor;
+use tiff::encoder::{Rational, TiffEncoder, colortype};
+use tiff::tags::ResolutionUnit;
+
+/// Ten-thousandths of a meter per inch
+const INCH: u64 = 254;
+
+// cavif's defaults
+const AVIF_QUALITY: u8 = 80;
+const AVIF_SPEED: u8 = 4;
+
+// palette entries a PNG can hold
+const MAX_PALETTE: usize = 256;
+
+/// Append `img` as PNG to `out`
+pub fn png(img: &RgbImage, encoding: &Encoding, out: &mut Vec<u8>) -> Result<()> {
+    let indexed = encoding.indexed.then(|| index(img)).flatten();
+
+    let mut encoder = png::Encoder::new(out, img.width(), img.height());
+    let data = match &indexed {
+        Some(indexed) => {
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_depth(indexed.depth);
+            encoder.set_palette(indexed.palette.as_slice());
+            indexed.data.as_slice()
+        }
+        None => {
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            img.as_raw().as_slice()
+        }
+    };
+    // colors are computed as sRGB; without the tag color-managed viewers on
+    // wide-gamut displays stretch them and they come out washed out
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    if let Some(level) = encoding.compression {
+        encoder.set_deflate_compression(match level {
+            0 => png::DeflateCompression::NoCompression,
+            level => png::DeflateCompression::Level(level),
+        });
+    }
+    if let Some(filter) = encoding.filter {
+        encoder.set_filter(match filter {
+            PngFilter::None => png::Filter::NoFilter,
+            PngFilter::Sub => png::Filter::Sub,
+            PngFilter::Up => png::Filter::Up,
+            PngFilter::Avg => png::Filter::Avg,
+            PngFilter::Paeth => png::Filter::Paeth,
+            PngFilter::Adaptive => png::Filter::Adaptive,
+            PngFilter::MinEntropy => png::Filter::MinEntropy,
+        });
+    }
+    if let Some(dpi) = encoding.dpi {
+        // pHYs only knows pixels per meter
+        let ppm = ((u64::from(dpi) * 10_000 + INCH / 2) / INCH) as u32;
+        encoder.set_pixel_dims(Some(png::PixelDimensions {
+            xppu: ppm,
+            yppu: ppm,
+            unit: png::Unit::Meter,
+        }));
+    }
+
+    let mut writer = encoder
+        .write_header()
+        .map_err(encode_error(ImageFormat::Png))?;
+    writer
+        .write_image_data(data)
+        .map_err(encode_error(ImageFormat::Png))?;
+    writer.finish().map_err(encode_error(ImageFormat::Png))?;
+    Ok(())
+}
+
+/// Palette image data, packed at the smallest bit depth that holds it
+struct Indexed {
+    palette: Vec<u8>,
+    depth: png::BitDepth,
+    data: Vec<u8>,
+}
+
+/// The exact palette of `img`, or `None` when it has too many colors to
+/// index without quantizing them away
+fn index(img: &RgbImage) -> Option<Indexed> {
+    let mut colors: HashMap<[u8; 3], u8> = HashMap::new();
+    let mut palette = Vec::new();
+    let mut indices = Vec::with_capacity(img.as_raw().len() / 3);
+    for pixel in img.pixels() {
+        let next = colors.len();
+        let idx = match colors.get(&pixel.0) {
+            Some(&idx) => idx,
+            None if next < MAX_PALETTE => {
+                colors.insert(pixel.0, next as u8);
+                palette.extend_from_slice(&pixel.0);
+                next as u8
+            }
+            None => return None,
+        };
+        indices.push(idx);
+    }
+
+    let (depth, bits) = match colors.len() {
+        0..=2 => (png::BitDepth::One, 1),
+        3..=4 => (png::BitDepth::Two, 2),
+        5..=16 => (png::BitDepth::Four, 4),
+        _ => (png::BitDepth::Eight, 8),
+    };
+    // each row starts on a fresh byte
+    let per_byte = 8 / bits;
+    let data = indices
+        .chunks(img.width() as usize)
+        .flat_map(|row| {
+            row.chunks(per_byte).map(|pixels| {
+                pixels
+                    .iter()
+                    .enumerate()
+                    .fold(0u8, |byte, (i, &idx)| byte | idx << (8 - bits * (i + 1)))
+            })
+        })
+        .collect();
+    Some(Indexed {
+        palette,
+        depth,
+        data,
+    })
+}
+
+pub fn tiff(img: &RgbImage, encoding: &Encoding) -> Result<Vec<u8>> {
+    let mut bytes = Cursor::new(Vec::new());
+    let mut encoder = TiffEncoder::new(&mut bytes).map_err(encode_error(ImageFormat::Tiff))?;
+    let mut image = encoder
+        .new_image::<colortype::RGB8>(img.width(), img.height())
+        .map_err(encode_error(ImageFormat::Tiff))?;
+    if let Some(dpi) = encoding.dpi {
+        image.resolution(ResolutionUnit::Inch, Rational { n: dpi, d: 1 });
+    }
+    image
+        .write_data(img.as_raw())
+        .map_err(encode_error(ImageFormat::Tiff))?;
+    Ok(bytes.into_inner())
+}
+
+/// Lossless webp, or lossy below a quality of 100
+pub fn webp(img: &RgbImage, encoding: &Encoding, out: &mut Vec<u8>) -> Result<()> {
+    if let Some(quality) = encoding.quality.filter(|&quality| quality < 100) {
+        return super::webp::lossy(img, quality, out);
+    }
+    WebPEncoder::new_lossless(out).write_image(
+        img.as_raw(),
+        img.width(),
+        img.height(),
+        ExtendedColorType::Rgb8,
+    )?;
+    Ok(())
+}
+
+pub fn avif(img: &RgbImage, encoding: &Encoding, out: &mut Vec<u8>) -> Result<()> {
+    let quality = encoding.quality.unwrap_or(AVIF_QUALITY);
+    AvifEncoder::new_with_speed_quality(out, AVIF_SPEED, quality).write_image(
+        img.as_raw(),
+        img.width(),
+        img.height(),
+        ExtendedColorType::Rgb8,
+    )?;
+    Ok(())
+}
+
+fn encode_error<E>(format: ImageFormat) -> impl FnOnce(E) -> ImageError
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    move |err| ImageError::Encoding(EncodingError::new(ImageFormatHint::Exact(format), err))
+}
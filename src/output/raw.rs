@@ -0,0 +1,26 @@
+//! Uncompressed RGBA encodings for piping into other tools
+
+use image::RgbImage;
+
+/// farbfeld: magic, big-endian dimensions, then 16-bit big-endian RGBA
+pub fn farbfeld(img: &RgbImage, out: &mut Vec<u8>) {
+    let (width, height) = img.dimensions();
+    out.reserve(16 + img.pixels().len() * 8);
+    out.extend_from_slice(b"farbfeld");
+    out.extend_from_slice(&width.to_be_bytes());
+    out.extend_from_slice(&height.to_be_bytes());
+    for pixel in img.pixels() {
+        for channel in pixel.0.into_iter().chain([u8::MAX]) {
+            // widen 8-bit to 16-bit so that 0xff maps to 0xffff
+            out.extend_from_slice(&(u16::from(channel) * 257).to_be_bytes());
+        }
+    }
+}
+
+/// Headerless 8-bit RGBA, row-major
+pub fn rgba(img: &RgbImage, out: &mut Vec<u8>) {
+    out.extend(
+        img.pixels()
+            .flat_map(|pixel| pixel.0.into_iter().chain([u8::MAX])),
+    );
+}
@@ -0,0 +1,130 @@
+//! Embeddable source-code encodings: XBM, XPM, and C/Rust byte arrays
+
+use crate::dither::Bitmap;
+use image::RgbImage;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+/// X BitMap: C source with rows packed least significant bit first
+pub fn xbm(bitmap: &Bitmap, name: &str) -> String {
+    let row_bytes = bitmap.width.div_ceil(8);
+    let mut bytes = vec![0u8; (row_bytes * bitmap.height) as usize];
+    for y in 0..bitmap.height {
+        for x in 0..bitmap.width {
+            if bitmap.get(x, y) {
+                bytes[(y * row_bytes + x / 8) as usize] |= 1 << (x % 8);
+            }
+        }
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(out, "#define {}_width {}", name, bitmap.width);
+    let _ = writeln!(out, "#define {}_height {}", name, bitmap.height);
+    let _ = writeln!(out, "static unsigned char {}_bits[] = {{", name);
+    write_hex_rows(&mut out, &bytes, 12, "   ");
+    out.push_str("};\n");
+    out
+}
+
+/// X PixMap: C source with a color table and one character code per color
+pub fn xpm(img: &RgbImage, name: &str) -> String {
+    // printable characters other than quote and backslash, which need escaping
+    const CODES: &[u8] =
+        b".#abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789+@$%&*=-;:<>,/?!";
+
+    let mut palette: BTreeMap<[u8; 3], usize> = BTreeMap::new();
+    for pixel in img.pixels() {
+        let next = palette.len();
+        palette.entry(pixel.0).or_insert(next);
+    }
+
+    // enough characters per pixel to give every color a unique code
+    let mut chars_per_pixel = 1;
+    while CODES.len().pow(chars_per_pixel as u32) < palette.len() {
+        chars_per_pixel += 1;
+    }
+    let code = |mut idx: usize| {
+        let mut code = String::with_capacity(chars_per_pixel);
+        for _ in 0..chars_per_pixel {
+            code.push(CODES[idx % CODES.len()] as char);
+            idx /= CODES.len();
+        }
+        code
+    };
+
+    let (width, height) = img.dimensions();
+    let mut out = String::from("/* XPM */\n");
+    let _ = writeln!(out, "static char *{}_xpm[] = {{", name);
+    let _ = writeln!(
+        out,
+        "\"{} {} {} {}\",",
+        width,
+        height,
+        palette.len(),
+        chars_per_pixel
+    );
+
+    let mut colors: Vec<(&[u8; 3], &usize)> = palette.iter().collect();
+    colors.sort_by_key(|(_, idx)| **idx);
+    for (rgb, &idx) in colors {
+        let _ = writeln!(
+            out,
+            "\"{} c #{:02X}{:02X}{:02X}\",",
+            code(idx),
+            rgb[0],
+            rgb[1],
+            rgb[2]
+        );
+    }
+
+    for y in 0..height {
+        out.push('"');
+        for x in 0..width {
+            out.push_str(&code(palette[&img.get_pixel(x, y).0]));
+        }
+        out.push('"');
+        out.push_str(if y + 1 < height { ",\n" } else { "\n" });
+    }
+    out.push_str("};\n");
+    out
+}
+
+/// C header with a 1-bit bitmap packed most significant bit first, the
+/// layout used by common OLED and e-paper display libraries
+pub fn c_array(bitmap: &Bitmap, name: &str) -> String {
+    let upper = name.to_ascii_uppercase();
+    let mut out = String::from("#include <stdint.h>\n\n");
+    let _ = writeln!(out, "#define {}_WIDTH {}", upper, bitmap.width);
+    let _ = writeln!(out, "#define {}_HEIGHT {}\n", upper, bitmap.height);
+    let _ = writeln!(out, "static const uint8_t {}[] = {{", name);
+    write_hex_rows(&mut out, &bitmap.packed_rows_msb(), 12, "   ");
+    out.push_str("};\n");
+    out
+}
+
+/// Rust source with the same bitmap layout as [`c_array`]
+pub fn rust_array(bitmap: &Bitmap, name: &str) -> String {
+    let upper = name.to_ascii_uppercase();
+    let bytes = bitmap.packed_rows_msb();
+    let mut out = String::new();
+    let _ = writeln!(out, "pub const {}_WIDTH: u32 = {};", upper, bitmap.width);
+    let _ = writeln!(
+        out,
+        "pub const {}_HEIGHT: u32 = {};\n",
+        upper, bitmap.height
+    );
+    let _ = writeln!(out, "pub const {}: [u8; {}] = [", upper, bytes.len());
+    write_hex_rows(&mut out, &bytes, 12, "   ");
+    out.push_str("];\n");
+    out
+}
+
+fn write_hex_rows(out: &mut String, bytes: &[u8], per_line: usize, indent: &str) {
+    for line in bytes.chunks(per_line) {
+        out.push_str(indent);
+        for byte in line {
+            let _ = write!(out, " 0x{:02x},", byte);
+        }
+        out.push('\n');
+    }
+}
@@ -0,0 +1,42 @@
+//! SVG encoding of a traced scene
+
+use crate::vector::Scene;
+use image::Rgb;
+use std::fmt::Write as _;
+
+/// One `<rect>` per traced rectangle over a full-size background, with
+/// crisp edges so cells stay sharp when scaled
+pub fn encode(scene: &Scene) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" shape-rendering=\"crispEdges\">",
+        w = scene.width,
+        h = scene.height
+    );
+    let _ = writeln!(
+        out,
+        "<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+        scene.width,
+        scene.height,
+        hex(scene.background)
+    );
+    for rect in &scene.rects {
+        let _ = writeln!(
+            out,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+            rect.x,
+            rect.y,
+            rect.width,
+            rect.height,
+            hex(rect.color)
+        );
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+fn hex(color: Rgb<u8>) -> String {
+    let [r, g, b] = color.0;
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
@@ -0,0 +1,24 @@
+//! Lossy WebP through libwebp, kept apart from the other encoders because
+//! libwebp takes its quality as a float
+
+#[cfg(not(feature = "webp"))]
+use crate::error::HashdenticonError;
+use crate::error::Result;
+use image::RgbImage;
+
+/// Append `img` to `out` as lossy webp at `quality`
+#[cfg(feature = "webp")]
+pub fn lossy(img: &RgbImage, quality: u8, out: &mut Vec<u8>) -> Result<()> {
+    let encoded = ::webp::Encoder::from_rgb(img.as_raw(), img.width(), img.height())
+        .encode(f32::from(quality));
+    out.extend_from_slice(&encoded);
+    Ok(())
+}
+
+#[cfg(not(feature = "webp"))]
+pub fn lossy(_img: &RgbImage, _quality: u8, _out: &mut Vec<u8>) -> Result<()> {
+    Err(HashdenticonError::Unsupported(
+        "Lossy webp needs hashdenticon's webp feature; leave --quality out for lossless"
+            .to_string(),
+    ))
+}
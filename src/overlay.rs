@@ -0,0 +1,101 @@
+//! Decorations drawn on top of a rendered identicon
+
+use crate::font::{self, GLYPH_HEIGHT};
+use image::{Rgb, RgbImage};
+
+/// Up to two initials from a seed: the local part of an email address is
+/// split on separators and the first letter of each word is taken
+pub fn initials_from(label: &str) -> String {
+    let name = label.split('@').next().unwrap_or(label);
+    let words = name
+        .split(|c: char| c.is_whitespace() || ".-_+".contains(c))
+        .filter_map(|word| word.chars().find(|c| c.is_alphanumeric()));
+
+    words.take(2).flat_map(char::to_uppercase).collect()
+}
+
+/// Center `text` on the image in black or white, whichever contrasts more
+/// with what is underneath
+pub fn draw_initials(img: &mut RgbImage, text: &str) {
+    let text: String = text.chars().take(3).collect();
+    if text.is_empty() {
+        return;
+    }
+
+    // fit within half the width and a third of the height
+    let unit_width = font::text_width(&text, 1);
+    let scale = (img.width() / 2 / unit_width)
+        .min(img.height() / 3 / GLYPH_HEIGHT)
+        .max(1);
+    let width = font::text_width(&text, scale);
+    let height = GLYPH_HEIGHT * scale;
+    let x = img.width().saturating_sub(width) / 2;
+    let y = img.height().saturating_sub(height) / 2;
+
+    let color = contrasting_color(img, x, y, width, height);
+    font::draw_text(img, &text, x, y, scale, |_| color);
+}
+
+/// Small half-transparent `text` along the bottom edge, cut short with an
+/// ellipsis of dots when it doesn't fit the width
+pub fn draw_watermark(img: &mut RgbImage, text: &str) {
+    let scale = (img.height() / 96).max(1);
+    let margin = 2 * scale;
+    let unit = font::text_width("00", scale) - font::text_width("0", scale);
+    let fits = (img.width().saturating_sub(2 * margin) / unit) as usize;
+
+    let mut text: String = text.chars().collect();
+    if text.chars().count() > fits {
+        text = text.chars().take(fits.saturating_sub(2)).collect();
+        text.push_str("..");
+    }
+    if text.is_empty() {
+        return;
+    }
+
+    let width = font::text_width(&text, scale);
+    let height = GLYPH_HEIGHT * scale;
+    let y = img.height().saturating_sub(height + margin);
+    let ink = contrasting_color(img, margin, y, width, height);
+    font::draw_text(img, &text, margin, y, scale, |under| {
+        Rgb(std::array::from_fn(|i| {
+            ((under[i] as u16 + ink[i] as u16) / 2) as u8
+        }))
+    });
+}
+
+/// Dark text on light areas, light text on dark ones, judged by the
+/// average luma of the region
+pub(crate) fn contrasting_color(
+    img: &RgbImage,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Rgb<u8> {
+    let mut total = 0u64;
+    let mut count = 0u64;
+    for py in y..(y + height).min(img.height()) {
+        for px in x..(x + width).min(img.width()) {
+            total += luma(*img.get_pixel(px, py));
+            count += 1;
+        }
+    }
+
+    ink_for(total.checked_div(count).unwrap_or(255))
+}
+
+/// Perceived brightness of `color`, 0 to 255
+pub(crate) fn luma(color: Rgb<u8>) -> u64 {
+    let [r, g, b] = color.0;
+    (299 * r as u64 + 587 * g as u64 + 114 * b as u64) / 1000
+}
+
+/// Text that reads on an area of average `luma`
+pub(crate) fn ink_for(luma: u64) -> Rgb<u8> {
+    if luma > 140 {
+        Rgb([30, 30, 30])
+    } else {
+        Rgb([255, 255, 255])
+    }
+}
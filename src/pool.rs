@@ -0,0 +1,174 @@
+//! A fixed set of render threads behind a bounded queue, so embedders can
+//! cap how much of a shared machine identicons take
+//!
+//! ```no_run
+//! use hashdenticon::{Identicon, Options, RenderPool};
+//!
+//! // two threads, and at most eight renders waiting for them
+//! let pool = RenderPool::new(2, 8);
+//! let pending = pool.submit(|| Identicon::from_seed("alice", &Options::default()));
+//! let identicon = pending.wait()?;
+//! # Ok::<(), hashdenticon::HashdenticonError>(())
+//! ```
+
+use crate::error::{HashdenticonError, Result};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::{self, JoinHandle};
+
+/// Renders [`RenderPool::default`] lets wait per thread
+pub const DEFAULT_QUEUE_PER_WORKER: usize = 4;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+static GLOBAL: OnceLock<RenderPool> = OnceLock::new();
+
+pub struct RenderPool {
+    sender: Option<SyncSender<Job>>,
+    threads: Vec<JoinHandle<()>>,
+    queue_depth: usize,
+}
+
+/// The result of a job on a [`RenderPool`], once it is done
+pub struct Pending<T>(Receiver<thread::Result<T>>);
+
+impl RenderPool {
+    /// `workers` threads, at least one, and room for `queue_depth` jobs to
+    /// wait for them; with none, a job waits until a thread is free
+    pub fn new(workers: usize, queue_depth: usize) -> RenderPool {
+        let (sender, receiver) = mpsc::sync_channel::<Job>(queue_depth);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let threads = (0..workers.max(1))
+            .map(|index| {
+                let receiver = Arc::clone(&receiver);
+                thread::Builder::new()
+                    .name(format!("hashdenticon-render-{}", index))
+                    .spawn(move || {
+                        loop {
+                            // the lock is only held while waiting, not while rendering
+                            let job = receiver.lock().expect("render queue poisoned").recv();
+                            match job {
+                                Ok(job) => job(),
+                                Err(_) => break,
+                            }
+                        }
+                    })
+                    .expect("Failed to spawn a render thread")
+            })
+            .collect();
+        RenderPool {
+            sender: Some(sender),
+            threads,
+            queue_depth,
+        }
+    }
+
+    /// The process-wide pool, made with [`RenderPool::default`] on first
+    /// use unless [`set_global`](RenderPool::set_global) came first
+    pub fn global() -> &'static RenderPool {
+        GLOBAL.get_or_init(RenderPool::default)
+    }
+
+    /// Make `pool` the [`global`](RenderPool::global) one; once it exists,
+    /// `pool` is handed back
+    pub fn set_global(pool: RenderPool) -> std::result::Result<(), RenderPool> {
+        GLOBAL.set(pool)
+    }
+
+    pub fn workers(&self) -> usize {
+        self.threads.len()
+    }
+
+    pub fn queue_depth(&self) -> usize {
+        self.queue_depth
+    }
+
+    /// Queue `job`, first waiting for room if the queue is full
+    pub fn submit<T, F>(&self, job: F) -> Pending<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (job, pending) = wrap(job);
+        self.sender()
+            .send(job)
+            .expect("render threads outlive their pool");
+        pending
+    }
+
+    /// Queue `job`, or fail with [`HashdenticonError::Busy`] if the queue
+    /// is full, for servers that would rather turn a request away
+    pub fn try_submit<T, F>(&self, job: F) -> Result<Pending<T>>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (job, pending) = wrap(job);
+        match self.sender().try_send(job) {
+            Ok(()) => Ok(pending),
+            Err(TrySendError::Full(_)) => Err(HashdenticonError::Busy),
+            Err(TrySendError::Disconnected(_)) => unreachable!("render threads outlive their pool"),
+        }
+    }
+
+    /// Run `job` on the pool and wait for it
+    pub fn run<T, F>(&self, job: F) -> T
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        self.submit(job).wait()
+    }
+
+    fn sender(&self) -> &SyncSender<Job> {
+        self.sender.as_ref().expect("only taken when dropped")
+    }
+}
+
+impl Default for RenderPool {
+    /// A thread per core
+    fn default() -> RenderPool {
+        let workers = default_workers();
+        RenderPool::new(workers, workers * DEFAULT_QUEUE_PER_WORKER)
+    }
+}
+
+/// Threads [`RenderPool::default`] starts: one per core
+pub fn default_workers() -> usize {
+    thread::available_parallelism().map_or(4, |n| n.get())
+}
+
+impl Drop for RenderPool {
+    /// Finish the jobs already queued, then stop the threads
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl<T> Pending<T> {
+    /// Wait for the job to finish; a panic in it resumes here
+    pub fn wait(self) -> T {
+        match self.0.recv().expect("render threads always answer") {
+            Ok(value) => value,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+}
+
+/// `job` as the pool runs it, catching panics so the thread survives them
+fn wrap<T, F>(job: F) -> (Job, Pending<T>)
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    let job = Box::new(move || {
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(job));
+        // nobody waiting is fine; the result is simply not wanted
+        let _ = sender.send(result);
+    });
+    (job, Pending(receiver))
+}
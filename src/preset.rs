@@ -0,0 +1,31 @@
+//! Presets: rendering options saved as TOML, so a look can be reused
+//!
+//! Any subset of [`Options`] fields may appear; the rest keep their
+//! defaults.
+//!
+//! ```toml
+//! style = "blob"
+//! grid_size = 7
+//! density = 45
+//! hue_shift = 120
+//! ```
+
+use crate::error::{HashdenticonError, Result};
+use crate::identicon::Options;
+use std::path::Path;
+
+/// Parse preset TOML into options
+pub fn parse(text: &str) -> Result<Options> {
+    toml::from_str(text).map_err(|err| HashdenticonError::InvalidPreset(err.to_string()))
+}
+
+/// Read and parse a preset file
+pub fn load(path: &Path) -> Result<Options> {
+    let text = std::fs::read_to_string(path).map_err(HashdenticonError::io(path))?;
+    parse(&text)
+}
+
+/// Serialize options as preset TOML
+pub fn to_toml(options: &Options) -> String {
+    toml::to_string(options).expect("options always serialize to toml")
+}
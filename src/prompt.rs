@@ -0,0 +1,79 @@
+//! Asking for the seed on the terminal when none is given: the seed,
+//! optionally without echo, then an inline preview before where to save
+
+use crate::exit;
+use anyhow::{Context, Result};
+use hashdenticon::{Identicon, Input, MIN_SIZE, Options};
+use image::imageops::{self, FilterType};
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+/// Columns the preview is drawn across, each row covering two pixel rows
+const PREVIEW_SIDE: u32 = 32;
+
+/// Read the seed from the terminal, without echoing it when `hidden`
+pub fn seed(hidden: bool) -> Result<String> {
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        return Err(exit::Invalid(
+            "No seed given, and not on a terminal to ask for one".to_string(),
+        )
+        .into());
+    }
+    let seed = if hidden {
+        rpassword::prompt_password("Seed (hidden): ").context("Failed to read seed")?
+    } else {
+        line("Seed: ")?
+    };
+    if seed.is_empty() {
+        return Err(exit::Invalid("No seed entered".to_string()).into());
+    }
+    Ok(seed)
+}
+
+/// Draw the identicon on stderr, two image rows per line, using the upper
+/// half block with the top pixel as foreground and the bottom as background
+pub fn preview(input: &Input, options: &Options) -> Result<()> {
+    let mut options = options.clone();
+    options.size = PREVIEW_SIDE.max(MIN_SIZE);
+    options.height = None;
+    let identicon = Identicon::new(&input.digest, &options)?;
+    let side = PREVIEW_SIDE;
+    let img = imageops::resize(identicon.as_rgb(), side, side, FilterType::Nearest);
+
+    let mut text = String::new();
+    for row in 0..side.div_ceil(2) {
+        for x in 0..side {
+            let top = img.get_pixel(x, row * 2);
+            let bottom = img.get_pixel(x, (row * 2 + 1).min(side - 1));
+            text += &format!(
+                "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m▀",
+                top[0], top[1], top[2], bottom[0], bottom[1], bottom[2]
+            );
+        }
+        text += "\x1b[0m\n";
+    }
+    eprint!("{}", text);
+    Ok(())
+}
+
+/// Where to save, `default` if nothing is typed
+pub fn save_path(default: &Path) -> Result<PathBuf> {
+    let answer = line(&format!("Save to [{}]: ", default.display()))?;
+    if answer.is_empty() {
+        return Ok(default.to_path_buf());
+    }
+    Ok(PathBuf::from(answer))
+}
+
+fn line(prompt: &str) -> Result<String> {
+    eprint!("{}", prompt);
+    std::io::stderr()
+        .flush()
+        .context("Failed to write prompt")?;
+    let mut answer = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .context("Failed to read from the terminal")?;
+    Ok(answer.trim_end_matches(['\r', '\n']).to_string())
+}
@@ -0,0 +1,235 @@
+//! QR codes with the identicon at their center, so one image carries both
+//! the data for a scanner and a look people recognize
+//!
+//! The code uses the highest error correction level, which recovers from
+//! 30% of the symbol being unreadable. The identicon sits in a light square
+//! of its own covering under a tenth of the symbol, a third of that budget,
+//! so scanners read straight past it with room to spare for glare and
+//! smudges. The square keeps off the finder, timing, and alignment patterns
+//! scanners locate the code by; from version 7 on an alignment pattern can
+//! hold the very center, and the square moves to the nearest gap beside it.
+//!
+//! ```no_run
+//! use hashdenticon::qr::Qr;
+//! use hashdenticon::{Identicon, Options};
+//!
+//! let qr = Qr::new(b"https://example.com/u/alice", 420)?;
+//! let options = Options {
+//!     size: qr.identicon_side(),
+//!     ..Options::default()
+//! };
+//! let identicon = Identicon::from_seed("alice", &options)?;
+//! let image = qr.render(identicon.as_rgb());
+//! # Ok::<(), hashdenticon::HashdenticonError>(())
+//! ```
+
+use crate::error::{HashdenticonError, Result};
+use crate::identicon::MIN_SIZE;
+use image::{Rgb, RgbImage, imageops};
+use qrcode::{Color, EcLevel, QrCode};
+use std::cmp::Reverse;
+
+/// Light modules around the code that scanners need to find its edges
+const QUIET_ZONE: u32 = 4;
+/// The cleared square covers under this percentage of the symbol, a third
+/// of the 30% that level H error correction recovers
+const CLEARED_SHARE: u32 = 10;
+
+const DARK: Rgb<u8> = Rgb([0, 0, 0]);
+const LIGHT: Rgb<u8> = Rgb([255, 255, 255]);
+
+/// A QR code laid out on a square image, with a cleared square at its center
+pub struct Qr {
+    dark: Vec<bool>,
+    /// Modules along each side, without the quiet zone
+    width: u32,
+    /// Pixels along each side of a module
+    module: u32,
+    side: u32,
+    /// Module at the top left corner of the cleared square
+    corner: (u32, u32),
+    /// Modules along each side of the cleared square
+    cleared: u32,
+}
+
+impl Qr {
+    /// Encode `data` as a QR code filling a `side` x `side` image
+    pub fn new(data: &[u8], side: u32) -> Result<Qr> {
+        let code = QrCode::with_error_correction_level(data, EcLevel::H).map_err(|err| {
+            HashdenticonError::InvalidInput(format!("Can't encode QR data: {}", err))
+        })?;
+        let width = code.width() as u32;
+        let module = side / (width + 2 * QUIET_ZONE);
+        let (corner, cleared) = clearing(&code).ok_or_else(|| {
+            HashdenticonError::Unsupported(format!(
+                "A {} module QR code has no room to clear for an identicon",
+                width
+            ))
+        })?;
+        let qr = Qr {
+            dark: code
+                .to_colors()
+                .into_iter()
+                .map(|color| color == Color::Dark)
+                .collect(),
+            width,
+            module,
+            side,
+            corner,
+            cleared,
+        };
+        let needed = (width + 2 * QUIET_ZONE) * MIN_SIZE.div_ceil(cleared - 2);
+        if qr.identicon_side() < MIN_SIZE {
+            return Err(HashdenticonError::Unsupported(format!(
+                "A {} module QR code needs at least {} pixels to fit an identicon at its center",
+                width, needed
+            )));
+        }
+        Ok(qr)
+    }
+
+    /// Side of the identicon [`render`](Self::render) places at the center,
+    /// a module inside the cleared square
+    pub fn identicon_side(&self) -> u32 {
+        (self.cleared - 2) * self.module
+    }
+
+    /// The code with `identicon` centered in it
+    pub fn render(&self, identicon: &RgbImage) -> RgbImage {
+        let mut img = RgbImage::from_pixel(self.side, self.side, LIGHT);
+        let origin = (self.side - self.width * self.module) / 2;
+        let (left, top) = self.corner;
+        let columns = left..left + self.cleared;
+        let rows = top..top + self.cleared;
+        for y in 0..self.width {
+            for x in 0..self.width {
+                let is_cleared = columns.contains(&x) && rows.contains(&y);
+                if is_cleared || !self.dark[(y * self.width + x) as usize] {
+                    continue;
+                }
+                for py in 0..self.module {
+                    for px in 0..self.module {
+                        let (px, py) =
+                            (origin + x * self.module + px, origin + y * self.module + py);
+                        img.put_pixel(px, py, DARK);
+                    }
+                }
+            }
+        }
+
+        // a module in from the cleared square; overlay clips an identicon
+        // made larger by --tileable or --height
+        let inner = |start: u32| i64::from(origin + (start + 1) * self.module);
+        let offset = |len: u32| (i64::from(self.identicon_side()) - i64::from(len)) / 2;
+        let (w, h) = identicon.dimensions();
+        imageops::overlay(
+            &mut img,
+            identicon,
+            inner(left) + offset(w),
+            inner(top) + offset(h),
+        );
+        img
+    }
+}
+
+/// The square to clear, as its corner module and side: covering under
+/// CLEARED_SHARE of `code` and none of its function patterns, and of those,
+/// the nearest the center, then the largest, then the most centered
+fn clearing(code: &QrCode) -> Option<((u32, u32), u32)> {
+    let width = code.width();
+    // functional modules above and to the left of each corner, so any
+    // square is checked in constant time
+    let stride = width + 1;
+    let mut functional = vec![0u32; stride * stride];
+    for y in 0..width {
+        for x in 0..width {
+            functional[(y + 1) * stride + x + 1] = u32::from(code.is_functional(x, y))
+                + functional[y * stride + x + 1]
+                + functional[(y + 1) * stride + x]
+                - functional[y * stride + x];
+        }
+    }
+    let is_free = |x: usize, y: usize, side: usize| {
+        let (x1, y1) = (x + side, y + side);
+        functional[y1 * stride + x1] + functional[y * stride + x]
+            == functional[y * stride + x1] + functional[y1 * stride + x]
+    };
+
+    // distances along an axis in half modules, from the code's center to
+    // the nearest edge of the square and to its middle
+    let gap = |start: usize, side: usize| {
+        (2 * start)
+            .saturating_sub(width)
+            .max(width.saturating_sub(2 * (start + side)))
+    };
+    let off = |start: usize, side: usize| (2 * start + side).abs_diff(width);
+
+    let budget = width * width * CLEARED_SHARE as usize;
+    let largest = (1..width)
+        .take_while(|side| side * side * 100 < budget)
+        .last()?;
+    (3..=largest)
+        .flat_map(|side| {
+            let corners = 0..=width - side;
+            corners
+                .clone()
+                .flat_map(move |y| corners.clone().map(move |x| (x, y, side)))
+        })
+        .filter(|&(x, y, side)| is_free(x, y, side))
+        .min_by_key(|&(x, y, side)| {
+            (
+                gap(x, side).pow(2) + gap(y, side).pow(2),
+                Reverse(side),
+                off(x, side).pow(2) + off(y, side).pow(2),
+            )
+        })
+        .map(|(x, y, side)| ((x as u32, y as u32), side as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qrcode::Version;
+
+    #[test]
+    fn the_cleared_square_keeps_to_its_share_off_function_patterns() {
+        for version in 1..=40 {
+            let code =
+                QrCode::with_version(b"alice", Version::Normal(version), EcLevel::H).unwrap();
+            let width = code.width();
+            let ((left, top), side) = clearing(&code).unwrap();
+            let (left, top, side) = (left as usize, top as usize, side as usize);
+
+            assert!(side >= 3, "version {}", version);
+            assert!(
+                side * side * 100 < width * width * CLEARED_SHARE as usize,
+                "version {} clears {} of {} modules",
+                version,
+                side * side,
+                width * width
+            );
+            assert!(left + side <= width && top + side <= width);
+            for y in top..top + side {
+                for x in left..left + side {
+                    assert!(
+                        !code.is_functional(x, y),
+                        "version {} clears the function module at {},{}",
+                        version,
+                        x,
+                        y
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_square_is_centered_when_the_center_is_free() {
+        // version 14 has alignment patterns either side of the center
+        let code = QrCode::with_version(b"alice", Version::Normal(14), EcLevel::H).unwrap();
+        let ((left, top), side) = clearing(&code).unwrap();
+        let width = code.width() as u32;
+        assert_eq!(2 * left + side, width);
+        assert_eq!(2 * top + side, width);
+    }
+}
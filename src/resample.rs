@@ -0,0 +1,333 @@
+//! Scaling filters, for supersampling and for resizing image parts
+//!
+//! Downscales by a whole factor, which is what `--supersample` does, run on
+//! integers with every filter, so the same render comes out byte for byte on
+//! any platform. Other sizes, which only parts packs need, go through
+//! `image`'s floating-point resize.
+
+use image::RgbImage;
+use image::imageops::{self, FilterType};
+
+/// Filter used whenever an image is scaled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Filter {
+    /// Hard pixel edges, for pixel-art styles
+    Nearest,
+    /// Cheap linear blend
+    Bilinear,
+    /// Sharp, smooth scaling for curved and shaded styles
+    #[default]
+    Lanczos3,
+    /// Plain average of each block of pixels, the fastest for --supersample;
+    /// bilinear when the sizes are not a whole multiple
+    Box,
+}
+
+impl Filter {
+    pub fn filter_type(self) -> FilterType {
+        match self {
+            Filter::Nearest => FilterType::Nearest,
+            Filter::Bilinear => FilterType::Triangle,
+            Filter::Lanczos3 => FilterType::Lanczos3,
+            Filter::Box => FilterType::Triangle,
+        }
+    }
+}
+
+/// Scale `img` to `width` x `height` with `filter`
+pub fn resize(img: &RgbImage, width: u32, height: u32, filter: Filter) -> RgbImage {
+    match block_factor(img, width, height) {
+        Some(factor) if filter == Filter::Box => box_downsample(img, factor),
+        Some(factor) => convolve(img, factor, &Taps::new(filter, factor)),
+        None => imageops::resize(img, width, height, filter.filter_type()),
+    }
+}
+
+/// The whole factor `img` is larger than `width` x `height` by, if it is
+/// the same on both axes
+pub(crate) fn block_factor(img: &RgbImage, width: u32, height: u32) -> Option<u32> {
+    if width == 0 || height == 0 || !img.width().is_multiple_of(width) {
+        return None;
+    }
+    let factor = img.width() / width;
+    (img.height() == height * factor).then_some(factor)
+}
+
+/// Average every `factor` x `factor` block of `img` into one pixel
+///
+/// Source rows are summed into a row of per-channel totals with straight
+/// adds over contiguous bytes, which the compiler turns into SIMD, and
+/// only then folded horizontally; all in integers, rounding to nearest.
+fn box_downsample(img: &RgbImage, factor: u32) -> RgbImage {
+    let (width, height) = (img.width() / factor, img.height() / factor);
+    let factor = factor as usize;
+    let stride = img.width() as usize * 3;
+    let area = (factor * factor) as u32;
+
+    let mut out = Vec::with_capacity(width as usize * height as usize * 3);
+    let mut totals = vec![0u32; stride];
+    for block in img.as_raw().chunks_exact(stride * factor) {
+        totals.fill(0);
+        for row in block.chunks_exact(stride) {
+            for (total, &value) in totals.iter_mut().zip(row) {
+                *total += u32::from(value);
+            }
+        }
+        for pixels in totals.chunks_exact(factor * 3) {
+            let mut sum = [0u32; 3];
+            for pixel in pixels.chunks_exact(3) {
+                sum[0] += pixel[0];
+                sum[1] += pixel[1];
+                sum[2] += pixel[2];
+            }
+            out.extend(sum.map(|sum| ((sum + area / 2) / area) as u8));
+        }
+    }
+    RgbImage::from_raw(width, height, out).expect("one pixel per block")
+}
+
+/// Fixed-point weights of the source pixels around each output pixel of a
+/// whole-factor downscale, which are the same for every output pixel
+struct Taps {
+    /// Offset of the first weighted source pixel from the start of the block
+    first: i64,
+    factor: i64,
+    weights: Vec<i64>,
+}
+
+impl Taps {
+    /// Weights of `filter`, widened by `factor` as `image` does when it
+    /// shrinks; never [`Filter::Box`], which has its own fast path
+    fn new(filter: Filter, factor: u32) -> Taps {
+        let factor = i64::from(factor);
+        // twice the distance from a source pixel's center to the block's,
+        // in source pixels, is `2 * offset + 1 - factor`; over `2 * factor`
+        // it is the distance scaled to the filter's support
+        let (reach, weight): (i64, fn(i64, i64) -> i64) = match filter {
+            Filter::Nearest => {
+                return Taps {
+                    first: factor / 2,
+                    factor,
+                    weights: vec![1],
+                };
+            }
+            Filter::Bilinear | Filter::Box => (1, |twice, factor| 2 * factor - twice.abs()),
+            Filter::Lanczos3 => (3, lanczos3),
+        };
+        let offsets = (-reach * factor..(reach + 1) * factor)
+            .filter(|offset| (2 * offset + 1 - factor).abs() < 2 * reach * factor)
+            .collect::<Vec<_>>();
+        Taps {
+            first: offsets[0],
+            factor,
+            weights: offsets
+                .iter()
+                .map(|offset| weight(2 * offset + 1 - factor, factor))
+                .collect(),
+        }
+    }
+
+    /// Weighted sum of `values` around output `index`, and the total of
+    /// the weights that fell inside it
+    fn apply(&self, index: usize, values: impl Fn(usize) -> i64, len: usize) -> (i64, i64) {
+        let start = index as i64 * self.factor + self.first;
+        let (mut sum, mut total) = (0, 0);
+        for (source, &weight) in (start..).zip(&self.weights) {
+            if (0..len as i64).contains(&source) {
+                sum += weight * values(source as usize);
+                total += weight;
+            }
+        }
+        (sum, total)
+    }
+}
+
+/// Downscale `img` by `factor` with `taps`, rows first, then columns,
+/// keeping 8 fractional bits in between and rounding to nearest at the end
+fn convolve(img: &RgbImage, factor: u32, taps: &Taps) -> RgbImage {
+    let (width, height) = (img.width() / factor, img.height() / factor);
+    let (source_width, source_height) = (img.width() as usize, img.height() as usize);
+    let raw = img.as_raw();
+
+    let mut rows = vec![0i64; width as usize * source_height * 3];
+    for y in 0..source_height {
+        let row = &raw[y * source_width * 3..][..source_width * 3];
+        for x in 0..width as usize {
+            for channel in 0..3 {
+                let value = |source: usize| i64::from(row[source * 3 + channel]);
+                let (sum, total) = taps.apply(x, value, source_width);
+                rows[(y * width as usize + x) * 3 + channel] = divide_rounded(sum << 8, total);
+            }
+        }
+    }
+
+    let stride = width as usize * 3;
+    let mut out = Vec::with_capacity(stride * height as usize);
+    for y in 0..height as usize {
+        for i in 0..stride {
+            let value = |source: usize| rows[source * stride + i];
+            let (sum, total) = taps.apply(y, value, source_height);
+            out.push(divide_rounded(sum, total << 8).clamp(0, 255) as u8);
+        }
+    }
+    RgbImage::from_raw(width, height, out).expect("one pixel per block")
+}
+
+/// `value / divisor` rounded to nearest, halves up; `divisor` is positive
+fn divide_rounded(value: i64, divisor: i64) -> i64 {
+    (2 * value + divisor).div_euclid(2 * divisor)
+}
+
+/// The Lanczos kernel with 3 lobes, `sinc(x) * sinc(x / 3)`, at
+/// `x = twice / (2 * factor)`, with 16 fractional bits
+fn lanczos3(twice: i64, factor: i64) -> i64 {
+    // pi squared with 44 fractional bits
+    const PI_SQUARED: i128 = 173_627_916_808_751;
+    if twice == 0 {
+        return 1 << 16;
+    }
+    // sin(pi x) sin(pi x / 3) / (pi^2 x^2 / 3), x^2 = twice^2 / (4 factor^2)
+    let sines = i128::from(sin_pi(twice, 2 * factor)) * i128::from(sin_pi(twice, 6 * factor));
+    let factor = i128::from(factor);
+    let twice = i128::from(twice);
+    (sines * 12 * factor * factor / (twice * twice * PI_SQUARED)) as i64
+}
+
+/// `sin(pi * numerator / denominator)` with 30 fractional bits, from a
+/// Taylor series in fixed point
+fn sin_pi(numerator: i64, denominator: i64) -> i64 {
+    // pi with 61 fractional bits
+    const PI: i128 = 7_244_019_458_077_122_560;
+    let mut turn = numerator.rem_euclid(2 * denominator);
+    let sign = if turn >= denominator {
+        turn -= denominator;
+        -1
+    } else {
+        1
+    };
+    // sin(pi - a) = sin(a), so the angle stays within a quarter turn
+    if 2 * turn > denominator {
+        turn = denominator - turn;
+    }
+    let angle = PI * i128::from(turn) / i128::from(denominator);
+    let squared = (angle * angle) >> 61;
+    let (mut term, mut sum) = (angle, angle);
+    for k in 1..=12 {
+        term = -((term * squared) >> 61) / (2 * k * (2 * k + 1));
+        sum += term;
+    }
+    sign * (sum >> 31) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    fn sinc(x: f64) -> f64 {
+        if x == 0.0 {
+            1.0
+        } else {
+            (PI * x).sin() / (PI * x)
+        }
+    }
+
+    #[test]
+    fn sines_match_the_float_reference() {
+        for denominator in [2, 6, 12, 48] {
+            for numerator in -4 * denominator..=4 * denominator {
+                let expected = (PI * numerator as f64 / denominator as f64).sin();
+                let actual = sin_pi(numerator, denominator) as f64 / (1 << 30) as f64;
+                assert!(
+                    (actual - expected).abs() < 1e-8,
+                    "sin(pi * {}/{}) is {}, not {}",
+                    numerator,
+                    denominator,
+                    actual,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn lanczos_taps_match_the_float_reference() {
+        for factor in 1..=8 {
+            let taps = Taps::new(Filter::Lanczos3, factor as u32);
+            let offsets = taps.first..taps.first + taps.weights.len() as i64;
+            for (offset, &weight) in offsets.zip(&taps.weights) {
+                let x = (2 * offset + 1 - factor) as f64 / (2 * factor) as f64;
+                let expected = sinc(x) * sinc(x / 3.0);
+                let actual = weight as f64 / (1 << 16) as f64;
+                assert!(
+                    (actual - expected).abs() < 1e-4,
+                    "factor {} at {}: {} instead of {}",
+                    factor,
+                    x,
+                    actual,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn taps_sum_to_unity() {
+        for factor in 1..=8 {
+            for (filter, unit) in [
+                (Filter::Lanczos3, factor << 16),
+                (Filter::Bilinear, 2 * factor * factor),
+                (Filter::Nearest, 1),
+            ] {
+                let taps = Taps::new(filter, factor as u32);
+                let sum: i64 = taps.weights.iter().sum();
+                // sampled Lanczos sums a hair under 1, as it does in floats
+                let share = sum as f64 / unit as f64;
+                assert!(
+                    (share - 1.0).abs() < 0.005,
+                    "{:?} by {} sums to {}",
+                    filter,
+                    factor,
+                    share
+                );
+                // and dividing by the sum makes any constant come back whole
+                let (sum, total) = taps.apply(2, |_| 200, 8 * factor as usize);
+                assert_eq!(divide_rounded(sum, total), 200);
+            }
+        }
+    }
+
+    #[test]
+    fn uniform_images_keep_their_color() {
+        let color = image::Rgb([12, 128, 251]);
+        let img = RgbImage::from_pixel(48, 24, color);
+        for factor in [1, 2, 3, 4, 6, 8] {
+            let (width, height) = (48 / factor, 24 / factor);
+            for &filter in [
+                Filter::Box,
+                Filter::Nearest,
+                Filter::Bilinear,
+                Filter::Lanczos3,
+            ]
+            .iter()
+            {
+                let out = resize(&img, width, height, filter);
+                assert_eq!(out.dimensions(), (width, height));
+                assert!(
+                    out.pixels().all(|&pixel| pixel == color),
+                    "{:?} by {}",
+                    filter,
+                    factor
+                );
+            }
+            assert!(
+                box_downsample(&img, factor)
+                    .pixels()
+                    .all(|&pixel| pixel == color)
+            );
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! Small deterministic PRNG for styles that need more than the raw digest
+
+/// SplitMix64, seeded from digest bytes
+///
+/// Not cryptographic; it only stretches the hash into a reproducible stream
+/// so that derived art never changes between releases or platforms.
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    /// Fold all of `bytes` into the initial state
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut rng = Self { state: 0 };
+        for chunk in bytes.chunks(8) {
+            let mut word = [0u8; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            rng.state ^= u64::from_le_bytes(word);
+            rng.next_u64();
+        }
+        rng
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `0..bound`; `bound` must be non-zero
+    pub fn below(&mut self, bound: u64) -> u64 {
+        // slight modulo bias is irrelevant for such small bounds
+        self.next_u64() % bound
+    }
+}
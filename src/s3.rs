@@ -0,0 +1,61 @@
+//! Uploads to S3 and compatible object stores, and checks for objects already
+//! there, configured like the AWS CLI: `AWS_ACCESS_KEY_ID`,
+//! `AWS_SECRET_ACCESS_KEY`, optional `AWS_SESSION_TOKEN`, `AWS_REGION`, and
+//! `AWS_ENDPOINT_URL` for stores other than AWS
+
+use anyhow::{Context, Result};
+use rusty_s3::actions::{HeadObject, PutObject, S3Action};
+use rusty_s3::{Bucket, Credentials, UrlStyle};
+use std::time::Duration;
+
+const DEFAULT_REGION: &str = "us-east-1";
+// the signed request is sent right away; this only has to outlast a slow upload
+const SIGNATURE_LIFETIME: Duration = Duration::from_secs(15 * 60);
+
+/// Store `bytes` as object `key` in `bucket`
+pub fn put(bucket: &str, key: &str, bytes: &[u8], content_type: &str) -> Result<()> {
+    let (bucket, credentials) = connect(bucket)?;
+    let url = PutObject::new(&bucket, Some(&credentials), key).sign(SIGNATURE_LIFETIME);
+    ureq::put(url.as_str())
+        .header("Content-Type", content_type)
+        .send(bytes)?;
+    Ok(())
+}
+
+/// Whether `bucket` holds an object named `key`
+pub fn exists(bucket: &str, key: &str) -> Result<bool> {
+    let (bucket, credentials) = connect(bucket)?;
+    let url = HeadObject::new(&bucket, Some(&credentials), key).sign(SIGNATURE_LIFETIME);
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let response = agent.head(url.as_str()).call()?;
+    match response.status().as_u16() {
+        200 => Ok(true),
+        404 => Ok(false),
+        // without s3:ListBucket, stores answer 403 for missing objects too
+        status => anyhow::bail!("Object store answered with status {}", status),
+    }
+}
+
+/// `bucket` at the configured endpoint, and the credentials to sign with
+fn connect(bucket: &str) -> Result<(Bucket, Credentials)> {
+    let region = std::env::var("AWS_REGION")
+        .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+        .unwrap_or_else(|_| DEFAULT_REGION.to_string());
+    // custom endpoints (MinIO, R2, ...) rarely have per-bucket host names
+    let (endpoint, style) = match std::env::var("AWS_ENDPOINT_URL") {
+        Ok(endpoint) => (endpoint, UrlStyle::Path),
+        Err(_) => (
+            format!("https://s3.{}.amazonaws.com", region),
+            UrlStyle::VirtualHost,
+        ),
+    };
+    let endpoint = endpoint.parse().context("Invalid AWS_ENDPOINT_URL")?;
+    let bucket =
+        Bucket::new(endpoint, style, bucket.to_string(), region).context("Invalid bucket")?;
+    let credentials = Credentials::from_env()
+        .context("AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY must be set")?;
+    Ok((bucket, credentials))
+}
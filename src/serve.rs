@@ -0,0 +1,892 @@
+//! `hashdenticon serve`: identicons over HTTP
+//!
+//! - `GET /identicon/<seed>[.<ext>]` answers with one identicon, as png
+//!   unless the extension names another format; `?size=` overrides --size.
+//!   With --gravatar-fallback, raster requests for an email seed get its
+//!   Gravatar instead, in the format asked for, if it has one and --store
+//!   doesn't already hold its identicon; what Gravatar answers is kept for
+//!   an hour
+//! - `POST /batch` takes a json array of seeds, or an object of `seeds`
+//!   with shared `options` and `format`, and answers with a zip of the
+//!   images, or multipart/mixed when the request accepts it
+//! - `GET /healthz` answers as long as the server runs, and `GET /readyz`
+//!   once it can take requests; with --canary, only if rendering works
+//! - `GET /openapi.json` [describes](openapi) all of these
+//!
+//! Whatever --allow lets clients vary, a request that would draw an image
+//! side over --max-size, supersampling and tiling included, or evolve an
+//! automaton past [`MAX_STEPS`], answers 400.
+//!
+//! Edits to the --preset file apply to requests that come after them.
+//!
+//! On SIGTERM or SIGINT the server drains: it stops accepting connections,
+//! /readyz starts failing, requests already received are answered, and then
+//! it exits. A second signal exits at once.
+//!
+//! With --grpc, the same settings also answer the [gRPC service](grpc).
+//!
+//! With --signing-key, both only answer [signed URLs](signing). Browsers on
+//! a --cors-origin may call them cross-origin, preflight included. With
+//! --allow, they may only vary the options [it lets through](allow).
+//!
+//! With --out-dir, each identicon rendered is also written there, or
+//! uploaded to the s3:// prefix it names, under the hash of its bytes so
+//! renders with different options never overwrite each other.
+
+pub mod allow;
+mod gravatar;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod openapi;
+mod reload;
+pub mod signing;
+
+pub use reload::Settings;
+
+use crate::exit::{self, Kind};
+use crate::sink;
+#[cfg(feature = "sqlite")]
+use crate::store;
+use crate::zip::Zip;
+use crate::{RenderArgs, default_file_name};
+use anyhow::{Context, Result, anyhow};
+use clap::ValueEnum;
+use hashdenticon::output::{self, Encoding, Format};
+use hashdenticon::{HashdenticonError, Identicon, MAX_STEPS, Options, RenderPool, input};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use socket2::SockRef;
+use std::collections::HashSet;
+use std::io::Read;
+use std::net::{Shutdown, TcpListener};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use thiserror::Error;
+use tiny_http::{Header, Request, Response, Server};
+
+/// Largest request body accepted, in bytes; longer ones are refused with 413
+pub const MAX_BODY: u64 = 1 << 20;
+/// Seed of the identicon /readyz renders with --canary
+const CANARY: &str = "hashdenticon readiness canary";
+const TEXT: &str = "text/plain; charset=utf-8";
+
+pub struct Config {
+    /// Most seeds one POST /batch may ask for
+    pub max_batch: u16,
+    /// Longest image side a request may have drawn, supersampling and
+    /// tiling included, whatever --allow lets clients vary
+    pub max_size: u32,
+    /// Refuse requests without a valid signature under this key
+    pub signing_key: Option<[u8; 32]>,
+    /// Origins allowed to call cross-origin, `*` for any
+    pub cors_origins: Vec<String>,
+    /// Have /readyz render and encode an identicon before answering
+    pub canary: bool,
+    /// Answer email seeds with their Gravatar when they have one
+    pub gravatar_fallback: bool,
+    /// Options clients may vary, and within what; all of them when empty
+    pub allow: Vec<allow::Rule>,
+    /// Also write each identicon rendered into this directory or s3:// prefix
+    pub out_dir: Option<PathBuf>,
+    /// Also answer gRPC on this address
+    #[cfg(feature = "grpc")]
+    pub grpc: Option<String>,
+    #[cfg(feature = "sqlite")]
+    pub store: Option<store::Store>,
+    pub render: Settings,
+}
+
+/// A request that fails with a status of its own rather than one derived
+/// from the kind of error
+#[derive(Debug, Error)]
+#[error("{message}")]
+struct HttpError {
+    status: u16,
+    message: String,
+}
+
+impl HttpError {
+    fn new(status: u16, message: impl Into<String>) -> HttpError {
+        HttpError {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BatchRequest {
+    Seeds(Vec<String>),
+    Full {
+        seeds: Vec<String>,
+        /// Option fields to change from the server's own
+        #[serde(default)]
+        options: Map<String, Value>,
+        format: Option<String>,
+    },
+}
+
+/// A request as the endpoints see it, whichever way it arrived
+pub struct Incoming {
+    pub method: String,
+    /// Path and query, as sent
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Incoming {
+    fn header(&self, field: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(field))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A response, to send back the way its request came
+pub struct Reply {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Reply {
+    fn new(status: u16, body: Vec<u8>, content_type: &str) -> Reply {
+        Reply {
+            status,
+            headers: vec![("Content-Type", content_type.to_string())],
+            body,
+        }
+    }
+
+    fn with_header(mut self, field: &'static str, value: &str) -> Reply {
+        self.headers.push((field, value.to_string()));
+        self
+    }
+}
+
+/// Set once a shutdown signal arrives
+static DRAINING: AtomicBool = AtomicBool::new(false);
+
+/// How long a worker waits for a request before checking for a shutdown,
+/// and how long a draining one waits before taking the queue for empty
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Serve requests on `listen` until a shutdown signal, with a worker per core
+pub fn run(listen: &str, config: Config) -> Result<()> {
+    let listener =
+        TcpListener::bind(listen).with_context(|| format!("Failed to listen on {}", listen))?;
+    // kept to close the socket under tiny_http's accept thread on shutdown
+    let accepting = listener
+        .try_clone()
+        .with_context(|| format!("Failed to listen on {}", listen))?;
+    let server = Server::from_listener(listener, None)
+        .map_err(|err| anyhow!("Failed to listen on {}: {}", listen, err))?;
+    eprintln!("Serving identicons on http://{}", server.server_addr());
+    let config = Arc::new(config);
+    #[cfg(feature = "grpc")]
+    let grpc = match &config.grpc {
+        Some(listen) => Some(grpc::spawn(listen, Arc::clone(&config))?),
+        None => None,
+    };
+
+    let workers = std::thread::available_parallelism().map_or(4, |n| n.get());
+    ctrlc::set_handler(move || {
+        if DRAINING.swap(true, Ordering::SeqCst) {
+            std::process::exit(130);
+        }
+        eprintln!("Shutting down once requests in progress are answered");
+        // new connections are refused, and accept fails so its thread ends
+        if let Err(err) = SockRef::from(&accepting).shutdown(Shutdown::Both) {
+            eprintln!("Failed to stop accepting connections: {}", err);
+        }
+    })
+    .context("Failed to install a shutdown handler")?;
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| config.render.watch());
+        for _ in 0..workers {
+            scope.spawn(|| serve_queue(&server, &config));
+        }
+    });
+    #[cfg(feature = "grpc")]
+    if let Some(grpc) = grpc {
+        grpc.join().expect("gRPC server panicked")?;
+    }
+    // the store commits each identicon as it goes, so there is nothing left to flush
+    eprintln!("Server stopped");
+    Ok(())
+}
+
+/// Answer requests as they come in, then once draining, until none are left
+fn serve_queue(server: &Server, config: &Config) {
+    loop {
+        match server.recv_timeout(POLL_INTERVAL) {
+            Ok(Some(request)) => handle(request, config),
+            Ok(None) if DRAINING.load(Ordering::SeqCst) => return,
+            Ok(None) => {}
+            // the accept thread ended, on shutdown or a failed listener;
+            // what it queued before then is still answered
+            Err(err) => {
+                if !DRAINING.swap(true, Ordering::SeqCst) {
+                    eprintln!("Stopped accepting connections: {}", err);
+                }
+            }
+        }
+    }
+}
+
+/// Answer one request; the logic every way of serving shares
+pub fn answer(request: &Incoming, config: &Config) -> Reply {
+    let mut reply = respond(request, config).unwrap_or_else(|err| error_reply(&err));
+    reply.headers.extend(cors_headers(request, config));
+    reply
+}
+
+/// A request body, refused rather than cut short when it is over MAX_BODY
+pub fn read_body(reader: impl Read) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    reader
+        .take(MAX_BODY + 1)
+        .read_to_end(&mut body)
+        .context("Failed to read request body")?;
+    if body.len() as u64 > MAX_BODY {
+        return Err(HttpError::new(413, format!("Request body is over {} bytes", MAX_BODY)).into());
+    }
+    Ok(body)
+}
+
+fn handle(mut request: Request, config: &Config) {
+    let reply = match read_body(request.as_reader()) {
+        Ok(body) => {
+            let incoming = Incoming {
+                method: request.method().to_string(),
+                url: request.url().to_string(),
+                headers: request
+                    .headers()
+                    .iter()
+                    .map(|header| (header.field.to_string(), header.value.to_string()))
+                    .collect(),
+                body,
+            };
+            answer(&incoming, config)
+        }
+        Err(err) => error_reply(&err),
+    };
+    eprintln!("{} {} {}", request.method(), request.url(), reply.status);
+
+    let mut response = Response::from_data(reply.body).with_status_code(reply.status);
+    for (field, value) in reply.headers {
+        if let Ok(header) = Header::from_bytes(field, value) {
+            response.add_header(header);
+        }
+    }
+    if let Err(err) = request.respond(response) {
+        eprintln!("Failed to respond: {}", err);
+    }
+}
+
+fn respond(request: &Incoming, config: &Config) -> Result<Reply> {
+    let (path, query) = request.url.split_once('?').unwrap_or((&request.url, ""));
+    // one request keeps to one version of the settings, even mid-reload
+    let args = &config.render.current();
+
+    let Some(method) = method_of(path) else {
+        return Err(HttpError::new(404, format!("No such endpoint: {}", path)).into());
+    };
+    if request.method == "OPTIONS" {
+        let allow = format!("{}, OPTIONS", method);
+        return Ok(Reply::new(204, Vec::new(), TEXT).with_header("Allow", &allow));
+    }
+    if request.method != method {
+        return Err(HttpError::new(405, format!("Use {} for {}", method, path)).into());
+    }
+
+    if path == "/healthz" {
+        return Ok(Reply::new(200, b"ok\n".to_vec(), TEXT));
+    }
+    if path == "/readyz" {
+        if DRAINING.load(Ordering::SeqCst) {
+            return Err(HttpError::new(503, "Shutting down").into());
+        }
+        if config.canary {
+            let options = args.options(CANARY);
+            render(CANARY, &options, Format::Png, args, config)
+                .map_err(|err| HttpError::new(503, format!("Not ready: {:#}", err)))?;
+        }
+        return Ok(Reply::new(200, b"ready\n".to_vec(), TEXT));
+    }
+    if path == "/openapi.json" {
+        let body = serde_json::to_vec_pretty(&openapi::document(config))?;
+        return Ok(Reply::new(200, body, "application/json"));
+    }
+
+    if let Some(name) = path.strip_prefix("/identicon/") {
+        check_signature(config, request, path, query, None)?;
+        let (seed, format) = split_format(&percent_decode(name));
+        let mut options = args.options(&seed);
+        if let Some(size) = query_param(query, "size") {
+            options.size = size
+                .parse()
+                .map_err(|_| exit::Invalid(format!("Invalid size: {}", size)))?;
+            allow::check(&config.allow, "size", &options.size.into())
+                .map_err(|reason| HttpError::new(403, reason))?;
+        }
+        let is_raster = matches!(format, Format::Png | Format::Webp | Format::Avif);
+        let fallback = config.gravatar_fallback && is_raster && seed.contains('@');
+        // a stored identicon means the seed had no Gravatar when it was made
+        if fallback && stored(&seed, &options, format, args, config)?.is_none() {
+            let name = default_file_name(&seed, Some(format), false);
+            let avatar = gravatar::lookup(&seed, options.size).and_then(|avatar| {
+                avatar
+                    .map(|avatar| gravatar::encode(&avatar, format, &name.to_string_lossy()))
+                    .transpose()
+            });
+            match avatar {
+                Ok(Some((bytes, content_type))) => {
+                    return Ok(Reply::new(200, bytes, content_type));
+                }
+                Ok(None) => {}
+                // an identicon still beats no avatar at all
+                Err(err) => eprintln!("{:#}; answering with an identicon", err),
+            }
+        }
+        let bytes = render(&seed, &options, format, args, config)?;
+        return Ok(Reply::new(200, bytes, format.mime_type()));
+    }
+
+    if path == "/batch" {
+        check_signature(config, request, path, query, Some(&request.body))?;
+        return batch(request, args, config);
+    }
+
+    unreachable!("every endpoint with a method is handled")
+}
+
+/// The method an endpoint answers, if `path` is one
+fn method_of(path: &str) -> Option<&'static str> {
+    if path.starts_with("/identicon/")
+        || path == "/healthz"
+        || path == "/readyz"
+        || path == "/openapi.json"
+    {
+        Some("GET")
+    } else if path == "/batch" {
+        Some("POST")
+    } else {
+        None
+    }
+}
+
+/// Access-Control-Allow-* headers for requests from an allowed origin
+fn cors_headers(request: &Incoming, config: &Config) -> Vec<(&'static str, String)> {
+    let Some(origin) = request.header("Origin") else {
+        return Vec::new();
+    };
+    let any = config.cors_origins.iter().any(|allowed| allowed == "*");
+    if !any
+        && !config
+            .cors_origins
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(origin))
+    {
+        return Vec::new();
+    }
+
+    let mut headers = Vec::new();
+    if any {
+        headers.push(("Access-Control-Allow-Origin", "*".to_string()));
+    } else {
+        headers.push(("Access-Control-Allow-Origin", origin.to_string()));
+        headers.push(("Vary", "Origin".to_string()));
+    }
+    if request.method == "OPTIONS" {
+        let path = request.url.split('?').next().unwrap_or_default();
+        if let Some(method) = method_of(path) {
+            let methods = format!("{}, OPTIONS", method);
+            headers.push(("Access-Control-Allow-Methods", methods));
+        }
+        if let Some(requested) = request.header("Access-Control-Request-Headers") {
+            headers.push(("Access-Control-Allow-Headers", requested.to_string()));
+        }
+        headers.push(("Access-Control-Max-Age", "86400".to_string()));
+    } else {
+        let exposed = "Content-Disposition".to_string();
+        headers.push(("Access-Control-Expose-Headers", exposed));
+    }
+    headers
+}
+
+fn batch(request: &Incoming, args: &RenderArgs, config: &Config) -> Result<Reply> {
+    let batch: BatchRequest = serde_json::from_slice(&request.body)
+        .map_err(|err| exit::Invalid(format!("Invalid batch request: {}", err)))?;
+    let (seeds, overrides, format) = match batch {
+        BatchRequest::Seeds(seeds) => (seeds, Map::new(), None),
+        BatchRequest::Full {
+            seeds,
+            options,
+            format,
+        } => (seeds, options, format),
+    };
+    let format = parse_format(format.as_deref())?;
+    check_overrides(config, &overrides)?;
+
+    let mut seen = HashSet::new();
+    let seeds: Vec<String> = seeds
+        .into_iter()
+        .filter(|seed| seen.insert(seed.clone()))
+        .collect();
+    if seeds.len() > usize::from(config.max_batch) {
+        let message = format!(
+            "{} seeds is more than the {} one batch may ask for",
+            seeds.len(),
+            config.max_batch
+        );
+        return Err(HttpError::new(413, message).into());
+    }
+
+    // every seed is checked before any is drawn
+    let options = seeds
+        .iter()
+        .map(|seed| {
+            let options = overlay(&args.options(seed), &overrides)?;
+            check_max_size(&options, config.max_size)?;
+            Ok(options)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut files = Vec::with_capacity(seeds.len());
+    for (seed, options) in seeds.iter().zip(&options) {
+        let bytes = render(seed, options, format, args, config)
+            .with_context(|| format!("Failed to generate {}", seed))?;
+        let name = default_file_name(seed, Some(format), false);
+        files.push((name.display().to_string(), bytes));
+    }
+
+    if accepts(request, "multipart/mixed") {
+        return Ok(multipart(&files, format.mime_type()));
+    }
+    let mut zip = Zip::new();
+    for (name, bytes) in &files {
+        zip.add(name, bytes)?;
+    }
+    let reply = Reply::new(200, zip.finish()?, "application/zip").with_header(
+        "Content-Disposition",
+        "attachment; filename=\"identicons.zip\"",
+    );
+    Ok(reply)
+}
+
+/// Fail with 403 unless the request is signed, when signatures are required
+fn check_signature(
+    config: &Config,
+    request: &Incoming,
+    path: &str,
+    query: &str,
+    body: Option<&[u8]>,
+) -> Result<()> {
+    let Some(key) = &config.signing_key else {
+        return Ok(());
+    };
+    signing::verify(key, &request.method, path, query, body)
+        .map_err(|reason| HttpError::new(403, reason).into())
+}
+
+/// Fail with 400 if `options` would have an image side drawn over
+/// --max-size, or an automaton evolved past MAX_STEPS generations
+fn check_max_size(options: &Options, max_size: u32) -> Result<()> {
+    let height = options.height.unwrap_or(options.size);
+    let sides = [
+        ("Size", options.size),
+        ("Height", height),
+        (
+            "Tile",
+            options.tile.map_or(0, |(width, height)| width.max(height)),
+        ),
+        (
+            "Supersampled size",
+            options.size.max(height).saturating_mul(options.supersample),
+        ),
+    ];
+    for (what, side) in sides {
+        if side > max_size {
+            let message = format!(
+                "{} of {}px is over the {}px this server draws",
+                what, side, max_size
+            );
+            return Err(HttpError::new(400, message).into());
+        }
+    }
+    if options.steps > MAX_STEPS {
+        let message = format!(
+            "{} automaton steps is over the {} this server evolves",
+            options.steps, MAX_STEPS
+        );
+        return Err(HttpError::new(400, message).into());
+    }
+    Ok(())
+}
+
+/// Render `seed` and encode it as `format` on the render pool, through the
+/// --store when there is one; options over --max-size answer 400, and a
+/// full queue 503
+fn render(
+    seed: &str,
+    options: &Options,
+    format: Format,
+    args: &RenderArgs,
+    config: &Config,
+) -> Result<Vec<u8>> {
+    check_max_size(options, config.max_size)?;
+    let input = args.input(seed)?.variant(args.variant);
+    let encoding = encoding(&input.digest);
+
+    #[cfg(feature = "sqlite")]
+    let entry = match &config.store {
+        Some(store) => Some(store.entry(&input.digest, options, Some(format), None, &encoding)?),
+        None => None,
+    };
+    #[cfg(feature = "sqlite")]
+    if let Some(bytes) = entry.as_ref().map(store::Entry::get).transpose()?.flatten() {
+        return Ok(bytes);
+    }
+
+    let name = default_file_name(seed, Some(format), false);
+    let options = options.clone();
+    let render = move || -> Result<Vec<u8>> {
+        let identicon =
+            Identicon::new(&input.digest, &options).context("Failed to generate identicon")?;
+        Ok(output::encode(
+            identicon.as_rgb(),
+            &name,
+            Some(format),
+            &encoding,
+        )?)
+    };
+    let bytes = match RenderPool::global().try_submit(render) {
+        Ok(pending) => pending.wait()?,
+        Err(err @ HashdenticonError::Busy) => {
+            return Err(HttpError::new(503, err.to_string()).into());
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    #[cfg(feature = "sqlite")]
+    if let Some(entry) = &entry {
+        entry.put(format.mime_type(), &bytes)?;
+    }
+    if let Some(dir) = config.out_dir.as_ref().filter(|_| seed != CANARY) {
+        let name = format!("{:x}.{}", Sha256::digest(&bytes), format.extension());
+        // the client gets its identicon whether or not the copy is written
+        if let Err(err) = sink::write_as(&dir.join(name), &bytes, format.mime_type()) {
+            eprintln!("{:#}", err);
+        }
+    }
+    Ok(bytes)
+}
+
+/// The identicon of `seed` if --store already holds it
+#[cfg(feature = "sqlite")]
+fn stored(
+    seed: &str,
+    options: &Options,
+    format: Format,
+    args: &RenderArgs,
+    config: &Config,
+) -> Result<Option<Vec<u8>>> {
+    let Some(store) = &config.store else {
+        return Ok(None);
+    };
+    let input = args.input(seed)?.variant(args.variant);
+    let encoding = encoding(&input.digest);
+    store
+        .entry(&input.digest, options, Some(format), None, &encoding)?
+        .get()
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn stored(
+    _seed: &str,
+    _options: &Options,
+    _format: Format,
+    _args: &RenderArgs,
+    _config: &Config,
+) -> Result<Option<Vec<u8>>> {
+    Ok(None)
+}
+
+/// How an identicon of `digest` is encoded, with its badge labelled by it
+fn encoding(digest: &[u8]) -> Encoding {
+    Encoding {
+        label: Some(output::badge_label(digest)),
+        ..Encoding::default()
+    }
+}
+
+/// The format named, png if none is
+fn parse_format(name: Option<&str>) -> Result<Format> {
+    let Some(name) = name else {
+        return Ok(Format::Png);
+    };
+    Format::from_str(name, true)
+        .map_err(|_| exit::Invalid(format!("Unknown format: {}", name)).into())
+}
+
+/// Fail with 403 if `overrides` sets an option --allow keeps fixed
+fn check_overrides(config: &Config, overrides: &Map<String, Value>) -> Result<()> {
+    for (field, value) in overrides {
+        allow::check(&config.allow, field, value).map_err(|reason| HttpError::new(403, reason))?;
+    }
+    Ok(())
+}
+
+/// `base` with the fields named in `overrides` replaced
+fn overlay(base: &Options, overrides: &Map<String, Value>) -> Result<Options> {
+    let mut options = serde_json::to_value(base)?;
+    for (field, value) in overrides {
+        let Some(slot) = options.get_mut(field) else {
+            return Err(exit::Invalid(format!("Unknown option: {}", field)).into());
+        };
+        *slot = value.clone();
+    }
+    serde_json::from_value(options)
+        .map_err(|err| exit::Invalid(format!("Invalid options: {}", err)).into())
+}
+
+/// The seed and format of an `/identicon/` path, the longest extension
+/// winning so `.badge.svg` isn't taken for `.svg`; an extension that names
+/// no format, as in `alice@example.com`, is part of the seed
+fn split_format(name: &str) -> (String, Format) {
+    let format = Format::value_variants()
+        .iter()
+        .filter_map(|&format| {
+            let at = name.len().checked_sub(format.extension().len() + 1)?;
+            let (seed, ext) = name.split_at_checked(at)?;
+            let ext = ext.strip_prefix('.')?;
+            ext.eq_ignore_ascii_case(format.extension())
+                .then_some((seed, format))
+        })
+        .max_by_key(|(_, format)| format.extension().len());
+    match format {
+        Some((seed, format)) => (seed.to_string(), format),
+        None => (name.to_string(), Format::Png),
+    }
+}
+
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// Decode `%XX` escapes; malformed ones are kept as they are
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        // from_str_radix takes a sign too, so `%+5` is kept as it is
+        let escape = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escape {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn accepts(request: &Incoming, media_type: &str) -> bool {
+    request
+        .header("Accept")
+        .is_some_and(|accept| accept.contains(media_type))
+}
+
+/// Every file as a part of one multipart/mixed body
+fn multipart(files: &[(String, Vec<u8>)], content_type: &str) -> Reply {
+    let boundary = boundary(files);
+
+    let mut body = Vec::new();
+    for (name, bytes) in files {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Type: {}\r\nContent-Disposition: attachment; filename=\"{}\"\r\n\r\n",
+                boundary, content_type, name
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(bytes);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+    let content_type = format!("multipart/mixed; boundary={}", boundary);
+    Reply::new(200, body, &content_type)
+}
+
+/// A multipart boundary found in none of `files`: drawn from their contents,
+/// so turning up by chance is astronomically unlikely, and drawn again
+/// should it anyway
+fn boundary(files: &[(String, Vec<u8>)]) -> String {
+    let candidates = (0u32..).map(|attempt| {
+        let mut hasher = Sha256::new();
+        for (_, bytes) in files {
+            hasher.update(bytes);
+        }
+        hasher.update(attempt.to_be_bytes());
+        format!("hashdenticon-{}", &input::hex(&hasher.finalize())[..32])
+    });
+    first_free(files, candidates)
+}
+
+fn first_free(files: &[(String, Vec<u8>)], mut candidates: impl Iterator<Item = String>) -> String {
+    candidates
+        .find(|boundary| !occurs_in(files, boundary))
+        .expect("some candidate is free")
+}
+
+/// Whether a delimiter line of `boundary` appears in a part's name or data
+fn occurs_in(files: &[(String, Vec<u8>)], boundary: &str) -> bool {
+    let delimiter = format!("--{}", boundary);
+    let delimiter = delimiter.as_bytes();
+    files.iter().any(|(name, bytes)| {
+        name.contains(boundary)
+            || bytes
+                .windows(delimiter.len())
+                .any(|window| window == delimiter)
+    })
+}
+
+pub fn error_reply(err: &anyhow::Error) -> Reply {
+    let status = match err.downcast_ref::<HttpError>() {
+        Some(err) => err.status,
+        None => match Kind::of(err) {
+            Kind::Usage => 400,
+            _ => 500,
+        },
+    };
+    let message = format!("{:#}\n", err);
+    Reply::new(status, message.into_bytes(), TEXT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(parts: &[(&str, &[u8])]) -> Vec<(String, Vec<u8>)> {
+        parts
+            .iter()
+            .map(|&(name, bytes)| (name.to_string(), bytes.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn bodies_over_the_limit_are_refused() {
+        let limit = MAX_BODY as usize;
+        assert_eq!(read_body(&vec![7; limit][..]).unwrap().len(), limit);
+        assert!(read_body(&[][..]).unwrap().is_empty());
+
+        let err = read_body(&vec![7; limit + 1][..]).unwrap_err();
+        assert_eq!(error_reply(&err).status, 413);
+        let err = read_body(&vec![7; limit * 3][..]).unwrap_err();
+        assert_eq!(error_reply(&err).status, 413);
+    }
+
+    #[test]
+    fn sides_over_the_ceiling_are_refused() {
+        let options = Options {
+            size: 2000,
+            ..Options::default()
+        };
+        assert!(check_max_size(&options, 2000).is_ok());
+
+        let oversized = [
+            Options {
+                size: 2001,
+                ..options.clone()
+            },
+            Options {
+                height: Some(8192),
+                ..options.clone()
+            },
+            Options {
+                tile: Some((8192, 64)),
+                ..options.clone()
+            },
+            Options {
+                size: 1024,
+                supersample: 2,
+                ..options.clone()
+            },
+            Options {
+                style: hashdenticon::Style::Automaton,
+                steps: 4_000_000_000,
+                ..options.clone()
+            },
+        ];
+        for options in oversized {
+            let err = check_max_size(&options, 2000).unwrap_err();
+            assert_eq!(error_reply(&err).status, 400);
+        }
+    }
+
+    #[test]
+    fn percent_escapes_are_hex_digits_only() {
+        assert_eq!(percent_decode("alice%40example.com"), "alice@example.com");
+        assert_eq!(percent_decode("a%2Bb%2bc"), "a+b+c");
+        assert_eq!(percent_decode("%+5%-1"), "%+5%-1");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%4"), "%4");
+    }
+
+    #[test]
+    fn boundaries_follow_the_contents() {
+        let one = files(&[("a.png", b"one")]);
+        let two = files(&[("a.png", b"two")]);
+        assert_eq!(boundary(&one), boundary(&one));
+        assert_ne!(boundary(&one), boundary(&two));
+    }
+
+    #[test]
+    fn boundaries_in_a_part_are_found() {
+        let parts = files(&[("a.png", b"x\r\n--hashdenticon-0123\r\ny")]);
+        assert!(occurs_in(&parts, "hashdenticon-0123"));
+        assert!(!occurs_in(&parts, "hashdenticon-4567"));
+        let named = files(&[("hashdenticon-0123.png", b"")]);
+        assert!(occurs_in(&named, "hashdenticon-0123"));
+    }
+
+    #[test]
+    fn a_colliding_boundary_is_drawn_again() {
+        let parts = files(&[("a.png", b"--taken--")]);
+        let candidates = ["taken", "free"].map(String::from).into_iter();
+        assert_eq!(first_free(&parts, candidates), "free");
+        assert!(!occurs_in(&parts, &boundary(&parts)));
+    }
+
+    #[test]
+    fn every_part_is_delimited() {
+        let parts = files(&[("a.png", b"one"), ("b.png", b"two")]);
+        let reply = multipart(&parts, "image/png");
+        let boundary = boundary(&parts);
+        let body = String::from_utf8(reply.body).unwrap();
+        assert_eq!(body.matches(&format!("--{}\r\n", boundary)).count(), 2);
+        assert!(body.ends_with(&format!("--{}--\r\n", boundary)));
+    }
+}
@@ -0,0 +1,225 @@
+//! `serve --allow`: the options clients may vary per request
+//!
+//! Without any `--allow`, `?size=` and the `options` of POST /batch may
+//! set anything. With them, only the options named may be set, each to any
+//! value, to a number within `MIN-MAX`, or to one of a comma-separated
+//! list; everything else stays as the server has it. Options that name a
+//! path on the server, like `parts_dir`, are never the client's to set.
+
+use hashdenticon::Options;
+use serde_json::{Value, json};
+
+/// Options that name a path on the server, which no `--allow` opens up
+pub const SERVER_ONLY: &[&str] = &["parts_dir"];
+
+/// One `--allow` rule
+#[derive(Debug, Clone)]
+pub struct Rule {
+    /// Option name, as in presets and the `options` of POST /batch
+    field: String,
+    values: Values,
+}
+
+#[derive(Debug, Clone)]
+enum Values {
+    Any,
+    Range(u64, u64),
+    OneOf(Vec<String>),
+}
+
+impl Rule {
+    /// `<option>`, `<option>=<MIN>-<MAX>`, or `<option>=<a>,<b>,...`
+    pub fn parse(arg: &str) -> Result<Rule, String> {
+        let (field, values) = match arg.split_once('=') {
+            Some((field, values)) => (field, Some(values)),
+            None => (arg, None),
+        };
+        let defaults = serde_json::to_value(Options::default()).map_err(|err| err.to_string())?;
+        if defaults.get(field).is_none() {
+            return Err(format!("{} is not an option", field));
+        }
+        if SERVER_ONLY.contains(&field) {
+            return Err(format!("{} names a path on the server", field));
+        }
+
+        let range = values
+            .and_then(|values| values.split_once('-'))
+            .and_then(|(min, max)| {
+                Some((
+                    min.trim().parse::<u64>().ok()?,
+                    max.trim().parse::<u64>().ok()?,
+                ))
+            });
+        let values = match (values, range) {
+            (None, _) => Values::Any,
+            (Some(_), Some((min, max))) if min > max => {
+                return Err(format!("{}-{} is an empty range", min, max));
+            }
+            (Some(_), Some((min, max))) => Values::Range(min, max),
+            (Some(values), None) => Values::OneOf(
+                values
+                    .split(',')
+                    .map(|value| value.trim().to_string())
+                    .collect(),
+            ),
+        };
+        Ok(Rule {
+            field: field.to_string(),
+            values,
+        })
+    }
+
+    /// Option name and the JSON Schema of what clients may set it to
+    pub fn schema(&self) -> (&str, Value) {
+        let schema = match &self.values {
+            Values::Any => json!({}),
+            Values::Range(min, max) => json!({"type": "integer", "minimum": min, "maximum": max}),
+            Values::OneOf(allowed) => json!({"enum": allowed}),
+        };
+        (&self.field, schema)
+    }
+}
+
+/// Why a client may not set `field` to `value` under `rules`, if it may not
+pub fn check(rules: &[Rule], field: &str, value: &Value) -> Result<(), String> {
+    if SERVER_ONLY.contains(&field) {
+        return Err(format!("{} is fixed on this server", field));
+    }
+    if rules.is_empty() {
+        return Ok(());
+    }
+    let Some(rule) = rules.iter().find(|rule| rule.field == field) else {
+        return Err(format!("{} is fixed on this server", field));
+    };
+    match &rule.values {
+        Values::Any => Ok(()),
+        Values::Range(min, max) => match value.as_u64() {
+            Some(n) if (*min..=*max).contains(&n) => Ok(()),
+            _ => Err(format!("{} must be within {}-{}", field, min, max)),
+        },
+        Values::OneOf(allowed) => {
+            let given = match value {
+                Value::String(text) => text.clone(),
+                value => value.to_string(),
+            };
+            if allowed
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(&given))
+            {
+                Ok(())
+            } else {
+                Err(format!("{} must be one of {}", field, allowed.join(", ")))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(args: &[&str]) -> Vec<Rule> {
+        args.iter().map(|arg| Rule::parse(arg).unwrap()).collect()
+    }
+
+    #[test]
+    fn parses_each_form() {
+        assert!(matches!(Rule::parse("size").unwrap().values, Values::Any));
+        assert!(matches!(
+            Rule::parse("size=16-512").unwrap().values,
+            Values::Range(16, 512)
+        ));
+        assert!(matches!(
+            Rule::parse("size= 16 - 512").unwrap().values,
+            Values::Range(16, 512)
+        ));
+        let Values::OneOf(styles) = Rule::parse("style=grid, rings").unwrap().values else {
+            panic!("expected a list");
+        };
+        assert_eq!(styles, ["grid", "rings"]);
+    }
+
+    #[test]
+    fn a_single_number_is_a_list_of_one() {
+        let Values::OneOf(sizes) = Rule::parse("size=64").unwrap().values else {
+            panic!("expected a list");
+        };
+        assert_eq!(sizes, ["64"]);
+    }
+
+    #[test]
+    fn rejects_bad_rules() {
+        assert_eq!(
+            Rule::parse("colour=red").unwrap_err(),
+            "colour is not an option"
+        );
+        assert_eq!(
+            Rule::parse("size=512-16").unwrap_err(),
+            "512-16 is an empty range"
+        );
+        assert_eq!(
+            Rule::parse("parts_dir").unwrap_err(),
+            "parts_dir names a path on the server"
+        );
+    }
+
+    #[test]
+    fn server_paths_are_always_fixed() {
+        for rules in [vec![], rules(&["size"])] {
+            assert_eq!(
+                check(&rules, "parts_dir", &json!("/etc")),
+                Err("parts_dir is fixed on this server".to_string())
+            );
+            assert_eq!(
+                check(&rules, "parts_dir", &Value::Null),
+                Err("parts_dir is fixed on this server".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn no_rules_allow_anything() {
+        assert_eq!(check(&[], "size", &json!(4096)), Ok(()));
+        assert_eq!(check(&[], "style", &json!("rings")), Ok(()));
+    }
+
+    #[test]
+    fn unnamed_options_are_fixed() {
+        let rules = rules(&["size"]);
+        assert_eq!(
+            check(&rules, "style", &json!("rings")),
+            Err("style is fixed on this server".to_string())
+        );
+    }
+
+    #[test]
+    fn ranges_are_inclusive() {
+        let rules = rules(&["size=16-512"]);
+        for size in [16, 100, 512] {
+            assert_eq!(check(&rules, "size", &json!(size)), Ok(()));
+        }
+        for value in [json!(15), json!(513), json!(-1), json!("64"), json!(64.5)] {
+            assert_eq!(
+                check(&rules, "size", &value),
+                Err("size must be within 16-512".to_string()),
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn lists_match_case_insensitively() {
+        let rules = rules(&["style=grid,rings", "size=64,128"]);
+        assert_eq!(check(&rules, "style", &json!("Rings")), Ok(()));
+        assert_eq!(check(&rules, "size", &json!(128)), Ok(()));
+        assert_eq!(
+            check(&rules, "style", &json!("blob")),
+            Err("style must be one of grid, rings".to_string())
+        );
+        assert_eq!(
+            check(&rules, "size", &json!(256)),
+            Err("size must be one of 64, 128".to_string())
+        );
+    }
+}
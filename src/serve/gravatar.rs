@@ -0,0 +1,156 @@
+//! `--gravatar-fallback`: the Gravatar of an email seed when it has one, so
+//! identicons only fill in for addresses without a picture
+
+use anyhow::{Context, Result, bail};
+use hashdenticon::input;
+use hashdenticon::output::{self, Encoding, Format};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+const AVATAR_URL: &str = "https://gravatar.com/avatar";
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Largest size Gravatar serves
+const MAX_SIZE: u32 = 2048;
+
+/// How long a Gravatar, or the lack of one, is remembered
+const TTL: Duration = Duration::from_secs(60 * 60);
+/// How long a failure to reach Gravatar is remembered, so that an outage
+/// doesn't hold up every request for the timeout
+const RETRY: Duration = Duration::from_secs(60);
+/// Most answers remembered at once
+const MAX_CACHED: usize = 10_000;
+
+/// A Gravatar's bytes and the media type they are in
+pub struct Avatar {
+    pub bytes: Vec<u8>,
+    pub content_type: String,
+}
+
+/// Answers by email hash and size, with when they stop being fresh
+type Cache = HashMap<(String, u32), (Instant, Option<Arc<Avatar>>)>;
+
+static CACHE: LazyLock<Mutex<Cache>> = LazyLock::new(Mutex::default);
+
+/// The Gravatar of `email`, or `None` when it has none; answers are
+/// remembered for a while, failures to fetch for a shorter one
+pub fn lookup(email: &str, size: u32) -> Result<Option<Arc<Avatar>>> {
+    let hash = input::hex(&Sha256::digest(email.trim().to_lowercase()));
+    let size = size.min(MAX_SIZE);
+    let key = (hash, size);
+    let now = Instant::now();
+    if let Some((expires, avatar)) = CACHE.lock().expect("cache poisoned").get(&key)
+        && *expires > now
+    {
+        return Ok(avatar.clone());
+    }
+
+    // not under the lock, so other lookups don't wait on this one
+    let fetched = fetch(&key.0, size).map(|avatar| avatar.map(Arc::new));
+    let (ttl, avatar) = match &fetched {
+        Ok(avatar) => (TTL, avatar.clone()),
+        Err(_) => (RETRY, None),
+    };
+    let mut cache = CACHE.lock().expect("cache poisoned");
+    if cache.len() >= MAX_CACHED {
+        cache.retain(|_, (expires, _)| *expires > now);
+        if cache.len() >= MAX_CACHED {
+            cache.clear();
+        }
+    }
+    cache.insert(key, (now + ttl, avatar));
+    fetched
+}
+
+/// `avatar` as `format`: as it is when Gravatar served it that way, else
+/// decoded and encoded again, so the bytes are what the request named
+pub fn encode(avatar: &Avatar, format: Format, name: &str) -> Result<(Vec<u8>, &'static str)> {
+    if avatar.content_type == format.mime_type() {
+        return Ok((avatar.bytes.clone(), format.mime_type()));
+    }
+    let image = image::load_from_memory(&avatar.bytes)
+        .with_context(|| format!("Failed to decode Gravatar ({})", avatar.content_type))?;
+    let bytes = output::encode(
+        &image.to_rgb8(),
+        name.as_ref(),
+        Some(format),
+        &Encoding::default(),
+    )?;
+    Ok((bytes, format.mime_type()))
+}
+
+fn fetch(hash: &str, size: u32) -> Result<Option<Avatar>> {
+    let url = format!("{}/{}?d=404&s={}", AVATAR_URL, hash, size);
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(TIMEOUT))
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let mut response = agent.get(&url).call().context("Failed to reach Gravatar")?;
+
+    match response.status().as_u16() {
+        200 => {
+            let header = response
+                .headers()
+                .get("Content-Type")
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            let bytes = response
+                .body_mut()
+                .read_to_vec()
+                .context("Failed to download Gravatar")?;
+            // the bytes themselves over the header, which may be missing
+            let content_type = image::guess_format(&bytes)
+                .map(|format| format.to_mime_type().to_string())
+                .ok()
+                .or(header)
+                .unwrap_or_else(|| "application/octet-stream".to_string());
+            Ok(Some(Avatar {
+                bytes,
+                content_type,
+            }))
+        }
+        404 => Ok(None),
+        status => bail!("Gravatar answered with status {}", status),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, Rgb, RgbImage};
+
+    fn png() -> Avatar {
+        let image = RgbImage::from_pixel(4, 4, Rgb([200, 30, 90]));
+        let bytes = output::encode(&image, "a.png".as_ref(), None, &Encoding::default()).unwrap();
+        Avatar {
+            bytes,
+            content_type: "image/png".to_string(),
+        }
+    }
+
+    #[test]
+    fn avatars_in_the_asked_format_pass_through() {
+        let avatar = png();
+        let (bytes, content_type) = encode(&avatar, Format::Png, "a.png").unwrap();
+        assert_eq!(bytes, avatar.bytes);
+        assert_eq!(content_type, "image/png");
+    }
+
+    #[test]
+    fn avatars_are_encoded_as_asked() {
+        let (bytes, content_type) = encode(&png(), Format::Webp, "a.webp").unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap(), ImageFormat::WebP);
+        assert_eq!(content_type, "image/webp");
+    }
+
+    #[test]
+    fn undecodable_avatars_are_an_error() {
+        let avatar = Avatar {
+            bytes: b"not an image".to_vec(),
+            content_type: "image/jpeg".to_string(),
+        };
+        assert!(encode(&avatar, Format::Png, "a.png").is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! `serve --grpc`: the Generate service of `proto/hashdenticon.proto`,
+//! answered with the same settings, --allow and --store as the HTTP
+//! endpoints
+//!
+//! Signed URLs and CORS are about HTTP; a mesh that speaks gRPC brings its
+//! own authentication.
+
+use super::{Config, DRAINING, HttpError, check_overrides, overlay, parse_format, render};
+use crate::exit::Kind;
+use anyhow::{Context, Result};
+use prost_types::value::Kind as ProtoKind;
+use serde_json::{Map, Number, Value};
+use std::net::{TcpListener, ToSocketAddrs};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
+
+mod proto {
+    tonic::include_proto!("hashdenticon.v1");
+}
+
+use proto::identicons_server::{Identicons, IdenticonsServer};
+use proto::{GenerateReply, GenerateRequest};
+
+/// How often the server checks whether HTTP has started draining
+const DRAIN_POLL: Duration = Duration::from_millis(100);
+
+struct Service {
+    config: Arc<Config>,
+}
+
+#[tonic::async_trait]
+impl Identicons for Service {
+    async fn generate(
+        &self,
+        request: Request<GenerateRequest>,
+    ) -> Result<Response<GenerateReply>, Status> {
+        let config = Arc::clone(&self.config);
+        // rendering is CPU work, kept off the threads that move requests
+        tokio::task::spawn_blocking(move || generate(request.into_inner(), &config))
+            .await
+            .map_err(|err| Status::internal(err.to_string()))?
+            .map(Response::new)
+            .map_err(|err| status(&err))
+    }
+}
+
+fn generate(request: GenerateRequest, config: &Config) -> Result<GenerateReply> {
+    let overrides = request.options.map(to_json).unwrap_or_default();
+    check_overrides(config, &overrides)?;
+    let format = parse_format(Some(request.format.as_str()).filter(|name| !name.is_empty()))?;
+    let args = config.render.current();
+    let options = overlay(&args.options(&request.seed), &overrides)?;
+    let image = render(&request.seed, &options, format, &args, config)?;
+    Ok(GenerateReply {
+        image,
+        content_type: format.mime_type().to_string(),
+    })
+}
+
+/// Bind `listen` now, so a bad address fails before serving starts, and
+/// answer on it from a thread of its own until the HTTP server drains
+pub fn spawn(listen: &str, config: Arc<Config>) -> Result<JoinHandle<Result<()>>> {
+    let addr = listen
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .with_context(|| format!("Invalid gRPC address: {}", listen))?;
+    let listener =
+        TcpListener::bind(addr).with_context(|| format!("Failed to listen on {}", addr))?;
+    listener
+        .set_nonblocking(true)
+        .context("Failed to set up the gRPC listener")?;
+    eprintln!("Serving gRPC on {}", listener.local_addr()?);
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("Failed to start the gRPC runtime")?;
+    Ok(std::thread::spawn(move || {
+        runtime.block_on(async {
+            let listener = tokio::net::TcpListener::from_std(listener)
+                .context("Failed to set up the gRPC listener")?;
+            let drained = async {
+                while !DRAINING.load(Ordering::SeqCst) {
+                    tokio::time::sleep(DRAIN_POLL).await;
+                }
+            };
+            tonic::transport::Server::builder()
+                .add_service(IdenticonsServer::new(Service { config }))
+                .serve_with_incoming_shutdown(TcpIncoming::from(listener), drained)
+                .await
+                .context("gRPC server failed")
+        })
+    }))
+}
+
+/// The gRPC status for what the HTTP endpoints would answer `err` with
+fn status(err: &anyhow::Error) -> Status {
+    let message = format!("{:#}", err);
+    match err.downcast_ref::<HttpError>().map(|err| err.status) {
+        Some(403) => Status::permission_denied(message),
+        Some(413) => Status::resource_exhausted(message),
+        Some(503) => Status::unavailable(message),
+        Some(_) => Status::invalid_argument(message),
+        None => match Kind::of(err) {
+            Kind::Usage => Status::invalid_argument(message),
+            _ => Status::internal(message),
+        },
+    }
+}
+
+/// The JSON object a protobuf Struct stands for
+fn to_json(options: prost_types::Struct) -> Map<String, Value> {
+    options
+        .fields
+        .into_iter()
+        .map(|(field, value)| (field, value_to_json(value)))
+        .collect()
+}
+
+fn value_to_json(value: prost_types::Value) -> Value {
+    match value.kind {
+        None | Some(ProtoKind::NullValue(_)) => Value::Null,
+        Some(ProtoKind::BoolValue(flag)) => Value::Bool(flag),
+        // protobuf only has doubles; options are mostly integers
+        Some(ProtoKind::NumberValue(n)) if n.fract() == 0.0 && n.abs() < 2f64.powi(53) => {
+            Value::Number(Number::from(n as i64))
+        }
+        Some(ProtoKind::NumberValue(n)) => Number::from_f64(n).map_or(Value::Null, Value::Number),
+        Some(ProtoKind::StringValue(text)) => Value::String(text),
+        Some(ProtoKind::ListValue(list)) => {
+            Value::Array(list.values.into_iter().map(value_to_json).collect())
+        }
+        Some(ProtoKind::StructValue(fields)) => Value::Object(to_json(fields)),
+    }
+}
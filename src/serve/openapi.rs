@@ -0,0 +1,191 @@
+//! `GET /openapi.json`: an OpenAPI 3.1 description of the endpoints, as
+//! this server is configured, for API gateways and client generators
+
+use super::{Config, TEXT, allow};
+use clap::ValueEnum;
+use hashdenticon::output::Format;
+use serde_json::{Map, Value, json};
+
+/// The description of every endpoint, with the limits `config` puts on them
+pub fn document(config: &Config) -> Value {
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "hashdenticon",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Identicons rendered from hashed seed strings.",
+        },
+        "paths": {
+            "/identicon/{seed}": {"get": identicon(config)},
+            "/batch": {"post": batch(config)},
+            "/healthz": {"get": {
+                "summary": "Liveness: answers as long as the server runs",
+                "responses": {"200": text("Running")},
+            }},
+            "/readyz": {"get": {
+                "summary": "Readiness: fails while shutting down, or with --canary when rendering fails",
+                "responses": {"200": text("Ready"), "503": text("Not ready")},
+            }},
+            "/openapi.json": {"get": {
+                "summary": "This description",
+                "responses": {"200": {
+                    "description": "OpenAPI document",
+                    "content": {"application/json": {}},
+                }},
+            }},
+        },
+    })
+}
+
+fn identicon(config: &Config) -> Value {
+    let extensions = Format::value_variants()
+        .iter()
+        .map(|format| format.extension())
+        .collect::<Vec<_>>();
+    let mut parameters = vec![json!({
+        "name": "seed",
+        "in": "path",
+        "required": true,
+        "description": format!(
+            "Seed text, percent-encoded, with an optional .<ext> naming the format: {} (png if none)",
+            extensions.join(", ")
+        ),
+        "schema": {"type": "string"},
+    })];
+    if let Some(size) = size_schema(config) {
+        parameters.push(json!({
+            "name": "size",
+            "in": "query",
+            "description": "Side in pixels, instead of the server's",
+            "schema": size,
+        }));
+    }
+    parameters.extend(signature_parameters(config));
+
+    let mut content = Map::new();
+    for format in Format::value_variants() {
+        content.insert(format.mime_type().to_string(), binary());
+    }
+    json!({
+        "summary": "One identicon",
+        "parameters": parameters,
+        "responses": {
+            "200": {"description": "The identicon", "content": content},
+            "400": text("Invalid seed or parameter, or a size larger than the server draws"),
+            "403": text("Missing or invalid signature, or an option the server keeps fixed"),
+        },
+    })
+}
+
+fn batch(config: &Config) -> Value {
+    let formats = Format::value_variants()
+        .iter()
+        .filter_map(|format| Some(format.to_possible_value()?.get_name().to_string()))
+        .collect::<Vec<_>>();
+    let seeds = json!({
+        "type": "array",
+        "items": {"type": "string"},
+        "maxItems": config.max_batch,
+    });
+    let options = if config.allow.is_empty() {
+        json!({
+            "type": "object",
+            "description": "Options to change from the server's own, named as in presets",
+            "not": {"anyOf": allow::SERVER_ONLY
+                .iter()
+                .map(|field| json!({"required": [field]}))
+                .collect::<Vec<_>>()},
+        })
+    } else {
+        let allowed = config
+            .allow
+            .iter()
+            .map(|rule| {
+                let (field, schema) = rule.schema();
+                (field.to_string(), schema)
+            })
+            .collect::<Map<_, _>>();
+        json!({
+            "type": "object",
+            "description": "Options to change from the server's own; only these may be",
+            "properties": allowed,
+            "additionalProperties": false,
+        })
+    };
+    json!({
+        "summary": "Many identicons in one round trip",
+        "parameters": signature_parameters(config),
+        "requestBody": {
+            "required": true,
+            "content": {"application/json": {"schema": {"oneOf": [
+                seeds,
+                {
+                    "type": "object",
+                    "required": ["seeds"],
+                    "properties": {
+                        "seeds": seeds,
+                        "options": options,
+                        "format": {"enum": formats, "default": "png"},
+                    },
+                },
+            ]}}},
+        },
+        "responses": {
+            "200": {
+                "description": "Every identicon, as a zip, or multipart/mixed when accepted",
+                "content": {"application/zip": binary(), "multipart/mixed": binary()},
+            },
+            "400": text("Invalid request, or an image larger than the server draws"),
+            "403": text("Missing or invalid signature, or an option the server keeps fixed"),
+            "413": text("More seeds than one batch may ask for, or a body over 1 MiB"),
+        },
+    })
+}
+
+/// What clients may ask for as `?size=`, or nothing if it is fixed
+fn size_schema(config: &Config) -> Option<Value> {
+    if config.allow.is_empty() {
+        return Some(json!({
+            "type": "integer",
+            "minimum": hashdenticon::MIN_SIZE,
+            "maximum": config.max_size,
+        }));
+    }
+    config
+        .allow
+        .iter()
+        .map(|rule| rule.schema())
+        .find(|(field, _)| *field == "size")
+        .map(|(_, schema)| schema)
+}
+
+/// `exp` and `sig`, required when the server only answers signed URLs
+fn signature_parameters(config: &Config) -> Vec<Value> {
+    if config.signing_key.is_none() {
+        return Vec::new();
+    }
+    vec![
+        json!({
+            "name": "exp",
+            "in": "query",
+            "required": true,
+            "description": "Unix time the signed URL stops working at",
+            "schema": {"type": "integer"},
+        }),
+        json!({
+            "name": "sig",
+            "in": "query",
+            "required": true,
+            "description": "Hex HMAC-SHA256 of the request under the signing key",
+            "schema": {"type": "string"},
+        }),
+    ]
+}
+
+fn binary() -> Value {
+    json!({"schema": {"type": "string", "format": "binary"}})
+}
+
+fn text(description: &str) -> Value {
+    json!({"description": description, "content": {TEXT: {"schema": {"type": "string"}}}})
+}
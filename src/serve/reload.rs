@@ -0,0 +1,87 @@
+//! The server's rendering settings, applied again whenever their --preset
+//! or --theme-file changes, so the house style can be adjusted without a
+//! restart
+
+use super::DRAINING;
+use crate::RenderArgs;
+use anyhow::Result;
+use clap::ArgMatches;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub struct Settings {
+    /// As given on the command line, before the preset and theme fill in the rest
+    base: RenderArgs,
+    matches: ArgMatches,
+    current: RwLock<Arc<RenderArgs>>,
+}
+
+impl Settings {
+    pub fn new(base: RenderArgs, matches: ArgMatches) -> Result<Settings> {
+        let current = RwLock::new(Arc::new(load(&base, &matches)?));
+        Ok(Settings {
+            base,
+            matches,
+            current,
+        })
+    }
+
+    /// The settings to answer a request with
+    pub fn current(&self) -> Arc<RenderArgs> {
+        Arc::clone(&self.current.read().expect("settings lock poisoned"))
+    }
+
+    /// Reload the preset and theme each time either is saved, until the
+    /// server drains; settings that fail to load leave the previous ones
+    /// in place
+    pub fn watch(&self) {
+        let files: Vec<&PathBuf> = [&self.base.preset, &self.base.theme_file]
+            .into_iter()
+            .flatten()
+            .collect();
+        if files.is_empty() {
+            return;
+        }
+        let modified = || -> Vec<Option<SystemTime>> {
+            files
+                .iter()
+                .map(|path| {
+                    std::fs::metadata(path)
+                        .and_then(|meta| meta.modified())
+                        .ok()
+                })
+                .collect()
+        };
+        let mut last_modified = modified();
+        while !DRAINING.load(Ordering::SeqCst) {
+            std::thread::sleep(POLL_INTERVAL);
+            // editors often replace the file on save, so it can briefly vanish
+            let modified = modified();
+            if modified.contains(&None) || modified == last_modified {
+                continue;
+            }
+            last_modified = modified;
+            match load(&self.base, &self.matches) {
+                Ok(render) => {
+                    *self.current.write().expect("settings lock poisoned") = Arc::new(render);
+                    let names: Vec<String> = files
+                        .iter()
+                        .map(|path| path.display().to_string())
+                        .collect();
+                    eprintln!("Reloaded {}", names.join(" and "));
+                }
+                Err(err) => eprintln!("Keeping the previous settings: {:#}", err),
+            }
+        }
+    }
+}
+
+fn load(base: &RenderArgs, matches: &ArgMatches) -> Result<RenderArgs> {
+    let mut render = base.clone();
+    render.apply_preset(matches)?;
+    Ok(render)
+}
@@ -0,0 +1,290 @@
+//! Signed URLs for `serve --signing-key`
+//!
+//! A signed URL carries `exp`, the unix time it stops working at, and `sig`,
+//! the hex HMAC-SHA256 under the key of `<METHOD> <path>?<query>`, where the
+//! query is as sent minus `sig`. Requests with a body, like POST /batch,
+//! add a newline and the hex SHA-256 of the body to what is signed.
+
+use hashdenticon::input;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// `url` with an `exp` of `ttl` from now and a `sig` for GET requests
+pub fn sign_url(key: &[u8; 32], url: &str, ttl: Duration) -> String {
+    // the origin, if any, isn't signed; the server only sees the path
+    let start = url.find("://").map_or(0, |scheme| {
+        let host = scheme + 3;
+        url[host..].find('/').map_or(url.len(), |path| host + path)
+    });
+    let (origin, target) = url.split_at(start);
+    let target = if target.is_empty() { "/" } else { target };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let exp = (SystemTime::now() + ttl)
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let query = match query {
+        "" => format!("exp={}", exp),
+        query => format!("{}&exp={}", query, exp),
+    };
+    let sig = input::hex(&mac(key, "GET", path, &query, None).finalize().into_bytes());
+    format!("{}{}?{}&sig={}", origin, path, query, sig)
+}
+
+/// Whether `query` carries an unexpired `exp` and a `sig` of this request
+/// under `key`, and why not when it doesn't
+pub fn verify(
+    key: &[u8; 32],
+    method: &str,
+    path: &str,
+    query: &str,
+    body: Option<&[u8]>,
+) -> Result<(), &'static str> {
+    let pairs: Vec<(&str, &str)> = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .collect();
+    let param = |name: &str| {
+        pairs
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| *value)
+    };
+
+    let exp: u64 = param("exp")
+        .ok_or("The URL is not signed")?
+        .parse()
+        .map_err(|_| "The URL's exp is not a unix time")?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    if exp < now {
+        return Err("The signed URL has expired");
+    }
+    let sig = param("sig").ok_or("The URL is not signed")?;
+    let sig = input::parse_key(sig).map_err(|_| "The URL's sig is not 64 hex digits")?;
+
+    let unsigned = query
+        .split('&')
+        .filter(|pair| !pair.starts_with("sig="))
+        .collect::<Vec<_>>()
+        .join("&");
+    mac(key, method, path, &unsigned, body)
+        .verify_slice(&sig)
+        .map_err(|_| "The URL's signature does not match")
+}
+
+fn mac(key: &[u8; 32], method: &str, path: &str, query: &str, body: Option<&[u8]>) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("hmac takes keys of any length");
+    mac.update(format!("{} {}?{}", method, path, query).as_bytes());
+    if let Some(body) = body {
+        mac.update(format!("\n{}", input::hex(&Sha256::digest(body))).as_bytes());
+    }
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [7; 32];
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    /// The path and query of a signed URL
+    fn split(url: &str) -> (String, String) {
+        let target = &url[url.find("/identicon").unwrap()..];
+        let (path, query) = target.split_once('?').unwrap();
+        (path.to_string(), query.to_string())
+    }
+
+    /// `query` with a `sig` of it appended, as a client would send it
+    fn signed(method: &str, path: &str, query: &str, body: Option<&[u8]>) -> String {
+        let sig = input::hex(&mac(&KEY, method, path, query, body).finalize().into_bytes());
+        format!("{}&sig={}", query, sig)
+    }
+
+    #[test]
+    fn signed_urls_verify() {
+        for url in [
+            "https://example.com/identicon/alice.png",
+            "https://example.com/identicon/alice.png?size=64",
+            "/identicon/alice.png?size=64&style=rings",
+        ] {
+            let signed = sign_url(&KEY, url, Duration::from_secs(60));
+            let (path, query) = split(&signed);
+            assert_eq!(
+                verify(&KEY, "GET", &path, &query, None),
+                Ok(()),
+                "{}",
+                signed
+            );
+        }
+    }
+
+    #[test]
+    fn origin_is_kept_and_a_bare_one_gets_a_path() {
+        let signed = sign_url(&KEY, "https://example.com", Duration::from_secs(60));
+        assert!(
+            signed.starts_with("https://example.com/?exp="),
+            "{}",
+            signed
+        );
+        let query = signed.split_once('?').unwrap().1;
+        assert_eq!(verify(&KEY, "GET", "/", query, None), Ok(()));
+    }
+
+    #[test]
+    fn another_key_does_not_verify() {
+        let signed = sign_url(&KEY, "/identicon/alice", Duration::from_secs(60));
+        let (path, query) = split(&signed);
+        assert_eq!(
+            verify(&[8; 32], "GET", &path, &query, None),
+            Err("The URL's signature does not match")
+        );
+    }
+
+    #[test]
+    fn tampering_breaks_the_signature() {
+        let signed = sign_url(&KEY, "/identicon/alice?size=64", Duration::from_secs(60));
+        let (path, query) = split(&signed);
+        let mismatch = Err("The URL's signature does not match");
+
+        let bigger = query.replace("size=64", "size=4096");
+        assert_eq!(verify(&KEY, "GET", &path, &bigger, None), mismatch);
+        let added = format!("style=rings&{}", query);
+        assert_eq!(verify(&KEY, "GET", &path, &added, None), mismatch);
+        assert_eq!(
+            verify(&KEY, "GET", "/identicon/bob", &query, None),
+            mismatch
+        );
+        assert_eq!(verify(&KEY, "POST", &path, &query, None), mismatch);
+    }
+
+    #[test]
+    fn the_query_is_signed_in_the_order_sent() {
+        let exp = now() + 60;
+        let query = signed(
+            "GET",
+            "/identicon/alice",
+            &format!("size=64&style=rings&exp={}", exp),
+            None,
+        );
+        assert_eq!(
+            verify(&KEY, "GET", "/identicon/alice", &query, None),
+            Ok(())
+        );
+
+        let sig = query.rsplit_once("&sig=").unwrap().1;
+        let reordered = format!("style=rings&size=64&exp={}&sig={}", exp, sig);
+        assert_eq!(
+            verify(&KEY, "GET", "/identicon/alice", &reordered, None),
+            Err("The URL's signature does not match")
+        );
+    }
+
+    #[test]
+    fn sig_may_sit_anywhere_in_the_query() {
+        let exp = now() + 60;
+        let query = signed(
+            "GET",
+            "/identicon/alice",
+            &format!("exp={}&size=64", exp),
+            None,
+        );
+        let sig = query.rsplit_once("&sig=").unwrap().1;
+        let moved = format!("exp={}&sig={}&size=64", exp, sig);
+        assert_eq!(
+            verify(&KEY, "GET", "/identicon/alice", &moved, None),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn unsigned_urls_are_refused() {
+        let exp = format!("exp={}", now() + 60);
+        assert_eq!(
+            verify(&KEY, "GET", "/identicon/alice", "", None),
+            Err("The URL is not signed")
+        );
+        assert_eq!(
+            verify(&KEY, "GET", "/identicon/alice", &exp, None),
+            Err("The URL is not signed")
+        );
+        let sig_only = signed("GET", "/identicon/alice", "size=64", None);
+        assert_eq!(
+            verify(&KEY, "GET", "/identicon/alice", &sig_only, None),
+            Err("The URL is not signed")
+        );
+    }
+
+    #[test]
+    fn malformed_parameters_are_refused() {
+        assert_eq!(
+            verify(&KEY, "GET", "/identicon/alice", "exp=soon&sig=00", None),
+            Err("The URL's exp is not a unix time")
+        );
+        let short_sig = format!("exp={}&sig=abcd", now() + 60);
+        assert_eq!(
+            verify(&KEY, "GET", "/identicon/alice", &short_sig, None),
+            Err("The URL's sig is not 64 hex digits")
+        );
+    }
+
+    #[test]
+    fn expired_urls_are_refused() {
+        let past = signed(
+            "GET",
+            "/identicon/alice",
+            &format!("exp={}", now() - 1),
+            None,
+        );
+        assert_eq!(
+            verify(&KEY, "GET", "/identicon/alice", &past, None),
+            Err("The signed URL has expired")
+        );
+
+        // the current second still counts
+        let current = signed(
+            "GET",
+            "/identicon/alice",
+            &format!("exp={}", now() + 1),
+            None,
+        );
+        assert_eq!(
+            verify(&KEY, "GET", "/identicon/alice", &current, None),
+            Ok(())
+        );
+
+        let zero_ttl = sign_url(&KEY, "/identicon/alice", Duration::ZERO);
+        let (path, query) = split(&zero_ttl);
+        assert_eq!(verify(&KEY, "GET", &path, &query, None), Ok(()));
+    }
+
+    #[test]
+    fn bodies_are_signed_by_their_hash() {
+        let body: &[u8] = br#"["alice","bob"]"#;
+        let query = signed("POST", "/batch", &format!("exp={}", now() + 60), Some(body));
+        assert_eq!(verify(&KEY, "POST", "/batch", &query, Some(body)), Ok(()));
+
+        let mismatch = Err("The URL's signature does not match");
+        let other: &[u8] = br#"["alice","mallory"]"#;
+        assert_eq!(
+            verify(&KEY, "POST", "/batch", &query, Some(other)),
+            mismatch
+        );
+        assert_eq!(verify(&KEY, "POST", "/batch", &query, None), mismatch);
+        // an empty body is still a body, unlike none at all
+        assert_eq!(verify(&KEY, "POST", "/batch", &query, Some(b"")), mismatch);
+    }
+}
@@ -0,0 +1,110 @@
+//! Where output goes: local files and stdout through the library, object
+//! storage for `s3://bucket/key` paths, or an HTTP PUT for `--upload-url`
+
+use anyhow::{Context, Result};
+use hashdenticon::output::{self, Encoding, Format};
+use image::RgbImage;
+use std::path::Path;
+
+const S3_SCHEME: &str = "s3://";
+
+/// Whether `path` names an object in a bucket rather than a file
+pub fn is_remote(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|path| path.starts_with(S3_SCHEME))
+}
+
+/// [`output::save`], or an upload for remote paths
+pub fn save(
+    img: &RgbImage,
+    path: &Path,
+    format: Option<Format>,
+    encoding: &Encoding,
+) -> Result<()> {
+    if !is_remote(path) {
+        return Ok(output::save(img, path, format, encoding)?);
+    }
+    let bytes = output::encode(img, path, format, encoding)?;
+    upload(path, &bytes, output::content_type(path, format))
+}
+
+/// [`output::write`], or an upload for remote paths
+pub fn write(path: &Path, bytes: &[u8]) -> Result<()> {
+    write_as(path, bytes, output::content_type(path, None))
+}
+
+/// [`write`] with an explicit media type for uploads
+pub fn write_as(path: &Path, bytes: &[u8], content_type: &str) -> Result<()> {
+    if !is_remote(path) {
+        return Ok(output::write(path, bytes)?);
+    }
+    upload(path, bytes, content_type)
+}
+
+/// Whether `path` already holds a file or object
+pub fn exists(path: &Path) -> Result<bool> {
+    if !is_remote(path) {
+        return Ok(path.exists());
+    }
+    remote_exists(path)
+}
+
+/// Make sure `dir` exists; buckets have no directories to create
+pub fn create_dir(dir: &Path) -> Result<()> {
+    if is_remote(dir) {
+        return Ok(());
+    }
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))
+}
+
+/// PUT `bytes` to `url`, with `token` as a bearer token if there is one
+#[cfg(feature = "http")]
+pub fn put_url(url: &str, token: Option<&str>, bytes: &[u8], content_type: &str) -> Result<()> {
+    let mut request = ureq::put(url).header("Content-Type", content_type);
+    if let Some(token) = token {
+        request = request.header("Authorization", format!("Bearer {}", token));
+    }
+    request
+        .send(bytes)
+        .with_context(|| format!("Failed to upload to {}", url))?;
+    Ok(())
+}
+
+/// The bucket and key of an `s3://bucket/key` path
+#[cfg(feature = "s3")]
+fn object(path: &Path) -> Result<(&str, &str)> {
+    let url = path.to_str().unwrap_or_default();
+    url[S3_SCHEME.len()..]
+        .split_once('/')
+        .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+        .with_context(|| format!("Expected s3://bucket/key, got {}", url))
+}
+
+#[cfg(feature = "s3")]
+fn upload(path: &Path, bytes: &[u8], content_type: &str) -> Result<()> {
+    let (bucket, key) = object(path)?;
+    crate::s3::put(bucket, key, bytes, content_type)
+        .with_context(|| format!("Failed to upload {}", path.display()))
+}
+
+#[cfg(feature = "s3")]
+fn remote_exists(path: &Path) -> Result<bool> {
+    let (bucket, key) = object(path)?;
+    crate::s3::exists(bucket, key).with_context(|| format!("Failed to check {}", path.display()))
+}
+
+#[cfg(not(feature = "s3"))]
+fn upload(path: &Path, _bytes: &[u8], _content_type: &str) -> Result<()> {
+    anyhow::bail!(
+        "Writing to {} needs hashdenticon built with the s3 feature",
+        path.display()
+    )
+}
+
+#[cfg(not(feature = "s3"))]
+fn remote_exists(path: &Path) -> Result<bool> {
+    anyhow::bail!(
+        "Reading {} needs hashdenticon built with the s3 feature",
+        path.display()
+    )
+}
@@ -0,0 +1,169 @@
+//! Memorable glyph stamped at the center, picked from a curated set
+
+use crate::draw::{fill_circle, fill_rect};
+use image::{Rgb, RgbImage};
+
+/// How the stamp glyph is colored
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum StampKind {
+    /// Full-color glyph, like the matching emoji
+    Emoji,
+    /// Monochrome glyph in a dark ink
+    Dingbat,
+}
+
+/// One curated glyph: an 8x8 bitmap plus the characters it stands for
+pub struct Stamp {
+    pub name: &'static str,
+    pub emoji: &'static str,
+    pub dingbat: &'static str,
+    color: Rgb<u8>,
+    bitmap: [u8; 8],
+}
+
+const STAMPS: [Stamp; 12] = [
+    stamp(
+        "heart",
+        "❤️",
+        "♥",
+        [220, 40, 60],
+        [0x00, 0x66, 0xff, 0xff, 0xff, 0x7e, 0x3c, 0x18],
+    ),
+    stamp(
+        "star",
+        "⭐",
+        "★",
+        [240, 180, 20],
+        [0x18, 0x18, 0xff, 0x7e, 0x3c, 0x7e, 0x66, 0x42],
+    ),
+    stamp(
+        "sun",
+        "☀️",
+        "☀",
+        [245, 160, 0],
+        [0x99, 0x42, 0x3c, 0xbd, 0xbd, 0x3c, 0x42, 0x99],
+    ),
+    stamp(
+        "moon",
+        "🌙",
+        "☾",
+        [200, 170, 40],
+        [0x3c, 0x70, 0xe0, 0xe0, 0xe0, 0xe0, 0x70, 0x3c],
+    ),
+    stamp(
+        "bolt",
+        "⚡",
+        "ϟ",
+        [250, 200, 0],
+        [0x0e, 0x1c, 0x38, 0x7e, 0x1c, 0x38, 0x70, 0x40],
+    ),
+    stamp(
+        "note",
+        "🎵",
+        "♫",
+        [90, 60, 160],
+        [0x1f, 0x11, 0x11, 0x11, 0x11, 0x77, 0xff, 0x66],
+    ),
+    stamp(
+        "drop",
+        "💧",
+        "☂",
+        [40, 120, 220],
+        [0x08, 0x18, 0x3c, 0x3c, 0x7e, 0x7e, 0x7e, 0x3c],
+    ),
+    stamp(
+        "leaf",
+        "🍃",
+        "❦",
+        [60, 170, 70],
+        [0x07, 0x1f, 0x3e, 0x7e, 0x7c, 0x78, 0x50, 0x80],
+    ),
+    stamp(
+        "diamond",
+        "💎",
+        "◆",
+        [60, 190, 220],
+        [0x18, 0x3c, 0x7e, 0xff, 0xff, 0x7e, 0x3c, 0x18],
+    ),
+    stamp(
+        "clover",
+        "🍀",
+        "♣",
+        [30, 140, 60],
+        [0x18, 0x3c, 0xdb, 0xff, 0xff, 0xdb, 0x18, 0x3c],
+    ),
+    stamp(
+        "crown",
+        "👑",
+        "♛",
+        [230, 170, 30],
+        [0x00, 0x99, 0xdb, 0xff, 0xff, 0x7e, 0x7e, 0x00],
+    ),
+    stamp(
+        "key",
+        "🔑",
+        "⚷",
+        [180, 140, 50],
+        [0x00, 0x60, 0x90, 0x9f, 0x95, 0x65, 0x00, 0x00],
+    ),
+];
+
+const fn stamp(
+    name: &'static str,
+    emoji: &'static str,
+    dingbat: &'static str,
+    color: [u8; 3],
+    bitmap: [u8; 8],
+) -> Stamp {
+    Stamp {
+        name,
+        emoji,
+        dingbat,
+        color: Rgb(color),
+        bitmap,
+    }
+}
+
+/// Pick a stamp from the second-to-last digest byte
+pub fn select(hash: &[u8]) -> &'static Stamp {
+    let byte = hash.iter().rev().nth(1).copied().unwrap_or(0);
+    &STAMPS[byte as usize % STAMPS.len()]
+}
+
+impl Stamp {
+    /// Draw the glyph on a white disc at the center of `img`
+    pub fn draw(&self, img: &mut RgbImage, kind: StampKind) {
+        let size = img.width().min(img.height());
+        let (cx, cy) = (img.width() / 2, img.height() / 2);
+
+        // disc covers roughly a third of the image, glyph sits inside it
+        let radius = size / 6;
+        fill_circle(img, cx, cy, radius, Rgb([255, 255, 255]));
+
+        let pixel = (radius * 2 * 2 / 3 / 8).max(1);
+        let glyph_size = pixel * 8;
+        let (x0, y0) = (cx - glyph_size / 2, cy - glyph_size / 2);
+        let ink = match kind {
+            StampKind::Emoji => self.color,
+            StampKind::Dingbat => Rgb([30, 30, 30]),
+        };
+
+        for (row, bits) in self.bitmap.iter().enumerate() {
+            for col in 0..8 {
+                if bits & (0x80 >> col) != 0 {
+                    fill_rect(
+                        img,
+                        x0 + col * pixel,
+                        y0 + row as u32 * pixel,
+                        pixel,
+                        pixel,
+                        ink,
+                    );
+                }
+            }
+        }
+    }
+}
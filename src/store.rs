@@ -0,0 +1,216 @@
+//! `--store`: encoded identicons cached in SQLite, keyed by seed hash and
+//! rendering parameters, so repeat requests skip rendering
+//!
+//! The key also holds the crate version, so an upgrade that changes how
+//! identicons look renders them afresh, and for the parts style what the
+//! pack holds, so editing a pack does too.
+
+use anyhow::{Context, Result};
+use hashdenticon::Options;
+use hashdenticon::input;
+use hashdenticon::output::{Encoding, Format};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS identicons (
+    hash TEXT NOT NULL,
+    params TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    data BLOB NOT NULL,
+    created INTEGER NOT NULL DEFAULT (unixepoch()),
+    PRIMARY KEY (hash, params)
+)";
+
+/// Manifest of a parts pack, as the parts style reads it
+const PARTS_MANIFEST: &str = "parts.txt";
+
+/// An open store, shared by every identicon of a run
+#[derive(Debug)]
+pub struct Store {
+    conn: Mutex<Connection>,
+}
+
+impl Store {
+    pub fn open(db: &Path) -> Result<Store> {
+        let conn = Connection::open(db)
+            .and_then(|conn| conn.execute(SCHEMA, []).map(|_| conn))
+            .with_context(|| format!("Failed to open store {}", db.display()))?;
+        Ok(Store {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// The slot for `digest` rendered with `options`, encoded as `format`
+    /// (or as the `extension` implies) with `encoding`
+    pub fn entry(
+        &self,
+        digest: &[u8],
+        options: &Options,
+        format: Option<Format>,
+        extension: Option<&str>,
+        encoding: &Encoding,
+    ) -> Result<Entry<'_>> {
+        let parts = options
+            .parts_dir
+            .as_deref()
+            .map(parts_fingerprint)
+            .transpose()?;
+        let params = json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "options": options,
+            "parts": parts,
+            "format": format.map(Format::extension).or(extension),
+            "encoding": format!("{:?}", encoding),
+        });
+        Ok(Entry {
+            store: self,
+            hash: input::hex(digest),
+            params: params.to_string(),
+        })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        // a panic mid-statement leaves nothing half done that SQLite keeps
+        self.conn
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// One identicon's slot in the store
+pub struct Entry<'a> {
+    store: &'a Store,
+    hash: String,
+    params: String,
+}
+
+impl Entry<'_> {
+    /// The stored bytes, if this identicon was stored before
+    pub fn get(&self) -> Result<Option<Vec<u8>>> {
+        self.store
+            .conn()
+            .query_row(
+                "SELECT data FROM identicons WHERE hash = ?1 AND params = ?2",
+                params![self.hash, self.params],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to read from store")
+    }
+
+    pub fn put(&self, content_type: &str, data: &[u8]) -> Result<()> {
+        self.store
+            .conn()
+            .execute(
+                "INSERT OR REPLACE INTO identicons (hash, params, content_type, data)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![self.hash, self.params, content_type, data],
+            )
+            .context("Failed to write to store")?;
+        Ok(())
+    }
+}
+
+/// SHA-256 of a parts pack's manifest, and of the name, size, and
+/// modification time of each file in its layers, so any edit changes it
+fn parts_fingerprint(dir: &Path) -> Result<String> {
+    let failed = || format!("Failed to read parts pack {}", dir.display());
+    let mut hasher = Sha256::new();
+    match std::fs::read(dir.join(PARTS_MANIFEST)) {
+        Ok(manifest) => hasher.update(&manifest),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err).with_context(failed),
+    }
+
+    let mut files = Vec::new();
+    for layer in std::fs::read_dir(dir).with_context(failed)? {
+        let layer = layer.with_context(failed)?.path();
+        if !layer.is_dir() {
+            continue;
+        }
+        for part in std::fs::read_dir(&layer).with_context(failed)? {
+            let part = part.with_context(failed)?;
+            let metadata = part.metadata().with_context(failed)?;
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |since| since.as_nanos());
+            let name = part.path().strip_prefix(dir).map(Path::to_path_buf);
+            let name = name.unwrap_or_else(|_| part.path());
+            files.push((name, metadata.len(), modified));
+        }
+    }
+    files.sort();
+    for (name, len, modified) in files {
+        hasher.update(name.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(len.to_le_bytes());
+        hasher.update(modified.to_le_bytes());
+    }
+    Ok(input::hex(&hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn entry_params(store: &Store, options: &Options) -> String {
+        let encoding = Encoding::default();
+        let entry = store.entry(&[7; 32], options, Some(Format::Png), None, &encoding);
+        entry.unwrap().params
+    }
+
+    #[test]
+    fn entries_round_trip() {
+        let store = Store::open(Path::new(":memory:")).unwrap();
+        let options = Options::default();
+        let entry = store
+            .entry(
+                &[7; 32],
+                &options,
+                Some(Format::Png),
+                None,
+                &Encoding::default(),
+            )
+            .unwrap();
+        assert_eq!(entry.get().unwrap(), None);
+        entry.put("image/png", b"png bytes").unwrap();
+        assert_eq!(entry.get().unwrap().as_deref(), Some(&b"png bytes"[..]));
+        assert!(entry_params(&store, &options).contains(env!("CARGO_PKG_VERSION")));
+    }
+
+    #[test]
+    fn editing_a_parts_pack_changes_the_key() {
+        let dir = std::env::temp_dir().join(format!("hashdenticon-store-{}", std::process::id()));
+        let layer = dir.join("body");
+        std::fs::create_dir_all(&layer).unwrap();
+        std::fs::write(layer.join("a.png"), b"one").unwrap();
+
+        let store = Store::open(Path::new(":memory:")).unwrap();
+        let options = Options {
+            parts_dir: Some(PathBuf::from(&dir)),
+            ..Options::default()
+        };
+        let before = entry_params(&store, &options);
+        assert_eq!(entry_params(&store, &options), before);
+
+        std::fs::write(dir.join(PARTS_MANIFEST), "body\n").unwrap();
+        let manifest = entry_params(&store, &options);
+        assert_ne!(manifest, before);
+
+        std::fs::write(layer.join("b.png"), b"two").unwrap();
+        let added = entry_params(&store, &options);
+        assert_ne!(added, manifest);
+
+        std::fs::write(layer.join("b.png"), b"longer").unwrap();
+        assert_ne!(entry_params(&store, &options), added);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
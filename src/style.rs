@@ -0,0 +1,86 @@
+//! Rendering styles for the hashed seed
+
+pub mod automaton;
+pub mod bars;
+pub mod blob;
+pub mod invader;
+pub mod iso;
+pub mod maze;
+pub mod parts;
+pub mod randomart;
+pub mod rings;
+pub mod voronoi;
+
+/// Visual style used to render the identicon
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Style {
+    /// Mirrored grid of filled cells
+    #[default]
+    Grid,
+    /// OpenSSH drunken-bishop fingerprint art
+    Randomart,
+    /// Vertical color-coded bars, one per digest byte
+    Bars,
+    /// Filled cells extruded into shaded isometric cubes
+    Iso,
+    /// Smooth rounded contours traced over the grid
+    Blob,
+    /// Mirrored Voronoi regions around hash-placed points
+    Voronoi,
+    /// Concentric rings of filled arc segments
+    Rings,
+    /// Grid evolved by a cellular automaton for --steps generations
+    Automaton,
+    /// Mirrored perfect maze of walls and paths
+    Maze,
+    /// Symmetric 5x6 pixel monster with outline and eyes
+    Invader,
+    /// Layered PNG parts from a pack given by --parts-dir
+    Parts,
+}
+
+impl Style {
+    /// Whether the style draws the boolean cell pattern, and so can be
+    /// exported to and drawn from a pattern model
+    pub fn draws_pattern(self) -> bool {
+        matches!(
+            self,
+            Style::Grid | Style::Iso | Style::Blob | Style::Automaton
+        )
+    }
+}
+
+impl std::fmt::Display for Style {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Style::Grid => "grid",
+            Style::Randomart => "randomart",
+            Style::Bars => "bars",
+            Style::Iso => "iso",
+            Style::Blob => "blob",
+            Style::Voronoi => "voronoi",
+            Style::Rings => "rings",
+            Style::Automaton => "automaton",
+            Style::Maze => "maze",
+            Style::Invader => "invader",
+            Style::Parts => "parts",
+        })
+    }
+}
+
+#[cfg(all(test, feature = "clap"))]
+mod tests {
+    use super::*;
+    use clap::ValueEnum;
+
+    #[test]
+    fn styles_display_as_their_argument_names() {
+        for style in Style::value_variants() {
+            let value = style.to_possible_value().unwrap();
+            assert_eq!(style.to_string(), value.get_name());
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Life-like cellular automaton evolved from the hashed grid
+
+/// Birth and survival neighbour counts of a Life-like rule
+pub struct Rule {
+    pub name: &'static str,
+    birth: &'static [u8],
+    survive: &'static [u8],
+}
+
+// rules that stay lively on small grids; all are isotropic, so evolving a
+// mirrored grid keeps it mirrored
+const RULES: [Rule; 4] = [
+    Rule {
+        name: "B3/S23",
+        birth: &[3],
+        survive: &[2, 3],
+    },
+    Rule {
+        name: "B36/S23",
+        birth: &[3, 6],
+        survive: &[2, 3],
+    },
+    Rule {
+        name: "B3678/S34678",
+        birth: &[3, 6, 7, 8],
+        survive: &[3, 4, 6, 7, 8],
+    },
+    Rule {
+        name: "B3/S12345",
+        birth: &[3],
+        survive: &[1, 2, 3, 4, 5],
+    },
+];
+
+impl Rule {
+    /// Pick a rule deterministically from the last digest byte
+    pub fn from_hash(hash: &[u8]) -> &'static Rule {
+        let byte = hash.last().copied().unwrap_or(0);
+        &RULES[byte as usize % RULES.len()]
+    }
+
+    /// Advance one generation; cells outside the grid count as dead
+    pub fn step(&self, grid: &[Vec<bool>]) -> Vec<Vec<bool>> {
+        let size = grid.len() as i64;
+        let alive = |x: i64, y: i64| {
+            (0..size).contains(&x) && (0..size).contains(&y) && grid[y as usize][x as usize]
+        };
+
+        (0..size)
+            .map(|y| {
+                (0..size)
+                    .map(|x| {
+                        let mut neighbours = 0u8;
+                        for dy in -1..=1 {
+                            for dx in -1..=1 {
+                                if (dx, dy) != (0, 0) && alive(x + dx, y + dy) {
+                                    neighbours += 1;
+                                }
+                            }
+                        }
+
+                        if alive(x, y) {
+                            self.survive.contains(&neighbours)
+                        } else {
+                            self.birth.contains(&neighbours)
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Generation 0 through `steps`, inclusive
+    pub fn generations(&self, start: Vec<Vec<bool>>, steps: u32) -> Vec<Vec<Vec<bool>>> {
+        let mut generations = vec![start];
+        for _ in 0..steps {
+            let next = self.step(generations.last().expect("starts non-empty"));
+            generations.push(next);
+        }
+        generations
+    }
+
+    /// Only generation `steps`, keeping no more than one grid at a time
+    pub fn generation(&self, start: Vec<Vec<bool>>, steps: u32) -> Vec<Vec<bool>> {
+        (0..steps).fold(start, |grid, _| self.step(&grid))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_last_generation_is_the_one_kept() {
+        let start = vec![
+            vec![false, true, false, false],
+            vec![false, true, true, false],
+            vec![true, true, false, true],
+            vec![false, false, true, false],
+        ];
+        for rule in &RULES {
+            for steps in [0, 1, 5] {
+                let all = rule.generations(start.clone(), steps);
+                assert_eq!(all.len(), steps as usize + 1);
+                assert_eq!(&rule.generation(start.clone(), steps), all.last().unwrap());
+            }
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Color-coded fingerprint of vertical bars, one per digest byte
+
+use crate::color::hsl_to_rgb;
+use crate::draw::fill_rect;
+use image::RgbImage;
+
+/// Render the bars across a `width` x `height` image
+///
+/// Each byte picks a hue; bars share the width evenly, so the style stays
+/// legible at very wide, short sizes.
+pub fn render(hash: &[u8], img: &mut RgbImage, padding_percent: u32) {
+    let (width, height) = img.dimensions();
+
+    let x_padding = width * padding_percent / 100;
+    let y_padding = height * padding_percent / 100;
+    let drawable_width = width - 2 * x_padding;
+    let drawable_height = height - 2 * y_padding;
+
+    // cap the bar count so every bar is at least one pixel wide
+    let count = (hash.len() as u32).min(drawable_width).max(1);
+    let bar_width = drawable_width / count;
+    let x_offset = x_padding + (drawable_width - bar_width * count) / 2;
+
+    for (idx, &byte) in hash.iter().take(count as usize).enumerate() {
+        // alternate lightness so neighbouring bars with close hues stay distinct
+        let lightness = if idx % 2 == 0 { 110 } else { 150 };
+        let color = hsl_to_rgb(byte as u32 * 6, 170, lightness);
+
+        fill_rect(
+            img,
+            x_offset + idx as u32 * bar_width,
+            y_padding,
+            bar_width,
+            drawable_height,
+            color,
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! Smooth, rounded contours traced over the grid with marching squares
+
+use crate::draw::{SUBPIXEL, fill_polygon};
+use image::{Rgb, RgbImage};
+
+// lattice samples per grid cell
+const STEPS: i64 = 8;
+
+// field values are fixed point, 1.0 == ONE
+const ONE: i64 = 1 << 16;
+const THRESHOLD: i64 = ONE / 2;
+
+/// Render the pattern as merged metaball-like blobs
+///
+/// Every filled cell contributes a smooth falloff to a scalar field, and
+/// marching squares traces the field's iso-line with linear interpolation.
+/// Lone cells become circles and neighbours melt into rounded shapes.
+pub fn render(pattern: &[Vec<bool>], color: Rgb<u8>, img: &mut RgbImage, padding_percent: u32) {
+    let image_size = img.width();
+
+    // same geometry as the grid style, so blobs sit where the cells would
+    let grid_size = pattern.len() as i64;
+    let padding = (image_size * padding_percent / 100) as i64;
+    let drawable_size = image_size as i64 - 2 * padding;
+    let cell_size = drawable_size / grid_size;
+    let total_padding = padding + (drawable_size - cell_size * grid_size) / 2;
+
+    // the lattice extends one cell beyond the grid on every side
+    let samples = (grid_size + 2) * STEPS + 1;
+    let field = sample_field(pattern, samples);
+    let at = |i: i64, j: i64| field[(j * samples + i) as usize];
+
+    let origin = (total_padding - cell_size) * SUBPIXEL;
+    let to_subpixel = |lattice: i64| origin + lattice * cell_size * SUBPIXEL / STEPS;
+
+    for j in 0..samples - 1 {
+        for i in 0..samples - 1 {
+            // corners clockwise from top-left, in lattice units
+            let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+            let values = corners.map(|(ci, cj)| at(ci, cj));
+            if values.iter().all(|&v| v < THRESHOLD) {
+                continue;
+            }
+
+            // walk the square's outline, keeping inside corners and the
+            // interpolated crossings on edges that change sides
+            let mut polygon = Vec::with_capacity(8);
+            for k in 0..4 {
+                let (a, b) = (values[k], values[(k + 1) % 4]);
+                let (pa, pb) = (corners[k], corners[(k + 1) % 4]);
+                let pa = (to_subpixel(pa.0), to_subpixel(pa.1));
+                let pb = (to_subpixel(pb.0), to_subpixel(pb.1));
+
+                if a >= THRESHOLD {
+                    polygon.push(pa);
+                }
+                if (a >= THRESHOLD) != (b >= THRESHOLD) {
+                    let t_num = THRESHOLD - a;
+                    let t_den = b - a;
+                    polygon.push((
+                        pa.0 + (pb.0 - pa.0) * t_num / t_den,
+                        pa.1 + (pb.1 - pa.1) * t_num / t_den,
+                    ));
+                }
+            }
+
+            fill_polygon(img, &polygon, color);
+        }
+    }
+}
+
+/// Sum each filled cell's `(1 - d²/r²)²` falloff, with a radius of one cell
+fn sample_field(pattern: &[Vec<bool>], samples: i64) -> Vec<i64> {
+    let radius_sq = STEPS * STEPS;
+    let mut field = vec![0i64; (samples * samples) as usize];
+
+    for (y, row) in pattern.iter().enumerate() {
+        for (x, _) in row.iter().enumerate().filter(|(_, filled)| **filled) {
+            // cell center in lattice units, offset by the one-cell margin
+            let cx = (x as i64 + 1) * STEPS + STEPS / 2;
+            let cy = (y as i64 + 1) * STEPS + STEPS / 2;
+
+            for j in (cy - STEPS).max(0)..=(cy + STEPS).min(samples - 1) {
+                for i in (cx - STEPS).max(0)..=(cx + STEPS).min(samples - 1) {
+                    let dist_sq = (i - cx).pow(2) + (j - cy).pow(2);
+                    if dist_sq < radius_sq {
+                        let falloff = radius_sq - dist_sq;
+                        field[(j * samples + i) as usize] +=
+                            falloff * falloff * ONE / (radius_sq * radius_sq);
+                    }
+                }
+            }
+        }
+    }
+
+    field
+}
@@ -0,0 +1,124 @@
+//! Classic symmetric "pixel monster" sprites
+
+use crate::color::hsl_to_rgb;
+use crate::draw::{blend, fill_rect};
+use image::{Rgb, RgbImage};
+
+const WIDTH: usize = 5;
+const HEIGHT: usize = 6;
+
+// the eyes always sit on this row, one cell in from either side
+const EYE_ROW: usize = 2;
+const EYE_COLUMNS: [usize; 2] = [1, WIDTH - 2];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Cell {
+    Empty,
+    Body,
+    Eye,
+    Outline,
+}
+
+/// Render a 5x6 sprite with an outline, eyes, and an accent color
+pub fn render(hash: &[u8], color: Rgb<u8>, img: &mut RgbImage, padding_percent: u32) {
+    let sprite = outlined(&sprite_cells(hash));
+
+    // accent comes from the last digest byte so it varies independently
+    let accent_hue = hash.last().copied().unwrap_or(0) as u32 * 6;
+    let accent = hsl_to_rgb(accent_hue, 200, 150);
+    let outline = blend(color, Rgb([0, 0, 0]), 3, 5);
+
+    let image_size = img.width();
+    let padding = image_size * padding_percent / 100;
+    let drawable_size = image_size - 2 * padding;
+
+    // the outlined sprite is taller than wide; size cells by height
+    let rows = sprite.len() as u32;
+    let cols = sprite[0].len() as u32;
+    let cell_size = drawable_size / rows;
+    let x_offset = (image_size - cell_size * cols) / 2;
+    let y_offset = (image_size - cell_size * rows) / 2;
+
+    for (y, row) in sprite.iter().enumerate() {
+        for (x, &cell) in row.iter().enumerate() {
+            let fill = match cell {
+                Cell::Empty => continue,
+                Cell::Body => color,
+                Cell::Eye => accent,
+                Cell::Outline => outline,
+            };
+            fill_rect(
+                img,
+                x_offset + x as u32 * cell_size,
+                y_offset + y as u32 * cell_size,
+                cell_size,
+                cell_size,
+                fill,
+            );
+        }
+    }
+}
+
+/// Fill the left half from pattern bits and mirror it, then apply the eye
+/// heuristics so every sprite reads as a face
+fn sprite_cells(hash: &[u8]) -> [[Cell; WIDTH]; HEIGHT] {
+    // skip the first 3 bytes used for color
+    let mut bits = hash
+        .iter()
+        .skip(3)
+        .flat_map(|byte| (0..8).map(move |bit| (byte >> bit) & 1 == 1));
+
+    let mut cells = [[Cell::Empty; WIDTH]; HEIGHT];
+    for row in cells.iter_mut() {
+        for x in 0..WIDTH.div_ceil(2) {
+            if bits.next().unwrap_or(false) {
+                row[x] = Cell::Body;
+                row[WIDTH - 1 - x] = Cell::Body;
+            }
+        }
+    }
+
+    // eyes need a solid band of body around them, plus a spine to hang it on
+    cells[EYE_ROW] = [Cell::Body; WIDTH];
+    for row in cells.iter_mut().take(EYE_ROW + 2).skip(EYE_ROW - 1) {
+        row[WIDTH / 2] = Cell::Body;
+    }
+    for x in EYE_COLUMNS {
+        cells[EYE_ROW][x] = Cell::Eye;
+    }
+
+    cells
+}
+
+/// Grow the sprite by one cell on each side and outline every empty cell
+/// that touches the body
+fn outlined(cells: &[[Cell; WIDTH]; HEIGHT]) -> Vec<Vec<Cell>> {
+    let mut grid = vec![vec![Cell::Empty; WIDTH + 2]; HEIGHT + 2];
+    for (y, row) in cells.iter().enumerate() {
+        for (x, &cell) in row.iter().enumerate() {
+            grid[y + 1][x + 1] = cell;
+        }
+    }
+
+    let snapshot = grid.clone();
+    let is_sprite = |x: i64, y: i64| {
+        snapshot
+            .get(y as usize)
+            .and_then(|row| row.get(x as usize))
+            .is_some_and(|&cell| cell == Cell::Body || cell == Cell::Eye)
+    };
+
+    for (y, row) in grid.iter_mut().enumerate() {
+        for (x, cell) in row.iter_mut().enumerate() {
+            let (xi, yi) = (x as i64, y as i64);
+            let touches = [(0, -1), (0, 1), (-1, 0), (1, 0)]
+                .iter()
+                .any(|(dx, dy)| is_sprite(xi + dx, yi + dy));
+            if *cell == Cell::Empty && touches {
+                *cell = Cell::Outline;
+            }
+        }
+    }
+
+    grid
+}
@@ -0,0 +1,84 @@
+//! Isometric "voxel" cubes extruded from the filled grid cells
+
+use crate::draw::{SUBPIXEL, blend, fill_polygon};
+use image::{Rgb, RgbImage};
+
+/// Render each filled cell as a unit cube in a 2:1 isometric projection
+///
+/// Cubes are painted back to front so nearer faces overlap farther ones.
+pub fn render(
+    pattern: &[Vec<bool>],
+    color: Rgb<u8>,
+    hash: &[u8],
+    img: &mut RgbImage,
+    padding_percent: u32,
+) {
+    let image_size = img.width();
+
+    let grid_size = pattern.len() as i64;
+    let padding = (image_size * padding_percent / 100) as i64;
+    let drawable = image_size as i64 - 2 * padding;
+
+    // a tile spans `tile` horizontally and `tile / 2` vertically; the whole
+    // scene is grid_size tiles wide and (grid_size + 1) / 2 tiles tall
+    let tile = drawable * SUBPIXEL / grid_size;
+    let scene_height = (grid_size + 1) * tile / 2;
+    let origin_x = (padding + drawable / 2) * SUBPIXEL;
+    let origin_y = image_size as i64 * SUBPIXEL / 2 - scene_height / 2 + tile / 2;
+
+    let project = |gx: i64, gy: i64, gz: i64| {
+        (
+            origin_x + (gx - gy) * tile / 2,
+            origin_y + (gx + gy) * tile / 4 - gz * tile / 2,
+        )
+    };
+
+    // last byte decides which side the light comes from
+    let light_from_left = hash.last().is_some_and(|b| b & 1 == 0);
+    let top = blend(color, Rgb([255, 255, 255]), 2, 5);
+    let lit = color;
+    let shaded = blend(color, Rgb([0, 0, 0]), 2, 5);
+    let (left_face, right_face) = if light_from_left {
+        (lit, shaded)
+    } else {
+        (shaded, lit)
+    };
+
+    // back to front: cells nearer the viewer have a larger x + y
+    let mut cells: Vec<(i64, i64)> = pattern
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, filled)| **filled)
+                .map(move |(x, _)| (x as i64, y as i64))
+        })
+        .collect();
+    cells.sort_by_key(|&(x, y)| (x + y, x));
+
+    for (x, y) in cells {
+        let top_face = [
+            project(x, y, 1),
+            project(x + 1, y, 1),
+            project(x + 1, y + 1, 1),
+            project(x, y + 1, 1),
+        ];
+        let left = [
+            project(x, y + 1, 1),
+            project(x + 1, y + 1, 1),
+            project(x + 1, y + 1, 0),
+            project(x, y + 1, 0),
+        ];
+        let right = [
+            project(x + 1, y, 1),
+            project(x + 1, y + 1, 1),
+            project(x + 1, y + 1, 0),
+            project(x + 1, y, 0),
+        ];
+
+        fill_polygon(img, &left, left_face);
+        fill_polygon(img, &right, right_face);
+        fill_polygon(img, &top_face, top);
+    }
+}
@@ -0,0 +1,97 @@
+//! Mirrored perfect maze carved with a hash-seeded PRNG
+
+use crate::draw::{blend, fill_rect};
+use crate::rng::SplitMix64;
+use image::{Rgb, RgbImage};
+
+/// Render an `n` x `n` maze, `n` being `grid_size` rounded up to even
+///
+/// The left half is carved by a randomized depth-first search, mirrored onto
+/// the right half, and both halves are joined by a single passage across the
+/// middle, so the maze stays perfect: exactly one path between any two cells.
+pub fn render(
+    hash: &[u8],
+    grid_size: u32,
+    color: Rgb<u8>,
+    img: &mut RgbImage,
+    padding_percent: u32,
+) {
+    let half = grid_size.div_ceil(2) as usize;
+    let size = half * 2;
+    let mut rng = SplitMix64::from_bytes(hash);
+
+    // open[y][x] marks passable blocks on a (2n + 1)^2 block layout where
+    // cells sit at odd coordinates and walls between them at even ones
+    let blocks = 2 * size + 1;
+    let mut open = vec![vec![false; blocks]; blocks];
+    carve_half(&mut open, half, size, &mut rng);
+
+    for row in open.iter_mut() {
+        for x in 0..blocks / 2 {
+            row[blocks - 1 - x] = row[x];
+        }
+    }
+
+    // one door across the middle wall joins the two halves
+    let door_row = rng.below(size as u64) as usize;
+    open[2 * door_row + 1][blocks / 2] = true;
+
+    let wall = color;
+    let path = blend(Rgb([255, 255, 255]), color, 1, 6);
+    let image_size = img.width();
+
+    let padding = image_size * padding_percent / 100;
+    let drawable_size = image_size - 2 * padding;
+    let block_size = drawable_size / blocks as u32;
+    let offset = padding + (drawable_size - block_size * blocks as u32) / 2;
+
+    for (y, row) in open.iter().enumerate() {
+        for (x, &is_open) in row.iter().enumerate() {
+            fill_rect(
+                img,
+                offset + x as u32 * block_size,
+                offset + y as u32 * block_size,
+                block_size,
+                block_size,
+                if is_open { path } else { wall },
+            );
+        }
+    }
+}
+
+/// Iterative recursive-backtracker over the left `half` columns
+fn carve_half(open: &mut [Vec<bool>], half: usize, size: usize, rng: &mut SplitMix64) {
+    let mut visited = vec![vec![false; half]; size];
+    let mut stack = vec![(0usize, 0usize)];
+    visited[0][0] = true;
+    open[1][1] = true;
+
+    while let Some(&(x, y)) = stack.last() {
+        let mut neighbours = Vec::with_capacity(4);
+        if x > 0 && !visited[y][x - 1] {
+            neighbours.push((x - 1, y));
+        }
+        if x + 1 < half && !visited[y][x + 1] {
+            neighbours.push((x + 1, y));
+        }
+        if y > 0 && !visited[y - 1][x] {
+            neighbours.push((x, y - 1));
+        }
+        if y + 1 < size && !visited[y + 1][x] {
+            neighbours.push((x, y + 1));
+        }
+
+        if neighbours.is_empty() {
+            stack.pop();
+            continue;
+        }
+
+        let (nx, ny) = neighbours[rng.below(neighbours.len() as u64) as usize];
+        visited[ny][nx] = true;
+
+        // open the next cell and the wall block between the two cells
+        open[2 * ny + 1][2 * nx + 1] = true;
+        open[y + ny + 1][x + nx + 1] = true;
+        stack.push((nx, ny));
+    }
+}
@@ -0,0 +1,227 @@
+//! Avatars composited from a user-provided pack of layered PNG parts
+//!
+//! A pack is a directory with one subdirectory of PNGs per layer. The layer
+//! order comes from an optional `parts.txt` manifest, one layer per line,
+//! bottom first; a trailing `?` marks a layer that the hash may leave out:
+//!
+//! ```text
+//! # bottom to top
+//! body
+//! eyes
+//! mouth
+//! accessory?
+//! ```
+//!
+//! Without a manifest the layers are `body`, `eyes`, `mouth`, `accessory?`.
+//! Each layer is listed once, by the name of a subdirectory of the pack.
+
+use crate::error::{HashdenticonError, Result};
+use crate::resample::Filter;
+use crate::rng::SplitMix64;
+use image::imageops;
+use image::{DynamicImage, RgbImage};
+use std::path::{Path, PathBuf};
+
+const MANIFEST: &str = "parts.txt";
+const DEFAULT_LAYERS: &str = "body\neyes\nmouth\naccessory?\n";
+
+struct Layer {
+    name: String,
+    optional: bool,
+}
+
+/// Deterministically pick one part per layer and composite them in order
+/// over the canvas
+pub fn render(
+    hash: &[u8],
+    parts_dir: &Path,
+    img: &mut RgbImage,
+    padding_percent: u32,
+    filter: Filter,
+) -> Result<()> {
+    let manifest_path = parts_dir.join(MANIFEST);
+    let manifest = if manifest_path.exists() {
+        std::fs::read_to_string(&manifest_path).map_err(HashdenticonError::io(&manifest_path))?
+    } else {
+        DEFAULT_LAYERS.to_string()
+    };
+    let layers = parse_manifest(&manifest)?;
+    if layers.is_empty() {
+        return Err(HashdenticonError::InvalidParts(
+            "manifest lists no layers".to_string(),
+        ));
+    }
+
+    let image_size = img.width();
+    let padding = image_size * padding_percent / 100;
+    let drawable_size = image_size - 2 * padding;
+    let mut canvas = DynamicImage::from(img.clone()).into_rgba8();
+    let mut rng = SplitMix64::from_bytes(hash);
+
+    for layer in &layers {
+        let layer_dir = parts_dir.join(&layer.name);
+        let candidates = list_parts(&layer_dir)?;
+
+        // draw both values up front so one layer's choice never shifts the next
+        let pick = rng.next_u64();
+        let include = rng.next_u64() & 1 == 1;
+        if candidates.is_empty() {
+            if layer.optional {
+                continue;
+            }
+            return Err(HashdenticonError::InvalidParts(format!(
+                "layer '{}' has no PNG parts",
+                layer.name
+            )));
+        }
+        if layer.optional && !include {
+            continue;
+        }
+
+        let part_path = &candidates[(pick % candidates.len() as u64) as usize];
+        tracing::debug!(layer = %layer.name, part = %part_path.display(), "selected part");
+        let part = image::open(part_path)
+            .map_err(|source| HashdenticonError::LoadError {
+                path: part_path.clone(),
+                source,
+            })?
+            .into_rgba8();
+        let part = imageops::resize(&part, drawable_size, drawable_size, filter.filter_type());
+
+        imageops::overlay(&mut canvas, &part, padding as i64, padding as i64);
+    }
+
+    *img = DynamicImage::from(canvas).into_rgb8();
+    Ok(())
+}
+
+fn parse_manifest(manifest: &str) -> Result<Vec<Layer>> {
+    let mut layers: Vec<Layer> = Vec::new();
+    for (idx, line) in manifest.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let invalid = |reason: String| {
+            HashdenticonError::InvalidParts(format!("{} line {}: {}", MANIFEST, idx + 1, reason))
+        };
+        let (name, optional) = match line.strip_suffix('?') {
+            Some(name) => (name.trim(), true),
+            None => (line, false),
+        };
+        if name.is_empty() {
+            return Err(invalid("missing layer name".to_string()));
+        }
+        if name == "." || name == ".." || name.contains(['/', '\\']) {
+            return Err(invalid(format!(
+                "'{}' is not a directory in the pack",
+                name
+            )));
+        }
+        if layers.iter().any(|layer| layer.name == name) {
+            return Err(invalid(format!("layer '{}' is listed twice", name)));
+        }
+        layers.push(Layer {
+            name: name.to_string(),
+            optional,
+        });
+    }
+    Ok(layers)
+}
+
+/// PNG files in a layer directory, sorted so selection is stable
+fn list_parts(layer_dir: &Path) -> Result<Vec<PathBuf>> {
+    if !layer_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut parts: Vec<PathBuf> = std::fs::read_dir(layer_dir)
+        .map_err(HashdenticonError::io(layer_dir))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+        })
+        .collect();
+    parts.sort();
+
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layers(manifest: &str) -> Vec<(String, bool)> {
+        parse_manifest(manifest)
+            .unwrap()
+            .into_iter()
+            .map(|layer| (layer.name, layer.optional))
+            .collect()
+    }
+
+    fn reason(manifest: &str) -> String {
+        match parse_manifest(manifest) {
+            Err(HashdenticonError::InvalidParts(reason)) => reason,
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("expected {:?} to be refused", manifest),
+        }
+    }
+
+    #[test]
+    fn the_default_layers_parse() {
+        assert_eq!(
+            layers(DEFAULT_LAYERS),
+            [
+                ("body".to_string(), false),
+                ("eyes".to_string(), false),
+                ("mouth".to_string(), false),
+                ("accessory".to_string(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let manifest = "# bottom to top\n\n  body  # the base\n\t\n# eyes\nhat ?\n";
+        assert_eq!(
+            layers(manifest),
+            [("body".to_string(), false), ("hat".to_string(), true)]
+        );
+        assert!(layers("# nothing\n\n").is_empty());
+    }
+
+    #[test]
+    fn keeps_the_listed_order() {
+        let names: Vec<String> = layers("mouth\r\nbody\r\neyes\r\n")
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["mouth", "body", "eyes"]);
+    }
+
+    #[test]
+    fn rejects_duplicate_layers() {
+        assert_eq!(
+            reason("body\neyes\nbody?\n"),
+            "parts.txt line 3: layer 'body' is listed twice"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert_eq!(
+            reason("body\n  ?  # optional what?\n"),
+            "parts.txt line 2: missing layer name"
+        );
+        for name in ["..", "../secrets", "a/b", "a\\b", "."] {
+            assert_eq!(
+                reason(name),
+                format!(
+                    "parts.txt line 1: '{}' is not a directory in the pack",
+                    name
+                )
+            );
+        }
+    }
+}
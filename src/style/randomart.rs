@@ -0,0 +1,189 @@
+//! OpenSSH "drunken bishop" fingerprint visualization
+//!
+//! The bishop starts in the center of a 17x9 board and takes four diagonal
+//! steps per input byte, two bits per step (least significant first). Each
+//! square counts how often it was visited, which maps to a symbol.
+
+use crate::draw::{blend, fill_rect};
+use image::{Rgb, RgbImage};
+
+const FIELD_WIDTH: usize = 17;
+const FIELD_HEIGHT: usize = 9;
+
+// visit counts saturate at the last symbol, same as ssh-keygen
+const SYMBOLS: &[u8] = b" .o+=*BOX@%&#/^";
+const MAX_COUNT: u8 = (SYMBOLS.len() - 1) as u8;
+
+/// Board state after the bishop's walk
+pub struct Field {
+    counts: [[u8; FIELD_WIDTH]; FIELD_HEIGHT],
+    start: (usize, usize),
+    end: (usize, usize),
+}
+
+impl Field {
+    /// Walk the bishop across the board using every byte of `hash`
+    pub fn walk(hash: &[u8]) -> Self {
+        let mut counts = [[0u8; FIELD_WIDTH]; FIELD_HEIGHT];
+        let start = (FIELD_WIDTH / 2, FIELD_HEIGHT / 2);
+        let (mut x, mut y) = start;
+
+        for &byte in hash {
+            let mut input = byte;
+            for _ in 0..4 {
+                // low bit moves horizontally, high bit vertically; clamp to walls
+                x = if input & 0x1 != 0 {
+                    (x + 1).min(FIELD_WIDTH - 1)
+                } else {
+                    x.saturating_sub(1)
+                };
+                y = if input & 0x2 != 0 {
+                    (y + 1).min(FIELD_HEIGHT - 1)
+                } else {
+                    y.saturating_sub(1)
+                };
+
+                let count = &mut counts[y][x];
+                if *count < MAX_COUNT {
+                    *count += 1;
+                }
+
+                input >>= 2;
+            }
+        }
+
+        Self {
+            counts,
+            start,
+            end: (x, y),
+        }
+    }
+
+    /// Classic ASCII box, e.g. as printed by `ssh-keygen -lv`
+    pub fn to_ascii(&self, header: &str, footer: &str) -> String {
+        let mut out = border(header);
+        out.push('\n');
+
+        for (y, row) in self.counts.iter().enumerate() {
+            out.push('|');
+            for (x, &count) in row.iter().enumerate() {
+                let symbol = if (x, y) == self.end {
+                    b'E'
+                } else if (x, y) == self.start {
+                    b'S'
+                } else {
+                    SYMBOLS[count as usize]
+                };
+                out.push(symbol as char);
+            }
+            out.push_str("|\n");
+        }
+
+        out.push_str(&border(footer));
+        out
+    }
+
+    /// Heatmap of visit counts, shaded from white towards `color`
+    pub fn render(&self, color: Rgb<u8>, img: &mut RgbImage, padding_percent: u32) {
+        let white = Rgb([255, 255, 255]);
+        let image_size = img.width();
+
+        let padding = image_size * padding_percent / 100;
+        let drawable_size = image_size - (2 * padding);
+
+        // board is wider than tall; center it vertically
+        let cell_size = drawable_size / FIELD_WIDTH as u32;
+        let x_offset = padding + (drawable_size - cell_size * FIELD_WIDTH as u32) / 2;
+        let y_offset = (image_size - cell_size * FIELD_HEIGHT as u32) / 2;
+
+        for (y, row) in self.counts.iter().enumerate() {
+            for (x, &count) in row.iter().enumerate() {
+                let cell_color = if (x, y) == self.end {
+                    Rgb([30, 30, 30])
+                } else if (x, y) == self.start {
+                    blend(color, Rgb([0, 0, 0]), 1, 2)
+                } else if count > 0 {
+                    blend(white, color, count as u32, MAX_COUNT as u32)
+                } else {
+                    continue;
+                };
+
+                fill_rect(
+                    img,
+                    x_offset + x as u32 * cell_size,
+                    y_offset + y as u32 * cell_size,
+                    cell_size,
+                    cell_size,
+                    cell_color,
+                );
+            }
+        }
+    }
+}
+
+fn border(title: &str) -> String {
+    let title = if title.is_empty() {
+        String::new()
+    } else {
+        format!("[{}]", title)
+    };
+    let title: String = title.chars().take(FIELD_WIDTH).collect();
+    let title_len = title.chars().count();
+    let left = (FIELD_WIDTH - title_len) / 2;
+    let right = FIELD_WIDTH - title_len - left;
+
+    format!("+{}{}{}+", "-".repeat(left), title, "-".repeat(right))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_ssh_keygen() {
+        // `ssh-keygen -lv` of ssh-ed25519
+        // AAAAC3NzaC1lZDI1NTE5AAAAIAcJLRhjw/auPtHj8jylU1RBF+wG0Nd6B8nOQMLmLZja,
+        // SHA256:X5kuDf4JIhfAXXJVIxQAjyEg65A83bvjSqMP5bHjzjk
+        let digest = [
+            0x5f, 0x99, 0x2e, 0x0d, 0xfe, 0x09, 0x22, 0x17, 0xc0, 0x5d, 0x72, 0x55, 0x23, 0x14,
+            0x00, 0x8f, 0x21, 0x20, 0xeb, 0x90, 0x3c, 0xdd, 0xbb, 0xe3, 0x4a, 0xa3, 0x0f, 0xe5,
+            0xb1, 0xe3, 0xce, 0x39,
+        ];
+        let expected = "\
++--[ED25519 256]--+
+|  . ... +.++=oo  |
+|...o.. o B   . . |
+|oo.. .o o .      |
+| o.   ..     o   |
+|  .o .  S . +    |
+|  o o .  + =     |
+| . * o. o = o    |
+|  *E= .o . + .   |
+| .oBo.      o    |
++----[SHA256]-----+";
+        assert_eq!(
+            Field::walk(&digest).to_ascii("ED25519 256", "SHA256"),
+            expected
+        );
+    }
+
+    #[test]
+    fn visits_saturate_at_the_last_symbol() {
+        // up-left into the corner for 31 bytes, then down-right away from it
+        let mut digest = [0x00; 32];
+        digest[31] = 0xff;
+        let expected = "\
++-----------------+
+|^....            |
+| .   .           |
+|  .   .          |
+|   .   .         |
+|    E   S        |
+|                 |
+|                 |
+|                 |
+|                 |
++-----------------+";
+        assert_eq!(Field::walk(&digest).to_ascii("", ""), expected);
+    }
+}
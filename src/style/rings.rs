@@ -0,0 +1,195 @@
+//! Concentric rings of hash-selected arc segments
+//!
+//! Distances and angles are fixed point, from integer square roots and
+//! CORDIC, so the rings land on the same pixels on every platform.
+
+use crate::draw::blend;
+use image::{Rgb, RgbImage};
+
+// subsamples per pixel along each axis for antialiasing
+const SUPERSAMPLE: u32 = 4;
+
+/// Fractional bits of a pixel in distances
+const DISTANCE_BITS: u32 = 11;
+
+/// A full turn, 2 pi, with 16 fractional bits
+const TAU: u128 = 411_775;
+
+/// `atan(2^-i)` as a fraction of a turn, with 32 fractional bits
+const ATAN_TURNS: [u32; 31] = [
+    536_870_912,
+    316_933_406,
+    167_458_907,
+    85_004_756,
+    42_667_331,
+    21_354_465,
+    10_679_838,
+    5_340_245,
+    2_670_163,
+    1_335_087,
+    667_544,
+    333_772,
+    166_886,
+    83_443,
+    41_722,
+    20_861,
+    10_430,
+    5_215,
+    2_608,
+    1_304,
+    652,
+    326,
+    163,
+    81,
+    41,
+    20,
+    10,
+    5,
+    3,
+    1,
+    1,
+];
+
+/// Render `rings` concentric rings, innermost first, whose arc segments are
+/// filled by successive pattern bits
+///
+/// Ring `k` has `8 + 4k` segments. Only the left half of each ring takes
+/// bits; the right half mirrors it, like the grid style.
+pub fn render(hash: &[u8], rings: u32, color: Rgb<u8>, img: &mut RgbImage, padding_percent: u32) {
+    let image_size = img.width();
+
+    let segments = segment_bits(hash, rings);
+
+    let padding = image_size * padding_percent / 100;
+    let diameter = u64::from(image_size - 2 * padding);
+
+    // the innermost ring width is left as an empty hub
+    let ring_width = (diameter << DISTANCE_BITS) / (2 * u64::from(rings + 1));
+    let ring_gap = ring_width * 3 / 25;
+    let rings = Rings {
+        segments: &segments,
+        width: ring_width,
+        gap: ring_gap,
+    };
+
+    // subsample centers, in `2 * SUPERSAMPLE`ths of a pixel from the center
+    let scale = 2 * SUPERSAMPLE as i64;
+    let center = i64::from(image_size) * SUPERSAMPLE as i64;
+    let samples = SUPERSAMPLE * SUPERSAMPLE;
+    for py in 0..image_size {
+        for px in 0..image_size {
+            let mut covered = 0;
+            for sy in 0..SUPERSAMPLE {
+                for sx in 0..SUPERSAMPLE {
+                    let x = i64::from(px) * scale + 2 * i64::from(sx) + 1 - center;
+                    let y = i64::from(py) * scale + 2 * i64::from(sy) + 1 - center;
+                    if rings.is_filled(x, y, scale) {
+                        covered += 1;
+                    }
+                }
+            }
+
+            if covered > 0 {
+                let base = *img.get_pixel(px, py);
+                img.put_pixel(px, py, blend(base, color, covered, samples));
+            }
+        }
+    }
+}
+
+/// Fill flags per ring, mirrored so each ring reads the same left and right
+fn segment_bits(hash: &[u8], rings: u32) -> Vec<Vec<bool>> {
+    // skip the first 3 bytes used for color
+    let mut bits = hash
+        .iter()
+        .skip(3)
+        .flat_map(|byte| (0..8).map(move |bit| (byte >> bit) & 1 == 1));
+
+    (0..rings)
+        .map(|ring| {
+            let count = (8 + 4 * ring) as usize;
+            let half: Vec<bool> = (0..count / 2)
+                .map(|_| bits.next().unwrap_or(false))
+                .collect();
+
+            // segment i starts at angle i / count from the top, clockwise;
+            // segment count - 1 - i is its mirror across the vertical axis
+            let mut ring_bits = vec![false; count];
+            for (i, &filled) in half.iter().enumerate() {
+                ring_bits[i] = filled;
+                ring_bits[count - 1 - i] = filled;
+            }
+            ring_bits
+        })
+        .collect()
+}
+
+/// The layout of every ring, with lengths in pixels with
+/// [`DISTANCE_BITS`] fractional bits
+struct Rings<'a> {
+    segments: &'a [Vec<bool>],
+    width: u64,
+    gap: u64,
+}
+
+impl Rings<'_> {
+    /// Whether the point `x`, `y` from the center, in `scale`ths of a pixel,
+    /// is on a filled segment
+    fn is_filled(&self, x: i64, y: i64, scale: i64) -> bool {
+        let squared = (x * x + y * y) as u64;
+        let dist = (squared << (2 * DISTANCE_BITS)).isqrt() / scale as u64;
+
+        // ring index counted outward from the empty hub
+        let offset = dist / self.width;
+        if offset == 0 {
+            return false;
+        }
+        let Some(ring_bits) = self.segments.get(offset as usize - 1) else {
+            return false;
+        };
+        let within = dist % self.width;
+        if 2 * within < self.gap || 2 * within > 2 * self.width - self.gap {
+            return false;
+        }
+
+        // clockwise angle from the top, in turns with 32 fractional bits
+        let count = ring_bits.len() as u64;
+        let position = u64::from(turns(-y, x)) * count;
+        let segment = ((position >> 32) as usize).min(ring_bits.len() - 1);
+
+        // keep an arc gap about as wide as the ring gap: the arc from the
+        // segment's nearer edge, fraction * dist * tau / count, is at least
+        // half the gap
+        let within_segment = position & 0xffff_ffff;
+        let nearer = within_segment.min((1 << 32) - within_segment);
+        let arc = u128::from(nearer) * u128::from(dist) * TAU;
+        if 2 * arc < (u128::from(self.gap) * u128::from(count)) << 48 {
+            return false;
+        }
+
+        ring_bits[segment]
+    }
+}
+
+/// The angle of the vector `x`, `y` counterclockwise from the x axis, as a
+/// fraction of a turn with 32 fractional bits
+fn turns(x: i64, y: i64) -> u32 {
+    // headroom for the CORDIC gain, about 1.65
+    let (mut x, mut y) = (x << 30, y << 30);
+    let mut angle = 0u32;
+    if x < 0 {
+        (x, y) = (-x, -y);
+        angle = 1 << 31;
+    }
+    for (i, &step) in ATAN_TURNS.iter().enumerate() {
+        let (dx, dy) = (y >> i, x >> i);
+        if y > 0 {
+            (x, y) = (x + dx, y - dy);
+            angle = angle.wrapping_add(step);
+        } else {
+            (x, y) = (x - dx, y + dy);
+            angle = angle.wrapping_sub(step);
+        }
+    }
+    angle
+}
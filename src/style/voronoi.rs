@@ -0,0 +1,49 @@
+//! Mirrored Voronoi regions around hash-placed seed points
+
+use crate::color::hsl_to_rgb;
+use image::{Rgb, RgbImage};
+
+/// Render up to `max_points` seed points on the left half, mirrored onto the
+/// right, and color each pixel by its nearest point
+///
+/// Every point consumes three digest bytes: x, y, and hue.
+pub fn render(hash: &[u8], max_points: u32, img: &mut RgbImage, padding_percent: u32) {
+    let image_size = img.width();
+
+    let padding = image_size * padding_percent / 100;
+    let drawable_size = (image_size - 2 * padding) as i64;
+
+    let points = points(hash, max_points, drawable_size);
+    if points.is_empty() {
+        return;
+    }
+
+    for py in 0..drawable_size {
+        for px in 0..drawable_size {
+            // mirrored pairs share a color, so the regions come out symmetric
+            let nearest = points
+                .iter()
+                .min_by_key(|(x, y, _)| (px - x).pow(2) + (py - y).pow(2))
+                .map(|p| p.2)
+                .unwrap_or(Rgb([255, 255, 255]));
+            img.put_pixel(padding + px as u32, padding + py as u32, nearest);
+        }
+    }
+}
+
+/// The seed points within a `drawable_size` square, in the order that
+/// settles ties: each hash-placed point, then its mirror image
+pub(crate) fn points(hash: &[u8], max_points: u32, drawable_size: i64) -> Vec<(i64, i64, Rgb<u8>)> {
+    let half = drawable_size / 2;
+    let mut points = Vec::new();
+    for chunk in hash.chunks_exact(3).take(max_points as usize) {
+        let x = chunk[0] as i64 * half / 256;
+        let y = chunk[1] as i64 * drawable_size / 256;
+        let lightness = if chunk[2] & 1 == 0 { 120 } else { 165 };
+        let color = hsl_to_rgb(chunk[2] as u32 * 6, 150, lightness);
+
+        points.push((x, y, color));
+        points.push((drawable_size - 1 - x, y, color));
+    }
+    points
+}
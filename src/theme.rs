@@ -0,0 +1,255 @@
+//! Themes: the look of an identicon apart from what the hash decides, as
+//! one TOML document a team can share
+//!
+//! Every key is optional. With `--theme-file`, a theme's keys take
+//! precedence over a `--preset`, and flags given on the command line over
+//! both.
+//!
+//! ```toml
+//! background = "#f4f1ea"
+//! palette = ["#e63946", "#457b9d", "#2a9d8f"]
+//! cell_shape = "circle"
+//! gap = 10
+//! mask = "rounded"
+//! border = 6
+//! border_color = "#1d3557"
+//! ```
+
+use crate::draw::{self, SUBPIXEL};
+#[cfg(feature = "serde")]
+use crate::error::{HashdenticonError, Result};
+use image::{Rgb, RgbImage};
+#[cfg(feature = "serde")]
+use std::path::Path;
+
+pub use crate::color::parse_hex;
+
+/// Shape drawn for each filled cell of the grid and automaton styles
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum CellShape {
+    #[default]
+    Square,
+    Circle,
+    /// Square with corners rounded by a quarter of its side
+    Rounded,
+    Diamond,
+}
+
+/// Outline the finished identicon is cut to, the rest left as background
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Mask {
+    Circle,
+    /// Square with corners rounded by a fifth of its side
+    Rounded,
+}
+
+/// The keys of a theme file, each overriding the option of the same name
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    #[serde(with = "crate::color::hex::option")]
+    pub background: Option<Rgb<u8>>,
+    #[serde(with = "crate::color::hex::option_list")]
+    pub palette: Option<Vec<Rgb<u8>>>,
+    pub cell_shape: Option<CellShape>,
+    pub gap: Option<u32>,
+    pub mask: Option<Mask>,
+    pub border: Option<u32>,
+    #[serde(with = "crate::color::hex::option")]
+    pub border_color: Option<Rgb<u8>>,
+}
+
+/// Parse theme TOML
+#[cfg(feature = "serde")]
+pub fn parse(text: &str) -> Result<Theme> {
+    toml::from_str(text).map_err(|err| HashdenticonError::InvalidTheme(err.to_string()))
+}
+
+/// Read and parse a theme file
+#[cfg(feature = "serde")]
+pub fn load(path: &Path) -> Result<Theme> {
+    let text = std::fs::read_to_string(path).map_err(HashdenticonError::io(path))?;
+    parse(&text)
+}
+
+/// The palette color picked by the first two bytes of `hash`, if there is
+/// a palette
+pub fn pick(palette: &[Rgb<u8>], hash: &[u8]) -> Option<Rgb<u8>> {
+    if palette.is_empty() {
+        return None;
+    }
+    let index = u16::from_be_bytes([hash[0], hash[1]]) as usize % palette.len();
+    Some(palette[index])
+}
+
+/// Fill one cell of `size` pixels at (`x`, `y`), leaving `gap` percent of
+/// it empty around the shape
+pub(crate) fn draw_cell(
+    img: &mut RgbImage,
+    (x, y): (u32, u32),
+    size: u32,
+    shape: CellShape,
+    gap: u32,
+    color: Rgb<u8>,
+) {
+    let inset = size * gap / 200;
+    let (x, y, size) = (x + inset, y + inset, size - 2 * inset);
+    match shape {
+        CellShape::Square => draw::fill_rect(img, x, y, size, size, color),
+        CellShape::Circle => draw::fill_circle(img, x + size / 2, y + size / 2, size / 2, color),
+        CellShape::Rounded => fill_rounded(img, (x, y), (size, size), size / 4, color),
+        CellShape::Diamond => {
+            let (x, y, half) = (
+                x as i64 * SUBPIXEL,
+                y as i64 * SUBPIXEL,
+                size as i64 * SUBPIXEL / 2,
+            );
+            let points = [
+                (x + half, y),
+                (x + 2 * half, y + half),
+                (x + half, y + 2 * half),
+                (x, y + half),
+            ];
+            draw::fill_polygon(img, &points, color);
+        }
+    }
+}
+
+fn fill_rounded(
+    img: &mut RgbImage,
+    (x, y): (u32, u32),
+    (width, height): (u32, u32),
+    radius: u32,
+    color: Rgb<u8>,
+) {
+    for py in y..(y + height).min(img.height()) {
+        for px in x..(x + width).min(img.width()) {
+            let distance = edge_distance((px - x, py - y), (width, height), radius);
+            if distance <= 0 {
+                img.put_pixel(px, py, color);
+            }
+        }
+    }
+}
+
+/// Cut `img` to `mask`, painting what falls outside it `background`, and
+/// draw a `border` pixels wide just inside the outline
+pub fn frame(
+    img: &mut RgbImage,
+    mask: Option<Mask>,
+    border: u32,
+    border_color: Rgb<u8>,
+    background: Rgb<u8>,
+) {
+    if mask.is_none() && border == 0 {
+        return;
+    }
+    let (width, height) = img.dimensions();
+    let radius = match mask {
+        None => 0,
+        Some(Mask::Circle) => width.min(height) / 2,
+        Some(Mask::Rounded) => width.min(height) / 5,
+    };
+    for (px, py, pixel) in img.enumerate_pixels_mut() {
+        let distance = edge_distance((px, py), (width, height), radius);
+        if distance > 0 {
+            *pixel = background;
+        } else if distance > -2 * border as i64 {
+            *pixel = border_color;
+        }
+    }
+}
+
+/// Signed distance, in half pixels, from the center of pixel (`x`, `y`) to
+/// the outline of a `width` by `height` box with corners of `radius`;
+/// positive outside
+fn edge_distance((x, y): (u32, u32), (width, height): (u32, u32), radius: u32) -> i64 {
+    let (width, height, radius) = (width as i64, height as i64, 2 * radius as i64);
+    // doubled, so pixel centers land on integers
+    let dx = (2 * x as i64 + 1 - width).abs() - (width - radius);
+    let dy = (2 * y as i64 + 1 - height).abs() - (height - radius);
+    if dx <= 0 || dy <= 0 {
+        return dx.max(dy) - radius;
+    }
+    ceil_sqrt(dx * dx + dy * dy) - radius
+}
+
+/// Exact, so corners come out the same on every platform
+fn ceil_sqrt(value: i64) -> i64 {
+    let root = value.isqrt();
+    if root * root < value { root + 1 } else { root }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_key() {
+        let theme = parse(
+            r##"
+            background = "#f4f1ea"
+            palette = ["#e63946", "#457B9D"]
+            cell_shape = "circle"
+            gap = 10
+            mask = "rounded"
+            border = 6
+            border_color = "#1d3557"
+            "##,
+        )
+        .unwrap();
+        assert_eq!(
+            theme,
+            Theme {
+                background: Some(Rgb([0xf4, 0xf1, 0xea])),
+                palette: Some(vec![Rgb([0xe6, 0x39, 0x46]), Rgb([0x45, 0x7b, 0x9d])]),
+                cell_shape: Some(CellShape::Circle),
+                gap: Some(10),
+                mask: Some(Mask::Rounded),
+                border: Some(6),
+                border_color: Some(Rgb([0x1d, 0x35, 0x57])),
+            }
+        );
+    }
+
+    #[test]
+    fn every_key_is_optional() {
+        assert_eq!(parse("").unwrap(), Theme::default());
+        let theme = parse("gap = 5").unwrap();
+        assert_eq!(
+            theme,
+            Theme {
+                gap: Some(5),
+                ..Theme::default()
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_bad_themes() {
+        for text in [
+            "colour = \"#ffffff\"",
+            "background = \"white\"",
+            "background = \"#fff\"",
+            "palette = \"#ffffff\"",
+            "palette = [\"#ffffff\", \"nope\"]",
+            "cell_shape = \"hexagon\"",
+            "gap = -1",
+            "gap = \"10\"",
+            "background = \"#ffffff",
+        ] {
+            assert!(
+                matches!(parse(text), Err(HashdenticonError::InvalidTheme(_))),
+                "{}",
+                text
+            );
+        }
+    }
+}
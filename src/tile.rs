@@ -0,0 +1,39 @@
+//! Seamless tiles: patterns that continue across their own edges, and
+//! wallpapers built by repeating them
+
+use crate::style::Style;
+use image::{RgbImage, imageops};
+
+/// Whether `style` can be drawn as a seamless tile
+pub fn supports(style: Style) -> bool {
+    matches!(style, Style::Grid | Style::Automaton)
+}
+
+/// Mirror the top half of the rows onto the bottom, so that with the usual
+/// left-right mirroring every edge reflects into the next tile over
+pub fn wrap_symmetric(mut pattern: Vec<Vec<bool>>) -> Vec<Vec<bool>> {
+    let rows = pattern.len();
+    for y in 0..rows / 2 {
+        pattern[rows - 1 - y] = pattern[y].clone();
+    }
+    pattern
+}
+
+/// Trim the leftover border an unpadded grid leaves when the size isn't a
+/// whole number of cells, so the tile repeats without gaps
+pub fn trim(img: &RgbImage, grid_size: u32) -> RgbImage {
+    let side = img.width() / grid_size * grid_size;
+    let offset = (img.width() - side) / 2;
+    imageops::crop_imm(img, offset, offset, side, side).to_image()
+}
+
+/// Cover a `width` x `height` image with copies of `tile` from the top left
+pub fn repeat(tile: &RgbImage, width: u32, height: u32) -> RgbImage {
+    let mut out = RgbImage::new(width, height);
+    for y in (0..height).step_by(tile.height() as usize) {
+        for x in (0..width).step_by(tile.width() as usize) {
+            imageops::replace(&mut out, tile, x as i64, y as i64);
+        }
+    }
+    out
+}
@@ -0,0 +1,58 @@
+//! Rotating and mirroring the rendered identicon
+
+use crate::error::{HashdenticonError, Result};
+use image::{RgbImage, imageops};
+
+/// Mirror axis applied after rotation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Flip {
+    /// Mirror left to right
+    H,
+    /// Mirror top to bottom
+    V,
+}
+
+/// Where the rotation comes from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Orient {
+    /// The rotation given in the options
+    #[default]
+    Fixed,
+    /// A quarter turn count taken from the last byte of the hash
+    FromHash,
+}
+
+/// Degrees clockwise for `orient`, given the fixed `rotate` option
+pub fn rotation(orient: Orient, rotate: u32, hash: &[u8]) -> u32 {
+    match orient {
+        Orient::Fixed => rotate,
+        Orient::FromHash => 90 * (hash.last().copied().unwrap_or(0) & 3) as u32,
+    }
+}
+
+/// Rotate `img` clockwise by `degrees`, a multiple of 90, then flip it
+pub fn apply(img: RgbImage, degrees: u32, flip: Option<Flip>) -> Result<RgbImage> {
+    let img = match degrees % 360 {
+        0 => img,
+        90 => imageops::rotate90(&img),
+        180 => imageops::rotate180(&img),
+        270 => imageops::rotate270(&img),
+        _ => {
+            return Err(HashdenticonError::Unsupported(format!(
+                "Rotation of {} degrees is not a quarter turn",
+                degrees
+            )));
+        }
+    };
+    Ok(match flip {
+        Some(Flip::H) => imageops::flip_horizontal(&img),
+        Some(Flip::V) => imageops::flip_vertical(&img),
+        None => img,
+    })
+}
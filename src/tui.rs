@@ -0,0 +1,179 @@
+//! Interactive tweak mode: a live half-block preview in the terminal while
+//! keys adjust the options, which can then be exported as a preset
+
+use crate::RenderArgs;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use hashdenticon::background::BackgroundPattern;
+use hashdenticon::{Identicon, Input, MIN_SIZE, Style, preset};
+use image::imageops::{self, FilterType};
+use ratatui::DefaultTerminal;
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::Color;
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph};
+use std::path::Path;
+
+const KEYS: &[&str] = &[
+    "up/down     grid size",
+    "left/right  style",
+    "+/-         density",
+    "[/]         hue",
+    "p/P         padding",
+    "b           background",
+    "e           export preset",
+    "q           quit",
+];
+
+struct App<'a> {
+    input: &'a Input,
+    render: &'a mut RenderArgs,
+    preset_out: &'a Path,
+    status: String,
+}
+
+pub fn run(input: &Input, render: &mut RenderArgs, preset_out: &Path) -> Result<()> {
+    let mut app = App {
+        input,
+        render,
+        preset_out,
+        status: String::new(),
+    };
+    ratatui::run(|terminal| app.run(terminal))
+}
+
+impl App<'_> {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal
+                .draw(|frame| self.draw(frame))
+                .context("Failed to draw")?;
+
+            let Event::Key(key) = event::read().context("Failed to read input")? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            let render = &mut *self.render;
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Up => render.grid_size = (render.grid_size + 1).min(15),
+                KeyCode::Down => render.grid_size = render.grid_size.saturating_sub(1).max(3),
+                KeyCode::Right => render.style = cycle(Style::value_variants(), render.style, 1),
+                KeyCode::Left => render.style = cycle(Style::value_variants(), render.style, -1),
+                KeyCode::Char('+') | KeyCode::Char('=') => {
+                    render.density = Some(render.density.map_or(55, |d| (d + 5).min(100)));
+                }
+                KeyCode::Char('-') => {
+                    render.density = Some(render.density.map_or(45, |d| d.saturating_sub(5)));
+                }
+                KeyCode::Char(']') => render.hue_shift = (render.hue_shift + 15) % 360,
+                KeyCode::Char('[') => render.hue_shift = (render.hue_shift + 345) % 360,
+                KeyCode::Char('p') => render.padding = render.padding.saturating_sub(1),
+                KeyCode::Char('P') => render.padding = (render.padding + 1).min(25),
+                KeyCode::Char('b') => {
+                    let patterns = BackgroundPattern::value_variants();
+                    render.background_pattern = match render.background_pattern {
+                        None => patterns.first().copied(),
+                        Some(current) => patterns
+                            .iter()
+                            .position(|p| *p == current)
+                            .and_then(|idx| patterns.get(idx + 1))
+                            .copied(),
+                    };
+                }
+                KeyCode::Char('e') => self.status = self.export(),
+                _ => {}
+            }
+        }
+    }
+
+    fn export(&self) -> String {
+        let options = self.render.options(&self.input.label);
+        match std::fs::write(self.preset_out, preset::to_toml(&options)) {
+            Ok(()) => format!("Preset saved to: {}", self.preset_out.display()),
+            Err(err) => format!("Failed to save preset: {}", err),
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [preview, panel] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Length(34)]).areas(frame.area());
+
+        let block = Block::bordered().title(format!(" {} ", self.input.label));
+        let inner = block.inner(preview);
+        frame.render_widget(block, preview);
+        let error = self.draw_preview(frame, inner).err();
+
+        let render = &self.render;
+        let mut lines = vec![
+            Line::from(format!("style       {}", render.style)),
+            Line::from(format!("grid        {}", render.grid_size)),
+            Line::from(format!(
+                "density     {}",
+                render
+                    .density
+                    .map_or("hash bits".to_string(), |d| format!("{}%", d))
+            )),
+            Line::from(format!("hue shift   {}°", render.hue_shift)),
+            Line::from(format!("padding     {}%", render.padding)),
+            Line::from(format!(
+                "background  {}",
+                render
+                    .background_pattern
+                    .and_then(|p| p.to_possible_value())
+                    .map_or("plain".to_string(), |p| p.get_name().to_string())
+            )),
+            Line::from(""),
+        ];
+        lines.extend(KEYS.iter().map(|keys| Line::from(*keys)));
+        lines.push(Line::from(""));
+        if let Some(error) = error {
+            lines.push(Line::from(format!("{:#}", error)));
+        }
+        lines.push(Line::from(self.status.as_str()));
+
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" options ")),
+            panel,
+        );
+    }
+
+    /// Two image rows per terminal row, using the upper half block with the
+    /// top pixel as foreground and the bottom pixel as background
+    fn draw_preview(&self, frame: &mut Frame, area: Rect) -> Result<()> {
+        let side = u32::from(area.width.min(area.height * 2));
+        if side == 0 {
+            return Ok(());
+        }
+
+        let mut options = self.render.options(&self.input.label);
+        options.size = side.max(MIN_SIZE);
+        options.height = None;
+        let identicon = Identicon::new(&self.input.digest, &options)?;
+        let img = imageops::resize(identicon.as_rgb(), side, side, FilterType::Nearest);
+
+        let buffer = frame.buffer_mut();
+        for row in 0..side.div_ceil(2) {
+            for x in 0..side {
+                let top = img.get_pixel(x, row * 2);
+                let bottom = img.get_pixel(x, (row * 2 + 1).min(side - 1));
+                let cell = &mut buffer[(area.x + x as u16, area.y + row as u16)];
+                cell.set_symbol("▀")
+                    .set_fg(Color::Rgb(top[0], top[1], top[2]))
+                    .set_bg(Color::Rgb(bottom[0], bottom[1], bottom[2]));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The value `step` places away from `current`, wrapping around
+fn cycle<T: Copy + PartialEq>(values: &[T], current: T, step: isize) -> T {
+    let idx = values.iter().position(|v| *v == current).unwrap_or(0) as isize;
+    values[(idx + step).rem_euclid(values.len() as isize) as usize]
+}
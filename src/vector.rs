@@ -0,0 +1,87 @@
+//! Resolution-independent description of a rendered identicon as filled
+//! rectangles, for vector output formats
+
+use image::{Rgb, RgbImage};
+use std::collections::HashMap;
+
+/// Axis-aligned filled rectangle in pixel units, origin top left
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub color: Rgb<u8>,
+}
+
+/// An image as a background fill plus rectangles painted over it in order
+pub struct Scene {
+    pub width: u32,
+    pub height: u32,
+    pub background: Rgb<u8>,
+    pub rects: Vec<Rect>,
+}
+
+impl Scene {
+    /// Trace `img` into rectangles. Pixel-aligned styles such as the grid
+    /// come out as one rectangle per run of cells; anti-aliased styles
+    /// degrade to many small rectangles but stay exact.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn trace(img: &RgbImage) -> Scene {
+        let (width, height) = img.dimensions();
+
+        // the most common color becomes the background and is not traced
+        let mut counts: HashMap<Rgb<u8>, u32> = HashMap::new();
+        for pixel in img.pixels() {
+            *counts.entry(*pixel).or_default() += 1;
+        }
+        let background = counts
+            .into_iter()
+            .max_by_key(|&(color, count)| (count, color.0))
+            .map_or(Rgb([255, 255, 255]), |(color, _)| color);
+
+        // horizontal runs, each merged into an identical run directly above
+        let mut rects: Vec<Rect> = Vec::new();
+        let mut open: HashMap<(u32, u32), usize> = HashMap::new();
+        for y in 0..height {
+            let mut next_open = HashMap::new();
+            let mut x = 0;
+            while x < width {
+                let color = *img.get_pixel(x, y);
+                let start = x;
+                while x < width && *img.get_pixel(x, y) == color {
+                    x += 1;
+                }
+                if color == background {
+                    continue;
+                }
+
+                let run = (start, x - start);
+                match open.get(&run) {
+                    Some(&idx) if rects[idx].color == color => {
+                        rects[idx].height += 1;
+                        next_open.insert(run, idx);
+                    }
+                    _ => {
+                        next_open.insert(run, rects.len());
+                        rects.push(Rect {
+                            x: start,
+                            y,
+                            width: run.1,
+                            height: 1,
+                            color,
+                        });
+                    }
+                }
+            }
+            open = next_open;
+        }
+
+        Scene {
+            width,
+            height,
+            background,
+            rects,
+        }
+    }
+}
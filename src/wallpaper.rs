@@ -0,0 +1,64 @@
+//! Desktop-resolution backgrounds built from an identicon
+
+mod blur;
+
+use crate::error::Result;
+use crate::identicon::{Identicon, Options};
+use crate::tile;
+use image::RgbImage;
+use image::imageops;
+
+/// How the identicon fills the wallpaper
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Mode {
+    /// Copies at the identicon size, seamless where the style allows
+    #[default]
+    Repeat,
+    /// One identicon enlarged to cover the whole screen
+    Scale,
+    /// The enlarged identicon, softly blurred
+    Blur,
+}
+
+/// Render a `width` x `height` wallpaper from the identicon of `hash`
+pub fn render(
+    hash: &[u8],
+    options: &Options,
+    width: u32,
+    height: u32,
+    mode: Mode,
+) -> Result<RgbImage> {
+    if mode == Mode::Repeat {
+        let options = Options {
+            height: None,
+            tileable: tile::supports(options.style),
+            tile: Some((width, height)),
+            ..options.clone()
+        };
+        return Ok(Identicon::new(hash, &options)?.into_rgb());
+    }
+
+    // drawn at full size rather than upscaled, so edges stay crisp
+    let side = width.max(height);
+    let options = Options {
+        size: side,
+        height: None,
+        ..options.clone()
+    };
+    let cover = Identicon::new(hash, &options)?.into_rgb();
+    let cropped = imageops::crop_imm(
+        &cover,
+        (side - width) / 2,
+        (side - height) / 2,
+        width,
+        height,
+    )
+    .to_image();
+
+    if mode == Mode::Scale {
+        return Ok(cropped);
+    }
+
+    Ok(blur::blur(&cropped, width, height))
+}
@@ -0,0 +1,19 @@
+//! The softened backdrop of `Mode::Blur`, kept apart from the rest of the
+//! wallpaper code because the blur radius is a float; the identicons of the
+//! other modes are drawn without one
+
+use image::RgbImage;
+use image::imageops::{self, FilterType};
+
+/// `cropped` blurred softly, at `width` x `height`
+pub fn blur(cropped: &RgbImage, width: u32, height: u32) -> RgbImage {
+    // a blur this wide loses nothing at an eighth of the size, and is far cheaper there
+    let small = imageops::resize(
+        cropped,
+        (width / 8).max(1),
+        (height / 8).max(1),
+        FilterType::Triangle,
+    );
+    let blurred = imageops::fast_blur(&small, width.min(height) as f32 / 240.0);
+    imageops::resize(&blurred, width, height, FilterType::Triangle)
+}
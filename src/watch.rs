@@ -0,0 +1,110 @@
+//! `batch --watch`: keep a directory of identicons in sync with a seeds file
+
+use crate::batch;
+use crate::{NameHash, OutputArgs, RenderArgs, generate, output_path};
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Generate identicons for new or edited lines of `seeds` whenever it changes,
+/// and remove those of lines that went away, in the output directory until
+/// interrupted
+pub fn run(seeds: &Path, output: &OutputArgs, render: &RenderArgs) -> Result<()> {
+    let dir = batch::output_dir(output)?;
+
+    println!("Watching {} for changes", seeds.display());
+    let mut generated = existing(seeds, &batch::seed_output(&dir, output), render);
+    let mut last_modified = None;
+    loop {
+        // editors often replace the file on save, so it can briefly vanish
+        let modified = std::fs::metadata(seeds)
+            .and_then(|meta| meta.modified())
+            .ok();
+        if modified.is_some() && modified != last_modified {
+            last_modified = modified;
+            sync(seeds, &dir, &mut generated, output, render)?;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// The seeds already in the output directory from an earlier run, so that
+/// starting the watch doesn't render them all again; writes are atomic, so
+/// an existing file is a finished one
+fn existing(seeds: &Path, output: &OutputArgs, render: &RenderArgs) -> HashMap<String, PathBuf> {
+    // content-hashed names are only known after rendering
+    if output.name_by_hash == Some(NameHash::Content) {
+        return HashMap::new();
+    }
+    let Ok(contents) = std::fs::read_to_string(seeds) else {
+        return HashMap::new();
+    };
+    batch::lines(&contents)
+        .map(|seed| (seed, output_path(&render.file_label(seed), output)))
+        .filter(|(_, path)| path.exists())
+        .map(|(seed, path)| (seed.to_string(), path))
+        .collect()
+}
+
+/// Generate every seed that hasn't been yet, or whose file has gone missing,
+/// and remove the files of seeds no longer listed
+fn sync(
+    seeds: &Path,
+    dir: &Path,
+    generated: &mut HashMap<String, PathBuf>,
+    output: &OutputArgs,
+    render: &RenderArgs,
+) -> Result<()> {
+    let contents = std::fs::read_to_string(seeds)
+        .with_context(|| format!("Failed to read {}", seeds.display()))?;
+
+    let output = &batch::seed_output(dir, output);
+    let mut count = 0;
+    for seed in batch::lines(&contents) {
+        if generated.get(seed).is_some_and(|path| path.exists()) {
+            continue;
+        }
+
+        // one bad seed shouldn't stop the watch
+        match generate(&render.input(seed)?, output, render) {
+            Ok((path, _)) => {
+                generated.insert(seed.to_string(), path);
+                count += 1;
+            }
+            Err(err) => eprintln!("Failed to generate {}: {:#}", seed, err),
+        }
+    }
+
+    // a file another listed seed also names is still wanted
+    let listed: HashSet<&str> = batch::lines(&contents).collect();
+    let kept: HashSet<PathBuf> = generated
+        .iter()
+        .filter(|(seed, _)| listed.contains(seed.as_str()))
+        .map(|(_, path)| path.clone())
+        .collect();
+    let mut removed = 0;
+    generated.retain(|seed, path| {
+        if listed.contains(seed.as_str()) {
+            return true;
+        }
+        if !kept.contains(path) {
+            match std::fs::remove_file(&*path) {
+                Ok(()) => removed += 1,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => eprintln!("Failed to remove {}: {}", path.display(), err),
+            }
+        }
+        false
+    });
+
+    if count > 0 {
+        println!("{} identicons updated", count);
+    }
+    if removed > 0 {
+        println!("{} identicons removed", removed);
+    }
+    Ok(())
+}
@@ -0,0 +1,196 @@
+//! Zip archives of stored entries, for handing back several images at once;
+//! the images are compressed already, so entries are not deflated again.
+//! There is no Zip64, so an archive that outgrows the classic format's
+//! fields fails instead of coming out corrupt
+
+use anyhow::{Result, anyhow};
+
+/// Entries are stamped 1980-01-01, the earliest date zip can record
+const DOS_DATE: u16 = (1 << 5) | 1;
+/// Names are UTF-8
+const UTF8_FLAG: u16 = 1 << 11;
+
+#[derive(Default)]
+pub struct Zip {
+    bytes: Vec<u8>,
+    directory: Vec<u8>,
+    entries: u16,
+}
+
+impl Zip {
+    pub fn new() -> Zip {
+        Zip::default()
+    }
+
+    pub fn add(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let offset = fit32(self.bytes.len(), "Archive")?;
+        let size = fit32(data.len(), name)?;
+        let name_len = fit16(name.len(), "File name")?;
+        let entries = fit16(usize::from(self.entries) + 1, "Entry count")?;
+        let crc = crc32fast::hash(data);
+
+        let bytes = &mut self.bytes;
+        put32(bytes, 0x0403_4b50);
+        put16(bytes, 20);
+        put16(bytes, UTF8_FLAG);
+        put16(bytes, 0); // stored
+        put16(bytes, 0);
+        put16(bytes, DOS_DATE);
+        put32(bytes, crc);
+        put32(bytes, size);
+        put32(bytes, size);
+        put16(bytes, name_len);
+        put16(bytes, 0);
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.extend_from_slice(data);
+
+        let directory = &mut self.directory;
+        put32(directory, 0x0201_4b50);
+        put16(directory, 20);
+        put16(directory, 20);
+        put16(directory, UTF8_FLAG);
+        put16(directory, 0);
+        put16(directory, 0);
+        put16(directory, DOS_DATE);
+        put32(directory, crc);
+        put32(directory, size);
+        put32(directory, size);
+        put16(directory, name_len);
+        put16(directory, 0);
+        put16(directory, 0);
+        put16(directory, 0);
+        put16(directory, 0);
+        put32(directory, 0);
+        put32(directory, offset);
+        directory.extend_from_slice(name.as_bytes());
+
+        self.entries = entries;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<Vec<u8>> {
+        let offset = fit32(self.bytes.len(), "Archive")?;
+        let size = fit32(self.directory.len(), "Central directory")?;
+        self.bytes.append(&mut self.directory);
+
+        let bytes = &mut self.bytes;
+        put32(bytes, 0x0605_4b50);
+        put16(bytes, 0);
+        put16(bytes, 0);
+        put16(bytes, self.entries);
+        put16(bytes, self.entries);
+        put32(bytes, size);
+        put32(bytes, offset);
+        put16(bytes, 0);
+        Ok(self.bytes)
+    }
+}
+
+/// `value` as a 16-bit field, short of the all-ones value Zip64 reserves
+fn fit16(value: usize, what: &str) -> Result<u16> {
+    u16::try_from(value)
+        .ok()
+        .filter(|&value| value != u16::MAX)
+        .ok_or_else(|| anyhow!("{} is too large for a zip archive: {}", what, value))
+}
+
+/// `value` as a 32-bit field, short of the all-ones value Zip64 reserves
+fn fit32(value: usize, what: &str) -> Result<u32> {
+    u32::try_from(value)
+        .ok()
+        .filter(|&value| value != u32::MAX)
+        .ok_or_else(|| anyhow!("{} is too large for a zip archive: {} bytes", what, value))
+}
+
+fn put16(bytes: &mut Vec<u8>, value: u16) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get16(bytes: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes(bytes[at..at + 2].try_into().unwrap())
+    }
+
+    fn get32(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn archives_read_back() {
+        let files: [(&str, &[u8]); 3] = [
+            ("alice.png", b"first image"),
+            ("bob.png", b""),
+            ("caf\u{e9}.png", b"third"),
+        ];
+        let mut zip = Zip::new();
+        for (name, data) in files {
+            zip.add(name, data).unwrap();
+        }
+        let archive = zip.finish().unwrap();
+
+        let eocd = archive.len() - 22;
+        assert_eq!(get32(&archive, eocd), 0x0605_4b50);
+        assert_eq!(get16(&archive, eocd + 8), files.len() as u16);
+        assert_eq!(get16(&archive, eocd + 10), files.len() as u16);
+        let directory_size = get32(&archive, eocd + 12) as usize;
+        let directory = get32(&archive, eocd + 16) as usize;
+        assert_eq!(directory + directory_size, eocd);
+
+        let mut at = directory;
+        for (name, data) in files {
+            assert_eq!(get32(&archive, at), 0x0201_4b50);
+            let crc = get32(&archive, at + 16);
+            assert_eq!(crc, crc32fast::hash(data));
+            assert_eq!(get32(&archive, at + 20) as usize, data.len());
+            let name_len = get16(&archive, at + 28) as usize;
+            assert_eq!(&archive[at + 46..at + 46 + name_len], name.as_bytes());
+
+            let local = get32(&archive, at + 42) as usize;
+            assert_eq!(get32(&archive, local), 0x0403_4b50);
+            assert_eq!(get32(&archive, local + 14), crc);
+            assert_eq!(get32(&archive, local + 18) as usize, data.len());
+            assert_eq!(get16(&archive, local + 26) as usize, name_len);
+            let start = local + 30 + name_len;
+            assert_eq!(&archive[local + 30..start], name.as_bytes());
+            assert_eq!(&archive[start..start + data.len()], data);
+
+            at += 46 + name_len;
+        }
+        assert_eq!(at, eocd);
+    }
+
+    #[test]
+    fn an_empty_archive_is_only_its_end_record() {
+        let archive = Zip::new().finish().unwrap();
+        assert_eq!(archive.len(), 22);
+        assert_eq!(get16(&archive, 10), 0);
+    }
+
+    #[test]
+    fn overflowing_fields_are_refused() {
+        let mut zip = Zip::new();
+        assert!(zip.add(&"a".repeat(usize::from(u16::MAX)), b"").is_err());
+        assert!(zip.add(&"a".repeat(usize::from(u16::MAX) - 1), b"").is_ok());
+
+        assert!(fit32(u32::MAX as usize, "Archive").is_err());
+        assert!(fit32(u32::MAX as usize + 1, "Archive").is_err());
+        assert_eq!(
+            fit32(u32::MAX as usize - 1, "Archive").unwrap(),
+            u32::MAX - 1
+        );
+
+        let mut zip = Zip::new();
+        for i in 0..u16::MAX - 1 {
+            zip.add(&i.to_string(), b"").unwrap();
+        }
+        assert!(zip.add("one too many", b"").is_err());
+        assert_eq!(zip.entries, u16::MAX - 1);
+    }
+}
@@ -0,0 +1,158 @@
+//! Rendering never touches floating point, so a seed renders to the same
+//! pixels, and so the same PNG bytes, on x86, ARM, and WASM alike
+//!
+//! Float math is exact for `+`, `-`, `*`, `/`, and `sqrt`, but `sin`,
+//! `atan2`, and the like come from each platform's math library and may
+//! round differently. Rather than tell the two apart, the sources of
+//! everything that draws stay free of `f32` and `f64` and of float literals
+//! altogether; the golden images pin down the pixels themselves.
+//!
+//! This reads this crate's own source text, so it can't see float math
+//! inside dependencies. Two paths lean on `image`'s floating-point resize
+//! and are outside the guarantee: parts pack images scaled by other than a
+//! whole factor, and the blurred backdrop of `wallpaper --mode blur`. Their
+//! golden images only hold on the platform they were made on.
+
+use std::path::{Path, PathBuf};
+
+/// Sources that use floats outside of rendering, and why that is safe
+const ALLOWED: &[(&str, &str)] = &[
+    ("decode.rs", "reports the share of pixels that differ"),
+    ("dedup.rs", "reports how alike two identicons look"),
+    ("gallery.rs", "writes CSS lengths for the gallery page"),
+    ("neighbors.rs", "reports how alike two identicons look"),
+    (
+        "output/pdf.rs",
+        "writes colors as decimal operands, formatted the same everywhere",
+    ),
+    (
+        "output/webp.rs",
+        "hands lossy webp its quality, which libwebp takes as a float",
+    ),
+    (
+        "serve/grpc.rs",
+        "reads options from protobuf Structs, which hold every number as a double",
+    ),
+    (
+        "wallpaper/blur.rs",
+        "blurs the backdrop of --mode blur, outside the guarantee",
+    ),
+];
+
+fn sources(dir: &Path, found: &mut Vec<PathBuf>) {
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            sources(&path, found);
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            found.push(path);
+        }
+    }
+}
+
+/// Whether `line` names a float type as a word of its own, so `as_secs_f64`
+/// doesn't count, or holds a float literal such as `0.5` or `2f64`
+fn names_float(line: &str) -> bool {
+    let code = line.split("//").next().unwrap_or_default();
+    let word = |c: char| c.is_alphanumeric() || c == '_';
+    let names_type = ["f32", "f64"].iter().any(|float| {
+        code.match_indices(float).any(|(at, _)| {
+            let before = code[..at].chars().next_back();
+            let after = code[at + float.len()..].chars().next().is_some_and(word);
+            // a suffix ends a number, as in `2f64` or `1_f32`
+            let suffix = code[..at]
+                .trim_end_matches('_')
+                .rsplit(|c: char| !word(c))
+                .next()
+                .is_some_and(|number| number.starts_with(|c: char| c.is_ascii_digit()));
+            !after && (!before.is_some_and(word) || suffix)
+        })
+    });
+    names_type || has_float_literal(code)
+}
+
+/// Whether `code` has a number with a fractional part, like `0.0`; tuple
+/// fields like `pair.0.1` and versions in strings don't count
+fn has_float_literal(code: &str) -> bool {
+    let mut in_string = false;
+    let chars: Vec<char> = code.chars().collect();
+    for (at, &c) in chars.iter().enumerate() {
+        if c == '"' && chars.get(at.wrapping_sub(1)) != Some(&'\\') {
+            in_string = !in_string;
+        }
+        if in_string || c != '.' || at == 0 {
+            continue;
+        }
+        let digits_before = chars[..at]
+            .iter()
+            .rev()
+            .take_while(|c| c.is_ascii_digit() || **c == '_')
+            .count();
+        let start = at - digits_before;
+        let number_starts = digits_before > 0
+            && chars[start].is_ascii_digit()
+            && !(start > 0
+                && (chars[start - 1].is_alphanumeric() || "_.".contains(chars[start - 1])));
+        if number_starts && chars.get(at + 1).is_some_and(char::is_ascii_digit) {
+            return true;
+        }
+    }
+    false
+}
+
+#[test]
+fn rendering_is_float_free() {
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+    let mut files = Vec::new();
+    sources(&src, &mut files);
+    files.sort();
+
+    let mut uses = Vec::new();
+    for path in files {
+        let name = path
+            .strip_prefix(&src)
+            .unwrap()
+            .to_string_lossy()
+            .replace('\\', "/");
+        if ALLOWED.iter().any(|&(allowed, _)| allowed == name) {
+            continue;
+        }
+        let text = std::fs::read_to_string(&path).unwrap();
+        // test modules close each file and never render for real
+        let lines = text
+            .lines()
+            .take_while(|line| line.trim() != "#[cfg(test)]");
+        for (number, line) in lines.enumerate() {
+            if names_float(line) {
+                uses.push(format!("src/{}:{}: {}", name, number + 1, line.trim()));
+            }
+        }
+    }
+
+    assert!(
+        uses.is_empty(),
+        "floats would make renders differ between platforms; use integer or fixed-point math, or add the file to ALLOWED if it doesn't render:\n  {}",
+        uses.join("\n  ")
+    );
+}
+
+#[test]
+fn floats_are_found_however_written() {
+    for line in [
+        "let x: f32 = 1;",
+        "let y = 2f64.powi(53);",
+        "let z = 1_f32;",
+        "if n.fract() == 0.0 {",
+        "scale * 1.5",
+    ] {
+        assert!(names_float(line), "{}", line);
+    }
+    for line in [
+        "let secs = elapsed.as_secs_f64();",
+        "let second = pair.0.1;",
+        "let version = \"1.2.3\";",
+        "let x = 1; // about 0.5",
+    ] {
+        assert!(!names_float(line), "{}", line);
+    }
+}
@@ -0,0 +1,195 @@
+//! Pixel-exact snapshots of a fixed matrix of identicons
+//!
+//! Every (seed, style, size) combination, plus a few option variants, is
+//! rendered and compared against the PNG of the same name in
+//! `tests/golden/`, as are resizes by other than a whole factor, which go
+//! through `image`'s floating-point resize and so only hold on the platform
+//! the golden images were made on. Pixels are compared, not file bytes, so encoder tuning
+//! never counts as drift.
+//!
+//! After an intended change to the output, regenerate the files and review
+//! the difference in the images before committing them:
+//!
+//! ```text
+//! UPDATE_GOLDEN=1 cargo test --test golden
+//! ```
+
+use clap::ValueEnum;
+use hashdenticon::derive::Derivation;
+use hashdenticon::resample::{self, Filter};
+use hashdenticon::theme::{CellShape, Mask};
+use hashdenticon::{Identicon, Options, Style};
+use image::RgbImage;
+use std::path::{Path, PathBuf};
+
+const SEEDS: &[&str] = &["alice", "bob@example.com", "0"];
+const SIZES: &[u32] = &[64, 200];
+
+/// Options beyond style and size whose output also has to stay put
+fn variants() -> Vec<(&'static str, Options)> {
+    vec![
+        (
+            "circle-cells",
+            Options {
+                cell_shape: CellShape::Circle,
+                gap: 10,
+                ..Options::default()
+            },
+        ),
+        (
+            "supersampled-blob",
+            Options {
+                style: Style::Blob,
+                supersample: 4,
+                ..Options::default()
+            },
+        ),
+        (
+            "hkdf",
+            Options {
+                derivation: Derivation::Hkdf,
+                ..Options::default()
+            },
+        ),
+        (
+            "tileable",
+            Options {
+                tileable: true,
+                ..Options::default()
+            },
+        ),
+        (
+            "circle-mask-border",
+            Options {
+                mask: Some(Mask::Circle),
+                border: 4,
+                ..Options::default()
+            },
+        ),
+    ]
+}
+
+/// Every case: the file name of its golden image, seed, and options
+fn cases() -> Vec<(String, &'static str, Options)> {
+    let mut cases = Vec::new();
+    // parts draws from a pack on disk, not from the seed alone
+    let styles = Style::value_variants()
+        .iter()
+        .filter(|&&style| style != Style::Parts);
+    for &style in styles {
+        for &size in SIZES {
+            for (index, &seed) in SEEDS.iter().enumerate() {
+                let options = Options {
+                    style,
+                    size,
+                    ..Options::default()
+                };
+                cases.push((format!("{}-{}-seed{}", style, size, index), seed, options));
+            }
+        }
+    }
+    for (name, options) in variants() {
+        for (index, &seed) in SEEDS.iter().enumerate() {
+            cases.push((format!("{}-seed{}", name, index), seed, options.clone()));
+        }
+    }
+    cases
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+/// Where the actual image of a failing case is left for inspection
+fn actual_dir() -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden-actual")
+}
+
+#[test]
+fn renders_match_golden_images() {
+    let mut failures = Vec::new();
+    for (name, seed, options) in cases() {
+        let actual = Identicon::from_seed(seed, &options)
+            .unwrap_or_else(|err| panic!("{}: {}", name, err))
+            .into_rgb();
+        check(&name, &actual, &mut failures);
+    }
+    report(&failures);
+}
+
+#[test]
+fn uneven_resizes_match_golden_images() {
+    let options = Options {
+        style: Style::Rings,
+        size: 200,
+        ..Options::default()
+    };
+    let source = Identicon::from_seed(SEEDS[0], &options).unwrap().into_rgb();
+    let mut failures = Vec::new();
+    // 200 to 120 is no whole factor, so every filter falls back
+    for &filter in Filter::value_variants() {
+        let actual = resample::resize(&source, 120, 120, filter);
+        let name = format!(
+            "resize-120-{}",
+            filter.to_possible_value().unwrap().get_name()
+        );
+        check(&name, &actual, &mut failures);
+    }
+    report(&failures);
+}
+
+/// Compare `actual` with the golden image `name`, or replace that image
+/// with UPDATE_GOLDEN set, noting any difference in `failures`
+fn check(name: &str, actual: &RgbImage, failures: &mut Vec<String>) {
+    let path = golden_dir().join(format!("{}.png", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(golden_dir()).unwrap();
+        actual.save(&path).unwrap();
+        return;
+    }
+    let reason = match image::open(&path) {
+        Err(_) => Some("no golden image".to_string()),
+        Ok(golden) => difference(&golden.into_rgb8(), actual),
+    };
+    if let Some(reason) = reason {
+        std::fs::create_dir_all(actual_dir()).unwrap();
+        actual
+            .save(actual_dir().join(format!("{}.png", name)))
+            .unwrap();
+        failures.push(format!("{}: {}", name, reason));
+    }
+}
+
+fn report(failures: &[String]) {
+    assert!(
+        failures.is_empty(),
+        "{} renders drifted from tests/golden (actual images in {}); if intended, rerun with UPDATE_GOLDEN=1:\n  {}",
+        failures.len(),
+        actual_dir().display(),
+        failures.join("\n  ")
+    );
+}
+
+/// How `actual` differs from `golden`, if it does
+fn difference(golden: &RgbImage, actual: &RgbImage) -> Option<String> {
+    if golden.dimensions() != actual.dimensions() {
+        return Some(format!(
+            "size {:?} instead of {:?}",
+            actual.dimensions(),
+            golden.dimensions()
+        ));
+    }
+    let mut differing = golden
+        .enumerate_pixels()
+        .zip(actual.pixels())
+        .filter(|((_, _, expected), found)| expected != found);
+    let ((x, y, expected), found) = differing.next()?;
+    Some(format!(
+        "{} pixels differ, first at ({}, {}): {:?} instead of {:?}",
+        differing.count() + 1,
+        x,
+        y,
+        found.0,
+        expected.0
+    ))
+}
@@ -0,0 +1,82 @@
+//! The GPU draws exactly the pixels the CPU does, for everything `--gpu`
+//! renders; skipped where wgpu finds no adapter
+
+use hashdenticon::gpu::Gpu;
+use hashdenticon::resample::Filter;
+use hashdenticon::{Identicon, Options, Style};
+
+const SEEDS: &[&str] = &["alice", "bob", "carol", "trent"];
+
+/// Whether there is a GPU to compare against, saying so when there isn't
+fn have_gpu() -> bool {
+    match Gpu::global() {
+        Ok(_) => true,
+        Err(err) => {
+            eprintln!("skipping, no GPU: {}", err);
+            false
+        }
+    }
+}
+
+fn assert_same_on_gpu(options: &Options) {
+    assert!(options.gpu_applies());
+    for seed in SEEDS {
+        let cpu = Identicon::from_seed(seed, options).unwrap();
+        let gpu = Options {
+            gpu: true,
+            ..options.clone()
+        };
+        let gpu = Identicon::from_seed(seed, &gpu).unwrap();
+        assert!(
+            cpu.as_rgb() == gpu.as_rgb(),
+            "{} renders differently on the GPU with {:?}",
+            seed,
+            options
+        );
+    }
+}
+
+#[test]
+fn voronoi_matches_the_cpu() {
+    if !have_gpu() {
+        return;
+    }
+    for (size, padding) in [(420, 8), (97, 0), (512, 20)] {
+        assert_same_on_gpu(&Options {
+            style: Style::Voronoi,
+            size,
+            padding,
+            ..Options::default()
+        });
+    }
+}
+
+#[test]
+fn box_supersampling_matches_the_cpu() {
+    if !have_gpu() {
+        return;
+    }
+    for (style, supersample) in [(Style::Grid, 2), (Style::Blob, 4), (Style::Rings, 3)] {
+        assert_same_on_gpu(&Options {
+            style,
+            size: 300,
+            supersample,
+            filter: Filter::Box,
+            ..Options::default()
+        });
+    }
+}
+
+#[test]
+fn only_voronoi_and_box_supersampling_use_the_gpu() {
+    let lanczos = Options {
+        supersample: 2,
+        ..Options::default()
+    };
+    assert!(!lanczos.gpu_applies());
+    let unscaled = Options {
+        filter: Filter::Box,
+        ..Options::default()
+    };
+    assert!(!unscaled.gpu_applies());
+}
@@ -0,0 +1,118 @@
+//! Invariants that hold for any seed and any valid options
+//!
+//! ```text
+//! cargo test --features proptest --test properties
+//! ```
+//!
+//! A failing case is shrunk to the smallest options that still fail, and
+//! saved under `tests/properties.proptest-regressions` to run first from
+//! then on.
+
+use hashdenticon::output::Format;
+use hashdenticon::theme::CellShape;
+use hashdenticon::{Identicon, Options, Style, generate_pattern, generate_pattern_with_density};
+use image::{Rgb, RgbImage};
+use proptest::prelude::*;
+use proptest::sample::select;
+
+const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+
+/// Options for the plain grid, with nothing drawn over or around the cells
+fn plain_grid() -> impl Strategy<Value = Options> {
+    any::<Options>().prop_map(|options| Options {
+        style: Style::Grid,
+        height: None,
+        rotate: 0,
+        flip: None,
+        supersample: 1,
+        mask: None,
+        border: 0,
+        ..options
+    })
+}
+
+/// [`plain_grid`] with cells that are their own mirror image at any size;
+/// circles and diamonds of an odd width lean a pixel to one side
+fn mirrored_grid() -> impl Strategy<Value = Options> {
+    let shapes = select(vec![CellShape::Square, CellShape::Rounded]);
+    (plain_grid(), shapes).prop_map(|(options, cell_shape)| Options {
+        cell_shape,
+        ..options
+    })
+}
+
+/// Width of the canvas left of each cell column and right of the last one
+fn margins(options: &Options) -> (u32, u32) {
+    let padding = options.size * options.padding / 100;
+    let drawable = options.size - 2 * padding;
+    let used = drawable / options.grid_size * options.grid_size;
+    let left = padding + (drawable - used) / 2;
+    (left, options.size - used - left)
+}
+
+fn is_mirrored(img: &RgbImage) -> bool {
+    let width = img.width();
+    img.enumerate_pixels()
+        .all(|(x, y, pixel)| img.get_pixel(width - 1 - x, y) == pixel)
+}
+
+proptest! {
+    #[test]
+    fn same_inputs_encode_to_same_bytes(seed in any::<String>(), options in any::<Options>()) {
+        let encode = || {
+            let mut png = Vec::new();
+            Identicon::from_seed(&seed, &options)
+                .and_then(|identicon| identicon.encode_into(&mut png, Format::Png))
+                .map(|()| png)
+        };
+        prop_assert_eq!(encode()?, encode()?);
+    }
+
+    #[test]
+    fn renders_have_the_requested_dimensions(seed in any::<String>(), options in any::<Options>()) {
+        let identicon = Identicon::from_seed(&seed, &options)?;
+        let (width, height) = (options.size, options.height.unwrap_or(options.size));
+        let expected = if options.rotate % 180 == 90 {
+            (height, width)
+        } else {
+            (width, height)
+        };
+        prop_assert_eq!(identicon.as_rgb().dimensions(), expected);
+    }
+
+    #[test]
+    fn patterns_are_mirrored(
+        hash in proptest::collection::vec(any::<u8>(), 32),
+        grid_size in 1..=hashdenticon::MAX_GRID_SIZE,
+        density in proptest::option::of(0..=100u32),
+    ) {
+        let pattern = match density {
+            Some(percent) => generate_pattern_with_density(&hash, grid_size, percent),
+            None => generate_pattern(&hash, grid_size),
+        };
+        for row in &pattern {
+            prop_assert!(row.iter().eq(row.iter().rev()), "row {:?} is not mirrored", row);
+        }
+    }
+
+    #[test]
+    fn grid_renders_are_mirrored(seed in any::<String>(), options in mirrored_grid()) {
+        let (left, right) = margins(&options);
+        // an odd pixel of leftover space goes to the right
+        prop_assume!(left == right);
+        let identicon = Identicon::from_seed(&seed, &options)?;
+        prop_assert!(is_mirrored(identicon.as_rgb()));
+    }
+
+    #[test]
+    fn cells_stay_inside_the_padding(seed in any::<String>(), options in plain_grid()) {
+        let identicon = Identicon::from_seed(&seed, &options)?;
+        let (left, right) = margins(&options);
+        let inside = left..options.size - right;
+        for (x, y, pixel) in identicon.as_rgb().enumerate_pixels() {
+            if !inside.contains(&x) || !inside.contains(&y) {
+                prop_assert_eq!(*pixel, WHITE, "drawn at ({}, {})", x, y);
+            }
+        }
+    }
+}